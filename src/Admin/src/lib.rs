@@ -45,6 +45,40 @@ pub struct ApproveRejectResult {
     pub status: IdeaStatus,
 }
 
+/// A moderator's temporary claim on a pending idea.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ReviewLock {
+    pub reviewer: Principal,
+    pub claimed_at_ns: u64,
+    pub expires_at_ns: u64,
+}
+
+/// One approve/reject decision, kept for reviewer stats.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ReviewRecord {
+    pub idea_id: u64,
+    pub reviewer: Principal,
+    pub claimed_at_ns: Option<u64>, // None when decided without claiming first
+    pub decided_at_ns: u64,
+    pub approved: bool,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ReviewQueueItem {
+    pub idea: Idea,
+    pub in_review_by: Option<Principal>,
+    pub lock_expires_at_ns: Option<u64>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ReviewerStats {
+    pub reviewer: Principal,
+    pub reviews_completed: u64,
+    pub approved: u64,
+    pub approval_rate_pct: u8,
+    pub avg_decision_ns: Option<u64>, // claim -> decision, over claimed reviews only
+}
+
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub enum AdminError {
     NotAuthorized,
@@ -52,6 +86,7 @@ pub enum AdminError {
     IdeaNotFound,
     AlreadyExists,
     InvalidInput(String),
+    InReview(Principal), // another moderator holds the review lock
 }
 
 type Result<T> = std::result::Result<T, AdminError>;
@@ -64,6 +99,23 @@ struct State {
     ideas: BTreeMap<u64, Idea>,
     next_idea_id: u64,
     admins: BTreeSet<Principal>,
+    // Option so state saved before reviews existed still restores
+    reviews: Option<ReviewState>,
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+struct ReviewState {
+    lock_window_ns: u64,
+    locks: BTreeMap<u64, ReviewLock>,
+    history: Vec<ReviewRecord>,
+}
+
+const DEFAULT_REVIEW_LOCK_NS: u64 = 30 * 60 * 1_000_000_000; // 30 minutes
+
+impl Default for ReviewState {
+    fn default() -> Self {
+        ReviewState { lock_window_ns: DEFAULT_REVIEW_LOCK_NS, locks: BTreeMap::new(), history: Vec::new() }
+    }
 }
 
 thread_local! {
@@ -83,6 +135,55 @@ fn ensure_admin() -> Result<()> {
     }
 }
 
+/// Live (unexpired) lock on an idea, if any. Expired locks are treated as released.
+fn active_lock(st: &State, idea_id: u64, now: u64) -> Option<ReviewLock> {
+    st.reviews
+        .as_ref()
+        .and_then(|r| r.locks.get(&idea_id))
+        .filter(|l| l.expires_at_ns > now)
+        .cloned()
+}
+
+/// Drop locks that have expired. Run by every update that touches locks, so `locks` only holds
+/// live claims (and ones that lapsed since); reads still skip the lapsed ones via `active_lock`.
+fn prune_expired_locks(reviews: &mut ReviewState, now: u64) {
+    reviews.locks.retain(|_, l| l.expires_at_ns > now);
+}
+
+/// Shared by approve/reject: enforce the lock, set the status, and record the decision.
+fn decide(id: u64, status: IdeaStatus) -> Result<ApproveRejectResult> {
+    ensure_admin()?;
+    let me = caller();
+    let now = time();
+    STATE.with(|s| {
+        let mut st = s.borrow_mut();
+        let current = st.ideas.get(&id).ok_or(AdminError::IdeaNotFound)?;
+        // A second decision would count twice in the reviewer stats.
+        if current.status != IdeaStatus::Pending {
+            return Err(AdminError::InvalidInput("idea is not pending review".into()));
+        }
+        let lock = active_lock(&st, id, now);
+        if let Some(l) = &lock {
+            if l.reviewer != me {
+                return Err(AdminError::InReview(l.reviewer));
+            }
+        }
+        let idea = st.ideas.get_mut(&id).ok_or(AdminError::IdeaNotFound)?;
+        idea.status = status.clone();
+        let reviews = st.reviews.get_or_insert_with(ReviewState::default);
+        reviews.locks.remove(&id);
+        prune_expired_locks(reviews, now);
+        reviews.history.push(ReviewRecord {
+            idea_id: id,
+            reviewer: me,
+            claimed_at_ns: lock.map(|l| l.claimed_at_ns),
+            decided_at_ns: now,
+            approved: status == IdeaStatus::Approved,
+        });
+        Ok(ApproveRejectResult { id, status })
+    })
+}
+
 /// ====== Lifecycle ======
 
 #[init]
@@ -250,40 +351,134 @@ fn submit_idea(title: String, description: String) -> Result<Idea> {
 
 #[update]
 fn approve_idea(id: u64) -> Result<ApproveRejectResult> {
+    decide(id, IdeaStatus::Approved)
+}
+
+#[update]
+fn reject_idea(id: u64) -> Result<ApproveRejectResult> {
+    decide(id, IdeaStatus::Rejected)
+}
+
+#[query]
+fn get_ideas() -> Vec<Idea> {
+    STATE.with(|s| s.borrow().ideas.values().cloned().collect())
+}
+
+#[query]
+fn get_idea(id: u64) -> Option<Idea> {
+    STATE.with(|s| s.borrow().ideas.get(&id).cloned())
+}
+
+/// ====== Review Assignment ======
+
+#[update]
+fn set_review_lock_window(secs: u64) -> Result<()> {
     ensure_admin()?;
+    if secs == 0 {
+        return Err(AdminError::InvalidInput("lock window must be > 0".into()));
+    }
     STATE.with(|s| {
         let mut st = s.borrow_mut();
-        let idea = st.ideas.get_mut(&id).ok_or(AdminError::IdeaNotFound)?;
-        idea.status = IdeaStatus::Approved;
-        Ok(ApproveRejectResult {
-            id,
-            status: idea.status.clone(),
-        })
-    })
+        st.reviews.get_or_insert_with(ReviewState::default).lock_window_ns = secs.saturating_mul(1_000_000_000);
+    });
+    Ok(())
 }
 
+/// Claim a pending idea for review. Re-claiming your own lock extends it.
 #[update]
-fn reject_idea(id: u64) -> Result<ApproveRejectResult> {
+fn claim_review(idea_id: u64) -> Result<ReviewLock> {
     ensure_admin()?;
+    let me = caller();
+    let now = time();
     STATE.with(|s| {
         let mut st = s.borrow_mut();
-        let idea = st.ideas.get_mut(&id).ok_or(AdminError::IdeaNotFound)?;
-        idea.status = IdeaStatus::Rejected;
-        Ok(ApproveRejectResult {
-            id,
-            status: idea.status.clone(),
-        })
+        let idea = st.ideas.get(&idea_id).ok_or(AdminError::IdeaNotFound)?;
+        if idea.status != IdeaStatus::Pending {
+            return Err(AdminError::InvalidInput("idea is not pending review".into()));
+        }
+        if let Some(l) = active_lock(&st, idea_id, now) {
+            if l.reviewer != me {
+                return Err(AdminError::InReview(l.reviewer));
+            }
+        }
+        let reviews = st.reviews.get_or_insert_with(ReviewState::default);
+        prune_expired_locks(reviews, now);
+        let lock = ReviewLock {
+            reviewer: me,
+            claimed_at_ns: now,
+            expires_at_ns: now.saturating_add(reviews.lock_window_ns),
+        };
+        reviews.locks.insert(idea_id, lock.clone());
+        Ok(lock)
     })
 }
 
+/// Release a review lock. The assignee releases their own; any admin may force-release a stuck one.
+#[update]
+fn release_review(idea_id: u64) -> Result<()> {
+    ensure_admin()?;
+    let now = time();
+    STATE.with(|s| {
+        let mut st = s.borrow_mut();
+        let reviews = st.reviews.get_or_insert_with(ReviewState::default);
+        reviews.locks.remove(&idea_id);
+        prune_expired_locks(reviews, now);
+    });
+    Ok(())
+}
+
+/// Pending ideas with who (if anyone) currently holds the review lock.
 #[query]
-fn get_ideas() -> Vec<Idea> {
-    STATE.with(|s| s.borrow().ideas.values().cloned().collect())
+fn get_review_queue() -> Result<Vec<ReviewQueueItem>> {
+    ensure_admin()?;
+    let now = time();
+    STATE.with(|s| {
+        let st = s.borrow();
+        Ok(st
+            .ideas
+            .values()
+            .filter(|i| i.status == IdeaStatus::Pending)
+            .map(|i| {
+                let lock = active_lock(&st, i.id, now);
+                ReviewQueueItem {
+                    idea: i.clone(),
+                    in_review_by: lock.as_ref().map(|l| l.reviewer),
+                    lock_expires_at_ns: lock.map(|l| l.expires_at_ns),
+                }
+            })
+            .collect())
+    })
 }
 
 #[query]
-fn get_idea(id: u64) -> Option<Idea> {
-    STATE.with(|s| s.borrow().ideas.get(&id).cloned())
+fn get_reviewer_stats() -> Result<Vec<ReviewerStats>> {
+    ensure_admin()?;
+    STATE.with(|s| {
+        let st = s.borrow();
+        // reviewer -> (completed, approved, total claimed decision time, claimed count)
+        let mut acc: BTreeMap<Principal, (u64, u64, u128, u64)> = BTreeMap::new();
+        for r in st.reviews.iter().flat_map(|r| r.history.iter()) {
+            let e = acc.entry(r.reviewer).or_default();
+            e.0 += 1;
+            if r.approved {
+                e.1 += 1;
+            }
+            if let Some(claimed) = r.claimed_at_ns {
+                e.2 += r.decided_at_ns.saturating_sub(claimed) as u128;
+                e.3 += 1;
+            }
+        }
+        Ok(acc
+            .into_iter()
+            .map(|(reviewer, (completed, approved, total_ns, claimed))| ReviewerStats {
+                reviewer,
+                reviews_completed: completed,
+                approved,
+                approval_rate_pct: (approved * 100 / completed.max(1)) as u8,
+                avg_decision_ns: (claimed > 0).then(|| (total_ns / claimed as u128) as u64),
+            })
+            .collect())
+    })
 }
 
 ic_cdk::export_candid!();
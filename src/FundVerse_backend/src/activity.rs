@@ -0,0 +1,201 @@
+//! Activity log: who did what to an idea or campaign, for audits and "recent activity" widgets.
+//!
+//! Update endpoints append an `Event` in the same call as the change it describes, so events keep
+//! the order of the calls that made them. Each event is filed under its idea, its campaign or both.
+//! Once an entity has more events than the retention cap (`event_retention` in the init or upgrade
+//! args, 200 by default) its oldest are dropped, and an event leaves the log when no entity files
+//! it any more. Recording only writes to stable memory and returns nothing, so it can't fail the
+//! call that emits it.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::thread::LocalKey;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::query;
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{paginate, EntityRef, Memory, PagedResult, MAX_LISTING_PAGE, MEMORY_MANAGER};
+
+const DEFAULT_EVENT_RETENTION: u32 = 200; // events per idea or campaign
+
+type EventIndex = RefCell<StableBTreeMap<(u64, u64), (), Memory>>;
+
+thread_local! {
+    static EVENTS: RefCell<StableBTreeMap<u64, LoggedEvent, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(67)))
+        )
+    );
+
+    // (idea_id, event_id) -> ()
+    static IDEA_EVENTS: EventIndex = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(68)))
+        )
+    );
+
+    // (campaign_id, event_id) -> ()
+    static CAMPAIGN_EVENTS: EventIndex = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(69)))
+        )
+    );
+
+    static EVENT_RETENTION: RefCell<StableCell<u32, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(70))),
+            DEFAULT_EVENT_RETENTION,
+        ).expect("init event retention")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    IdeaCreated,
+    IdeaApproved,
+    CampaignCreated,
+    ContributionReceived,
+    DocUploaded,
+    CampaignCompleted, // finalized with the goal met
+    CampaignExpired,   // finalized short of the goal
+    RecordQuarantined, // an idea record failed to decode; the actor is the canister itself
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Event {
+    pub id: u64,
+    pub timestamp: u64, // ns
+    pub actor: Principal,
+    pub kind: EventKind,
+    pub entity_id: u64, // the idea, campaign, contribution or doc the kind names
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+struct LoggedEvent {
+    event: Event,
+    filed_under: Vec<EntityRef>, // Idea and Campaign refs only
+}
+
+impl Storable for LoggedEvent {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode LoggedEvent"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode LoggedEvent")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Set how many events each idea and campaign keeps (at least 1); None keeps the stored setting.
+pub(crate) fn configure(event_retention: Option<u32>) {
+    if let Some(retention) = event_retention {
+        EVENT_RETENTION.with(|r| r.borrow_mut().set(retention.max(1))).expect("store event retention");
+    }
+}
+
+fn index_of(entity: EntityRef) -> Option<(&'static LocalKey<EventIndex>, u64)> {
+    match entity {
+        EntityRef::Idea(id) => Some((&IDEA_EVENTS, id)),
+        EntityRef::Campaign(id) => Some((&CAMPAIGN_EVENTS, id)),
+        EntityRef::Doc(_) | EntityRef::Comment(_) => None,
+    }
+}
+
+fn is_filed(event_id: u64, entity: EntityRef) -> bool {
+    index_of(entity).is_some_and(|(index, id)| index.with(|i| i.borrow().contains_key(&(id, event_id))))
+}
+
+/// Drop the entity's oldest events beyond `retention`, and from the log those nothing else files.
+fn trim(index: &'static LocalKey<EventIndex>, id: u64, retention: usize) {
+    let filed: Vec<u64> =
+        index.with(|i| i.borrow().range((id, 0)..=(id, u64::MAX)).map(|((_, e), _)| e).collect());
+    for event_id in filed.iter().take(filed.len().saturating_sub(retention)) {
+        index.with(|i| i.borrow_mut().remove(&(id, *event_id)));
+        let Some(logged) = EVENTS.with(|e| e.borrow().get(event_id)) else { continue };
+        if !logged.filed_under.iter().any(|entity| is_filed(*event_id, *entity)) {
+            EVENTS.with(|e| e.borrow_mut().remove(event_id));
+        }
+    }
+}
+
+/// Append an event filed under the given ideas and campaigns. Call after the change it describes.
+pub(crate) fn record_event(actor: Principal, kind: EventKind, entity_id: u64, filed_under: &[EntityRef]) {
+    append(ic_cdk::api::time(), actor, kind, entity_id, filed_under);
+}
+
+/// `record_event` as of `timestamp` (ns).
+pub(crate) fn append(timestamp: u64, actor: Principal, kind: EventKind, entity_id: u64, filed_under: &[EntityRef]) {
+    // The newest event is always kept, so the last id only grows.
+    let id = EVENTS.with(|e| e.borrow().last_key_value().map_or(0, |(id, _)| id + 1));
+    let event = Event { id, timestamp, actor, kind, entity_id };
+    EVENTS.with(|e| e.borrow_mut().insert(id, LoggedEvent { event, filed_under: filed_under.to_vec() }));
+    let retention = EVENT_RETENTION.with(|r| *r.borrow().get()) as usize;
+    for (index, entity_id) in filed_under.iter().filter_map(|entity| index_of(*entity)) {
+        index.with(|i| i.borrow_mut().insert((entity_id, id), ()));
+        trim(index, entity_id, retention);
+    }
+}
+
+fn events_for(entity: EntityRef, offset: u64, limit: u64) -> PagedResult<Event> {
+    let Some((index, id)) = index_of(entity) else { return paginate(Vec::new(), offset, limit) };
+    let ids: Vec<u64> =
+        index.with(|i| i.borrow().range((id, 0)..=(id, u64::MAX)).rev().map(|((_, e), _)| e).collect());
+    let events = EVENTS.with(|e| {
+        let e = e.borrow();
+        ids.into_iter().filter_map(|id| e.get(&id)).map(|logged| logged.event).collect()
+    });
+    paginate(events, offset, limit)
+}
+
+/// An idea's events, newest first: its creation, approval, docs and campaigns.
+#[query]
+fn get_events_for_idea(idea_id: u64, offset: u64, limit: u64) -> PagedResult<Event> {
+    events_for(EntityRef::Idea(idea_id), offset, limit)
+}
+
+/// A campaign's events, newest first: its creation, every pledge and its outcome.
+#[query]
+fn get_events_for_campaign(campaign_id: u64, offset: u64, limit: u64) -> PagedResult<Event> {
+    events_for(EntityRef::Campaign(campaign_id), offset, limit)
+}
+
+/// The latest events across the canister, newest first, at most 100.
+#[query]
+fn get_recent_events(limit: u64) -> Vec<Event> {
+    let limit = limit.min(MAX_LISTING_PAGE) as usize;
+    EVENTS.with(|e| e.borrow().iter().rev().take(limit).map(|(_, logged)| logged.event).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_entity_keeps_its_latest_events_and_shared_events_stay_while_filed() {
+        configure(Some(2));
+        let owner = Principal::from_slice(&[1]);
+        let (idea, campaign) = (EntityRef::Idea(7), EntityRef::Campaign(3));
+        append(1, owner, EventKind::IdeaCreated, 7, &[idea]);
+        append(2, owner, EventKind::CampaignCreated, 3, &[idea, campaign]);
+        let kinds = |entity| events_for(entity, 0, 10).items.iter().map(|e| e.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(idea), [EventKind::CampaignCreated, EventKind::IdeaCreated], "newest first");
+
+        append(3, owner, EventKind::DocUploaded, 11, &[idea]);
+        append(4, owner, EventKind::DocUploaded, 12, &[idea]);
+        assert_eq!(kinds(idea), [EventKind::DocUploaded, EventKind::DocUploaded]);
+        assert_eq!(kinds(campaign), [EventKind::CampaignCreated], "still filed under the campaign");
+        let ids: Vec<u64> = get_recent_events(10).iter().map(|e| e.id).collect();
+        assert_eq!(ids, [3, 2, 1], "the idea's first event left the log");
+
+        append(5, owner, EventKind::ContributionReceived, 1, &[campaign]);
+        append(6, owner, EventKind::ContributionReceived, 2, &[campaign]);
+        let ids: Vec<u64> = get_recent_events(10).iter().map(|e| e.id).collect();
+        assert_eq!(ids, [5, 4, 3, 2], "filed nowhere any more");
+        assert_eq!(get_recent_events(1).len(), 1);
+        assert_eq!(events_for(campaign, 1, 10).items.first().map(|e| e.entity_id), Some(1));
+    }
+}
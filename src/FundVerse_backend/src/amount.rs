@@ -0,0 +1,97 @@
+//! Checked arithmetic for pledged amounts and funding totals.
+//!
+//! Canister wasm is built without overflow checks, so a bare `+` on u64 wraps. Every total in the
+//! funding paths goes through `Amount`, which fails with an `AmountError` instead; callers turn
+//! that into the call's error before anything is written.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Amount(u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AmountError {
+    Overflow,  // the total would pass u64::MAX
+    Underflow, // more taken off than the total holds
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Overflow => write!(f, "amount would overflow the total"),
+            AmountError::Underflow => write!(f, "amount is more than the total"),
+        }
+    }
+}
+
+impl From<AmountError> for String {
+    fn from(e: AmountError) -> Self {
+        e.to_string()
+    }
+}
+
+impl Amount {
+    pub(crate) const MAX: Amount = Amount(u64::MAX);
+
+    pub(crate) fn new(value: u64) -> Self {
+        Amount(value)
+    }
+
+    pub(crate) fn get(self) -> u64 {
+        self.0
+    }
+
+    pub(crate) fn add(self, other: Amount) -> Result<Amount, AmountError> {
+        self.0.checked_add(other.0).map(Amount).ok_or(AmountError::Overflow)
+    }
+
+    pub(crate) fn sub(self, other: Amount) -> Result<Amount, AmountError> {
+        self.0.checked_sub(other.0).map(Amount).ok_or(AmountError::Underflow)
+    }
+
+    /// This amount as basis points of `whole` (10_000 = all of it), rounded down; past 10_000 once
+    /// it exceeds `whole`. A zero `whole` counts as 1.
+    pub(crate) fn share_bps(self, whole: Amount) -> Result<u64, AmountError> {
+        let bps = self.0 as u128 * 10_000 / whole.0.max(1) as u128;
+        u64::try_from(bps).map_err(|_| AmountError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Values around the edges that each operation has to get right.
+    const EDGES: [u64; 9] = [0, 1, 2, 9_999, 10_000, u64::MAX / 10_000, u64::MAX / 2, u64::MAX - 1, u64::MAX];
+
+    #[test]
+    fn add_and_sub_fail_exactly_when_u64_would() {
+        for a in EDGES {
+            for b in EDGES {
+                let (x, y) = (Amount::new(a), Amount::new(b));
+                assert_eq!(x.add(y).map(Amount::get), a.checked_add(b).ok_or(AmountError::Overflow), "{} + {}", a, b);
+                assert_eq!(x.sub(y).map(Amount::get), a.checked_sub(b).ok_or(AmountError::Underflow), "{} - {}", a, b);
+                if let Ok(sum) = x.add(y) {
+                    assert_eq!(sum.sub(y), Ok(x));
+                }
+            }
+        }
+        assert_eq!(Amount::MAX.add(Amount::new(1)).unwrap_err().to_string(), "amount would overflow the total");
+    }
+
+    #[test]
+    fn share_bps_is_exact_until_it_no_longer_fits() {
+        for part in EDGES {
+            for whole in EDGES {
+                let exact = part as u128 * 10_000 / whole.max(1) as u128;
+                let share = Amount::new(part).share_bps(Amount::new(whole));
+                assert_eq!(share.is_err(), exact > u64::MAX as u128, "{} of {}", part, whole);
+                if let Ok(bps) = share {
+                    assert_eq!(bps as u128, exact);
+                }
+            }
+        }
+        assert_eq!(Amount::new(40).share_bps(Amount::new(100)), Ok(4_000));
+        assert_eq!(Amount::new(u64::MAX).share_bps(Amount::MAX), Ok(10_000));
+    }
+}
@@ -0,0 +1,175 @@
+//! Cold tier for the contributions of long-finished campaigns.
+//!
+//! A daily timer moves every contribution of a campaign that ended more than the archive age ago
+//! out of the hot maps into one block per campaign. Blocks are written once and never changed;
+//! contributions can't arrive after the end date. Campaign and idea totals are left as they are,
+//! and `get_contributions_by_campaign` / `get_my_contributions` read cold blocks transparently.
+//! Blocks are candid-encoded like every other stable record, without compression.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::update;
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{
+    contributions_by_id, effective_end_date, ensure_controller, now_secs, Contribution, Memory, CAMPAIGNS,
+    CAMPAIGN_CONTRIBUTIONS, CONTRIBUTIONS, CONTRIBUTOR_CONTRIBUTIONS, MEMORY_MANAGER,
+};
+
+const DEFAULT_ARCHIVE_AFTER_SECS: u64 = 365 * 24 * 60 * 60;
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const CAMPAIGNS_PER_RUN: usize = 10; // keeps each timer tick's work bounded
+
+thread_local! {
+    // campaign_id -> every contribution the campaign had when it was archived
+    static COLD_BLOCKS: RefCell<StableBTreeMap<u64, ColdBlock, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(33)))
+        )
+    );
+
+    // (contributor, contribution_id) -> campaign_id, replacing the hot contributor index entry
+    static COLD_CONTRIBUTORS: RefCell<StableBTreeMap<(Principal, u64), u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(34)))
+        )
+    );
+
+    static ARCHIVE_AFTER_SECS: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(35))),
+            DEFAULT_ARCHIVE_AFTER_SECS,
+        ).expect("init archive age")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+struct ColdBlock {
+    contributions: Vec<Contribution>, // oldest first
+    count: u64,
+    total: u64,
+    archived_at: u64, // ns
+}
+
+impl ColdBlock {
+    fn new(contributions: Vec<Contribution>, archived_at: u64) -> Self {
+        let (count, total) = tally(&contributions);
+        ColdBlock { contributions, count, total, archived_at }
+    }
+}
+
+impl Storable for ColdBlock {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode ColdBlock"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode ColdBlock")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+fn tally(contributions: &[Contribution]) -> (u64, u64) {
+    let total = contributions.iter().fold(0u64, |sum, c| sum.saturating_add(c.amount));
+    (contributions.len() as u64, total)
+}
+
+/// Run the archiver once a day. Timers don't survive upgrades, so this is called from both hooks.
+pub(crate) fn schedule_archiving() {
+    ic_cdk_timers::set_timer_interval(ARCHIVE_INTERVAL, archive_due_campaigns);
+}
+
+// One timer tick is one message: a trap part-way rolls back every move it made.
+fn archive_due_campaigns() {
+    let now = now_secs();
+    let age = ARCHIVE_AFTER_SECS.with(|a| *a.borrow().get());
+    let due: Vec<u64> = CAMPAIGNS.with(|c| {
+        c.borrow()
+            .iter()
+            // cancelled campaigns still awaiting refunds stay hot
+            .filter(|(_, c)| c.refund_pending != Some(true))
+            .filter(|(id, c)| effective_end_date(c, now).saturating_add(age) < now && !is_cold(*id))
+            .map(|(id, _)| id)
+            .take(CAMPAIGNS_PER_RUN)
+            .collect()
+    });
+    for campaign_id in due {
+        if let Err(e) = archive_campaign(campaign_id) {
+            ic_cdk::println!("archiving campaign {} failed: {}", campaign_id, e);
+        }
+    }
+}
+
+fn is_cold(campaign_id: u64) -> bool {
+    COLD_BLOCKS.with(|b| b.borrow().contains_key(&campaign_id))
+}
+
+/// Move one campaign's contributions to a cold block. Hot copies are deleted only after the
+/// stored block has been read back and its count and sum match them.
+fn archive_campaign(campaign_id: u64) -> Result<(), String> {
+    let ids: Vec<u64> = CAMPAIGN_CONTRIBUTIONS.with(|c| {
+        c.borrow()
+            .range((campaign_id, 0)..=(campaign_id, u64::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    });
+    let hot = contributions_by_id(ids.clone());
+    if hot.len() != ids.len() {
+        return Err("campaign index points at missing contributions".into());
+    }
+    let expected = tally(&hot);
+    COLD_BLOCKS.with(|b| b.borrow_mut().insert(campaign_id, ColdBlock::new(hot, ic_cdk::api::time())));
+
+    let stored = COLD_BLOCKS.with(|b| b.borrow().get(&campaign_id)).expect("block just written");
+    if tally(&stored.contributions) != expected || (stored.count, stored.total) != expected {
+        COLD_BLOCKS.with(|b| b.borrow_mut().remove(&campaign_id));
+        return Err(format!("stored block does not match the {} hot contributions", expected.0));
+    }
+    for c in &stored.contributions {
+        COLD_CONTRIBUTORS.with(|m| m.borrow_mut().insert((c.contributor, c.id), campaign_id));
+        CONTRIBUTOR_CONTRIBUTIONS.with(|m| m.borrow_mut().remove(&(c.contributor, c.id)));
+        CAMPAIGN_CONTRIBUTIONS.with(|m| m.borrow_mut().remove(&(campaign_id, c.id)));
+        CONTRIBUTIONS.with(|m| m.borrow_mut().remove(&c.id));
+    }
+    Ok(())
+}
+
+/// All of an archived campaign's contributions, or None if the campaign is still hot.
+pub(crate) fn cold_campaign_contributions(campaign_id: u64) -> Option<Vec<Contribution>> {
+    COLD_BLOCKS.with(|b| b.borrow().get(&campaign_id)).map(|block| block.contributions)
+}
+
+/// A contributor's archived contributions, decoding each block they appear in once.
+pub(crate) fn cold_contributions_of(contributor: Principal) -> Vec<Contribution> {
+    let mut ids_by_campaign: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    COLD_CONTRIBUTORS.with(|m| {
+        for ((_, id), campaign_id) in m.borrow().range((contributor, 0)..=(contributor, u64::MAX)) {
+            ids_by_campaign.entry(campaign_id).or_default().push(id);
+        }
+    });
+    ids_by_campaign
+        .into_iter()
+        .flat_map(|(campaign_id, ids)| {
+            cold_campaign_contributions(campaign_id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(move |c| ids.contains(&c.id))
+        })
+        .collect()
+}
+
+/// How long after a campaign ends its contributions move to the cold tier (controllers only).
+#[update]
+fn set_contribution_archive_age(secs: u64) -> Result<(), String> {
+    ensure_controller()?;
+    ARCHIVE_AFTER_SECS
+        .with(|a| a.borrow_mut().set(secs))
+        .map(|_| ())
+        .map_err(|e| format!("failed to store archive age: {:?}", e))
+}
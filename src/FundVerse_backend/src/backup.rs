@@ -0,0 +1,464 @@
+//! Bulk export and import of ideas, campaigns, docs and contributions, for backups before risky
+//! upgrades and for seeding local replicas and staging with real-ish data.
+//!
+//! Records keep their ids. `export_data` returns everything in one bundle; when that would be over
+//! the reply size limit, `export_data_chunk` returns one entity type a page at a time, as a bundle
+//! of its own. Import checks the whole bundle before writing anything: every campaign and doc must
+//! name an idea and every contribution a campaign, either in the bundle or (when merging) already
+//! stored; otherwise nothing is imported and every problem is reported. Chunks go in with
+//! MergeSkipExisting, ideas first, then docs, campaigns and contributions.
+//!
+//! ReplaceAll replaces the four stores and their indexes only. Comments, milestones, payouts and
+//! the other per-entity data stay as they are, so it is meant for fresh or disposable canisters.
+//! Like the other destructive admin calls it is dry-run first: the dry run reports what would be
+//! removed and imported, and its confirmation token (bound to the bundle and mode) starts the live
+//! run. Merges need no token.
+//! Archived contributions aren't exported. Counters end at least at the highest imported id, so
+//! later creates don't collide.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::thread::LocalKey;
+
+use candid::{CandidType, Deserialize, Encode};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::StableCell;
+
+use super::{
+    badges, ensure_admin, ensure_controller, fingerprint, fnv1a, index_fingerprint, issue_confirmation,
+    record_change, redeem_confirmation, syndication, Campaign,
+    ChangeKind, Contribution, Doc, EntityRef, Idea, Memory, CAMPAIGNS, CAMPAIGNS_BY_OWNER, CAMPAIGN_CONTRIBUTIONS,
+    CAMPAIGN_COUNTER, CAMPAIGN_PAUSES, CONTRIBUTIONS, CONTRIBUTION_COUNTER, CONTRIBUTOR_CONTRIBUTIONS, DOCS,
+    DOC_COUNTER, DUPLICATE_FLAGS, FINGERPRINT_INDEX, ICP_CONTRIBUTIONS, IDEAS, IDEAS_BY_OWNER, IDEA_COUNTER,
+    IDEA_FINGERPRINTS, MAX_LISTING_PAGE,
+};
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExportEntity {
+    Ideas,
+    Campaigns,
+    Docs,
+    Contributions,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ExportCounters {
+    pub ideas: u64,
+    pub campaigns: u64,
+    pub docs: u64,
+    pub contributions: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Default)]
+pub struct ExportBundle {
+    pub ideas: Vec<(u64, Idea)>,
+    pub campaigns: Vec<Campaign>,
+    pub docs: Vec<Doc>, // data is empty when exported without doc bytes
+    pub contributions: Vec<Contribution>,
+    pub counters: ExportCounters,
+    pub exported_at: u64, // ns
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportMode {
+    ReplaceAll,
+    MergeSkipExisting, // records whose id is already taken are left as stored
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportIssue {
+    pub entity: ExportEntity,
+    pub id: u64,
+    pub message: String,
+}
+
+impl ImportMode {
+    fn name(self) -> &'static str {
+        match self {
+            ImportMode::ReplaceAll => "ReplaceAll",
+            ImportMode::MergeSkipExisting => "MergeSkipExisting",
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum ImportError {
+    Unauthorized(String),
+    Invalid(Vec<ImportIssue>), // nothing was imported
+    Unconfirmed(String),       // ReplaceAll without a matching dry-run token; nothing was imported
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub ideas: u64, // imported (or that would be), not counting skipped records
+    pub campaigns: u64,
+    pub docs: u64,
+    pub contributions: u64,
+    pub skipped: Vec<(ExportEntity, u64)>, // MergeSkipExisting: ids that were already taken
+    pub removed: ExportCounters,           // ReplaceAll: stored records wiped first (record counts)
+    pub counters: ExportCounters,          // after the import
+    pub confirmation_token: Option<String>, // issued by ReplaceAll dry runs only
+}
+
+fn counters() -> ExportCounters {
+    ExportCounters {
+        ideas: IDEA_COUNTER.with(|c| *c.borrow().get()),
+        campaigns: CAMPAIGN_COUNTER.with(|c| *c.borrow().get()),
+        docs: DOC_COUNTER.with(|c| *c.borrow().get()),
+        contributions: CONTRIBUTION_COUNTER.with(|c| *c.borrow().get()),
+    }
+}
+
+fn without_data(mut doc: Doc, include_doc_data: bool) -> Doc {
+    if !include_doc_data {
+        doc.data = Vec::new();
+    }
+    doc
+}
+
+fn bundle_with(fill: impl FnOnce(&mut ExportBundle)) -> ExportBundle {
+    let mut bundle = ExportBundle { counters: counters(), exported_at: ic_cdk::api::time(), ..Default::default() };
+    fill(&mut bundle);
+    bundle
+}
+
+/// Everything in one bundle (admins). Large canisters should use `export_data_chunk` instead.
+#[query]
+fn export_data(include_doc_data: bool) -> Result<ExportBundle, String> {
+    ensure_admin()?;
+    Ok(bundle_with(|b| {
+        b.ideas = IDEAS.with(|m| m.borrow().iter().collect());
+        b.campaigns = CAMPAIGNS.with(|m| m.borrow().iter().map(|(_, c)| c).collect());
+        b.docs = DOCS.with(|m| m.borrow().iter().map(|(_, d)| without_data(d, include_doc_data)).collect());
+        b.contributions = CONTRIBUTIONS.with(|m| m.borrow().iter().map(|(_, c)| c).collect());
+    }))
+}
+
+/// One entity type a page at a time (admins): records with ids after `after_id`, at most `limit`
+/// (capped at 100). Pass the last id of one chunk as `after_id` for the next; an empty chunk ends.
+#[query]
+fn export_data_chunk(
+    entity: ExportEntity,
+    after_id: Option<u64>,
+    limit: u64,
+    include_doc_data: bool,
+) -> Result<ExportBundle, String> {
+    ensure_admin()?;
+    let start = after_id.map_or(0, |id| id.saturating_add(1));
+    let take = limit.min(MAX_LISTING_PAGE) as usize;
+    Ok(bundle_with(|b| match entity {
+        ExportEntity::Ideas => b.ideas = IDEAS.with(|m| m.borrow().range(start..).take(take).collect()),
+        ExportEntity::Campaigns => {
+            b.campaigns = CAMPAIGNS.with(|m| m.borrow().range(start..).take(take).map(|(_, c)| c).collect())
+        }
+        ExportEntity::Docs => {
+            b.docs = DOCS.with(|m| {
+                m.borrow().range(start..).take(take).map(|(_, d)| without_data(d, include_doc_data)).collect()
+            })
+        }
+        ExportEntity::Contributions => {
+            b.contributions =
+                CONTRIBUTIONS.with(|m| m.borrow().range(start..).take(take).map(|(_, c)| c).collect())
+        }
+    }))
+}
+
+/// What's wrong with a bundle; empty when it can be imported. `idea_stored` and `campaign_stored`
+/// say whether an id outside the bundle resolves (never, when replacing everything).
+fn check_bundle(
+    bundle: &ExportBundle,
+    idea_stored: impl Fn(u64) -> bool,
+    campaign_stored: impl Fn(u64) -> bool,
+) -> Vec<ImportIssue> {
+    let mut issues = Vec::new();
+    let mut issue = |entity, id, message: String| issues.push(ImportIssue { entity, id, message });
+    let mut seen = BTreeSet::new();
+    let ids = [
+        bundle.ideas.iter().map(|(id, _)| (ExportEntity::Ideas, *id)).collect::<Vec<_>>(),
+        bundle.campaigns.iter().map(|c| (ExportEntity::Campaigns, c.id)).collect(),
+        bundle.docs.iter().map(|d| (ExportEntity::Docs, d.id)).collect(),
+        bundle.contributions.iter().map(|c| (ExportEntity::Contributions, c.id)).collect(),
+    ];
+    for (entity, id) in ids.into_iter().flatten() {
+        if id == 0 {
+            issue(entity, id, "ids start at 1".into());
+        } else if !seen.insert((entity, id)) {
+            issue(entity, id, "appears more than once in the bundle".into());
+        }
+    }
+    let idea_resolves = |id| bundle.ideas.iter().any(|(i, _)| *i == id) || idea_stored(id);
+    let campaign_resolves = |id| bundle.campaigns.iter().any(|c| c.id == id) || campaign_stored(id);
+    for campaign in bundle.campaigns.iter().filter(|c| !idea_resolves(c.idea_id)) {
+        issue(ExportEntity::Campaigns, campaign.id, format!("idea {} not found", campaign.idea_id));
+    }
+    for doc in bundle.docs.iter().filter(|d| !idea_resolves(d.idea_id)) {
+        issue(ExportEntity::Docs, doc.id, format!("idea {} not found", doc.idea_id));
+    }
+    for contribution in bundle.contributions.iter().filter(|c| !campaign_resolves(c.campaign_id)) {
+        issue(ExportEntity::Contributions, contribution.id, format!("campaign {} not found", contribution.campaign_id));
+    }
+    issues
+}
+
+fn stored_counts() -> ExportCounters {
+    ExportCounters {
+        ideas: IDEAS.with(|m| m.borrow().len()),
+        campaigns: CAMPAIGNS.with(|m| m.borrow().len()),
+        docs: DOCS.with(|m| m.borrow().len()),
+        contributions: CONTRIBUTIONS.with(|m| m.borrow().len()),
+    }
+}
+
+/// Binds a ReplaceAll token to the exact bundle; any change to it needs a new dry run.
+fn bundle_digest(bundle: &ExportBundle) -> String {
+    format!("{:016x}", fnv1a(0, &Encode!(bundle).expect("encode ExportBundle")))
+}
+
+fn clear_stores() {
+    IDEAS.with(|m| m.borrow_mut().clear_new());
+    IDEAS_BY_OWNER.with(|m| m.borrow_mut().clear_new());
+    IDEA_FINGERPRINTS.with(|m| m.borrow_mut().clear_new());
+    FINGERPRINT_INDEX.with(|m| m.borrow_mut().clear_new());
+    DUPLICATE_FLAGS.with(|m| m.borrow_mut().clear_new());
+    CAMPAIGNS.with(|m| m.borrow_mut().clear_new());
+    CAMPAIGNS_BY_OWNER.with(|m| m.borrow_mut().clear_new());
+    CAMPAIGN_PAUSES.with(|m| m.borrow_mut().clear_new());
+    ICP_CONTRIBUTIONS.with(|m| m.borrow_mut().clear_new());
+    DOCS.with(|m| m.borrow_mut().clear_new());
+    CONTRIBUTIONS.with(|m| m.borrow_mut().clear_new());
+    CAMPAIGN_CONTRIBUTIONS.with(|m| m.borrow_mut().clear_new());
+    CONTRIBUTOR_CONTRIBUTIONS.with(|m| m.borrow_mut().clear_new());
+}
+
+/// Move a counter up to `at_least`; it never goes down, so ids are never handed out twice. With
+/// `write` unset, only says where it would end.
+fn raise_counter(counter: &'static LocalKey<RefCell<StableCell<u64, Memory>>>, at_least: u64, write: bool) -> u64 {
+    counter.with(|c| {
+        let mut c = c.borrow_mut();
+        let value = (*c.get()).max(at_least);
+        if write {
+            c.set(value).expect("store counter");
+        }
+        value
+    })
+}
+
+/// Load a bundle (controllers, since ReplaceAll wipes what's stored). Nothing is written unless
+/// the whole bundle checks out. `dry_run` reports what the import would do and writes nothing;
+/// a live ReplaceAll needs the confirmation token from a dry run of the same bundle.
+#[update]
+fn import_data(
+    bundle: ExportBundle,
+    mode: ImportMode,
+    dry_run: bool,
+    confirmation_token: Option<String>,
+) -> Result<ImportReport, ImportError> {
+    ensure_controller().map_err(ImportError::Unauthorized)?;
+    let replace = mode == ImportMode::ReplaceAll;
+    let issues = check_bundle(
+        &bundle,
+        |id| !replace && IDEAS.with(|m| m.borrow().contains_key(&id)),
+        |id| !replace && CAMPAIGNS.with(|m| m.borrow().contains_key(&id)),
+    );
+    if !issues.is_empty() {
+        return Err(ImportError::Invalid(issues));
+    }
+    let digest = bundle_digest(&bundle);
+    let params = [digest.as_str(), mode.name()];
+    let removed = if replace { stored_counts() } else { ExportCounters::default() };
+    if dry_run {
+        let token = replace.then(|| issue_confirmation("import_data", &params));
+        return Ok(ImportReport { removed, confirmation_token: token, ..load(&bundle, replace, false) });
+    }
+    if replace {
+        redeem_confirmation("import_data", &params, confirmation_token).map_err(ImportError::Unconfirmed)?;
+        clear_stores();
+    }
+    Ok(ImportReport { removed, ..load(&bundle, replace, true) })
+}
+
+/// Import a checked bundle, or with `write` unset only report what that would do. ReplaceAll
+/// imports everything: the stores are cleared first, so no id is taken.
+fn load(bundle: &ExportBundle, replace: bool, write: bool) -> ImportReport {
+    let mut report = ImportReport { dry_run: !write, ..Default::default() };
+    let taken = |stored: bool| !replace && stored;
+
+    for (id, idea) in bundle.ideas.iter().cloned() {
+        if taken(IDEAS.with(|m| m.borrow().contains_key(&id))) {
+            report.skipped.push((ExportEntity::Ideas, id));
+            continue;
+        }
+        report.ideas += 1;
+        if !write {
+            continue;
+        }
+        IDEAS_BY_OWNER.with(|o| o.borrow_mut().insert((idea.owner, id), ()));
+        index_fingerprint(id, fingerprint(&idea.title, &idea.description));
+        IDEAS.with(|m| m.borrow_mut().insert(id, idea));
+        record_change(EntityRef::Idea(id), ChangeKind::Created);
+    }
+    for doc in bundle.docs.iter().cloned() {
+        let id = doc.id;
+        if taken(DOCS.with(|m| m.borrow().contains_key(&id))) {
+            report.skipped.push((ExportEntity::Docs, id));
+            continue;
+        }
+        report.docs += 1;
+        if !write {
+            continue;
+        }
+        DOCS.with(|m| m.borrow_mut().insert(id, doc));
+        record_change(EntityRef::Doc(id), ChangeKind::Created);
+    }
+    for campaign in bundle.campaigns.iter().cloned() {
+        let id = campaign.id;
+        if taken(CAMPAIGNS.with(|m| m.borrow().contains_key(&id))) {
+            report.skipped.push((ExportEntity::Campaigns, id));
+            continue;
+        }
+        report.campaigns += 1;
+        if !write {
+            continue;
+        }
+        if let Some(owner) = campaign.owner {
+            CAMPAIGNS_BY_OWNER.with(|o| o.borrow_mut().insert((owner, id), ()));
+        }
+        let idea_owner = IDEAS.with(|m| m.borrow().get(&campaign.idea_id)).map(|idea| idea.owner);
+        if let (Some(partner), Some(external_ref)) = (idea_owner, &campaign.external_ref) {
+            syndication::bind_campaign_ref(&partner, external_ref, id);
+        }
+        CAMPAIGNS.with(|m| m.borrow_mut().insert(id, campaign));
+        record_change(EntityRef::Campaign(id), ChangeKind::Created);
+    }
+    for contribution in bundle.contributions.iter().cloned() {
+        let (id, campaign_id, contributor) = (contribution.id, contribution.campaign_id, contribution.contributor);
+        if taken(CONTRIBUTIONS.with(|m| m.borrow().contains_key(&id))) {
+            report.skipped.push((ExportEntity::Contributions, id));
+            continue;
+        }
+        report.contributions += 1;
+        if !write {
+            continue;
+        }
+        CONTRIBUTIONS.with(|m| m.borrow_mut().insert(id, contribution));
+        CAMPAIGN_CONTRIBUTIONS.with(|m| m.borrow_mut().insert((campaign_id, id), ()));
+        CONTRIBUTOR_CONTRIBUTIONS.with(|m| m.borrow_mut().insert((contributor, id), ()));
+        if let Some(campaign) = CAMPAIGNS.with(|m| m.borrow().get(&campaign_id)) {
+            badges::note_backer(campaign.idea_id, contributor);
+        }
+    }
+
+    let highest = |counter: u64, ids: &mut dyn Iterator<Item = u64>| ids.fold(counter, u64::max);
+    let counters = &bundle.counters;
+    report.counters = ExportCounters {
+        ideas: raise_counter(
+            &IDEA_COUNTER,
+            highest(counters.ideas, &mut bundle.ideas.iter().map(|(id, _)| *id)),
+            write,
+        ),
+        campaigns: raise_counter(
+            &CAMPAIGN_COUNTER,
+            highest(counters.campaigns, &mut bundle.campaigns.iter().map(|c| c.id)),
+            write,
+        ),
+        docs: raise_counter(&DOC_COUNTER, highest(counters.docs, &mut bundle.docs.iter().map(|d| d.id)), write),
+        contributions: raise_counter(
+            &CONTRIBUTION_COUNTER,
+            highest(counters.contributions, &mut bundle.contributions.iter().map(|c| c.id)),
+            write,
+        ),
+    };
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_campaign, test_idea};
+    use candid::Principal;
+
+    fn idea() -> Idea {
+        test_idea("Solar Kiosk", "Off-grid charging", "Energy")
+    }
+
+    fn campaign(id: u64, idea_id: u64) -> Campaign {
+        test_campaign(id, idea_id, 0)
+    }
+
+    fn doc(id: u64, idea_id: u64) -> Doc {
+        let (name, content_type) = ("deck.pdf".into(), "application/pdf".into());
+        Doc { id, idea_id, name, content_type, data: vec![], uploaded_at: 0 }
+    }
+
+    fn contribution(id: u64, campaign_id: u64) -> Contribution {
+        let contributor = Principal::anonymous();
+        Contribution {
+            id,
+            campaign_id,
+            contributor,
+            amount: 10,
+            timestamp: 0,
+            channel: None,
+            currency: None,
+            risks_acked_at: None,
+        }
+    }
+
+    #[test]
+    fn bundles_must_resolve_every_reference_in_themselves_or_the_store() {
+        let bundle = ExportBundle {
+            ideas: vec![(1, idea()), (2, idea())],
+            campaigns: vec![campaign(1, 2), campaign(2, 9)],
+            docs: vec![doc(1, 1), doc(2, 7)],
+            contributions: vec![contribution(1, 1), contribution(2, 5), contribution(2, 1)],
+            ..Default::default()
+        };
+        let problems = |issues: Vec<ImportIssue>| issues.into_iter().map(|i| (i.entity, i.id)).collect::<Vec<_>>();
+        assert_eq!(
+            problems(check_bundle(&bundle, |_| false, |_| false)),
+            [
+                (ExportEntity::Contributions, 2), // the id is used twice
+                (ExportEntity::Campaigns, 2),     // idea 9
+                (ExportEntity::Docs, 2),          // idea 7
+                (ExportEntity::Contributions, 2), // campaign 5
+            ]
+        );
+        let merged = check_bundle(&bundle, |id| [7, 9].contains(&id), |id| id == 5);
+        assert_eq!(problems(merged), [(ExportEntity::Contributions, 2)], "stored records resolve when merging");
+
+        let zero = ExportBundle { ideas: vec![(0, idea())], ..Default::default() };
+        assert_eq!(check_bundle(&zero, |_| false, |_| false)[0].message, "ids start at 1");
+    }
+
+    #[test]
+    fn dry_runs_report_the_import_without_writing() {
+        IDEAS.with(|m| m.borrow_mut().insert(1, idea()));
+        let bundle = ExportBundle {
+            ideas: vec![(1, idea()), (2, idea())],
+            campaigns: vec![campaign(4, 2)],
+            counters: ExportCounters { ideas: 2, campaigns: 4, ..Default::default() },
+            ..Default::default()
+        };
+
+        let merge = load(&bundle, false, false);
+        assert!(merge.dry_run);
+        assert_eq!((merge.ideas, merge.campaigns, merge.skipped), (1, 1, vec![(ExportEntity::Ideas, 1)]));
+        assert_eq!((merge.counters.ideas, merge.counters.campaigns), (2, 4));
+        let replace = load(&bundle, true, false);
+        assert_eq!((replace.ideas, replace.skipped.len()), (2, 0), "ReplaceAll clears the stores first");
+
+        assert_eq!(IDEAS.with(|m| m.borrow().len()), 1);
+        assert!(CAMPAIGNS.with(|m| m.borrow().is_empty()));
+        assert_eq!((counters().ideas, counters().campaigns), (0, 0), "counters are only raised live");
+    }
+
+    #[test]
+    fn replace_tokens_are_bound_to_the_exact_bundle() {
+        let bundle = ExportBundle { ideas: vec![(1, idea())], ..Default::default() };
+        let other_idea = test_idea("Wind Farm", "Turbines", "Energy");
+        let renamed = ExportBundle { ideas: vec![(1, other_idea)], ..bundle.clone() };
+        assert_eq!(bundle_digest(&bundle), bundle_digest(&bundle.clone()));
+        assert_ne!(bundle_digest(&bundle), bundle_digest(&renamed));
+    }
+}
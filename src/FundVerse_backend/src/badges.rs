@@ -0,0 +1,196 @@
+//! Credibility badges on ideas, from lifetime aggregates across all of an idea's campaigns.
+//!
+//! Total raised is the idea's `current_funding`, less any refunds backers have claimed. Unique
+//! backers are maintained per idea as pledges are recorded (ICP pledges forwarded by Fund_Flow
+//! carry no backer and aren't counted). Successful campaigns are counted on read, so campaigns
+//! that ended with the goal met count whether or not anyone has finalized them yet.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{
+    ensure_controller, get_contributions_by_campaign, get_idea, is_successful, now_secs, Idea, Memory, CAMPAIGNS,
+    MEMORY_MANAGER,
+};
+
+const BACKFILL_CAMPAIGNS_PER_CALL: usize = 20;
+
+thread_local! {
+    // (idea_id, backer) -> () for everyone who ever pledged to one of the idea's campaigns
+    static IDEA_BACKERS: RefCell<StableBTreeMap<(u64, Principal), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(37)))
+        )
+    );
+
+    static IDEA_BACKER_COUNTS: RefCell<StableBTreeMap<u64, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(38)))
+        )
+    );
+
+    static BADGE_THRESHOLDS: RefCell<StableCell<BadgeThresholds, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(39))),
+            BadgeThresholds::default(),
+        ).expect("init badge thresholds")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct BadgeThresholds {
+    pub total_raised: u64,           // "Raised 10k+"
+    pub successful_campaigns: u64,   // "Fully funded twice"
+    pub lifetime_backers: u64,       // "100+ backers lifetime"
+}
+
+impl Default for BadgeThresholds {
+    fn default() -> Self {
+        BadgeThresholds { total_raised: 10_000, successful_campaigns: 2, lifetime_backers: 100 }
+    }
+}
+
+impl Storable for BadgeThresholds {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode BadgeThresholds"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode BadgeThresholds")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Each badge carries the threshold it was earned against, so the UI can label it.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum IdeaBadge {
+    Raised(u64),
+    FullyFunded(u64),
+    Backers(u64),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct IdeaStats {
+    pub total_raised: u64,
+    pub successful_campaigns: u64,
+    pub lifetime_backers: u64,
+    pub badges: Vec<IdeaBadge>,
+}
+
+/// Count a backer once per idea. Called whenever a contribution is recorded.
+pub(crate) fn note_backer(idea_id: u64, backer: Principal) {
+    let is_new = IDEA_BACKERS.with(|b| b.borrow_mut().insert((idea_id, backer), ()).is_none());
+    if is_new {
+        IDEA_BACKER_COUNTS.with(|counts| {
+            let mut counts = counts.borrow_mut();
+            let n = counts.get(&idea_id).unwrap_or(0);
+            counts.insert(idea_id, n + 1);
+        });
+    }
+}
+
+pub(crate) fn forget_idea(idea_id: u64) {
+    let backers: Vec<(u64, Principal)> = IDEA_BACKERS.with(|b| {
+        b.borrow()
+            .range((idea_id, Principal::management_canister())..)
+            .take_while(|((id, _), _)| *id == idea_id)
+            .map(|(key, _)| key)
+            .collect()
+    });
+    IDEA_BACKERS.with(|b| {
+        let mut b = b.borrow_mut();
+        for key in backers {
+            b.remove(&key);
+        }
+    });
+    IDEA_BACKER_COUNTS.with(|counts| counts.borrow_mut().remove(&idea_id));
+}
+
+/// Campaigns that reached their goal, per idea, in one pass over all campaigns.
+pub(crate) fn successful_campaigns_by_idea() -> BTreeMap<u64, u64> {
+    let now = now_secs();
+    let mut counts = BTreeMap::new();
+    CAMPAIGNS.with(|store| {
+        for (_, c) in store.borrow().iter().filter(|(_, c)| is_successful(c, now)) {
+            *counts.entry(c.idea_id).or_insert(0) += 1;
+        }
+    });
+    counts
+}
+
+fn badges_for(total_raised: u64, successful_campaigns: u64, lifetime_backers: u64) -> Vec<IdeaBadge> {
+    let t = BADGE_THRESHOLDS.with(|t| t.borrow().get().clone());
+    let mut badges = Vec::new();
+    if total_raised >= t.total_raised {
+        badges.push(IdeaBadge::Raised(t.total_raised));
+    }
+    if successful_campaigns >= t.successful_campaigns {
+        badges.push(IdeaBadge::FullyFunded(t.successful_campaigns));
+    }
+    if lifetime_backers >= t.lifetime_backers {
+        badges.push(IdeaBadge::Backers(t.lifetime_backers));
+    }
+    badges
+}
+
+pub(crate) fn idea_stats(idea_id: u64, idea: &Idea, successful_campaigns: u64) -> IdeaStats {
+    let lifetime_backers = IDEA_BACKER_COUNTS.with(|c| c.borrow().get(&idea_id).unwrap_or(0));
+    IdeaStats {
+        total_raised: idea.current_funding,
+        successful_campaigns,
+        lifetime_backers,
+        badges: badges_for(idea.current_funding, successful_campaigns, lifetime_backers),
+    }
+}
+
+/// Lifetime aggregates and badges for one idea (e.g. its creator page).
+#[query]
+fn get_idea_stats(idea_id: u64) -> Option<IdeaStats> {
+    let idea = get_idea(idea_id)?;
+    let successful = successful_campaigns_by_idea().get(&idea_id).copied().unwrap_or(0);
+    Some(idea_stats(idea_id, &idea, successful))
+}
+
+/// Change the badge thresholds (controllers only). Badges are computed on read, so this applies
+/// to every idea at once.
+#[update]
+fn set_badge_thresholds(thresholds: BadgeThresholds) -> Result<(), String> {
+    ensure_controller()?;
+    if thresholds.total_raised == 0 || thresholds.successful_campaigns == 0 || thresholds.lifetime_backers == 0 {
+        return Err("badge thresholds must be positive".into());
+    }
+    BADGE_THRESHOLDS
+        .with(|t| t.borrow_mut().set(thresholds))
+        .map(|_| ())
+        .map_err(|e| format!("failed to store badge thresholds: {:?}", e))
+}
+
+/// Index the backers of up to 20 campaigns from `from_campaign_id` on, covering contributions
+/// recorded before backers were tracked (controllers only). Safe to repeat; pass the returned id
+/// back until it is None.
+#[update]
+fn backfill_idea_backers(from_campaign_id: u64) -> Result<Option<u64>, String> {
+    ensure_controller()?;
+    let batch: Vec<(u64, u64)> = CAMPAIGNS.with(|store| {
+        store
+            .borrow()
+            .range(from_campaign_id..)
+            .take(BACKFILL_CAMPAIGNS_PER_CALL + 1)
+            .map(|(id, c)| (id, c.idea_id))
+            .collect()
+    });
+    let next = batch.get(BACKFILL_CAMPAIGNS_PER_CALL).map(|(id, _)| *id);
+    for (campaign_id, idea_id) in batch.into_iter().take(BACKFILL_CAMPAIGNS_PER_CALL) {
+        for c in get_contributions_by_campaign(campaign_id) {
+            note_backer(idea_id, c.contributor);
+        }
+    }
+    Ok(next)
+}
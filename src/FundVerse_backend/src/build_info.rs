@@ -0,0 +1,68 @@
+//! Build metadata so auditors can check the running wasm against a reproducible build.
+//!
+//! `GIT_COMMIT` and `SOURCE_DATE_EPOCH` are read from the build environment (unset in local
+//! builds). The module hash is not known at compile time: it is fetched from the management
+//! canister by a one-shot timer after install and every upgrade, because queries can't call it.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use candid::{CandidType, Deserialize};
+use ic_cdk::api::management_canister::main::{canister_info, CanisterInfoRequest};
+use ic_cdk_macros::query;
+
+/// Bump whenever a stored record's schema changes (ideas are on their fourth: V0-V2, current).
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Cargo features compiled into this build. The crate defines none yet.
+const ENABLED_FEATURES: &[&str] = &[];
+
+thread_local! {
+    // (sha256 hex, fetched_at ns). Heap-only: refreshed after every upgrade anyway.
+    static MODULE_HASH: RefCell<Option<(String, u64)>> = const { RefCell::new(None) };
+}
+
+#[derive(CandidType, Deserialize, serde::Serialize, Clone, Debug)]
+pub struct BuildInfo {
+    pub crate_version: String,
+    pub git_commit: Option<String>,
+    pub build_timestamp: Option<u64>, // SOURCE_DATE_EPOCH, seconds
+    pub module_hash: Option<String>,  // sha256 of the installed wasm, hex
+    pub module_hash_fetched_at: Option<u64>, // ns
+    pub schema_version: u32,
+    pub features: Vec<String>,
+    pub interface_hash: String, // pin this and pass it to check_client_compatibility
+}
+
+/// Fetch the module hash shortly after install/upgrade (calls aren't allowed from those hooks).
+pub(crate) fn schedule_module_hash_refresh() {
+    ic_cdk_timers::set_timer(Duration::ZERO, || ic_cdk::spawn(refresh_module_hash()));
+}
+
+async fn refresh_module_hash() {
+    let request = CanisterInfoRequest { canister_id: ic_cdk::id(), num_requested_changes: None };
+    match canister_info(request).await {
+        Ok((info,)) => {
+            let hash = info.module_hash.map(|h| h.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+            MODULE_HASH.with(|m| *m.borrow_mut() = hash.map(|h| (h, ic_cdk::api::time())));
+        }
+        Err((code, msg)) => ic_cdk::println!("module hash refresh failed: {:?} {}", code, msg),
+    }
+}
+
+#[query]
+pub fn get_build_info() -> BuildInfo {
+    let (module_hash, module_hash_fetched_at) = MODULE_HASH
+        .with(|m| m.borrow().clone())
+        .map_or((None, None), |(hash, at)| (Some(hash), Some(at)));
+    BuildInfo {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: option_env!("GIT_COMMIT").map(str::to_string),
+        build_timestamp: option_env!("SOURCE_DATE_EPOCH").and_then(|s| s.parse().ok()),
+        module_hash,
+        module_hash_fetched_at,
+        schema_version: SCHEMA_VERSION,
+        features: ENABLED_FEATURES.iter().map(|f| f.to_string()).collect(),
+        interface_hash: super::interface::current_hash(),
+    }
+}
@@ -0,0 +1,197 @@
+//! A campaign page's feed: progress updates from the founder and questions from everyone else.
+//!
+//! Updates and comments are keyed by (campaign_id, id), so a campaign's feed is one range scan and
+//! can be dropped as a whole. Campaigns are never deleted; a takedown drops the feed with the rest
+//! of the public content.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{
+    ensure_campaign_owner, get_campaign, lifecycle, not_found, now_secs, CampaignLifecycle, EntityRef, Memory,
+    MEMORY_MANAGER, MAX_COMMENT_PAGE,
+};
+
+pub(crate) const MAX_UPDATE_TITLE_LEN: usize = 200; // chars
+pub(crate) const MAX_UPDATE_BODY_LEN: usize = 10_000; // bytes
+pub(crate) const MAX_CAMPAIGN_COMMENT_LEN: usize = 1_000; // bytes
+
+thread_local! {
+    static CAMPAIGN_UPDATES: RefCell<StableBTreeMap<(u64, u64), CampaignUpdate, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(53)))
+        )
+    );
+
+    static CAMPAIGN_COMMENTS: RefCell<StableBTreeMap<(u64, u64), CampaignComment, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(54)))
+        )
+    );
+
+    // One id sequence for updates and comments
+    static FEED_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(55))),
+            0,
+        ).expect("init campaign feed counter")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignUpdate {
+    pub id: u64,
+    pub campaign_id: u64,
+    pub author: Principal,
+    pub title: String,
+    pub body: String,
+    pub posted_at: u64, // ns
+}
+
+impl Storable for CampaignUpdate {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode CampaignUpdate"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode CampaignUpdate")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A question or remark on a campaign; flat, unlike idea discussion threads.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignComment {
+    pub id: u64,
+    pub campaign_id: u64,
+    pub author: Principal,
+    pub text: String,
+    pub created_at: u64, // ns
+}
+
+impl Storable for CampaignComment {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode CampaignComment"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode CampaignComment")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+fn next_id() -> u64 {
+    FEED_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store campaign feed counter");
+        id
+    })
+}
+
+/// Posting is open on any campaign that exists and wasn't taken down, ended ones included.
+fn ensure_open_feed(campaign_id: u64) -> Result<(), String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    if lifecycle(&campaign, now_secs()) == CampaignLifecycle::TakenDown {
+        return Err("campaign was taken down".into());
+    }
+    Ok(())
+}
+
+fn signed_in_caller() -> Result<Principal, String> {
+    let caller = ic_cdk::caller();
+    if caller == Principal::anonymous() {
+        return Err("sign in to post".into());
+    }
+    Ok(caller)
+}
+
+fn validate_update(title: &str, body: &str) -> Result<(), String> {
+    if title.is_empty() || title.chars().count() > MAX_UPDATE_TITLE_LEN {
+        return Err(format!("title must be 1-{} characters", MAX_UPDATE_TITLE_LEN));
+    }
+    if body.is_empty() || body.len() > MAX_UPDATE_BODY_LEN {
+        return Err(format!("body must be 1-{} bytes", MAX_UPDATE_BODY_LEN));
+    }
+    Ok(())
+}
+
+/// Drop a campaign's updates and comments.
+pub(crate) fn forget_campaign(campaign_id: u64) {
+    let range = (campaign_id, 0)..=(campaign_id, u64::MAX);
+    let updates: Vec<(u64, u64)> = CAMPAIGN_UPDATES.with(|u| u.borrow().range(range.clone()).map(|(k, _)| k).collect());
+    CAMPAIGN_UPDATES.with(|u| {
+        let mut u = u.borrow_mut();
+        for key in &updates {
+            u.remove(key);
+        }
+    });
+    let comments: Vec<(u64, u64)> = CAMPAIGN_COMMENTS.with(|c| c.borrow().range(range).map(|(k, _)| k).collect());
+    CAMPAIGN_COMMENTS.with(|c| {
+        let mut c = c.borrow_mut();
+        for key in &comments {
+            c.remove(key);
+        }
+    });
+}
+
+/// Post a progress update (the campaign's owner or controllers). Returns its id.
+#[update]
+fn post_campaign_update(campaign_id: u64, title: String, body: String) -> Result<u64, String> {
+    let author = signed_in_caller()?;
+    ensure_campaign_owner(campaign_id)?;
+    ensure_open_feed(campaign_id)?;
+    let (title, body) = (title.trim().to_string(), body.trim().to_string());
+    validate_update(&title, &body)?;
+    let id = next_id();
+    let update = CampaignUpdate { id, campaign_id, author, title, body, posted_at: ic_cdk::api::time() };
+    CAMPAIGN_UPDATES.with(|u| u.borrow_mut().insert((campaign_id, id), update));
+    Ok(id)
+}
+
+/// A campaign's updates, newest first.
+#[query]
+fn get_campaign_updates(campaign_id: u64) -> Vec<CampaignUpdate> {
+    CAMPAIGN_UPDATES.with(|u| {
+        u.borrow()
+            .range((campaign_id, 0)..=(campaign_id, u64::MAX))
+            .rev()
+            .map(|(_, update)| update)
+            .collect()
+    })
+}
+
+/// Comment on a campaign (any signed-in principal). Returns the comment id.
+#[update]
+fn post_campaign_comment(campaign_id: u64, text: String) -> Result<u64, String> {
+    let author = signed_in_caller()?;
+    ensure_open_feed(campaign_id)?;
+    let text = text.trim().to_string();
+    if text.is_empty() || text.len() > MAX_CAMPAIGN_COMMENT_LEN {
+        return Err(format!("comment must be 1-{} bytes", MAX_CAMPAIGN_COMMENT_LEN));
+    }
+    let id = next_id();
+    let comment = CampaignComment { id, campaign_id, author, text, created_at: ic_cdk::api::time() };
+    CAMPAIGN_COMMENTS.with(|c| c.borrow_mut().insert((campaign_id, id), comment));
+    Ok(id)
+}
+
+/// Comments on a campaign, oldest first; `limit` is capped like idea comment pages.
+#[query]
+fn get_campaign_comments(campaign_id: u64, offset: u64, limit: u64) -> Vec<CampaignComment> {
+    CAMPAIGN_COMMENTS.with(|c| {
+        c.borrow()
+            .range((campaign_id, 0)..=(campaign_id, u64::MAX))
+            .skip(offset as usize)
+            .take(limit.min(MAX_COMMENT_PAGE) as usize)
+            .map(|(_, comment)| comment)
+            .collect()
+    })
+}
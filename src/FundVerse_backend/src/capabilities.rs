@@ -0,0 +1,98 @@
+//! Every wire-facing limit in one query, so frontends can size uploads and forms up front
+//! instead of finding the limits through rejected calls. Values are read from the same
+//! constants the endpoints enforce.
+
+use candid::{CandidType, Deserialize};
+use ic_cdk_macros::query;
+
+use super::{
+    campaign_feed, categories, co_marketing, milestones, profiles, sync, syndication, takedown, FieldId, IDEA_TEXT_LIMITS, MAX_CAMPAIGN_TEASER_LEN, MAX_COMMENT_LEN, MAX_COMMENT_PAGE,
+    MAX_BASKET_ITEMS, MAX_DISPLAY_TITLE_LEN, MAX_DOC_CHUNKS, MAX_DOC_CHUNK_BYTES, MAX_DOC_UPLOAD_BYTES, MAX_LISTING_PAGE, MAX_PAUSE_REASON_LEN,
+    MAX_REJECTION_REASON_LEN, MAX_RESOLVE_REFS, MAX_RISKS_LEN, MAX_SUMMARY_PAGE,
+};
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthUnit {
+    Bytes, // UTF-8 bytes
+    Chars, // Unicode scalar values
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TextLimit {
+    pub field: String, // "<entity>.<field>", e.g. "idea.title"
+    pub max: u64,
+    pub unit: LengthUnit,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Capabilities {
+    pub max_doc_bytes: u64,       // total size of a chunked upload
+    pub max_doc_chunk_bytes: u64, // one upload_doc_chunk, or a whole single-call upload_doc
+    pub max_doc_chunks: u32,      // chunk indexes run 0..max_doc_chunks
+    pub max_refs_per_batch: u64,  // resolve_refs drops anything beyond this
+    pub max_basket_items: u64,    // contribute_many
+    pub max_summary_page: u64,
+    pub max_listing_page: u64, // the *_paged listings
+    pub max_comment_page: u64,
+    pub max_change_batch: u64,
+    pub text_limits: Vec<TextLimit>,
+}
+
+fn field_name(field: FieldId) -> &'static str {
+    match field {
+        FieldId::Title => "idea.title",
+        FieldId::Description => "idea.description",
+        FieldId::LegalEntity => "idea.legal_entity",
+        FieldId::ContactInfo => "idea.contact_info",
+        FieldId::Category => "idea.category",
+        FieldId::FundingGoal => "idea.funding_goal",
+        FieldId::DisplayName => "profile.display_name",
+        FieldId::Email => "profile.email",
+        FieldId::Bio => "profile.bio",
+    }
+}
+
+fn limit(field: &str, max: usize, unit: LengthUnit) -> TextLimit {
+    TextLimit { field: field.to_string(), max: max as u64, unit }
+}
+
+#[query]
+fn get_capabilities() -> Capabilities {
+    let mut text_limits: Vec<TextLimit> = IDEA_TEXT_LIMITS
+        .iter()
+        .map(|(field, max)| limit(field_name(*field), *max, LengthUnit::Bytes))
+        .collect();
+    text_limits.extend([
+        limit("idea.rejection_reason", MAX_REJECTION_REASON_LEN, LengthUnit::Bytes),
+        limit("campaign.display_title", MAX_DISPLAY_TITLE_LEN, LengthUnit::Chars),
+        limit("campaign.teaser", MAX_CAMPAIGN_TEASER_LEN, LengthUnit::Chars),
+        limit("campaign.risks", MAX_RISKS_LEN, LengthUnit::Chars),
+        limit("campaign.pause_reason", MAX_PAUSE_REASON_LEN, LengthUnit::Chars),
+        limit("comment.text", MAX_COMMENT_LEN, LengthUnit::Bytes),
+        limit("campaign_update.title", campaign_feed::MAX_UPDATE_TITLE_LEN, LengthUnit::Chars),
+        limit("campaign_update.body", campaign_feed::MAX_UPDATE_BODY_LEN, LengthUnit::Bytes),
+        limit("campaign_comment.text", campaign_feed::MAX_CAMPAIGN_COMMENT_LEN, LengthUnit::Bytes),
+        limit("syndication.external_id", syndication::MAX_EXTERNAL_ID_LEN, LengthUnit::Bytes),
+        limit("partner.name", co_marketing::MAX_PARTNER_NAME_LEN, LengthUnit::Chars),
+        limit("partner.url", co_marketing::MAX_PARTNER_URL_LEN, LengthUnit::Bytes),
+        limit("takedown.reason", takedown::MAX_TAKEDOWN_REASON_LEN, LengthUnit::Bytes),
+        limit("profile.display_name", profiles::MAX_DISPLAY_NAME_LEN, LengthUnit::Chars),
+        limit("profile.email", profiles::MAX_EMAIL_LEN, LengthUnit::Bytes),
+        limit("profile.bio", profiles::MAX_BIO_LEN, LengthUnit::Chars),
+        limit("milestone.title", milestones::MAX_MILESTONE_TITLE_LEN, LengthUnit::Chars),
+        limit("milestone.description", milestones::MAX_MILESTONE_DESCRIPTION_LEN, LengthUnit::Chars),
+        limit("category.display_name", categories::MAX_CATEGORY_NAME_LEN, LengthUnit::Chars),
+    ]);
+    Capabilities {
+        max_doc_bytes: MAX_DOC_UPLOAD_BYTES,
+        max_doc_chunk_bytes: MAX_DOC_CHUNK_BYTES,
+        max_doc_chunks: MAX_DOC_CHUNKS,
+        max_refs_per_batch: MAX_RESOLVE_REFS as u64,
+        max_basket_items: MAX_BASKET_ITEMS as u64,
+        max_summary_page: MAX_SUMMARY_PAGE,
+        max_listing_page: MAX_LISTING_PAGE,
+        max_comment_page: MAX_COMMENT_PAGE,
+        max_change_batch: sync::MAX_BATCH as u64,
+        text_limits,
+    }
+}
@@ -0,0 +1,229 @@
+//! The category registry: the categories ideas may be filed under, managed by admins.
+//!
+//! `create_idea`, `update_idea` and syndicated ideas name a category by slug, matched without
+//! regard to case, and the idea stores the canonical slug, so filters never miss an idea over
+//! casing or naming drift. A fresh canister starts with the frontend's categories. Slugs never
+//! change: renaming only changes the display name, and a deactivated category can't be chosen for
+//! new ideas but stays valid on the ideas already filed under it. Ideas from before the registry
+//! keep decoding whatever their category; `get_ideas_with_invalid_category` lists them for fixing.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{
+    ensure_admin, idea_text_limit, paginate, FieldError, FieldId, Idea, PagedResult, RuleId, IDEAS, Memory,
+    MEMORY_MANAGER,
+};
+
+pub(crate) const MAX_CATEGORIES: usize = 100;
+pub(crate) const MAX_CATEGORY_NAME_LEN: usize = 50; // chars
+
+// Seeded on a canister that has no categories yet: (slug, display name)
+const DEFAULT_CATEGORIES: [(&str, &str); 10] = [
+    ("technology", "Technology"),
+    ("healthcare", "Healthcare"),
+    ("education", "Education"),
+    ("environment", "Environment"),
+    ("arts", "Arts"),
+    ("sports", "Sports"),
+    ("food", "Food"),
+    ("travel", "Travel"),
+    ("finance", "Finance"),
+    ("other", "Other"),
+];
+
+thread_local! {
+    static CATEGORIES: RefCell<StableBTreeMap<u64, Category, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(73)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Category {
+    pub id: u64,
+    pub slug: String, // lowercase letters, digits and '-'; what ideas store
+    pub display_name: String,
+    pub active: bool, // inactive: kept on existing ideas, not offered for new ones
+}
+
+impl Storable for Category {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Category"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode Category")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+fn all() -> Vec<Category> {
+    CATEGORIES.with(|c| c.borrow().iter().map(|(_, category)| category).collect())
+}
+
+fn find(slug: &str) -> Option<Category> {
+    let slug = slug.trim();
+    all().into_iter().find(|c| c.slug.eq_ignore_ascii_case(slug))
+}
+
+fn insert(slug: String, display_name: String) -> Category {
+    let id = CATEGORIES.with(|c| c.borrow().last_key_value().map_or(0, |(id, _)| id + 1));
+    let category = Category { id, slug, display_name, active: true };
+    CATEGORIES.with(|c| c.borrow_mut().insert(id, category.clone()));
+    category
+}
+
+/// Fill a registry that has never had categories with the defaults.
+pub(crate) fn seed_defaults() {
+    if CATEGORIES.with(|c| c.borrow().is_empty()) {
+        for (slug, display_name) in DEFAULT_CATEGORIES {
+            insert(slug.to_string(), display_name.to_string());
+        }
+    }
+}
+
+/// The canonical slug of the active category `input` names, or the Category error to report.
+pub(crate) fn canonical_slug(input: &str) -> Result<String, FieldError> {
+    match find(input) {
+        Some(category) if category.active => Ok(category.slug),
+        _ => Err(FieldError { field: FieldId::Category, rule: RuleId::NotListed, limit: None, actual: None }),
+    }
+}
+
+/// Replace `category` with its canonical slug, unless `errors` already has a Category problem or
+/// the category isn't listed, which is then added to `errors`.
+pub(crate) fn canonicalize(category: &mut String, errors: &mut Vec<FieldError>) {
+    if errors.iter().any(|e| e.field == FieldId::Category) {
+        return;
+    }
+    match canonical_slug(category) {
+        Ok(slug) => *category = slug,
+        Err(e) => errors.push(e),
+    }
+}
+
+fn validate_slug(slug: &str) -> Result<(), String> {
+    let max = idea_text_limit(FieldId::Category);
+    if slug.is_empty() || slug.len() > max {
+        return Err(format!("slug must be 1-{} characters", max));
+    }
+    if !slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        return Err("slug may only use letters, digits and -".into());
+    }
+    Ok(())
+}
+
+fn validate_display_name(display_name: &str) -> Result<(), String> {
+    if display_name.is_empty() || display_name.chars().count() > MAX_CATEGORY_NAME_LEN {
+        return Err(format!("display_name must be 1-{} characters", MAX_CATEGORY_NAME_LEN));
+    }
+    Ok(())
+}
+
+fn stored(category_id: u64) -> Result<Category, String> {
+    CATEGORIES
+        .with(|c| c.borrow().get(&category_id))
+        .ok_or_else(|| format!("category {} not found", category_id))
+}
+
+/// Add a category (admins). The slug is lowercased; it must be new, including among deactivated
+/// categories.
+#[update]
+fn add_category(slug: String, display_name: String) -> Result<Category, String> {
+    ensure_admin()?;
+    let (slug, display_name) = (slug.trim().to_ascii_lowercase(), display_name.trim().to_string());
+    validate_slug(&slug)?;
+    validate_display_name(&display_name)?;
+    if find(&slug).is_some() {
+        return Err(format!("category '{}' already exists", slug));
+    }
+    if CATEGORIES.with(|c| c.borrow().len()) as usize >= MAX_CATEGORIES {
+        return Err(format!("at most {} categories", MAX_CATEGORIES));
+    }
+    Ok(insert(slug, display_name))
+}
+
+/// Change a category's display name (admins). The slug, and so every idea filed under it, stays.
+#[update]
+fn rename_category(category_id: u64, display_name: String) -> Result<Category, String> {
+    ensure_admin()?;
+    let display_name = display_name.trim().to_string();
+    validate_display_name(&display_name)?;
+    let category = Category { display_name, ..stored(category_id)? };
+    CATEGORIES.with(|c| c.borrow_mut().insert(category_id, category.clone()));
+    Ok(category)
+}
+
+/// Stop offering a category for new ideas (admins). Ideas already filed under it keep it.
+#[update]
+fn deactivate_category(category_id: u64) -> Result<Category, String> {
+    ensure_admin()?;
+    let category = Category { active: false, ..stored(category_id)? };
+    CATEGORIES.with(|c| c.borrow_mut().insert(category_id, category.clone()));
+    Ok(category)
+}
+
+/// Every category by id, deactivated ones too so existing ideas can show their display name;
+/// offer only the active ones in forms.
+#[query]
+fn list_categories() -> Vec<Category> {
+    all()
+}
+
+/// Whether a stored category is a registered slug, active or not, exactly as stored.
+fn is_registered(category: &str, slugs: &[String]) -> bool {
+    slugs.iter().any(|slug| slug == category)
+}
+
+/// Ideas whose category isn't a registered slug, e.g. free text from before the registry or a
+/// differently cased name (admins). Fix them with `update_idea` or `migrate_category`. Drafts
+/// are left out: their category is only checked when they are submitted.
+#[query]
+fn get_ideas_with_invalid_category(offset: u64, limit: u64) -> Result<PagedResult<(u64, Idea)>, String> {
+    ensure_admin()?;
+    let slugs: Vec<String> = all().into_iter().map(|c| c.slug).collect();
+    let ideas = IDEAS.with(|ideas| {
+        ideas.borrow().iter().filter(|(_, idea)| !idea.is_draft() && !is_registered(&idea.category, &slugs)).collect()
+    });
+    Ok(paginate(ideas, offset, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categories_match_any_case_and_deactivated_ones_only_stay_on_existing_ideas() {
+        seed_defaults();
+        seed_defaults(); // only once
+        assert_eq!(all().len(), DEFAULT_CATEGORIES.len());
+        assert_eq!(canonical_slug(" Healthcare "), Ok("healthcare".to_string()));
+        assert_eq!(canonical_slug("Gaming").unwrap_err().rule, RuleId::NotListed);
+
+        let environment = find("environment").unwrap();
+        CATEGORIES.with(|c| c.borrow_mut().insert(environment.id, Category { active: false, ..environment }));
+        assert!(canonical_slug("Environment").is_err(), "not offered for new ideas");
+        let slugs: Vec<String> = all().into_iter().map(|c| c.slug).collect();
+        assert!(is_registered("environment", &slugs), "still valid where it's used");
+        assert!(!is_registered("Environment", &slugs), "stored slugs are canonical");
+
+        let (mut category, mut errors) = ("FOOD".to_string(), vec![]);
+        canonicalize(&mut category, &mut errors);
+        assert_eq!((category.as_str(), errors.len()), ("food", 0));
+        let mut category = "Gaming".to_string();
+        canonicalize(&mut category, &mut errors);
+        assert_eq!((category.as_str(), errors[0].rule), ("Gaming", RuleId::NotListed));
+
+        assert!(validate_slug("urban-farming2").is_ok());
+        assert!(validate_slug("").is_err() && validate_slug("Arts & Crafts").is_err());
+        assert!(validate_slug(&"a".repeat(idea_text_limit(FieldId::Category) + 1)).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Coarse attribution of pledges to the channel they came through.
+//!
+//! Callers may say they pledge from the web app or the mobile app. `Embed` and `PartnerApi` are
+//! only ever set by the canister: pledges from a registered syndication partner are tagged
+//! `PartnerApi` whatever they claim, and no caller can claim either one. The embed widget is
+//! read-only for now, so nothing is tagged `Embed` yet; the variant is reserved for its pledge
+//! path. Pledges recorded before channels were kept count as `Unspecified`. Amounts are summed per
+//! currency, never across currencies.
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk_macros::query;
+
+use super::{
+    ensure_admin, ensure_campaign_owner, get_campaign, get_contributions_by_campaign, not_found, syndication,
+    Contribution, Currency, EntityRef, CAMPAIGNS,
+};
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Channel {
+    #[default]
+    Unspecified,
+    Web,
+    Embed,      // implicit only
+    PartnerApi, // implicit only
+    MobileApp,
+}
+
+impl Channel {
+    const ALL: [Channel; 5] =
+        [Channel::Unspecified, Channel::Web, Channel::Embed, Channel::PartnerApi, Channel::MobileApp];
+
+    fn is_implicit(self) -> bool {
+        matches!(self, Channel::Embed | Channel::PartnerApi)
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub channel: Channel,
+    pub currency: Currency,
+    pub contributions: u64,
+    pub amount: u64,
+}
+
+/// The channel a candid call's pledges are recorded under. `is_partner` is whether the caller is
+/// a registered syndication partner.
+pub(crate) fn resolve(is_partner: bool, claimed: Option<Channel>) -> Result<Channel, String> {
+    if is_partner {
+        return Ok(Channel::PartnerApi);
+    }
+    match claimed.unwrap_or_default() {
+        channel if channel.is_implicit() => {
+            Err(format!("the {:?} channel is set by the canister, not by callers", channel))
+        }
+        channel => Ok(channel),
+    }
+}
+
+pub(crate) fn channel_for(caller: Principal, claimed: Option<Channel>) -> Result<Channel, String> {
+    resolve(syndication::is_partner(&caller), claimed)
+}
+
+/// Count and sum contributions per currency and channel: for each of `currencies` in turn, one row
+/// per channel in declaration order. Pledges in other currencies are left out.
+pub(crate) fn tally<'a>(
+    currencies: &[Currency],
+    contributions: impl IntoIterator<Item = &'a Contribution>,
+) -> Vec<ChannelStats> {
+    let mut stats: Vec<ChannelStats> = currencies
+        .iter()
+        .flat_map(|&currency| {
+            Channel::ALL.iter().map(move |&channel| ChannelStats { channel, currency, contributions: 0, amount: 0 })
+        })
+        .collect();
+    for c in contributions {
+        let currency = c.currency.unwrap_or_default();
+        let Some(block) = currencies.iter().position(|&listed| listed == currency) else { continue };
+        // ALL is in declaration order
+        let row = &mut stats[block * Channel::ALL.len() + c.channel.unwrap_or_default() as usize];
+        row.contributions += 1;
+        row.amount = row.amount.saturating_add(c.amount);
+    }
+    stats
+}
+
+/// Pledges to one campaign by channel, in its currency (the campaign's owner or controllers).
+#[query]
+fn get_campaign_channel_stats(campaign_id: u64) -> Result<Vec<ChannelStats>, String> {
+    ensure_campaign_owner(campaign_id)?;
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    Ok(tally(&[campaign.currency()], &get_contributions_by_campaign(campaign_id)))
+}
+
+/// Pledges across the platform by channel, for every currency (admins). Reads every contribution,
+/// archived ones included.
+#[query]
+fn get_platform_channel_stats() -> Result<Vec<ChannelStats>, String> {
+    ensure_admin()?;
+    let ids: Vec<u64> = CAMPAIGNS.with(|store| store.borrow().iter().map(|(id, _)| id).collect());
+    let contributions: Vec<Contribution> = ids.into_iter().flat_map(get_contributions_by_campaign).collect();
+    Ok(tally(&Currency::ALL, &contributions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callers_pick_web_or_mobile_but_never_an_implicit_channel() {
+        assert_eq!(resolve(false, None), Ok(Channel::Unspecified));
+        assert_eq!(resolve(false, Some(Channel::Web)), Ok(Channel::Web));
+        assert_eq!(resolve(false, Some(Channel::MobileApp)), Ok(Channel::MobileApp));
+        assert!(resolve(false, Some(Channel::Embed)).unwrap_err().contains("Embed"));
+        assert!(resolve(false, Some(Channel::PartnerApi)).is_err());
+        for claimed in [None, Some(Channel::Web), Some(Channel::Embed)] {
+            assert_eq!(resolve(true, claimed), Ok(Channel::PartnerApi), "partners can't tag anything else");
+        }
+    }
+
+    #[test]
+    fn tally_has_a_row_per_channel_and_counts_untagged_pledges_as_unspecified() {
+        let pledge = |amount, channel, currency| Contribution {
+            id: 0,
+            campaign_id: 1,
+            contributor: Principal::anonymous(),
+            amount,
+            timestamp: 0,
+            channel,
+            currency,
+            risks_acked_at: None,
+        };
+        let pledges = [
+            pledge(5, None, None),
+            pledge(7, Some(Channel::Web), Some(Currency::Icp)),
+            pledge(u64::MAX, Some(Channel::Web), None),
+            pledge(9, Some(Channel::Web), Some(Currency::CkBtc)),
+        ];
+        let stats = tally(&[Currency::Icp], &pledges);
+        let rows: Vec<(Channel, u64, u64)> = stats.iter().map(|s| (s.channel, s.contributions, s.amount)).collect();
+        assert_eq!(
+            rows,
+            [
+                (Channel::Unspecified, 1, 5),
+                (Channel::Web, 2, u64::MAX),
+                (Channel::Embed, 0, 0),
+                (Channel::PartnerApi, 0, 0),
+                (Channel::MobileApp, 0, 0),
+            ]
+        );
+
+        let stats = tally(&[Currency::Icp, Currency::CkBtc], &pledges);
+        assert_eq!(stats.len(), 2 * Channel::ALL.len());
+        let web_ckbtc = &stats[Channel::ALL.len() + Channel::Web as usize];
+        assert_eq!((web_ckbtc.currency, web_ckbtc.contributions, web_ckbtc.amount), (Currency::CkBtc, 1, 9));
+    }
+}
@@ -0,0 +1,258 @@
+//! Co-marketing partners: accelerators or grant programs shown as a "Supported by" chip on cards.
+//!
+//! Admins register partners. A campaign owner requests an association, and it displays only once
+//! the partner's own principal (or an admin) approves it. Either side can remove it. Cards read
+//! the approved links on every call, so a removal is visible at once; sync consumers also get a
+//! campaign update.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{
+    ensure_admin, ensure_campaign_owner, get_campaign, get_doc, is_admin, not_found, record_change, ChangeKind,
+    EntityRef, Memory, MEMORY_MANAGER,
+};
+
+pub(crate) const MAX_PARTNER_NAME_LEN: usize = 80; // chars
+pub(crate) const MAX_PARTNER_URL_LEN: usize = 200; // bytes
+const MAX_PARTNERS_PER_CAMPAIGN: usize = 5; // requested and approved together
+
+thread_local! {
+    static MARKETING_PARTNERS: RefCell<StableBTreeMap<u64, MarketingPartner, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(42)))
+        )
+    );
+
+    static MARKETING_PARTNER_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(43))),
+            0,
+        ).expect("init marketing partner counter")
+    );
+
+    // (campaign_id, partner_id) -> link, requested or approved
+    static PARTNER_LINKS: RefCell<StableBTreeMap<(u64, u64), PartnerLink, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(44)))
+        )
+    );
+
+    // (partner_id, campaign_id) -> (), mirror of PARTNER_LINKS for the partner's own listings
+    static LINKS_BY_PARTNER: RefCell<StableBTreeMap<(u64, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(45)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct MarketingPartner {
+    pub name: String,
+    pub logo_doc_id: Option<u64>,   // served at /docs/{id}
+    pub url: String,                // https only
+    pub principal: Option<Principal>, // may approve requests itself; None => admins approve
+    pub registered_at: u64,
+}
+
+impl Storable for MarketingPartner {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode MarketingPartner"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode MarketingPartner")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+struct PartnerLink {
+    approved_at: Option<u64>, // None while the request waits for the partner
+    requested_at: u64,
+}
+
+impl Storable for PartnerLink {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode PartnerLink"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode PartnerLink")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// What a card shows for an approved partner.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PartnerRef {
+    pub partner_id: u64,
+    pub name: String,
+    pub logo_doc_id: Option<u64>,
+    pub url: String,
+}
+
+fn get_partner(partner_id: u64) -> Result<MarketingPartner, String> {
+    MARKETING_PARTNERS
+        .with(|p| p.borrow().get(&partner_id))
+        .ok_or_else(|| format!("marketing partner {} not found", partner_id))
+}
+
+fn ensure_partner_or_admin(partner: &MarketingPartner) -> Result<(), String> {
+    let caller = ic_cdk::caller();
+    if partner.principal == Some(caller) || is_admin(&caller) {
+        Ok(())
+    } else {
+        Err("only the partner or an admin can do this".into())
+    }
+}
+
+fn links_of_campaign(campaign_id: u64) -> Vec<(u64, PartnerLink)> {
+    PARTNER_LINKS.with(|l| {
+        l.borrow()
+            .range((campaign_id, 0)..=(campaign_id, u64::MAX))
+            .map(|((_, partner_id), link)| (partner_id, link))
+            .collect()
+    })
+}
+
+/// Approved partners of a campaign, in partner id order.
+pub(crate) fn approved_partners(campaign_id: u64) -> Vec<PartnerRef> {
+    links_of_campaign(campaign_id)
+        .into_iter()
+        .filter(|(_, link)| link.approved_at.is_some())
+        .filter_map(|(partner_id, _)| {
+            let p = MARKETING_PARTNERS.with(|m| m.borrow().get(&partner_id))?;
+            Some(PartnerRef { partner_id, name: p.name, logo_doc_id: p.logo_doc_id, url: p.url })
+        })
+        .collect()
+}
+
+fn validate_partner(name: &str, logo_doc_id: Option<u64>, url: &str) -> Result<(), String> {
+    if name.is_empty() || name.chars().count() > MAX_PARTNER_NAME_LEN {
+        return Err(format!("name must be 1-{} characters", MAX_PARTNER_NAME_LEN));
+    }
+    if !url.starts_with("https://") || url.len() > MAX_PARTNER_URL_LEN {
+        return Err(format!("url must start with https:// and be at most {} bytes", MAX_PARTNER_URL_LEN));
+    }
+    if let Some(doc_id) = logo_doc_id {
+        if get_doc(doc_id).is_none() {
+            return Err(not_found(EntityRef::Doc(doc_id)));
+        }
+    }
+    Ok(())
+}
+
+/// Register a co-marketing partner (admins). Returns its id.
+#[update]
+fn register_marketing_partner(
+    name: String,
+    logo_doc_id: Option<u64>,
+    url: String,
+    principal: Option<Principal>,
+) -> Result<u64, String> {
+    ensure_admin()?;
+    let (name, url) = (name.trim().to_string(), url.trim().to_string());
+    validate_partner(&name, logo_doc_id, &url)?;
+    let id = MARKETING_PARTNER_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store marketing partner counter");
+        id
+    });
+    let partner = MarketingPartner { name, logo_doc_id, url, principal, registered_at: ic_cdk::api::time() };
+    MARKETING_PARTNERS.with(|p| p.borrow_mut().insert(id, partner));
+    Ok(id)
+}
+
+#[query]
+fn get_marketing_partners() -> Vec<(u64, MarketingPartner)> {
+    MARKETING_PARTNERS.with(|p| p.borrow().iter().collect())
+}
+
+/// Ask a partner to appear on the campaign's card (owner or controllers). Shown once approved.
+#[update]
+fn request_campaign_partner(campaign_id: u64, partner_id: u64) -> Result<(), String> {
+    ensure_campaign_owner(campaign_id)?;
+    get_partner(partner_id)?;
+    let links = links_of_campaign(campaign_id);
+    if links.iter().any(|(id, _)| *id == partner_id) {
+        return Err("partner already requested for this campaign".into());
+    }
+    if links.len() >= MAX_PARTNERS_PER_CAMPAIGN {
+        return Err(format!("a campaign can have at most {} partners", MAX_PARTNERS_PER_CAMPAIGN));
+    }
+    let link = PartnerLink { approved_at: None, requested_at: ic_cdk::api::time() };
+    PARTNER_LINKS.with(|l| l.borrow_mut().insert((campaign_id, partner_id), link));
+    LINKS_BY_PARTNER.with(|l| l.borrow_mut().insert((partner_id, campaign_id), ()));
+    Ok(())
+}
+
+/// Approve a pending request (the partner's principal or admins).
+#[update]
+fn approve_campaign_partner(campaign_id: u64, partner_id: u64) -> Result<(), String> {
+    ensure_partner_or_admin(&get_partner(partner_id)?)?;
+    let Some(mut link) = PARTNER_LINKS.with(|l| l.borrow().get(&(campaign_id, partner_id))) else {
+        return Err("no request from this campaign".into());
+    };
+    if link.approved_at.is_some() {
+        return Ok(());
+    }
+    link.approved_at = Some(ic_cdk::api::time());
+    PARTNER_LINKS.with(|l| l.borrow_mut().insert((campaign_id, partner_id), link));
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// Drop a request or an approved association; either side (campaign owner, partner) or admins.
+#[update]
+fn remove_campaign_partner(campaign_id: u64, partner_id: u64) -> Result<(), String> {
+    let partner = get_partner(partner_id)?;
+    if ensure_partner_or_admin(&partner).is_err() {
+        ensure_campaign_owner(campaign_id)?;
+    }
+    let removed = PARTNER_LINKS.with(|l| l.borrow_mut().remove(&(campaign_id, partner_id)));
+    let Some(link) = removed else {
+        return Err("partner is not linked to this campaign".into());
+    };
+    LINKS_BY_PARTNER.with(|l| l.borrow_mut().remove(&(partner_id, campaign_id)));
+    if link.approved_at.is_some() {
+        record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    }
+    Ok(())
+}
+
+fn partner_campaigns(partner_id: u64, approved: bool) -> Vec<u64> {
+    let ids: Vec<u64> = LINKS_BY_PARTNER.with(|l| {
+        l.borrow()
+            .range((partner_id, 0)..=(partner_id, u64::MAX))
+            .map(|((_, campaign_id), _)| campaign_id)
+            .collect()
+    });
+    ids.into_iter()
+        .filter(|campaign_id| {
+            let link = PARTNER_LINKS.with(|l| l.borrow().get(&(*campaign_id, partner_id)));
+            link.is_some_and(|l| l.approved_at.is_some() == approved) && get_campaign(*campaign_id).is_some()
+        })
+        .collect()
+}
+
+/// Campaigns currently showing the partner's chip.
+#[query]
+fn get_partner_campaigns(partner_id: u64) -> Vec<u64> {
+    partner_campaigns(partner_id, true)
+}
+
+/// Campaigns waiting for the partner's approval (the partner's principal or admins).
+#[query]
+fn get_pending_partner_requests(partner_id: u64) -> Result<Vec<u64>, String> {
+    ensure_partner_or_admin(&get_partner(partner_id)?)?;
+    Ok(partner_campaigns(partner_id, false))
+}
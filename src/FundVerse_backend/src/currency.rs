@@ -0,0 +1,97 @@
+//! The currency each campaign raises in.
+//!
+//! A campaign's `goal`, `amount_raised` and every pledge to it are amounts of the campaign's
+//! currency: ICP e8s, ckBTC satoshis or US cents. Campaigns and pledges stored before currencies
+//! were kept have none and count as ICP. Amounts in different currencies are never added up: a
+//! pledge must be in its campaign's currency, an idea's campaigns all raise in one currency so its
+//! `current_funding` stays a single sum, and platform totals are reported per currency.
+
+use std::fmt;
+
+use candid::{CandidType, Deserialize};
+
+use super::{Campaign, FundVerseError, CAMPAIGNS};
+
+/// Error prefix for a pledge in another currency than its campaign's.
+pub const CURRENCY_MISMATCH: &str = "CURRENCY_MISMATCH";
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Currency {
+    #[default]
+    Icp,      // e8s
+    CkBtc,    // satoshis
+    UsdCents, // fiat-equivalent pledges settled off-chain
+}
+
+impl Currency {
+    pub(crate) const ALL: [Currency; 3] = [Currency::Icp, Currency::CkBtc, Currency::UsdCents];
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Currency::Icp => write!(f, "ICP"),
+            Currency::CkBtc => write!(f, "ckBTC"),
+            Currency::UsdCents => write!(f, "USD cents"),
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Money {
+    pub currency: Currency,
+    pub amount: u64, // in the currency's smallest unit
+}
+
+/// Platform totals for the campaigns raising in one currency.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct CurrencyTotals {
+    pub currency: Currency,
+    pub campaigns: u64,
+    pub raised: u64,
+    pub goal: u64,
+}
+
+impl Campaign {
+    /// The currency its amounts are in; ICP for campaigns from before currencies were kept.
+    pub(crate) fn currency(&self) -> Currency {
+        self.currency.unwrap_or_default()
+    }
+}
+
+/// Reject a pledge in `pledged` to a campaign raising in another currency.
+pub(crate) fn check_pledge(campaign: &Campaign, pledged: Currency) -> Result<(), FundVerseError> {
+    let expected = campaign.currency();
+    if pledged != expected {
+        return Err(FundVerseError::CurrencyMismatch { expected, got: pledged });
+    }
+    Ok(())
+}
+
+/// A new campaign on `idea_id` must raise in the currency of the idea's existing campaigns.
+pub(crate) fn check_idea(idea_id: u64, currency: Currency) -> Result<(), String> {
+    let existing = CAMPAIGNS.with(|store| {
+        store.borrow().iter().map(|(_, c)| c).find(|c| c.idea_id == idea_id).map(|c| c.currency())
+    });
+    match existing {
+        Some(existing) if existing != currency => {
+            Err(format!("this idea's campaigns raise {}; a new one can't raise {}", existing, currency))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Add one campaign to the totals of its currency, starting them if it is the first.
+pub(crate) fn add_to_totals(totals: &mut Vec<CurrencyTotals>, c: &Campaign) {
+    let currency = c.currency();
+    let t = match totals.iter().position(|t| t.currency == currency) {
+        Some(i) => &mut totals[i],
+        None => {
+            totals.push(CurrencyTotals { currency, campaigns: 0, raised: 0, goal: 0 });
+            totals.last_mut().expect("just pushed")
+        }
+    };
+    t.campaigns += 1;
+    t.raised = t.raised.saturating_add(c.amount_raised);
+    t.goal = t.goal.saturating_add(c.goal);
+}
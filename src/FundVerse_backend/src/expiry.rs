@@ -0,0 +1,125 @@
+//! Finalizing campaigns when their end date passes, instead of only when someone asks.
+//!
+//! A timer sweeps hourly by default (`expiry_sweep_secs` in the init or upgrade args) for campaigns
+//! whose pause-adjusted end date has passed without a stored outcome, and finalizes them as
+//! Completed or Expired the way `finalize_campaign` does, which logs the outcome to the activity
+//! feed and opens refunds or the withdrawal. Each message finalizes at most `SWEEP_BATCH`
+//! campaigns; when more are due the sweep continues in a fresh message right away, so thousands of
+//! campaigns ending together can't exhaust one message's instructions. Timers don't survive
+//! upgrades, so both hooks schedule it; `run_expiry_sweep` runs one batch by hand.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use candid::{CandidType, Deserialize};
+use ic_cdk_macros::update;
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::StableCell;
+
+use super::{
+    effective_end_date, ensure_admin, payouts, to_secs, CampaignOutcome, Memory, CAMPAIGNS, MEMORY_MANAGER,
+};
+
+const DEFAULT_SWEEP_SECS: u64 = 60 * 60;
+const MIN_SWEEP_SECS: u64 = 60;
+const SWEEP_BATCH: usize = 50; // campaigns finalized per message
+
+thread_local! {
+    static SWEEP_SECS: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(74))),
+            DEFAULT_SWEEP_SECS,
+        ).expect("init expiry sweep interval")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ExpirySweep {
+    pub finalized: Vec<(u64, CampaignOutcome)>, // campaign id and the outcome stored
+    pub more_due: bool,                         // the batch was full; another run finalizes the rest
+}
+
+/// Set the sweep interval (at least a minute); None keeps the stored one.
+pub(crate) fn configure(sweep_secs: Option<u64>) {
+    if let Some(secs) = sweep_secs {
+        SWEEP_SECS.with(|s| s.borrow_mut().set(secs.max(MIN_SWEEP_SECS))).expect("store expiry sweep interval");
+    }
+}
+
+/// Start the periodic sweep. Call from both hooks, after `configure`.
+pub(crate) fn schedule_sweeps() {
+    let secs = SWEEP_SECS.with(|s| *s.borrow().get());
+    ic_cdk_timers::set_timer_interval(Duration::from_secs(secs), sweep_until_done);
+}
+
+/// Ended campaigns without a stored outcome, by id; cancelled and taken-down ones already have one.
+fn due_campaigns(now: u64, limit: usize) -> Vec<u64> {
+    CAMPAIGNS.with(|c| {
+        c.borrow()
+            .iter()
+            .filter(|(_, c)| c.status.is_none() && effective_end_date(c, now) < now)
+            .map(|(id, _)| id)
+            .take(limit)
+            .collect()
+    })
+}
+
+fn sweep() -> ExpirySweep {
+    let (caller, now) = (ic_cdk::caller(), ic_cdk::api::time());
+    let finalized: Vec<(u64, CampaignOutcome)> = due_campaigns(to_secs(now), SWEEP_BATCH)
+        .into_iter()
+        .filter_map(|id| match payouts::finalize(caller, now, id) {
+            Ok(outcome) => Some((id, outcome)),
+            Err(e) => {
+                ic_cdk::println!("finalizing campaign {} failed: {}", id, e);
+                None
+            }
+        })
+        .collect();
+    let more_due = finalized.len() == SWEEP_BATCH; // not after failures, which would only repeat
+    ExpirySweep { finalized, more_due }
+}
+
+// One batch per message: a full batch hands the rest to a new message.
+fn sweep_until_done() {
+    if sweep().more_due {
+        ic_cdk_timers::set_timer(Duration::ZERO, sweep_until_done);
+    }
+}
+
+/// Finalize one batch of ended campaigns now (admins), for tests and after an outage; the timer
+/// does the same on its own.
+#[update]
+fn run_expiry_sweep() -> Result<ExpirySweep, String> {
+    ensure_admin()?;
+    Ok(sweep())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Campaign, CampaignLifecycle};
+
+    fn campaign(id: u64, end_date: u64, status: Option<CampaignLifecycle>) -> Campaign {
+        Campaign { status, ..crate::tests::test_campaign(id, 1, end_date) }
+    }
+
+    #[test]
+    fn sweeps_pick_ended_campaigns_without_an_outcome_in_bounded_batches() {
+        let now = 1_700_000_000;
+        CAMPAIGNS.with(|c| {
+            let mut c = c.borrow_mut();
+            c.insert(1, campaign(1, now - 10, None));
+            c.insert(2, campaign(2, now + 10, None));
+            c.insert(3, campaign(3, now - 10, Some(CampaignLifecycle::Cancelled)));
+            c.insert(4, campaign(4, now - 10, Some(CampaignLifecycle::Expired)));
+            c.insert(5, campaign(5, now, None)); // ends this second: still running
+            for id in 6..=8 {
+                c.insert(id, campaign(id, now - 1, None));
+            }
+        });
+        assert_eq!(due_campaigns(now, 10), [1, 6, 7, 8]);
+        assert_eq!(due_campaigns(now, 2), [1, 6]);
+        assert_eq!(due_campaigns(now + 11, 10), [1, 2, 5, 6, 7, 8]);
+    }
+}
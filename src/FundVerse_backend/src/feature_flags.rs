@@ -0,0 +1,122 @@
+//! Feature flags, so risky features can ship dark and be turned on per environment.
+//!
+//! Admins set a flag to Off, AdminsOnly, Percentage(p) or On; a flag never set is Off. With
+//! Percentage, a hash of the flag name and the caller's principal puts each caller in one of 100
+//! buckets and the first `p` buckets get the feature, so a caller keeps their answer while the
+//! percentage stays the same and keeps the feature when it grows. Admins get every flag that
+//! isn't Off. Gated endpoints check `feature_enabled` and fail with `FeatureDisabled`.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{ensure_admin, fnv1a, is_admin, Memory, MEMORY_MANAGER};
+
+const MAX_FLAG_NAME_LEN: usize = 64;
+
+thread_local! {
+    static FEATURE_FLAGS: RefCell<StableBTreeMap<String, FlagState, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(64)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagState {
+    Off,
+    AdminsOnly,
+    Percentage(u8), // 0-100, of callers by principal
+    On,
+}
+
+impl Storable for FlagState {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode FlagState"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode FlagState")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// The caller's bucket for `flag`, 0-99. Seeded by the flag so rollouts don't pick the same users.
+fn bucket(flag: &str, caller: &Principal) -> u64 {
+    fnv1a(fnv1a(0, flag.as_bytes()), caller.as_slice()) % 100
+}
+
+fn enabled(state: FlagState, flag: &str, caller: &Principal, caller_is_admin: bool) -> bool {
+    match state {
+        FlagState::Off => false,
+        FlagState::On => true,
+        _ if caller_is_admin => true,
+        FlagState::AdminsOnly => false,
+        FlagState::Percentage(pct) => bucket(flag, caller) < pct as u64,
+    }
+}
+
+pub(crate) fn feature_enabled(flag: &str, caller: &Principal) -> bool {
+    let state = FEATURE_FLAGS.with(|f| f.borrow().get(&flag.to_string())).unwrap_or(FlagState::Off);
+    enabled(state, flag, caller, is_admin(caller))
+}
+
+/// Set a flag (admins). Names are 1-64 bytes of lowercase letters, digits and underscores.
+#[update]
+fn set_feature_flag(name: String, state: FlagState) -> Result<(), String> {
+    ensure_admin()?;
+    let valid_name = !name.is_empty()
+        && name.len() <= MAX_FLAG_NAME_LEN
+        && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+    if !valid_name {
+        return Err(format!("flag names are 1-{} bytes of a-z, 0-9 and _", MAX_FLAG_NAME_LEN));
+    }
+    if matches!(state, FlagState::Percentage(pct) if pct > 100) {
+        return Err("percentage must be 0-100".into());
+    }
+    FEATURE_FLAGS.with(|f| f.borrow_mut().insert(name, state));
+    Ok(())
+}
+
+/// Every flag that was ever set, by name (admins).
+#[query]
+fn get_feature_flags() -> Result<Vec<(String, FlagState)>, String> {
+    ensure_admin()?;
+    Ok(FEATURE_FLAGS.with(|f| f.borrow().iter().collect()))
+}
+
+/// Whether the caller gets a feature, so the frontend can hide what is still dark for them.
+#[query]
+fn is_feature_enabled(name: String) -> bool {
+    feature_enabled(&name, &ic_cdk::caller())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentage_rollouts_are_stable_per_principal_and_only_grow() {
+        let callers: Vec<Principal> = (0..1_000u32).map(|i| Principal::from_slice(&i.to_be_bytes())).collect();
+        let enabled_at = |pct| -> Vec<bool> {
+            callers.iter().map(|c| enabled(FlagState::Percentage(pct), "trending", c, false)).collect()
+        };
+        assert_eq!(enabled_at(30), enabled_at(30), "same answer every time");
+        let share = enabled_at(30).iter().filter(|on| **on).count();
+        assert!((200..400).contains(&share), "about 30% of callers, got {}", share);
+        let (at_30, at_60) = (enabled_at(30), enabled_at(60));
+        assert!(at_30.iter().zip(&at_60).all(|(before, after)| !before || *after), "no one loses it");
+        assert!(enabled_at(0).iter().all(|on| !on) && enabled_at(100).iter().all(|on| *on));
+
+        let someone = callers[0];
+        assert!(!enabled(FlagState::AdminsOnly, "trending", &someone, false));
+        assert!(enabled(FlagState::AdminsOnly, "trending", &someone, true));
+        assert!(enabled(FlagState::Percentage(0), "trending", &someone, true));
+        assert!(!enabled(FlagState::Off, "trending", &someone, true));
+    }
+}
@@ -0,0 +1,276 @@
+//! Fiat-denominated goals ("raise $50,000") for campaigns paid in tokens.
+//!
+//! The campaign's `goal` stays in token units and everything that measures progress keeps using
+//! it, so percent funded never mixes units. A fiat goal records the currency amount and the
+//! exchange rate locked when the campaign was created; `goal` is that amount at the locked rate.
+//! Rates come from a cache that controllers keep fresh with `set_exchange_rate`. When the price
+//! drifts, `refresh_goal_rate` re-locks at the cached rate and recomputes `goal`, at most once a
+//! day per campaign. The owner may refresh until the first pledge; after that, when backers have
+//! pledged against the locked goal, only admins can. Every lock is kept in an audit trail.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{
+    ensure_active, ensure_campaign_owner, ensure_controller, get_campaign, is_admin, not_found, record_change,
+    with_campaign_mut, Campaign, ChangeKind, EntityRef, Memory, MEMORY_MANAGER,
+};
+
+const MAX_RATE_AGE_NS: u64 = 24 * 3_600 * 1_000_000_000;
+const MIN_REFRESH_INTERVAL_NS: u64 = 24 * 3_600 * 1_000_000_000;
+
+thread_local! {
+    // currency (ISO 4217, e.g. "USD") -> latest rate
+    static EXCHANGE_RATES: RefCell<StableBTreeMap<String, ExchangeRate, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(57)))
+        )
+    );
+
+    // (campaign_id, seq) -> one rate lock, the first being the lock at creation
+    static GOAL_RATE_LOCKS: RefCell<StableBTreeMap<(u64, u64), GoalRateLock, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(58)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExchangeRate {
+    pub rate: u64,       // token units per 1.00 of the currency
+    pub updated_at: u64, // ns
+}
+
+impl Storable for ExchangeRate {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode ExchangeRate"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode ExchangeRate")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// What the creator asks for; the rate is filled in from the cache.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct FiatGoalRequest {
+    pub currency: String,
+    pub amount_cents: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct FiatGoal {
+    pub currency: String,
+    pub amount_cents: u64,
+    pub locked_rate: u64, // token units per 1.00 of the currency
+    pub locked_at: u64,   // ns
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct GoalRateLock {
+    pub by: Principal,
+    pub at: u64, // ns
+    pub currency: String,
+    pub amount_cents: u64,
+    pub previous_rate: Option<u64>, // None for the lock at creation
+    pub rate: u64,
+    pub previous_goal: Option<u64>,
+    pub goal: u64,
+}
+
+impl Storable for GoalRateLock {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode GoalRateLock"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode GoalRateLock")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+fn validate_currency(currency: &str) -> Result<String, String> {
+    let currency = currency.trim().to_ascii_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err("currency must be a three-letter ISO 4217 code".into());
+    }
+    Ok(currency)
+}
+
+/// The fiat amount in token units at `rate`, rounded up so the goal never falls short.
+pub(crate) fn token_goal(amount_cents: u64, rate: u64) -> Result<u64, String> {
+    let units = (amount_cents as u128 * rate as u128).div_ceil(100);
+    match u64::try_from(units) {
+        Ok(0) => Err("fiat goal is worth less than one token unit".into()),
+        Ok(units) => Ok(units),
+        Err(_) => Err("fiat goal is too large in token units".into()),
+    }
+}
+
+/// The cached rate for `currency`, if it was updated within the last day.
+fn fresh_rate(currency: &str, now: u64) -> Result<u64, String> {
+    let cached = EXCHANGE_RATES
+        .with(|r| r.borrow().get(&currency.to_string()))
+        .ok_or_else(|| format!("no exchange rate for {}", currency))?;
+    if now.saturating_sub(cached.updated_at) > MAX_RATE_AGE_NS {
+        return Err(format!("exchange rate for {} is more than a day old", currency));
+    }
+    Ok(cached.rate)
+}
+
+/// Lock a new campaign's fiat goal at the cached rate. Returns it with the token goal.
+pub(crate) fn lock_at_creation(request: FiatGoalRequest, now: u64) -> Result<(FiatGoal, u64), String> {
+    let currency = validate_currency(&request.currency)?;
+    if request.amount_cents == 0 {
+        return Err("fiat goal must be > 0".into());
+    }
+    let rate = fresh_rate(&currency, now)?;
+    let goal = token_goal(request.amount_cents, rate)?;
+    Ok((FiatGoal { currency, amount_cents: request.amount_cents, locked_rate: rate, locked_at: now }, goal))
+}
+
+/// The campaign's fiat goal re-locked at `rate`, and the token goal that goes with it.
+pub(crate) fn relocked(fiat: &FiatGoal, rate: u64, now: u64) -> Result<(FiatGoal, u64), String> {
+    let goal = token_goal(fiat.amount_cents, rate)?;
+    Ok((FiatGoal { locked_rate: rate, locked_at: now, ..fiat.clone() }, goal))
+}
+
+fn append_lock(campaign_id: u64, lock: GoalRateLock) {
+    GOAL_RATE_LOCKS.with(|l| {
+        let mut l = l.borrow_mut();
+        let seq = l.range((campaign_id, 0)..=(campaign_id, u64::MAX)).last().map_or(0, |((_, seq), _)| seq + 1);
+        l.insert((campaign_id, seq), lock);
+    });
+}
+
+/// Audit the lock taken when a campaign was created.
+pub(crate) fn record_creation_lock(campaign: &Campaign, by: Principal) {
+    if let Some(fiat) = &campaign.fiat_goal {
+        append_lock(
+            campaign.id,
+            GoalRateLock {
+                by,
+                at: fiat.locked_at,
+                currency: fiat.currency.clone(),
+                amount_cents: fiat.amount_cents,
+                previous_rate: None,
+                rate: fiat.locked_rate,
+                previous_goal: None,
+                goal: campaign.goal,
+            },
+        );
+    }
+}
+
+/// Cache the rate for a currency (controllers): token units per 1.00 of it.
+#[update]
+fn set_exchange_rate(currency: String, rate: u64) -> Result<(), String> {
+    ensure_controller()?;
+    let currency = validate_currency(&currency)?;
+    if rate == 0 {
+        return Err("rate must be > 0".into());
+    }
+    EXCHANGE_RATES.with(|r| r.borrow_mut().insert(currency, ExchangeRate { rate, updated_at: ic_cdk::api::time() }));
+    Ok(())
+}
+
+#[query]
+fn get_exchange_rate(currency: String) -> Option<ExchangeRate> {
+    let currency = validate_currency(&currency).ok()?;
+    EXCHANGE_RATES.with(|r| r.borrow().get(&currency))
+}
+
+/// Re-lock a fiat goal at the cached rate and recompute the token goal. The owner may do this
+/// until the campaign has raised anything, admins at any time; once a day per campaign at most.
+#[update]
+fn refresh_goal_rate(campaign_id: u64) -> Result<FiatGoal, String> {
+    let caller = ic_cdk::caller();
+    let campaign = if is_admin(&caller) {
+        get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?
+    } else {
+        let campaign = ensure_campaign_owner(campaign_id)?;
+        if campaign.amount_raised > 0 {
+            return Err("campaign has backers; only admins can change its goal rate".into());
+        }
+        campaign
+    };
+    ensure_active(&campaign)?;
+    let Some(fiat) = campaign.fiat_goal.clone() else {
+        return Err("campaign has no fiat goal".into());
+    };
+    let now = ic_cdk::api::time();
+    if now.saturating_sub(fiat.locked_at) < MIN_REFRESH_INTERVAL_NS {
+        return Err("goal rate was locked less than a day ago".into());
+    }
+    let rate = fresh_rate(&fiat.currency, now)?;
+    let (fiat, goal) = relocked(&fiat, rate, now)?;
+    with_campaign_mut(campaign_id, |c| {
+        c.goal = goal;
+        c.fiat_goal = Some(fiat.clone());
+        Ok(())
+    })?;
+    append_lock(
+        campaign_id,
+        GoalRateLock {
+            by: caller,
+            at: now,
+            currency: fiat.currency.clone(),
+            amount_cents: fiat.amount_cents,
+            previous_rate: campaign.fiat_goal.as_ref().map(|f| f.locked_rate),
+            rate,
+            previous_goal: Some(campaign.goal),
+            goal,
+        },
+    );
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(fiat)
+}
+
+/// Every rate lock of a campaign's fiat goal, oldest (the lock at creation) first.
+#[query]
+fn get_goal_rate_history(campaign_id: u64) -> Vec<GoalRateLock> {
+    GOAL_RATE_LOCKS.with(|l| {
+        l.borrow().range((campaign_id, 0)..=(campaign_id, u64::MAX)).map(|(_, lock)| lock).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::percent_bps;
+
+    #[test]
+    fn token_goals_round_up_and_never_overflow() {
+        assert_eq!(token_goal(5_000_000, 20_000_000), Ok(1_000_000_000_000)); // $50,000 at 0.2 ICP/$ in e8s
+        assert_eq!(token_goal(1, 150), Ok(2), "1.5 units rounds up");
+        assert!(token_goal(1, 99).is_ok() && token_goal(1, 0).is_err());
+        assert!(token_goal(u64::MAX, u64::MAX).is_err());
+        assert_eq!(token_goal(100, u64::MAX), Ok(u64::MAX));
+        assert_eq!(validate_currency(" usd "), Ok("USD".to_string()));
+        assert!(validate_currency("US$").is_err() && validate_currency("EURO").is_err());
+    }
+
+    #[test]
+    fn refreshing_mid_campaign_moves_the_token_goal_and_percent_stays_in_tokens() {
+        let (locked_at, refreshed_at) = (1_000, 2_000);
+        let fiat = FiatGoal { currency: "USD".into(), amount_cents: 10_000, locked_rate: 100, locked_at };
+        let goal = token_goal(fiat.amount_cents, fiat.locked_rate).unwrap();
+        let raised = 5_000;
+        assert_eq!((goal, percent_bps(raised, goal)), (10_000, 5_000));
+
+        // The token doubles in price: half as many tokens make up the same $100.
+        let (fiat, goal) = relocked(&fiat, 50, refreshed_at).unwrap();
+        assert_eq!((fiat.amount_cents, fiat.locked_rate, fiat.locked_at), (10_000, 50, refreshed_at));
+        assert_eq!((goal, percent_bps(raised, goal)), (5_000, 10_000));
+        let (_, goal) = relocked(&fiat, 400, refreshed_at).unwrap();
+        assert_eq!((goal, percent_bps(raised, goal)), (40_000, 1_250));
+    }
+}
@@ -0,0 +1,340 @@
+//! ICP pledges backed by a real ledger transfer.
+//!
+//! Backers transfer to this canister's account (`get_canister_account`) on the configured ICRC
+//! ledger, then call `contribute_icp` with the block index. The block is fetched with ICRC-3
+//! `icrc3_get_blocks`, so the ledger must serve ICRC-3, and the pledge is recorded only if the
+//! block is a transfer from the caller to this canister. Each block can back one pledge. Blocks the ledger has already moved to an archive can't be looked up here,
+//! so claims should follow the transfer promptly.
+//!
+//! ICP that Fund_Flow took in is credited by `receive_icp_contribution`, which only the configured
+//! Fund_Flow canister (`fund_flow` in the init or upgrade args) may call.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Nat, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{
+    channels, check_contribution, ensure_controller, get_campaign, record_contribution, totals_after, Amount, Currency,
+    Memory, Money, MEMORY_MANAGER,
+};
+
+thread_local! {
+    static LEDGER_CONFIG: RefCell<StableCell<LedgerConfig, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(49))),
+            LedgerConfig::default(),
+        ).expect("init ledger config")
+    );
+
+    // block index -> contribution it was credited as
+    static CLAIMED_BLOCKS: RefCell<StableBTreeMap<u64, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(50)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+struct LedgerConfig {
+    ledger: Option<Principal>,
+    fund_flow: Option<Principal>, // the only caller of receive_icp_contribution
+}
+
+impl Storable for LedgerConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode LedgerConfig"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode LedgerConfig")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// An ICRC-1 account.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Account {
+    pub owner: Principal,
+    pub subaccount: Option<Vec<u8>>, // 32 bytes; None is the default (all-zero) subaccount
+}
+
+impl Account {
+    fn is_default_of(&self, owner: Principal) -> bool {
+        self.owner == owner && self.subaccount.as_ref().is_none_or(|s| s.iter().all(|b| *b == 0))
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum IcpPledgeError {
+    LedgerNotConfigured,
+    LedgerUnavailable(String),
+    TransferNotFound,                          // no such block, or it is archived
+    NotATransferToCanister,                    // a mint, burn or approval, or sent elsewhere
+    NotYourTransfer,                           // sent from another principal
+    WrongAmount { amount_e8s: u64 },           // zero, or more than the totals can hold
+    AlreadyClaimed { contribution_id: u64 },
+    Rejected(String),                          // the campaign can't take pledges
+}
+
+fn config() -> LedgerConfig {
+    LEDGER_CONFIG.with(|c| c.borrow().get().clone())
+}
+
+fn store_config(config: LedgerConfig) -> Result<(), String> {
+    LEDGER_CONFIG
+        .with(|c| c.borrow_mut().set(config))
+        .map(|_| ())
+        .map_err(|e| format!("failed to store ledger config: {:?}", e))
+}
+
+/// Set the ledger and the Fund_Flow canister; None keeps the stored one.
+pub(crate) fn configure(ledger: Option<Principal>, fund_flow: Option<Principal>) {
+    let stored = config();
+    let config = LedgerConfig { ledger: ledger.or(stored.ledger), fund_flow: fund_flow.or(stored.fund_flow) };
+    store_config(config).expect("store ledger config");
+}
+
+fn ledger() -> Option<Principal> {
+    config().ledger
+}
+
+/// Only the configured Fund_Flow canister may relay ICP it took in.
+pub(crate) fn ensure_fund_flow() -> Result<(), String> {
+    match config().fund_flow {
+        Some(fund_flow) if fund_flow == ic_cdk::caller() => Ok(()),
+        Some(_) => Err("only the Fund_Flow canister can relay ICP contributions".into()),
+        None => Err("no Fund_Flow canister is configured".into()),
+    }
+}
+
+// ICRC-3 wire types; fields this canister doesn't read are left out and skipped on decode.
+
+#[derive(CandidType, Deserialize)]
+struct GetBlocksArgs {
+    start: Nat,
+    length: Nat,
+}
+
+#[derive(CandidType, Deserialize)]
+struct BlockWithId {
+    id: Nat,
+    block: Value,
+}
+
+#[derive(CandidType, Deserialize)]
+struct GetBlocksResult {
+    blocks: Vec<BlockWithId>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+enum Value {
+    Blob(Vec<u8>),
+    Text(String),
+    Nat(Nat),
+    Int(candid::Int),
+    Array(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn text(&self) -> Option<&str> {
+        match self {
+            Value::Text(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    fn nat(&self) -> Option<&Nat> {
+        match self {
+            Value::Nat(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    // Accounts are encoded as [owner, subaccount?], both blobs.
+    fn account(&self) -> Option<Account> {
+        let Value::Array(parts) = self else { return None };
+        let blob = |v: &Value| match v {
+            Value::Blob(b) => Some(b.clone()),
+            _ => None,
+        };
+        let owner = Principal::try_from_slice(&blob(parts.first()?)?).ok()?;
+        let subaccount = match parts.get(1) {
+            Some(v) => Some(blob(v)?),
+            None => None,
+        };
+        Some(Account { owner, subaccount })
+    }
+}
+
+/// A plain transfer in an ICRC-3 block: (from, to, amount). Newer blocks name the type in
+/// `btype`, older ones in `tx.op`.
+fn transfer_in(block: &Value) -> Option<(Account, Account, &Nat)> {
+    let tx = block.field("tx")?;
+    let kind = block.field("btype").and_then(Value::text).or_else(|| tx.field("op").and_then(Value::text))?;
+    if kind != "1xfer" && kind != "xfer" {
+        return None;
+    }
+    Some((tx.field("from")?.account()?, tx.field("to")?.account()?, tx.field("amt")?.nat()?))
+}
+
+/// What a block means for a pledge from `caller` to `canister`.
+fn check_transfer(block: &Value, caller: Principal, canister: Principal) -> Result<u64, IcpPledgeError> {
+    let Some((from, to, amount)) = transfer_in(block) else {
+        return Err(IcpPledgeError::NotATransferToCanister);
+    };
+    if !to.is_default_of(canister) {
+        return Err(IcpPledgeError::NotATransferToCanister);
+    }
+    if from.owner != caller {
+        return Err(IcpPledgeError::NotYourTransfer);
+    }
+    match u64::try_from(&amount.0) {
+        Ok(0) => Err(IcpPledgeError::WrongAmount { amount_e8s: 0 }),
+        Ok(amount_e8s) => Ok(amount_e8s),
+        Err(_) => Err(IcpPledgeError::WrongAmount { amount_e8s: u64::MAX }),
+    }
+}
+
+async fn fetch_block(ledger: Principal, block_index: u64) -> Result<Value, IcpPledgeError> {
+    let args = vec![GetBlocksArgs { start: Nat::from(block_index), length: Nat::from(1u64) }];
+    let res: Result<(GetBlocksResult,), _> = ic_cdk::call(ledger, "icrc3_get_blocks", (args,)).await;
+    let (result,) = res.map_err(|(code, msg)| IcpPledgeError::LedgerUnavailable(format!("{:?}: {}", code, msg)))?;
+    result
+        .blocks
+        .into_iter()
+        .find(|b| b.id == block_index)
+        .map(|b| b.block)
+        .ok_or(IcpPledgeError::TransferNotFound)
+}
+
+fn claimed_by(block_index: u64) -> Option<u64> {
+    CLAIMED_BLOCKS.with(|c| c.borrow().get(&block_index))
+}
+
+fn icp(amount_e8s: u64) -> Money {
+    Money { currency: Currency::Icp, amount: amount_e8s }
+}
+
+/// Pledge the ICP sent in ledger block `block_index` to a campaign. The transfer must come from
+/// the caller and go to `get_canister_account`; the whole amount (e8s) is credited. As for
+/// `contribute`, a campaign with a risks section needs `ack_risks = Some(true)`. Returns the new
+/// contribution id.
+#[update]
+async fn contribute_icp(campaign_id: u64, block_index: u64, ack_risks: Option<bool>) -> Result<u64, IcpPledgeError> {
+    let caller = ic_cdk::caller();
+    let channel = channels::channel_for(caller, None).map_err(IcpPledgeError::Rejected)?;
+    let ledger = ledger().ok_or(IcpPledgeError::LedgerNotConfigured)?;
+    if let Some(contribution_id) = claimed_by(block_index) {
+        return Err(IcpPledgeError::AlreadyClaimed { contribution_id });
+    }
+    let check = |now| check_contribution(caller, now, campaign_id, icp(1), Amount::default(), ack_risks);
+    check(ic_cdk::api::time()).map_err(IcpPledgeError::Rejected)?;
+
+    let block = fetch_block(ledger, block_index).await?;
+    let amount_e8s = check_transfer(&block, caller, ic_cdk::id())?;
+
+    // Everything may have changed while the ledger answered: a concurrent claim of the same
+    // block, or the campaign ending. Both are re-checked before anything is written.
+    if let Some(contribution_id) = claimed_by(block_index) {
+        return Err(IcpPledgeError::AlreadyClaimed { contribution_id });
+    }
+    let now = ic_cdk::api::time();
+    check(now).map_err(IcpPledgeError::Rejected)?;
+    let campaign = get_campaign(campaign_id).expect("checked campaign exists");
+    totals_after(&campaign, |total| total.add(Amount::new(amount_e8s)))
+        .map_err(|_| IcpPledgeError::WrongAmount { amount_e8s })?;
+    let contribution_id = record_contribution(caller, now, campaign_id, icp(amount_e8s), channel);
+    CLAIMED_BLOCKS.with(|c| c.borrow_mut().insert(block_index, contribution_id));
+    Ok(contribution_id)
+}
+
+/// Where backers send ICP before calling `contribute_icp`.
+#[query]
+fn get_canister_account() -> Account {
+    Account { owner: ic_cdk::id(), subaccount: None }
+}
+
+#[query]
+fn get_ledger_canister() -> Option<Principal> {
+    ledger()
+}
+
+/// Point pledges at another ledger (controllers only). Blocks claimed so far stay claimed.
+#[update]
+fn set_ledger_canister(ledger: Principal) -> Result<(), String> {
+    ensure_controller()?;
+    store_config(LedgerConfig { ledger: Some(ledger), ..config() })
+}
+
+#[query]
+fn get_fund_flow_canister() -> Option<Principal> {
+    config().fund_flow
+}
+
+/// Point ICP relays at another Fund_Flow canister (controllers only).
+#[update]
+fn set_fund_flow_canister(fund_flow: Principal) -> Result<(), String> {
+    ensure_controller()?;
+    store_config(LedgerConfig { fund_flow: Some(fund_flow), ..config() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(p: Principal, sub: Option<Vec<u8>>) -> Value {
+        let mut parts = vec![Value::Blob(p.as_slice().to_vec())];
+        parts.extend(sub.map(Value::Blob));
+        Value::Array(parts)
+    }
+
+    fn block(kind: (&str, &str), from: Value, to: Value, amt: u64) -> Value {
+        let mut tx = vec![("from".to_string(), from), ("to".to_string(), to), ("amt".to_string(), Value::Nat(amt.into()))];
+        let mut block = vec![("ts".to_string(), Value::Nat(1u64.into()))];
+        match kind {
+            ("btype", k) => block.push(("btype".to_string(), Value::Text(k.to_string()))),
+            (_, op) => tx.push(("op".to_string(), Value::Text(op.to_string()))),
+        }
+        block.push(("tx".to_string(), Value::Map(tx)));
+        Value::Map(block)
+    }
+
+    #[test]
+    fn only_plain_transfers_from_the_caller_to_the_canister_count() {
+        let (caller, canister, other) =
+            (Principal::from_slice(&[1; 29]), Principal::from_slice(&[2; 10]), Principal::from_slice(&[3; 29]));
+        let me = || account(caller, None);
+        let to_canister = || account(canister, Some(vec![0; 32]));
+
+        assert_eq!(check_transfer(&block(("btype", "1xfer"), me(), to_canister(), 500), caller, canister), Ok(500));
+        assert_eq!(check_transfer(&block(("op", "xfer"), me(), account(canister, None), 7), caller, canister), Ok(7));
+        let cases = [
+            (block(("btype", "1mint"), me(), to_canister(), 5), IcpPledgeError::NotATransferToCanister),
+            (block(("op", "xfer"), me(), account(canister, Some(vec![1; 32])), 5), IcpPledgeError::NotATransferToCanister),
+            (block(("op", "xfer"), me(), account(other, None), 5), IcpPledgeError::NotATransferToCanister),
+            (block(("op", "xfer"), account(other, None), to_canister(), 5), IcpPledgeError::NotYourTransfer),
+            (block(("op", "xfer"), me(), to_canister(), 0), IcpPledgeError::WrongAmount { amount_e8s: 0 }),
+        ];
+        for (b, err) in cases {
+            assert_eq!(check_transfer(&b, caller, canister), Err(err));
+        }
+        let Value::Map(mut too_big) = block(("op", "xfer"), me(), to_canister(), 1) else { unreachable!() };
+        let Some((_, Value::Map(tx))) = too_big.iter_mut().find(|(k, _)| k == "tx") else { unreachable!() };
+        tx[2].1 = Value::Nat(Nat::from(u128::MAX));
+        let err = check_transfer(&Value::Map(too_big), caller, canister);
+        assert_eq!(err, Err(IcpPledgeError::WrongAmount { amount_e8s: u64::MAX }));
+    }
+}
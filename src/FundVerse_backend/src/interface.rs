@@ -0,0 +1,329 @@
+//! Candid interface drift detection for integrators pinned to an older .did.
+//!
+//! The committed .did is embedded in the wasm; `candid_interface_matches_committed_did` keeps it
+//! equal to the interface the canister exports, so no build script is needed. The interface hash
+//! covers type definitions and method signatures only: comments, whitespace and declaration order
+//! don't change it. Every interface the canister has run is remembered by hash at install and
+//! upgrade, so a client's pinned hash (read from `get_build_info`) can be diffed against the
+//! running one.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_cdk_macros::query;
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{fnv1a, Memory, MEMORY_MANAGER};
+
+const DID: &str = include_str!("../FundVerse_backend.did");
+
+thread_local! {
+    // interface hash -> the interface as it was first deployed
+    static INTERFACES: RefCell<StableBTreeMap<String, InterfaceSnapshot, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(36)))
+        )
+    );
+
+    static CURRENT: (Interface, String) = {
+        let interface = parse(DID);
+        let hash = interface.hash();
+        (interface, hash)
+    };
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+struct Interface {
+    types: BTreeMap<String, String>,   // name -> normalized definition
+    methods: BTreeMap<String, String>, // name -> normalized signature
+}
+
+impl Interface {
+    fn hash(&self) -> String {
+        let mut canonical = String::new();
+        for (name, body) in &self.types {
+            canonical.push_str(&format!("type {}={};", name, body));
+        }
+        for (name, sig) in &self.methods {
+            canonical.push_str(&format!("method {}:{};", name, sig));
+        }
+        format!("{:016x}", fnv1a(0, canonical.as_bytes()))
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+struct InterfaceSnapshot {
+    interface: Interface,
+    first_deployed_at: u64, // ns
+}
+
+impl Storable for InterfaceSnapshot {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode InterfaceSnapshot"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode InterfaceSnapshot")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compatibility {
+    Identical,
+    Additive, // every change is safe for clients of the pinned interface
+    Breaking,
+    UnknownHash, // the hash was never deployed here, so there is nothing to compare against
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct InterfaceChange {
+    pub item: String, // "method <name>" or "type <name>"
+    pub kind: DiffKind,
+    pub breaking: bool,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CompatReport {
+    pub client_hash: String,
+    pub current_hash: String,
+    pub compatibility: Compatibility,
+    pub changes: Vec<InterfaceChange>, // empty unless the pinned interface is known and differs
+}
+
+pub(crate) fn current_hash() -> String {
+    CURRENT.with(|(_, hash)| hash.clone())
+}
+
+/// Remember the running interface. Called from init and post_upgrade.
+pub(crate) fn remember_current_interface() {
+    let (interface, hash) = CURRENT.with(|c| c.clone());
+    INTERFACES.with(|m| {
+        let mut m = m.borrow_mut();
+        if !m.contains_key(&hash) {
+            m.insert(hash, InterfaceSnapshot { interface, first_deployed_at: ic_cdk::api::time() });
+        }
+    });
+}
+
+// Collapse whitespace and drop it next to punctuation, so formatting never counts as a change.
+fn normalize(s: &str) -> String {
+    let punct = |c: Option<char>| c.is_some_and(|c| "{}();:,=".contains(c));
+    let mut out = String::new();
+    for word in s.split_whitespace() {
+        if !out.is_empty() && !punct(out.chars().last()) && !punct(word.chars().next()) {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out.replace(";}", "}")
+}
+
+// Split at `;` outside braces and parentheses, normalizing each non-empty piece.
+fn split_top(s: &str) -> Vec<String> {
+    let (mut depth, mut start, mut parts) = (0i32, 0, Vec::new());
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            ';' if depth == 0 => {
+                parts.push(normalize(&s[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(normalize(&s[start..]));
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+fn parse(did: &str) -> Interface {
+    let source: String = did.lines().map(|l| l.split("//").next().unwrap_or("")).collect::<Vec<_>>().join("\n");
+    let mut interface = Interface::default();
+    for stmt in split_top(&source) {
+        if let Some((name, body)) = stmt.strip_prefix("type ").and_then(|def| def.split_once('=')) {
+            interface.types.insert(name.to_string(), body.to_string());
+        } else if stmt.starts_with("service") {
+            // service:(init args)->{methods} or service:{methods}
+            let after_init = stmt.find("->").map_or(0, |i| i + 2);
+            let (Some(open), Some(close)) = (stmt[after_init..].find('{'), stmt.rfind('}')) else {
+                continue;
+            };
+            for method in split_top(&stmt[after_init + open + 1..close]) {
+                if let Some((name, sig)) = method.split_once(':') {
+                    interface.methods.insert(name.to_string(), sig.to_string());
+                }
+            }
+        }
+    }
+    interface
+}
+
+fn record_fields(body: &str) -> Option<BTreeMap<String, String>> {
+    let inner = body.strip_prefix("record{")?.strip_suffix('}')?;
+    split_top(inner)
+        .into_iter()
+        .map(|f| f.split_once(':').map(|(n, t)| (n.to_string(), t.to_string())))
+        .collect()
+}
+
+// The one type change treated as safe: a record keeps every field and only gains opt ones,
+// which old clients can both send (missing opt) and receive (ignored field).
+fn only_adds_opt_fields(before: &str, after: &str) -> bool {
+    match (record_fields(before), record_fields(after)) {
+        (Some(old), Some(new)) => {
+            old.iter().all(|(name, ty)| new.get(name) == Some(ty))
+                && new.iter().filter(|(name, _)| !old.contains_key(*name)).all(|(_, ty)| ty.starts_with("opt "))
+        }
+        _ => false,
+    }
+}
+
+fn diff_items(
+    label: &str,
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+    change_is_breaking: impl Fn(&str, &str) -> bool,
+    out: &mut Vec<InterfaceChange>,
+) {
+    let item = |name: &str| format!("{} {}", label, name);
+    for (name, before) in old {
+        match new.get(name) {
+            None => out.push(InterfaceChange { item: item(name), kind: DiffKind::Removed, breaking: true }),
+            Some(after) if after != before => out.push(InterfaceChange {
+                item: item(name),
+                kind: DiffKind::Changed,
+                breaking: change_is_breaking(before, after),
+            }),
+            Some(_) => {}
+        }
+    }
+    for name in new.keys().filter(|name| !old.contains_key(*name)) {
+        out.push(InterfaceChange { item: item(name), kind: DiffKind::Added, breaking: false });
+    }
+}
+
+/// Conservative: any method signature change or removal is breaking, as is any type change
+/// other than a record gaining opt fields.
+fn diff(old: &Interface, new: &Interface) -> Vec<InterfaceChange> {
+    let mut changes = Vec::new();
+    diff_items("method", &old.methods, &new.methods, |_, _| true, &mut changes);
+    diff_items("type", &old.types, &new.types, |before, after| !only_adds_opt_fields(before, after), &mut changes);
+    changes
+}
+
+fn compare(client_hash: String, pinned: Option<&Interface>, current: &Interface, current_hash: String) -> CompatReport {
+    let (compatibility, changes) = if client_hash == current_hash {
+        (Compatibility::Identical, vec![])
+    } else if let Some(pinned) = pinned {
+        let changes = diff(pinned, current);
+        let compatibility =
+            if changes.iter().any(|c| c.breaking) { Compatibility::Breaking } else { Compatibility::Additive };
+        (compatibility, changes)
+    } else {
+        (Compatibility::UnknownHash, vec![])
+    };
+    CompatReport { client_hash, current_hash, compatibility, changes }
+}
+
+/// The candid interface this wasm was built with, verbatim.
+#[query]
+fn get_candid_interface() -> String {
+    DID.to_string()
+}
+
+/// Compare the interface a client pinned (by `interface_hash`) with the running one.
+#[query]
+fn check_client_compatibility(client_hash: String) -> CompatReport {
+    let pinned = INTERFACES.with(|m| m.borrow().get(&client_hash)).map(|s| s.interface);
+    CURRENT.with(|(current, hash)| compare(client_hash, pinned.as_ref(), current, hash.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: &str = "
+        type Item = record { id : nat64; name : text };
+        // comments are not part of the interface
+        service : (opt InitArgs) -> {
+          get_item : (nat64) -> (opt Item) query;
+          put_item : (Item) -> ();
+        }
+    ";
+
+    fn report(new: &str) -> CompatReport {
+        let (old, new) = (parse(V1), parse(new));
+        compare(old.hash(), Some(&old), &new, new.hash())
+    }
+
+    fn breaking_items(r: &CompatReport) -> Vec<&str> {
+        r.changes.iter().filter(|c| c.breaking).map(|c| c.item.as_str()).collect()
+    }
+
+    #[test]
+    fn embedded_did_parses_to_the_exported_methods() {
+        let current = parse(DID);
+        assert!(current.methods.contains_key("check_client_compatibility"));
+        assert!(current.types.contains_key("CompatReport"));
+        assert_eq!(current.methods["get_candid_interface"], "()->(text)query");
+    }
+
+    #[test]
+    fn formatting_and_order_do_not_change_the_hash() {
+        let reformatted = "service:(opt InitArgs)->{put_item:(Item)->();get_item:(nat64)->(opt Item) query;};
+            type Item = record {
+              id : nat64;
+              name : text;
+            };";
+        assert_eq!(parse(reformatted), parse(V1));
+        assert_eq!(report(reformatted).compatibility, Compatibility::Identical);
+    }
+
+    #[test]
+    fn diffs_separate_additive_from_breaking_changes() {
+        let added_method = V1.replace("put_item : (Item) -> ();", "put_item : (Item) -> ();\n del_item : (nat64) -> ();");
+        let r = report(&added_method);
+        assert_eq!(r.compatibility, Compatibility::Additive);
+        assert_eq!(r.changes, vec![InterfaceChange { item: "method del_item".into(), kind: DiffKind::Added, breaking: false }]);
+
+        let added_opt_field = V1.replace("name : text }", "name : text; tags : opt vec text }");
+        assert_eq!(report(&added_opt_field).compatibility, Compatibility::Additive);
+
+        let added_required_field = V1.replace("name : text }", "name : text; owner : principal }");
+        let r = report(&added_required_field);
+        assert_eq!(r.compatibility, Compatibility::Breaking);
+        assert_eq!(breaking_items(&r), vec!["type Item"]);
+
+        let changed_type = V1.replace("id : nat64;", "id : text;");
+        assert_eq!(breaking_items(&report(&changed_type)), vec!["type Item"]);
+
+        let changed_method = V1.replace("(nat64) -> (opt Item)", "(text) -> (opt Item)");
+        assert_eq!(breaking_items(&report(&changed_method)), vec!["method get_item"]);
+
+        let removed_method = V1.replace("put_item : (Item) -> ();", "");
+        let r = report(&removed_method);
+        assert_eq!(r.changes[0].kind, DiffKind::Removed);
+        assert_eq!(r.compatibility, Compatibility::Breaking);
+    }
+
+    #[test]
+    fn unknown_client_hashes_are_reported_as_such() {
+        let current = parse(V1);
+        let r = compare("0123456789abcdef".into(), None, &current, current.hash());
+        assert_eq!(r.compatibility, Compatibility::UnknownHash);
+        assert!(r.changes.is_empty());
+    }
+}
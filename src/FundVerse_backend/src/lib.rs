@@ -5,27 +5,77 @@
 
 use std::{borrow::Cow, cell::RefCell};
 
-use candid::{CandidType, Decode, Encode, Deserialize};
+use candid::{CandidType, Decode, Encode, Deserialize, Principal};
 use ic_cdk::{self};
-use ic_cdk_macros::{init, query, update};
+use ic_cdk_macros::{init, post_upgrade, query, update};
+
+mod syndication;
+use syndication::{Partner, SyndicatedIdea, SyndicatedIdeaStatus, SyndicationError};
+mod sync;
+use sync::{record_change, record_change_at, ChangeBatch, ChangeKind};
+mod build_info;
+use build_info::BuildInfo;
+mod capabilities;
+mod archive;
+mod interface;
+mod badges;
+mod co_marketing;
+use co_marketing::{MarketingPartner, PartnerRef};
+mod payouts;
+use payouts::{CampaignOutcome, Payout};
+mod amount;
+use amount::{Amount, AmountError};
+mod icp_ledger;
+use icp_ledger::{Account, IcpPledgeError};
+mod takedown;
+use takedown::{Takedown, TakedownNotice, TakedownReport};
+mod campaign_feed;
+use campaign_feed::{CampaignComment, CampaignUpdate};
+mod search_ranking;
+use search_ranking::{CampaignSearchHit, SearchWeights};
+mod channels;
+use channels::{Channel, ChannelStats};
+mod fiat_goal;
+use fiat_goal::{ExchangeRate, FiatGoal, FiatGoalRequest, GoalRateLock};
+mod review_pipeline;
+use review_pipeline::{IdeaReview, ReviewPipeline, ReviewQueueEntry, ReviewStage};
+mod profiles;
+use profiles::{ProfileUpdate, UserProfile};
+mod permissions;
+use permissions::Permissions;
+mod feature_flags;
+use feature_flags::FlagState;
+mod milestones;
+use milestones::Milestone;
+mod activity;
+use activity::{Event, EventKind};
+mod backup;
+use backup::{ExportBundle, ExportEntity, ImportError, ImportMode, ImportReport};
+mod quotas;
+use quotas::{QuotaKind, QuotaLimits, QuotaUsage};
+mod categories;
+use categories::Category;
+mod expiry;
+use expiry::ExpirySweep;
+mod watchlist;
+mod currency;
+use currency::{Currency, CurrencyTotals, Money, CURRENCY_MISMATCH};
+use badges::{BadgeThresholds, IdeaBadge, IdeaStats};
+use interface::CompatReport;
+use capabilities::Capabilities;
 
 // ---- Stable storage (Ideas) ----
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
-use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, storable::Bound , Storable};
-use std::collections::HashMap;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell, storable::Bound , Storable};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 
-const MAX_VALUE_SIZE: u32 = 2000;
-
 // Global memory manager + stable map for ideas
 thread_local! {
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
         RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
 
-    static DOCS: std::cell::RefCell<HashMap<u64, Doc>> = Default::default();
-    static IDEA_COUNTER: std::cell::RefCell<u64> = std::cell::RefCell::new(0);
-    static DOC_COUNTER: std::cell::RefCell<u64> = std::cell::RefCell::new(0);
 
     static IDEAS: RefCell<StableBTreeMap<u64, Idea, Memory>> = RefCell::new(
         // Use memory 0 for ideas map
@@ -34,11 +84,221 @@ thread_local! {
         )
     );
 
-    // In-heap vector for campaigns (simple MVP). You can move this to stable later if needed.
-    static CAMPAIGNS: RefCell<Vec<Campaign>> = RefCell::new(Vec::new());
+    static CAMPAIGNS: RefCell<StableBTreeMap<u64, Campaign, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(21)))
+        )
+    );
+
+    static CAMPAIGN_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(22))),
+            0,
+        ).expect("init campaign counter")
+    );
+
+    // campaign_id -> every contribution pause, kept for transparency
+    static CAMPAIGN_PAUSES: RefCell<StableBTreeMap<u64, PauseLog, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(23)))
+        )
+    );
+
+    static DOCS: RefCell<StableBTreeMap<u64, Doc, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(24)))
+        )
+    );
+
+    static DOC_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(25))),
+            0,
+        ).expect("init doc counter")
+    );
+
+    // Chunked document uploads in progress; chunks keyed by (upload_id, chunk_index)
+    static DOC_UPLOADS: RefCell<StableBTreeMap<u64, PendingUpload, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(26)))
+        )
+    );
+
+    static DOC_UPLOAD_CHUNKS: RefCell<StableBTreeMap<(u64, u32), Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(27)))
+        )
+    );
+
+    static DOC_UPLOAD_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(28))),
+            0,
+        ).expect("init doc upload counter")
+    );
     
-    // ICP contributions tracking: campaign_id -> total ICP amount in e8s
-    static ICP_CONTRIBUTIONS: RefCell<HashMap<u64, u64>> = RefCell::new(HashMap::new());
+    // ICP relayed by Fund_Flow: campaign_id -> total e8s. Fund_Flow holds and settles it;
+    // contribute_icp pledges are held here and are not counted.
+    static ICP_CONTRIBUTIONS: RefCell<StableBTreeMap<u64, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(40)))
+        )
+    );
+
+    // Idea records that failed to decode, kept for inspection/repair (memory 1)
+    static CORRUPT_RECORDS: RefCell<StableBTreeMap<u64, CorruptRecord, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(1)))
+        )
+    );
+
+    // Duplicate detection: idea_id -> fingerprint (memory 2)
+    static IDEA_FINGERPRINTS: RefCell<StableBTreeMap<u64, IdeaFingerprint, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(2)))
+        )
+    );
+
+    // (bucket, idea_id) -> () so candidates are found by bucket prefix instead of a full scan (memory 3)
+    static FINGERPRINT_INDEX: RefCell<StableBTreeMap<(u64, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(3)))
+        )
+    );
+
+    // Ideas flagged as likely duplicates, waiting for moderation (memory 4)
+    static DUPLICATE_FLAGS: RefCell<StableBTreeMap<u64, DuplicateFlag, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(4)))
+        )
+    );
+
+    // In-progress category migration, so a large rewrite can span several calls (memory 6)
+    static CATEGORY_MIGRATION: RefCell<StableCell<CategoryMigration, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(6))),
+            CategoryMigration::default(),
+        ).expect("init category migration")
+    );
+
+    // Idea discussion: comments by id, thread indexes keyed by (idea_id | parent_id, comment_id)
+    static IDEA_COMMENTS: RefCell<StableBTreeMap<u64, IdeaComment, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(7)))
+        )
+    );
+
+    static TOP_LEVEL_COMMENTS: RefCell<StableBTreeMap<(u64, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(8)))
+        )
+    );
+
+    static COMMENT_REPLIES: RefCell<StableBTreeMap<(u64, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(9)))
+        )
+    );
+
+    static IDEA_COMMENT_COUNTS: RefCell<StableBTreeMap<u64, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(10)))
+        )
+    );
+
+    static COMMENT_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(11))),
+            0,
+        ).expect("init comment counter")
+    );
+
+    // Similarity (percent) at or above which a new idea is flagged (memory 5)
+    static DUPLICATE_THRESHOLD_PCT: RefCell<StableCell<u8, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(5))),
+            DEFAULT_DUPLICATE_THRESHOLD_PCT,
+        ).expect("init duplicate threshold")
+    );
+
+    // Platform default hour (UTC) campaign end dates snap to; NO_END_HOUR keeps them exact
+    static CAMPAIGN_END_HOUR: RefCell<StableCell<u8, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(41))),
+            NO_END_HOUR,
+        ).expect("init campaign end hour")
+    );
+
+    // MemoryIds 12-13 and 63 belong to the syndication module, 14-16 to the sync feed, 33-35 to the
+    // contribution archive, 36 to the interface history, 37-39 to idea badges, 42-45 to
+    // co-marketing partners, 46-48 to payouts, 49-50 to the ICP ledger link,
+    // 51-52 to takedowns, 53-55 to campaign updates and comments, 56 to search weights,
+    // 57-58 to fiat goals, 59-60 to review pipelines, 61-62 to user profiles,
+    // 64 to feature flags, 65-66 to campaign milestones, 67-70 to the activity log,
+    // 71-72 to quotas, 73 to the category registry, 74 to the expiry sweep, 75-76 to watchlists.
+
+    static CONTRIBUTIONS: RefCell<StableBTreeMap<u64, Contribution, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(17)))
+        )
+    );
+
+    static CONTRIBUTION_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(18))),
+            0,
+        ).expect("init contribution counter")
+    );
+
+    // (campaign_id, contribution_id) and (contributor, contribution_id) indexes
+    static CAMPAIGN_CONTRIBUTIONS: RefCell<StableBTreeMap<(u64, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(19)))
+        )
+    );
+
+    static CONTRIBUTOR_CONTRIBUTIONS: RefCell<StableBTreeMap<(Principal, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(20)))
+        )
+    );
+
+    // (owner, id) indexes for get_my_ideas / get_my_campaigns
+    static IDEAS_BY_OWNER: RefCell<StableBTreeMap<(Principal, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(29)))
+        )
+    );
+
+    static CAMPAIGNS_BY_OWNER: RefCell<StableBTreeMap<(Principal, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(30)))
+        )
+    );
+
+    // Highest idea id handed out; ideas can be deleted, so ids can't come from the map's length.
+    static IDEA_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(31))),
+            0,
+        ).expect("init idea counter")
+    );
+
+    // Principals allowed to review ideas (controllers always are)
+    static ADMINS: RefCell<StableBTreeMap<Principal, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(32)))
+        )
+    );
+
+    // Dry-run confirmation tokens. Heap-only: after an upgrade, just dry-run again.
+    static CONFIRMATIONS: RefCell<HashMap<String, PendingConfirmation>> = RefCell::new(HashMap::new());
+    static CONFIRMATION_SEQ: RefCell<u64> = const { RefCell::new(0) };
+}
+
+struct PendingConfirmation {
+    digest: u64, // operation + parameters + caller
+    expires_at: u64,
 }
 
 // ------------- Data Models -------------
@@ -50,13 +310,39 @@ pub struct Idea {
     pub funding_goal: u64,
     pub current_funding: u64,
     pub legal_entity: String,
-    pub status: Option<String>, // e.g., "pending", "approved", "rejected"
+    pub status: IdeaStatus,
     pub contact_info: String,
     pub category: String,       // e.g., "technology", "healthcare", "education"
     pub business_registration: u8,
     pub created_at: u64,        // ns since epoch
     pub updated_at: u64,        // ns since epoch
     pub doc_ids: Vec<u64>,      // IDs of uploaded documents
+    pub owner: Principal,       // creator; anonymous for ideas stored before ownership
+    pub external_ref: Option<String>, // the partner's external id, for syndicated ideas
+}
+
+#[derive(CandidType, Deserialize, serde::Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum IdeaStatus {
+    Draft, // saved by its owner and private to them until `submit_idea`
+    Pending,
+    Approved,
+    Rejected { reason: String },
+    Quarantined { bytes: Vec<u8> }, // placeholder holding an undecodable record's raw bytes; never set on a real idea
+}
+
+impl IdeaStatus {
+    /// Map the free-text status of older records; anything unrecognised stays pending.
+    fn from_legacy(status: Option<String>) -> Self {
+        match status.as_deref().map(str::trim) {
+            Some(s) if s.eq_ignore_ascii_case("approved") => IdeaStatus::Approved,
+            Some(s) if s.eq_ignore_ascii_case("rejected") => IdeaStatus::Rejected { reason: String::new() },
+            _ => IdeaStatus::Pending,
+        }
+    }
+
+    fn same_kind(&self, other: &IdeaStatus) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
 }
 
 #[derive(CandidType, Deserialize, Clone)]
@@ -66,370 +352,4605 @@ pub struct Doc {
     pub name: String,       // original filename
     pub content_type: String, // e.g., "application/pdf"
     pub data: Vec<u8>,        // raw file bytes
-    pub uploaded_at: u64,
+    pub uploaded_at: u64,     // ns, set by the canister
 }
 
-// Store Idea in stable memory by encoding/decoding with candid.
-impl Storable for Idea {
+impl Storable for Doc {
     fn to_bytes(&self) -> Cow<'_, [u8]> {
-        Cow::Owned(Encode!(self).expect("encode Idea"))
+        Cow::Owned(Encode!(self).expect("encode Doc"))
     }
 
-
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).expect("decode Idea")
+        Decode!(bytes.as_ref(), Self).expect("decode Doc")
     }
 
-    const BOUND: Bound = Bound::Bounded {
-        max_size: MAX_VALUE_SIZE,
-        is_fixed_size: false,
-    };
+    // Unbounded: a doc is whatever fits in one upload message (~2MB), and the map handles that size.
+    const BOUND: Bound = Bound::Unbounded;
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct Campaign {
-    pub id: u64,
-    pub idea_id: u64,      // 🔗 foreign key to Idea
-    pub amount_raised: u64,
-    pub goal: u64,
-    pub end_date: u64,     // seconds since Unix epoch
+pub struct PendingUpload {
+    pub idea_id: u64,
+    pub name: String,
+    pub content_type: String,
+    pub total_size: u64,
+    pub received: u64, // bytes across all chunks so far
+    pub chunk_count: u32,
+    pub uploader: Principal,
+    pub started_at: u64, // ns; the upload expires DOC_UPLOAD_TTL_NS after this
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct CampaignCard {
-    pub id: u64,
-    pub idea_id: u64,      // 🔗
-    pub title: String,     // from Idea
-    pub category: String,  // from Idea
-    pub amount_raised: u64,
-    pub goal: u64,
-    pub end_date: u64,
-<<<<<<< HEAD
-    pub days_left: i64,    // negative => ended
-=======
-    pub days_left: u64,    // negative => ended
->>>>>>> 9d8c40b (Initial commit)
-}
+impl Storable for PendingUpload {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode PendingUpload"))
+    }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub enum CampaignStatus {
-    Active,
-    Ended,
-}
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode PendingUpload")
+    }
 
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct CampaignWithIdea {
-    pub campaign: CampaignCard,
-    pub idea: Idea,
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// New struct for Fund_Flow canister integration
-#[derive(CandidType, Deserialize, Clone, Debug)]
-pub struct CampaignMeta {
-    pub campaign_id: u64,
-    pub goal: u64,
-    pub amount_raised: u64,
-    pub end_date_secs: u64, // seconds since epoch
+// Idea as stored before documents were attached (no `doc_ids`).
+#[derive(CandidType, Deserialize)]
+struct IdeaV0 {
+    title: String,
+    description: String,
+    funding_goal: u64,
+    current_funding: u64,
+    legal_entity: String,
+    status: Option<String>,
+    contact_info: String,
+    category: String,
+    business_registration: u8,
+    created_at: u64,
+    updated_at: u64,
 }
 
-// ------------- Helpers -------------
+impl From<IdeaV0> for Idea {
+    fn from(v: IdeaV0) -> Self {
+        Idea {
+            title: v.title,
+            description: v.description,
+            funding_goal: v.funding_goal,
+            current_funding: v.current_funding,
+            legal_entity: v.legal_entity,
+            status: IdeaStatus::from_legacy(v.status),
+            contact_info: v.contact_info,
+            category: v.category,
+            business_registration: v.business_registration,
+            created_at: v.created_at,
+            updated_at: v.updated_at,
+            doc_ids: vec![],
+            owner: Principal::anonymous(),
+            external_ref: None,
+        }
+    }
+}
 
-fn now_secs() -> u64 {
-    // ic_cdk::api::time returns ns
-    ic_cdk::api::time() / 1_000_000_000
+// Idea as stored before ownership (no `owner`).
+#[derive(CandidType, Deserialize)]
+struct IdeaV1 {
+    title: String,
+    description: String,
+    funding_goal: u64,
+    current_funding: u64,
+    legal_entity: String,
+    status: Option<String>,
+    contact_info: String,
+    category: String,
+    business_registration: u8,
+    created_at: u64,
+    updated_at: u64,
+    doc_ids: Vec<u64>,
 }
 
-fn to_card(c: &Campaign, idea: &Idea) -> CampaignCard {
-    let now = now_secs() as i64;
-<<<<<<< HEAD
-    let days_left = ((c.end_date as i64) - now) / 86_400; // 86400 secs/day
-=======
-    let days_left_i64 = ((c.end_date as i64) - now) / 86_400;
-    let days_left = if days_left_i64 < 0 { 0 } else { days_left_i64 as u64 };
-    
->>>>>>> 9d8c40b (Initial commit)
-    CampaignCard {
-        id: c.id,
-        idea_id: c.idea_id,
-        title: idea.title.clone(),
-        category: idea.category.clone(),
-        amount_raised: c.amount_raised,
-        goal: c.goal,
-        end_date: c.end_date,
-        days_left,
+impl From<IdeaV1> for Idea {
+    fn from(v: IdeaV1) -> Self {
+        Idea {
+            title: v.title,
+            description: v.description,
+            funding_goal: v.funding_goal,
+            current_funding: v.current_funding,
+            legal_entity: v.legal_entity,
+            status: IdeaStatus::from_legacy(v.status),
+            contact_info: v.contact_info,
+            category: v.category,
+            business_registration: v.business_registration,
+            created_at: v.created_at,
+            updated_at: v.updated_at,
+            doc_ids: v.doc_ids,
+            owner: Principal::anonymous(),
+            external_ref: None,
+        }
     }
 }
 
-<<<<<<< HEAD
-=======
-
->>>>>>> 9d8c40b (Initial commit)
-fn get_idea(id: u64) -> Option<Idea> {
-    IDEAS.with(|map| map.borrow().get(&id))
+// Idea as stored before the status enum (free-text `status`).
+#[derive(CandidType, Deserialize)]
+struct IdeaV2 {
+    title: String,
+    description: String,
+    funding_goal: u64,
+    current_funding: u64,
+    legal_entity: String,
+    status: Option<String>,
+    contact_info: String,
+    category: String,
+    business_registration: u8,
+    created_at: u64,
+    updated_at: u64,
+    doc_ids: Vec<u64>,
+    owner: Principal,
 }
 
-fn get_campaign(id: u64) -> Option<Campaign> {
-    CAMPAIGNS.with(|store| {
-        store.borrow().iter().find(|c| c.id == id).cloned()
-    })
+impl From<IdeaV2> for Idea {
+    fn from(v: IdeaV2) -> Self {
+        Idea {
+            title: v.title,
+            description: v.description,
+            funding_goal: v.funding_goal,
+            current_funding: v.current_funding,
+            legal_entity: v.legal_entity,
+            status: IdeaStatus::from_legacy(v.status),
+            contact_info: v.contact_info,
+            category: v.category,
+            business_registration: v.business_registration,
+            created_at: v.created_at,
+            updated_at: v.updated_at,
+            doc_ids: v.doc_ids,
+            owner: v.owner,
+            external_ref: None,
+        }
+    }
 }
 
-fn update_campaign_amount(campaign_id: u64, new_amount: u64) {
-    CAMPAIGNS.with(|store| {
-        if let Some(campaign) = store.borrow_mut().iter_mut().find(|c| c.id == campaign_id) {
-            campaign.amount_raised = new_amount;
+impl Idea {
+    /// Placeholder returned for records that match no known schema, carrying their raw bytes.
+    fn quarantined(bytes: Vec<u8>) -> Self {
+        Idea {
+            title: String::new(),
+            description: String::new(),
+            funding_goal: 0,
+            current_funding: 0,
+            legal_entity: String::new(),
+            status: IdeaStatus::Quarantined { bytes },
+            contact_info: String::new(),
+            category: String::new(),
+            business_registration: 0,
+            created_at: 0,
+            updated_at: 0,
+            doc_ids: vec![],
+            owner: Principal::anonymous(),
+            external_ref: None,
         }
-    });
-}
+    }
 
-/// Upload a document for an Idea. Returns the new doc_id or None if idea doesn't exist.
-#[update]
-fn upload_doc(idea_id: u64, name: String, content_type: String, data: Vec<u8>, uploaded_at: u64) -> Option<u64> {
-    if !IDEAS.with(|ideas| ideas.borrow().contains_key(&idea_id)) {
-        return None; // idea doesn't exist
+    fn is_quarantined(&self) -> bool {
+        matches!(self.status, IdeaStatus::Quarantined { .. })
     }
 
-    DOC_COUNTER.with(|c| {
-        let mut c = c.borrow_mut();
-        *c += 1;
-        let doc_id = *c;
+    fn is_draft(&self) -> bool {
+        self.status == IdeaStatus::Draft
+    }
 
-        let doc = Doc {
-            id: doc_id,
-            idea_id,
-            name,
-            content_type,
-            data,
-            uploaded_at,
-        };
+    /// Whether public listings and search show it: neither a draft nor an unreadable record.
+    fn is_listed(&self) -> bool {
+        !self.is_quarantined() && !self.is_draft()
+    }
+}
 
-        DOCS.with(|docs| docs.borrow_mut().insert(doc_id, doc));
+/// Try the current schema first, then known legacy schemas.
+fn decode_idea(bytes: &[u8]) -> Option<Idea> {
+    Decode!(bytes, Idea)
+        .ok()
+        .or_else(|| Decode!(bytes, IdeaV2).ok().map(Idea::from))
+        .or_else(|| Decode!(bytes, IdeaV1).ok().map(Idea::from))
+        .or_else(|| Decode!(bytes, IdeaV0).ok().map(Idea::from))
+}
 
-        // attach to idea
-        IDEAS.with(|ideas| {
-            if let Some(mut idea) = ideas.borrow().get(&idea_id) {
-                idea.doc_ids.push(doc_id);
-                ideas.borrow_mut().insert(idea_id, idea);
-            }
-        });
+// Store Idea in stable memory by encoding/decoding with candid.
+impl Storable for Idea {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Idea"))
+    }
 
-        Some(doc_id)
-    })
+    // Never trap here: one bad record would make every listing that touches it fail.
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_idea(bytes.as_ref()).unwrap_or_else(|| Idea::quarantined(bytes.into_owned()))
+    }
+
+    // doc_ids grows with every upload, so no fixed bound fits; maps load as v2 and keep large values in
+    // overflow pages, so records stored under the old 2000-byte bound read back unchanged.
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// ------------- Public API -------------
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CorruptRecord {
+    pub key: u64,
+    pub bytes: Vec<u8>,
+    pub detected_at: u64,
+}
 
-/// Create an Idea and persist it in stable storage. Returns the new idea_id.
-#[update]
-fn create_idea(
-    title: String,
-    description: String,
-    funding_goal: u64,
-    legal_entity: String,
-    contact_info: String,
-    category: String,
-    business_registration: u8,
-) -> u64 {
-    if title.is_empty()
-        || description.is_empty()
-        || funding_goal == 0
-        || legal_entity.is_empty()
-        || contact_info.is_empty()
-        || category.is_empty()
-    {
-        ic_cdk::trap(
-            "Invalid input: all fields must be provided and funding_goal must be > 0.",
-        );
+impl Storable for CorruptRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode CorruptRecord"))
     }
 
-    let now = ic_cdk::api::time();
-    let idea = Idea {
-        title,
-        description,
-        funding_goal,
-        current_funding: 0,
-        legal_entity,
-        status: Some("pending".to_string()),
-        contact_info,
-        doc_ids : vec![],
-        category,
-        business_registration,
-        created_at: now,
-        updated_at: now,
-    };
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode CorruptRecord")
+    }
 
-    // naive id generation = len + 1 (OK for MVP)
-    // consider a StableCell counter for production.
-    IDEAS.with(|ideas| {
-        let mut ideas = ideas.borrow_mut();
-        let id = (ideas.len() as u64) + 1;
-        ideas.insert(id, idea);
-        id
-    })
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct IdeaFingerprint {
+    pub title_hash: u64,
+    pub minhash: Vec<u64>, // MinHash signature of the description's word shingles
+}
+
+impl Storable for IdeaFingerprint {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode IdeaFingerprint"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode IdeaFingerprint")
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 256,
+        is_fixed_size: false,
+    };
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DuplicateMatch {
+    pub idea_id: u64,
+    pub similarity_pct: u8,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DuplicateFlag {
+    pub idea_id: u64,
+    pub matches: Vec<DuplicateMatch>, // best match first
+    pub flagged_at: u64,
+}
+
+impl Storable for DuplicateFlag {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode DuplicateFlag"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode DuplicateFlag")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct CategoryMigration {
+    pub active: bool,
+    pub from: String,
+    pub to: String,
+    pub next_idea_id: u64, // resume point
+    pub ideas_moved: u64,
+    pub campaigns_moved: u64,
+}
+
+impl Storable for CategoryMigration {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode CategoryMigration"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode CategoryMigration")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct MigrationReport {
+    pub from: String,
+    pub to: String,
+    pub dry_run: bool,
+    pub ideas_moved: u64,     // cumulative across calls of the same migration
+    pub campaigns_moved: u64, // campaigns whose idea was moved
+    pub done: bool,           // false => call again to continue
+    pub sample_ids: Vec<u64>, // dry run: first affected idea ids
+    pub confirmation_token: Option<String>, // dry run: pass back to start the live run
+}
+
+/// What a destructive admin operation changed, or would change when `dry_run` is set.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DryRunReport {
+    pub dry_run: bool,
+    pub affected: u64,
+    pub sample_ids: Vec<u64>, // capped at DRY_RUN_SAMPLE
+    pub confirmation_token: Option<String>, // issued by dry runs only
+}
+
+/// Slim idea view for list screens; see `get_idea_summaries`.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct IdeaSummary {
+    pub id: u64,
+    pub title: String,
+    pub teaser: String, // first ~140 chars of the description
+    pub category: String,
+    pub percent_funded: u32, // may exceed 100
+    pub comment_count: u64,
+    pub badges: Vec<IdeaBadge>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct IdeaSummaryFilter {
+    pub category: Option<String>, // case-insensitive
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SummaryPage {
+    pub items: Vec<IdeaSummary>,
+    pub next_cursor: Option<u64>, // pass back as `cursor`; None => no more items
+}
+
+/// One page of an id-ordered listing; see `paginate`.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PagedResult<T> {
+    pub items: Vec<T>,
+    pub total: u64,               // every match, not just this page
+    pub next_offset: Option<u64>, // pass back as `offset`; None => no more items
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct IdeaComment {
+    pub id: u64,
+    pub idea_id: u64,
+    pub parent_id: Option<u64>, // None => top-level
+    pub author: candid::Principal,
+    pub text: String,
+    pub created_at: u64,
+    pub reply_count: u64, // always 0 for replies
+}
+
+impl Storable for IdeaComment {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode IdeaComment"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode IdeaComment")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Contribution {
+    pub id: u64,
+    pub campaign_id: u64,
+    pub contributor: Principal,
+    pub amount: u64,
+    pub timestamp: u64, // ns
+    pub channel: Option<Channel>, // None for pledges recorded before channels were kept
+    pub currency: Option<Currency>, // always the campaign's; None for pledges from before currencies (ICP)
+    pub risks_acked_at: Option<u64>, // ns; set when the campaign had a risks section to acknowledge
+}
+
+impl Storable for Contribution {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Contribution"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode Contribution")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PauseWindow {
+    pub reason: String,        // shown publicly on the card
+    pub started_at: u64,       // seconds since epoch
+    pub ended_at: Option<u64>, // None while paused (or until `expires_at`)
+    pub expires_at: u64,       // start + pause budget left at that time; resumes on its own here
+}
+
+impl PauseWindow {
+    fn end(&self, now_secs: u64) -> u64 {
+        self.ended_at.unwrap_or(now_secs).min(self.expires_at)
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct PauseLog {
+    pub windows: Vec<PauseWindow>,
+}
+
+impl PauseLog {
+    fn active(&self, now_secs: u64) -> Option<&PauseWindow> {
+        self.windows.last().filter(|w| w.ended_at.is_none() && now_secs < w.expires_at)
+    }
+
+    fn paused_secs(&self, now_secs: u64) -> u64 {
+        self.windows.iter().map(|w| w.end(now_secs).saturating_sub(w.started_at)).sum()
+    }
+}
+
+impl Storable for PauseLog {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode PauseLog"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode PauseLog")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Form field a validation error refers to.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldId {
+    Title,
+    Description,
+    FundingGoal,
+    LegalEntity,
+    ContactInfo,
+    Category,
+    DisplayName, // profile fields
+    Email,
+    Bio,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleId {
+    Required,
+    MinValue,  // `limit` is the minimum
+    MaxLength, // `limit` is the maximum, in bytes
+    Format,    // contact_info, category or email doesn't have an accepted shape
+    NotListed, // category isn't an active one from list_categories
+}
+
+/// Machine-readable validation failure the frontend can map onto a form field.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: FieldId,
+    pub rule: RuleId,
+    pub limit: Option<u64>,
+    pub actual: Option<u64>,
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = match self.field {
+            FieldId::Title => "title",
+            FieldId::Description => "description",
+            FieldId::FundingGoal => "funding_goal",
+            FieldId::LegalEntity => "legal_entity",
+            FieldId::ContactInfo => "contact_info",
+            FieldId::Category => "category",
+            FieldId::DisplayName => "display_name",
+            FieldId::Email => "email",
+            FieldId::Bio => "bio",
+        };
+        match (self.rule, self.limit) {
+            (RuleId::MinValue, Some(min)) => write!(f, "{} must be at least {}", field, min),
+            (RuleId::MaxLength, Some(max)) => write!(f, "{} must be at most {} bytes", field, max),
+            (RuleId::Format, _) if self.field == FieldId::ContactInfo => {
+                write!(f, "contact_info must be an email address, a phone number or an https:// link")
+            }
+            (RuleId::Format, _) if self.field == FieldId::Email => write!(f, "email must be an email address"),
+            (RuleId::Format, _) => write!(f, "{} may only use letters, digits, spaces and & - /", field),
+            (RuleId::NotListed, _) => write!(f, "{} must be one of list_categories' active slugs", field),
+            _ => write!(f, "{} is required", field),
+        }
+    }
+}
+
+/// Typed error for endpoints the frontend shows next to a form, instead of a trap or bare text.
+/// New endpoints return this. A lone missing field or zero goal gets its own variant; any other
+/// invalid input is a ValidationError listing every offending field.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum FundVerseError {
+    EmptyField { field: FieldId },
+    InvalidFundingGoal,
+    NotFound(EntityRef),
+    Unauthorized,
+    ValidationError { message: String, errors: Vec<FieldError> },
+    AlreadyRegistered, // the caller already has a profile
+    NotRegistered,     // the caller needs a profile first
+    FeatureDisabled { flag: String },
+    QuotaExceeded { quota: QuotaKind, limit: u64, retry_at: Option<u64> }, // retry_at in ns, for daily quotas
+    CurrencyMismatch { expected: Currency, got: Currency }, // a pledge in another currency than the campaign's
+    NotADraft { status: IdeaStatus }, // the idea was submitted already
+}
+
+impl From<Vec<FieldError>> for FundVerseError {
+    fn from(errors: Vec<FieldError>) -> Self {
+        match errors.as_slice() {
+            [FieldError { field, rule: RuleId::Required, .. }] => FundVerseError::EmptyField { field: *field },
+            [FieldError { field: FieldId::FundingGoal, rule: RuleId::MinValue, .. }] => {
+                FundVerseError::InvalidFundingGoal
+            }
+            _ => FundVerseError::ValidationError { message: describe_field_errors(&errors), errors },
+        }
+    }
+}
+
+impl std::fmt::Display for FundVerseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FundVerseError::EmptyField { field } => {
+                write!(f, "{}", FieldError { field: *field, rule: RuleId::Required, limit: None, actual: None })
+            }
+            FundVerseError::InvalidFundingGoal => write!(f, "funding_goal must be at least 1"),
+            FundVerseError::NotFound(entity) => write!(f, "{}", not_found(*entity)),
+            FundVerseError::Unauthorized => write!(f, "not allowed for this caller"),
+            FundVerseError::ValidationError { message, .. } => write!(f, "{}", message),
+            FundVerseError::AlreadyRegistered => write!(f, "this principal already has a profile"),
+            FundVerseError::NotRegistered => write!(f, "register a profile first"),
+            FundVerseError::FeatureDisabled { flag } => write!(f, "feature '{}' is not enabled", flag),
+            FundVerseError::QuotaExceeded { quota, limit, .. } => match quota {
+                QuotaKind::IdeasPerDay => write!(f, "at most {} new ideas per 24 hours", limit),
+                QuotaKind::UploadBytesPerDay => write!(f, "at most {} bytes of uploads per 24 hours", limit),
+                QuotaKind::DocsPerIdea => write!(f, "an idea can have at most {} documents", limit),
+                QuotaKind::DocSize => write!(f, "documents must be at most {} bytes", limit),
+            },
+            FundVerseError::CurrencyMismatch { expected, got } => {
+                write!(f, "{}: campaign raises {}, not {}", CURRENCY_MISMATCH, expected, got)
+            }
+            FundVerseError::NotADraft { status } => {
+                write!(f, "idea was already submitted ({:?}); only drafts can be edited as drafts or submitted", status)
+            }
+        }
+    }
+}
+
+impl From<FundVerseError> for String {
+    fn from(e: FundVerseError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Typed reference to any entity the backend stores, so "idea 7" and "campaign 7" can't be confused.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityRef {
+    Idea(u64),
+    Campaign(u64),
+    Doc(u64),
+    Comment(u64),
+}
+
+impl std::fmt::Display for EntityRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntityRef::Idea(id) => write!(f, "idea {}", id),
+            EntityRef::Campaign(id) => write!(f, "campaign {}", id),
+            EntityRef::Doc(id) => write!(f, "doc {}", id),
+            EntityRef::Comment(id) => write!(f, "comment {}", id),
+        }
+    }
+}
+
+/// Just enough to render a link to an entity.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct EntitySummary {
+    pub entity: EntityRef,
+    pub label: String,               // title, file name or comment excerpt
+    pub parent: Option<EntityRef>,   // owning idea for campaigns, docs and comments
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Campaign {
+    pub id: u64,
+    pub idea_id: u64,      // 🔗 foreign key to Idea
+    pub amount_raised: u64,
+    pub goal: u64,
+    pub end_date: u64,     // seconds since Unix epoch
+    pub risks: Option<String>, // "Risks and challenges" disclosure
+    pub risk_amendments: Vec<RiskAmendment>, // appended once the campaign has raised funds
+    pub round: Option<FundingRound>,
+    pub display_title: Option<String>, // campaign headline shown instead of the idea title
+    pub teaser: Option<String>,
+    pub owner: Option<Principal>, // None for campaigns created before ownership
+    pub status: Option<CampaignLifecycle>, // Cancelled, or the outcome once finalized; see `lifecycle`
+    pub refund_pending: Option<bool>,      // cancelled after raising funds; for the refund flow
+    pub end_hour_utc: Option<u8>,          // hour end dates snap to; None => kept exact
+    pub requested_end_date: Option<u64>,   // the creator's date before snapping
+    pub launched_at: Option<u64>,          // seconds; None for campaigns created before it was kept
+    pub fiat_goal: Option<FiatGoal>,       // `goal` is this amount at its locked rate
+    pub external_ref: Option<String>,      // the syndicated idea's external id, for partner lookups
+    pub currency: Option<Currency>,        // of goal, amount_raised and pledges; None => ICP
+}
+
+// Stored in stable memory: fields added later must be Option so existing records still decode.
+impl Storable for Campaign {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Campaign"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode Campaign")
+    }
+
+    const BOUND: Bound = Bound::Unbounded; // risk amendments grow without limit
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FundingRound {
+    PreSeed,
+    Seed,
+    SeriesA,
+    SeriesB,
+    SeriesC,
+    Custom(String), // unordered: never blocks or is blocked by other rounds
+}
+
+impl FundingRound {
+    fn rank(&self) -> Option<u8> {
+        match self {
+            FundingRound::PreSeed => Some(0),
+            FundingRound::Seed => Some(1),
+            FundingRound::SeriesA => Some(2),
+            FundingRound::SeriesB => Some(3),
+            FundingRound::SeriesC => Some(4),
+            FundingRound::Custom(_) => None,
+        }
+    }
+}
+
+/// One round in an idea's funding history.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RoundTimelineEntry {
+    pub round: Option<FundingRound>, // None => campaigns created without a round
+    pub campaign_ids: Vec<u64>,      // oldest first
+    pub total_raised: u64,
+    pub total_goal: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CategoryRoundCount {
+    pub category: String,
+    pub round: Option<FundingRound>,
+    pub campaigns: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RiskAmendment {
+    pub text: String,
+    pub added_at: u64, // ns
+}
+
+/// Where a campaign is in its life. Until a campaign is cancelled or finalized this follows from
+/// the pause-adjusted end date and the amount raised, so no timer has to flip it.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CampaignLifecycle {
+    Active,
+    Cancelled,
+    Completed, // ended with the goal met
+    Expired,   // ended short of the goal
+    TakenDown, // its idea was removed for legal reasons; refunds queued
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignCard {
+    pub id: u64,
+    pub idea_id: u64,      // 🔗
+    pub title: String,     // campaign display_title, else the Idea title
+    pub idea_title: String,
+    pub teaser: Option<String>,
+    pub category: String,  // from Idea
+    pub amount_raised: u64,
+    pub goal: u64,
+    pub end_date: u64,     // seconds since Unix epoch, pause extensions included
+    pub days_left: i64,    // whole days, rounded down; negative => ended
+    pub ends_in_secs: i64, // 0 at the deadline itself (still open), negative => ended
+    pub status: CampaignLifecycle,
+    pub round: Option<FundingRound>,
+    pub paused: bool,
+    pub pause_reason: Option<String>,
+    pub end_hour_utc: Option<u8>,        // end_date was snapped forward to this hour
+    pub requested_end_date: Option<u64>, // what the creator asked for, when snapped
+    pub partners: Vec<PartnerRef>,       // approved co-marketing partners ("Supported by")
+    pub fiat_goal: Option<FiatGoal>,     // `goal` is its token equivalent at the locked rate
+    pub currency: Currency,              // of amount_raised and goal
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum CampaignStatus {
+    Active,
+    Ended,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortBy {
+    Newest,        // by launch time; campaigns from before it was kept count as launched at 0
+    EndingSoon,    // active campaigns only, soonest end first
+    MostFunded,    // amount raised
+    PercentFunded, // share of the goal raised
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignWithIdea {
+    pub campaign: CampaignCard,
+    pub idea: Idea,
+    pub risks: Option<String>,
+    pub risk_amendments: Vec<RiskAmendment>,
+}
+
+/// A doc without its bytes, for listing an idea's docs.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DocMeta {
+    pub id: u64,
+    pub name: String,
+    pub content_type: String,
+    pub size: u64, // bytes
+    pub uploaded_at: u64,
+}
+
+/// Everything the campaign detail page shows, in one call.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignDetails {
+    pub campaign: CampaignCard,
+    pub idea: Idea,
+    pub risks: Option<String>,
+    pub risk_amendments: Vec<RiskAmendment>,
+    pub docs: Vec<DocMeta>, // in upload order; fetch the bytes with get_doc
+    pub contribution_count: u64,
+}
+
+// New struct for Fund_Flow canister integration
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignMeta {
+    pub campaign_id: u64,
+    pub goal: u64,
+    pub amount_raised: u64,
+    pub end_date_secs: u64, // seconds since epoch
+    pub has_risks: bool,    // pledges must acknowledge the risk disclosure
+    pub paused_reason: Option<String>, // Some while contributions are paused
+    pub cancelled: bool,
+    pub currency: Currency, // of goal and amount_raised
+}
+
+// ------------- Helpers -------------
+
+fn now_secs() -> u64 {
+    // ic_cdk::api::time returns ns
+    to_secs(ic_cdk::api::time())
+}
+
+fn to_secs(ns: u64) -> u64 {
+    ns / 1_000_000_000
+}
+
+/// End dates past this many seconds (year 2286) must have been sent in ms or ns.
+const MAX_END_DATE_SECS: u64 = 10_000_000_000;
+
+fn check_end_date(end_date: u64, now_secs: u64) -> Result<(), String> {
+    if end_date > MAX_END_DATE_SECS {
+        return Err("end_date looks like milliseconds or nanoseconds; pass seconds since the Unix epoch".into());
+    }
+    if end_date <= now_secs {
+        return Err("end_date must be in the future".into());
+    }
+    Ok(())
+}
+
+/// Setting of `CAMPAIGN_END_HOUR` that keeps end dates exactly as requested.
+const NO_END_HOUR: u8 = u8::MAX;
+
+/// How a new campaign's end date is aligned; None in `create_campaign` uses the platform default.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum EndTime {
+    Exact,
+    HourUtc(u8), // 0-23
+}
+
+/// The first `hour`:00:00 UTC at or after `end_date`. It only moves forward, by less than a day,
+/// so snapping never makes a campaign shorter than requested. UTC days are always 86,400 seconds.
+fn snap_end_date(end_date: u64, hour_utc: Option<u8>) -> u64 {
+    let Some(hour) = hour_utc else { return end_date };
+    let target = end_date - end_date % 86_400 + hour as u64 * 3_600;
+    if target >= end_date {
+        target
+    } else {
+        target + 86_400
+    }
+}
+
+fn end_hour_for(end_time: Option<EndTime>) -> Result<Option<u8>, String> {
+    match end_time {
+        None => Ok(Some(CAMPAIGN_END_HOUR.with(|h| *h.borrow().get())).filter(|h| *h != NO_END_HOUR)),
+        Some(EndTime::Exact) => Ok(None),
+        Some(EndTime::HourUtc(hour)) if hour < 24 => Ok(Some(hour)),
+        Some(EndTime::HourUtc(_)) => Err("end hour must be 0-23 (UTC)".into()),
+    }
+}
+
+/// Signed seconds until `end_date`. A campaign takes contributions until this goes negative.
+fn ends_in_secs(end_date: u64, now_secs: u64) -> i64 {
+    end_date as i64 - now_secs as i64
+}
+
+fn lifecycle(c: &Campaign, now_secs: u64) -> CampaignLifecycle {
+    if let Some(stored) = c.status {
+        stored
+    } else if ends_in_secs(effective_end_date(c, now_secs), now_secs) >= 0 {
+        CampaignLifecycle::Active
+    } else if c.amount_raised >= c.goal {
+        CampaignLifecycle::Completed
+    } else {
+        CampaignLifecycle::Expired
+    }
+}
+
+fn to_card(c: &Campaign, idea: &Idea) -> CampaignCard {
+    card_at(c, idea, now_secs())
+}
+
+/// The card as of `now` (seconds).
+fn card_at(c: &Campaign, idea: &Idea, now: u64) -> CampaignCard {
+    let end_date = effective_end_date(c, now);
+    let ends_in_secs = ends_in_secs(end_date, now);
+    let days_left = ends_in_secs.div_euclid(86_400); // 86400 secs/day
+    let pause_reason = active_pause_reason(c.id, now);
+    CampaignCard {
+        id: c.id,
+        idea_id: c.idea_id,
+        title: c.display_title.clone().unwrap_or_else(|| idea.title.clone()),
+        idea_title: idea.title.clone(),
+        teaser: c.teaser.clone(),
+        category: idea.category.clone(),
+        amount_raised: c.amount_raised,
+        goal: c.goal,
+        end_date,
+        days_left,
+        ends_in_secs,
+        status: lifecycle(c, now),
+        round: c.round.clone(),
+        paused: pause_reason.is_some(),
+        pause_reason,
+        end_hour_utc: c.end_hour_utc,
+        requested_end_date: c.requested_end_date,
+        partners: co_marketing::approved_partners(c.id),
+        fiat_goal: c.fiat_goal.clone(),
+        currency: c.currency(),
+    }
+}
+
+/// Fetch an idea; quarantined (undecodable) records are treated as missing until repaired.
+fn get_idea(id: u64) -> Option<Idea> {
+    IDEAS.with(|map| map.borrow().get(&id)).filter(|idea| !idea.is_quarantined())
+}
+
+// ---- Duplicate detection ----
+
+const DEFAULT_DUPLICATE_THRESHOLD_PCT: u8 = 70;
+const MINHASH_SLOTS: u64 = 8;
+const SHINGLE_WORDS: usize = 3;
+const MAX_DUPLICATE_CANDIDATES: usize = 50;
+const TITLE_BUCKET: u64 = u64::MAX; // slot tag for the title bucket, distinct from minhash slots
+
+// FNV-1a: stable across compiler versions, unlike std's DefaultHasher.
+pub(crate) fn fnv1a(seed: u64, bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn normalized_words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn fingerprint(title: &str, description: &str) -> IdeaFingerprint {
+    let title_hash = fnv1a(0, normalized_words(title).join(" ").as_bytes());
+    let words = normalized_words(description);
+    let shingles: Vec<String> = if words.len() <= SHINGLE_WORDS {
+        vec![words.join(" ")]
+    } else {
+        words.windows(SHINGLE_WORDS).map(|w| w.join(" ")).collect()
+    };
+    let minhash = (0..MINHASH_SLOTS)
+        .map(|slot| {
+            shingles
+                .iter()
+                .map(|sh| fnv1a(slot + 1, sh.as_bytes()))
+                .min()
+                .unwrap_or(0)
+        })
+        .collect();
+    IdeaFingerprint { title_hash, minhash }
+}
+
+fn fingerprint_buckets(fp: &IdeaFingerprint) -> Vec<u64> {
+    let mut buckets = vec![fnv1a(TITLE_BUCKET, &fp.title_hash.to_le_bytes())];
+    for (slot, h) in fp.minhash.iter().enumerate() {
+        buckets.push(fnv1a(slot as u64, &h.to_le_bytes()));
+    }
+    buckets
+}
+
+/// Description similarity is the share of equal MinHash slots; a matching title pulls it halfway to 100.
+fn similarity_pct(a: &IdeaFingerprint, b: &IdeaFingerprint) -> u8 {
+    let equal = a.minhash.iter().zip(&b.minhash).filter(|(x, y)| x == y).count() as u64;
+    let desc = equal * 100 / MINHASH_SLOTS;
+    let score = if a.title_hash == b.title_hash { (100 + desc) / 2 } else { desc };
+    score as u8
+}
+
+fn find_duplicates(fp: &IdeaFingerprint) -> Vec<DuplicateMatch> {
+    let mut candidates: Vec<u64> = Vec::new();
+    FINGERPRINT_INDEX.with(|index| {
+        let index = index.borrow();
+        for bucket in fingerprint_buckets(fp) {
+            for ((_, idea_id), _) in index.range((bucket, 0)..=(bucket, u64::MAX)) {
+                if !candidates.contains(&idea_id) {
+                    candidates.push(idea_id);
+                }
+                if candidates.len() >= MAX_DUPLICATE_CANDIDATES {
+                    return;
+                }
+            }
+        }
+    });
+
+    let threshold = DUPLICATE_THRESHOLD_PCT.with(|t| *t.borrow().get());
+    let mut matches: Vec<DuplicateMatch> = candidates
+        .into_iter()
+        .filter_map(|idea_id| {
+            let other = IDEA_FINGERPRINTS.with(|m| m.borrow().get(&idea_id))?;
+            let similarity_pct = similarity_pct(fp, &other);
+            (similarity_pct >= threshold).then_some(DuplicateMatch { idea_id, similarity_pct })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.similarity_pct.cmp(&a.similarity_pct).then(a.idea_id.cmp(&b.idea_id)));
+    matches
+}
+
+fn unindex_fingerprint(idea_id: u64) {
+    let Some(fp) = IDEA_FINGERPRINTS.with(|m| m.borrow_mut().remove(&idea_id)) else {
+        return;
+    };
+    FINGERPRINT_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        for bucket in fingerprint_buckets(&fp) {
+            index.remove(&(bucket, idea_id));
+        }
+    });
+}
+
+fn index_fingerprint(idea_id: u64, fp: IdeaFingerprint) {
+    FINGERPRINT_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        for bucket in fingerprint_buckets(&fp) {
+            index.insert((bucket, idea_id), ());
+        }
+    });
+    IDEA_FINGERPRINTS.with(|m| m.borrow_mut().insert(idea_id, fp));
+}
+
+/// Uniform "not found" error naming the missing entity, e.g. "campaign 7 not found".
+fn not_found(entity: EntityRef) -> String {
+    format!("{} not found", entity)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn is_controller(principal: &Principal) -> bool {
+    ic_cdk::api::is_controller(principal)
+}
+
+/// Natively (unit tests) there is no system API to ask, and nobody is a controller.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_controller(_principal: &Principal) -> bool {
+    false
+}
+
+fn ensure_controller() -> Result<(), String> {
+    if is_controller(&ic_cdk::api::caller()) {
+        Ok(())
+    } else {
+        Err("only controllers can call this method".into())
+    }
+}
+
+fn is_admin(principal: &Principal) -> bool {
+    is_controller(principal) || ADMINS.with(|a| a.borrow().contains_key(principal))
+}
+
+fn ensure_admin() -> Result<(), String> {
+    check_admin(&ic_cdk::caller())
+}
+
+fn check_admin(caller: &Principal) -> Result<(), String> {
+    if is_admin(caller) {
+        Ok(())
+    } else {
+        Err("only admins can call this method".into())
+    }
+}
+
+fn get_campaign(id: u64) -> Option<Campaign> {
+    CAMPAIGNS.with(|store| store.borrow().get(&id))
+}
+
+fn update_campaign_amount(campaign_id: u64, new_amount: u64) {
+    CAMPAIGNS.with(|store| {
+        let mut store = store.borrow_mut();
+        if let Some(mut campaign) = store.get(&campaign_id) {
+            campaign.amount_raised = new_amount;
+            store.insert(campaign_id, campaign);
+        }
+    });
+}
+
+fn can_edit_idea(idea: &Idea, caller: &Principal) -> bool {
+    idea.owner == *caller || is_controller(caller)
+}
+
+/// Upload a document for an Idea (owner or controllers). Returns the new doc_id, or None if the
+/// idea doesn't exist, the caller may not edit it, `data` is over one chunk (use a chunked upload)
+/// or a quota is used up. The last argument is ignored: the canister stamps `uploaded_at` itself.
+#[update]
+fn upload_doc(idea_id: u64, name: String, content_type: String, data: Vec<u8>, _uploaded_at: u64) -> Option<u64> {
+    let idea = get_idea(idea_id)?; // idea doesn't exist
+    if permissions::may_attach_docs(&ic_cdk::caller(), &idea).is_err() || data.len() as u64 > MAX_DOC_CHUNK_BYTES {
+        return None;
+    }
+    quotas::charge_upload(&ic_cdk::caller(), &idea, data.len() as u64, ic_cdk::api::time()).ok()?;
+    Some(store_doc(idea_id, idea, name, content_type, data))
+}
+
+/// Persist a doc, attach it to `idea` and log the upload.
+fn store_doc(idea_id: u64, idea: Idea, name: String, content_type: String, data: Vec<u8>) -> u64 {
+    let doc_id = attach_doc(idea_id, idea, name, content_type, data, ic_cdk::api::time());
+    record_change(EntityRef::Doc(doc_id), ChangeKind::Created);
+    record_change(EntityRef::Idea(idea_id), ChangeKind::Updated);
+    activity::record_event(ic_cdk::caller(), EventKind::DocUploaded, doc_id, &[EntityRef::Idea(idea_id)]);
+    doc_id
+}
+
+/// Store a doc uploaded at `now` (ns) under the next doc id and append it to the idea's
+/// `doc_ids`, dropping ids whose docs no longer exist. Returns the new doc_id.
+fn attach_doc(idea_id: u64, mut idea: Idea, name: String, content_type: String, data: Vec<u8>, now: u64) -> u64 {
+    let doc_id = DOC_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store doc counter");
+        id
+    });
+    let doc = Doc {
+        id: doc_id,
+        idea_id,
+        name,
+        content_type,
+        data,
+        uploaded_at: now,
+    };
+    DOCS.with(|docs| docs.borrow_mut().insert(doc_id, doc));
+
+    // e.g. docs lost on upgrade before they moved to stable memory
+    idea.doc_ids.retain(|id| DOCS.with(|docs| docs.borrow().contains_key(id)));
+    idea.doc_ids.push(doc_id);
+    IDEAS.with(|ideas| ideas.borrow_mut().insert(idea_id, idea));
+    doc_id
+}
+
+/// Take a doc off its idea `idea_id`, if the idea is still there, then remove it. The idea is
+/// updated first, so its `doc_ids` never point at a removed doc. Returns whether an idea was updated.
+fn detach_doc(doc_id: u64, idea_id: u64, idea: Option<Idea>) -> bool {
+    let updated = if let Some(mut idea) = idea {
+        idea.doc_ids.retain(|id| *id != doc_id);
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(idea_id, idea));
+        true
+    } else {
+        false
+    };
+    DOCS.with(|docs| docs.borrow_mut().remove(&doc_id));
+    updated
+}
+
+/// A stored doc the caller may change: they own its idea or are an admin. Docs whose idea is gone
+/// are admins only.
+fn editable_doc(doc_id: u64) -> Result<(Doc, Option<Idea>), String> {
+    let doc = DOCS.with(|docs| docs.borrow().get(&doc_id)).ok_or_else(|| not_found(EntityRef::Doc(doc_id)))?;
+    let idea = get_idea(doc.idea_id);
+    permissions::may_change_doc(&ic_cdk::caller(), idea.as_ref())?;
+    Ok((doc, idea))
+}
+
+/// Delete a document and detach it from its idea (idea owner or admins).
+#[update]
+fn delete_doc(doc_id: u64) -> Result<(), String> {
+    let (doc, idea) = editable_doc(doc_id)?;
+    if detach_doc(doc_id, doc.idea_id, idea) {
+        record_change(EntityRef::Idea(doc.idea_id), ChangeKind::Updated);
+    }
+    record_change(EntityRef::Doc(doc_id), ChangeKind::Deleted);
+    Ok(())
+}
+
+/// Swap a document's file, keeping its id so links to it keep working (idea owner or admins).
+/// Up to one chunk of data, like `upload_doc`.
+#[update]
+fn replace_doc(doc_id: u64, name: String, content_type: String, data: Vec<u8>) -> Result<(), String> {
+    let (doc, _) = editable_doc(doc_id)?;
+    if data.len() as u64 > MAX_DOC_CHUNK_BYTES {
+        return Err(format!("data must be at most {} bytes; use a chunked upload", MAX_DOC_CHUNK_BYTES));
+    }
+    quotas::charge_bytes(&ic_cdk::caller(), data.len() as u64, ic_cdk::api::time())?;
+    let doc = Doc { name, content_type, data, uploaded_at: ic_cdk::api::time(), ..doc };
+    DOCS.with(|docs| docs.borrow_mut().insert(doc_id, doc));
+    record_change(EntityRef::Doc(doc_id), ChangeKind::Updated);
+    Ok(())
+}
+
+// ------------- Chunked document uploads -------------
+
+const MAX_DOC_UPLOAD_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_DOC_CHUNK_BYTES: u64 = 1_900_000; // leaves room for the envelope under the 2 MiB ingress limit
+const MAX_DOC_CHUNKS: u32 = 1024;
+const DOC_UPLOAD_TTL_NS: u64 = 24 * 60 * 60 * 1_000_000_000; // 24h
+const EXPIRED_UPLOAD_SWEEP: usize = 4; // abandoned uploads cleaned up per begin_doc_upload
+
+fn drop_upload(upload_id: u64) {
+    DOC_UPLOADS.with(|u| u.borrow_mut().remove(&upload_id));
+    DOC_UPLOAD_CHUNKS.with(|c| {
+        let mut c = c.borrow_mut();
+        let keys: Vec<(u64, u32)> = c.range((upload_id, 0)..=(upload_id, u32::MAX)).map(|(k, _)| k).collect();
+        for k in keys {
+            c.remove(&k);
+        }
+    });
+}
+
+fn is_expired(upload: &PendingUpload, now: u64) -> bool {
+    now.saturating_sub(upload.started_at) > DOC_UPLOAD_TTL_NS
+}
+
+/// The caller's live upload; an expired one is dropped on the spot.
+fn owned_upload(upload_id: u64) -> Result<PendingUpload, String> {
+    let upload = DOC_UPLOADS
+        .with(|u| u.borrow().get(&upload_id))
+        .ok_or_else(|| format!("upload {} not found", upload_id))?;
+    if upload.uploader != ic_cdk::caller() {
+        return Err("only the uploader can continue this upload".into());
+    }
+    if is_expired(&upload, ic_cdk::api::time()) {
+        drop_upload(upload_id);
+        return Err(format!("upload {} expired", upload_id));
+    }
+    Ok(upload)
+}
+
+/// Start a chunked upload for files over the message size limit. Returns the upload_id.
+#[update]
+fn begin_doc_upload(idea_id: u64, name: String, content_type: String, total_size: u64) -> Result<u64, String> {
+    let idea = get_idea(idea_id).ok_or_else(|| not_found(EntityRef::Idea(idea_id)))?;
+    permissions::may_attach_docs(&ic_cdk::caller(), &idea)?;
+    if total_size == 0 || total_size > MAX_DOC_UPLOAD_BYTES {
+        return Err(format!("total_size must be 1-{} bytes", MAX_DOC_UPLOAD_BYTES));
+    }
+    // Charged now, so uploads that are never finished still count.
+    let now = ic_cdk::api::time();
+    quotas::charge_upload(&ic_cdk::caller(), &idea, total_size, now)?;
+    let expired: Vec<u64> = DOC_UPLOADS.with(|u| {
+        u.borrow()
+            .iter()
+            .filter(|(_, up)| is_expired(up, now))
+            .take(EXPIRED_UPLOAD_SWEEP)
+            .map(|(id, _)| id)
+            .collect()
+    });
+    for id in expired {
+        drop_upload(id);
+    }
+
+    let upload_id = DOC_UPLOAD_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store doc upload counter");
+        id
+    });
+    let upload = PendingUpload {
+        idea_id,
+        name,
+        content_type,
+        total_size,
+        received: 0,
+        chunk_count: 0,
+        uploader: ic_cdk::caller(),
+        started_at: now,
+    };
+    DOC_UPLOADS.with(|u| u.borrow_mut().insert(upload_id, upload));
+    Ok(upload_id)
+}
+
+/// Add one chunk. Chunks may arrive in any order; each index can be sent once.
+#[update]
+fn upload_doc_chunk(upload_id: u64, chunk_index: u32, bytes: Vec<u8>) -> Result<(), String> {
+    let mut upload = owned_upload(upload_id)?;
+    if bytes.is_empty() || bytes.len() as u64 > MAX_DOC_CHUNK_BYTES {
+        return Err(format!("chunk must be 1-{} bytes", MAX_DOC_CHUNK_BYTES));
+    }
+    if chunk_index >= MAX_DOC_CHUNKS {
+        return Err(format!("chunk_index must be below {}", MAX_DOC_CHUNKS));
+    }
+    if DOC_UPLOAD_CHUNKS.with(|c| c.borrow().contains_key(&(upload_id, chunk_index))) {
+        return Err(format!("chunk {} was already uploaded", chunk_index));
+    }
+    let received = upload.received + bytes.len() as u64;
+    if received > upload.total_size {
+        return Err(format!("chunks exceed the declared total_size of {} bytes", upload.total_size));
+    }
+    upload.received = received;
+    upload.chunk_count += 1;
+    DOC_UPLOAD_CHUNKS.with(|c| c.borrow_mut().insert((upload_id, chunk_index), bytes));
+    DOC_UPLOADS.with(|u| u.borrow_mut().insert(upload_id, upload));
+    Ok(())
+}
+
+/// Assemble the chunks (in index order) into a Doc. Returns the new doc_id.
+#[update]
+fn finish_doc_upload(upload_id: u64) -> Result<u64, String> {
+    let upload = owned_upload(upload_id)?;
+    if upload.received != upload.total_size {
+        return Err(format!("received {} of {} bytes", upload.received, upload.total_size));
+    }
+    let chunks: Vec<(u32, Vec<u8>)> = DOC_UPLOAD_CHUNKS.with(|c| {
+        c.borrow()
+            .range((upload_id, 0)..=(upload_id, u32::MAX))
+            .map(|((_, i), bytes)| (i, bytes))
+            .collect()
+    });
+    if let Some(gap) = chunks.iter().enumerate().find(|(pos, (i, _))| *i as usize != *pos) {
+        return Err(format!("chunk {} is missing", gap.0));
+    }
+    let idea = get_idea(upload.idea_id).ok_or_else(|| not_found(EntityRef::Idea(upload.idea_id)))?;
+    quotas::check_doc_count(&upload.uploader, &idea)?; // other docs may have landed since it began
+    let data: Vec<u8> = chunks.into_iter().flat_map(|(_, bytes)| bytes).collect();
+    drop_upload(upload_id);
+    Ok(store_doc(upload.idea_id, idea, upload.name, upload.content_type, data))
+}
+
+/// Discard an unfinished upload and its chunks. Abandoned uploads also expire after 24 hours.
+#[update]
+fn abort_doc_upload(upload_id: u64) -> Result<(), String> {
+    owned_upload(upload_id)?;
+    drop_upload(upload_id);
+    Ok(())
+}
+
+// ------------- Public API -------------
+
+/// Byte limits for idea text fields.
+const IDEA_TEXT_LIMITS: [(FieldId, usize); 5] = [
+    (FieldId::Title, 100),
+    (FieldId::Description, 10_000),
+    (FieldId::LegalEntity, 100),
+    (FieldId::ContactInfo, 100),
+    (FieldId::Category, 50),
+];
+const MAX_REJECTION_REASON_LEN: usize = 280; // bytes; stored on the idea too
+
+fn idea_text_limit(field: FieldId) -> usize {
+    IDEA_TEXT_LIMITS
+        .iter()
+        .find(|(f, _)| *f == field)
+        .map(|(_, max)| *max)
+        .expect("every text field has a limit")
+}
+
+/// Every problem with a submitted idea, so one response can report them all.
+fn validate_idea_fields(
+    title: &str,
+    description: &str,
+    funding_goal: u64,
+    legal_entity: &str,
+    contact_info: &str,
+    category: &str,
+) -> Vec<FieldError> {
+    let text = |field, value: &str| {
+        let max = idea_text_limit(field);
+        if value.trim().is_empty() {
+            Some(FieldError { field, rule: RuleId::Required, limit: None, actual: None })
+        } else if value.len() > max {
+            Some(FieldError { field, rule: RuleId::MaxLength, limit: Some(max as u64), actual: Some(value.len() as u64) })
+        } else {
+            None
+        }
+    };
+    [
+        text(FieldId::Title, title),
+        text(FieldId::Description, description),
+        (funding_goal == 0).then_some(FieldError {
+            field: FieldId::FundingGoal,
+            rule: RuleId::MinValue,
+            limit: Some(1),
+            actual: Some(funding_goal),
+        }),
+        text(FieldId::LegalEntity, legal_entity),
+        text(FieldId::ContactInfo, contact_info)
+            .or_else(|| format_error(FieldId::ContactInfo, is_contact(contact_info))),
+        text(FieldId::Category, category).or_else(|| format_error(FieldId::Category, is_category(category))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn format_error(field: FieldId, ok: bool) -> Option<FieldError> {
+    (!ok).then_some(FieldError { field, rule: RuleId::Format, limit: None, actual: None })
+}
+
+/// An email address (`name@host.tld`), a phone number (7-15 digits, with optional + - ( ) . and
+/// spaces) or an https:// link.
+fn is_contact(contact: &str) -> bool {
+    let contact = contact.trim();
+    if let Some(rest) = contact.strip_prefix("https://") {
+        return !rest.is_empty() && !rest.contains(char::is_whitespace);
+    }
+    if let Some((local, domain)) = contact.split_once('@') {
+        return !local.is_empty()
+            && !contact.contains(char::is_whitespace)
+            && domain.contains('.')
+            && domain.split('.').all(|label| !label.is_empty() && !label.contains('@'));
+    }
+    let digits = contact.chars().filter(char::is_ascii_digit).count();
+    (7..=15).contains(&digits)
+        && contact.trim_start_matches('+').chars().all(|c| c.is_ascii_digit() || " -().".contains(c))
+}
+
+fn is_category(category: &str) -> bool {
+    category.chars().all(|c| c.is_alphanumeric() || " &-/".contains(c))
+}
+
+fn describe_field_errors(errors: &[FieldError]) -> String {
+    let parts: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    format!("Invalid input: {}", parts.join("; "))
+}
+
+/// Validate submitted fields and build a new idea in "pending" state.
+#[allow(clippy::too_many_arguments)] // one per submitted form field, plus the owner
+fn new_pending_idea(
+    title: String,
+    description: String,
+    funding_goal: u64,
+    legal_entity: String,
+    contact_info: String,
+    category: String,
+    business_registration: u8,
+    owner: Principal,
+) -> Result<Idea, Vec<FieldError>> {
+    let mut category = category;
+    let mut errors = validate_idea_fields(&title, &description, funding_goal, &legal_entity, &contact_info, &category);
+    categories::canonicalize(&mut category, &mut errors);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let now = ic_cdk::api::time();
+    Ok(Idea {
+        title,
+        description,
+        funding_goal,
+        current_funding: 0,
+        legal_entity,
+        status: IdeaStatus::Pending,
+        contact_info,
+        doc_ids : vec![],
+        category,
+        business_registration,
+        created_at: now,
+        updated_at: now,
+        owner,
+        external_ref: None,
+    })
+}
+
+/// Persist a new idea, index its fingerprint and flag likely duplicates. Returns the new idea_id.
+fn insert_idea(idea: Idea) -> u64 {
+    let id = store_new_idea(idea.clone());
+    publish_idea(id, &idea);
+    id
+}
+
+/// Store a new idea under the next id, indexed by owner only. Returns the new idea_id.
+fn store_new_idea(idea: Idea) -> u64 {
+    // max with the last key: canisters from before the counter assigned ids as len + 1
+    let last_key = IDEAS.with(|ideas| ideas.borrow().last_key_value().map(|(k, _)| k).unwrap_or(0));
+    let id = IDEA_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = (*c.get()).max(last_key) + 1;
+        c.set(id).expect("store idea counter");
+        id
+    });
+    IDEAS_BY_OWNER.with(|o| o.borrow_mut().insert((idea.owner, id), ()));
+    IDEAS.with(|ideas| ideas.borrow_mut().insert(id, idea));
+    id
+}
+
+/// Make a stored idea known as pending review: index its fingerprint, flag likely duplicates and
+/// record its creation. Drafts skip this until they are submitted.
+fn publish_idea(id: u64, idea: &Idea) {
+    let now = idea.created_at;
+    let fp = fingerprint(&idea.title, &idea.description);
+    let matches = find_duplicates(&fp);
+    index_fingerprint(id, fp);
+    if !matches.is_empty() {
+        DUPLICATE_FLAGS.with(|flags| {
+            flags.borrow_mut().insert(id, DuplicateFlag { idea_id: id, matches, flagged_at: now })
+        });
+    }
+    record_change(EntityRef::Idea(id), ChangeKind::Created);
+    activity::record_event(ic_cdk::caller(), EventKind::IdeaCreated, id, &[EntityRef::Idea(id)]);
+}
+
+/// Create an Idea and persist it in stable storage. Returns the new idea_id, or a FundVerseError
+/// for the form to show against the offending fields.
+#[update]
+fn create_idea(
+    title: String,
+    description: String,
+    funding_goal: u64,
+    legal_entity: String,
+    contact_info: String,
+    category: String,
+    business_registration: u8,
+) -> Result<u64, FundVerseError> {
+    let caller = ic_cdk::caller();
+    if caller == Principal::anonymous() {
+        return Err(FundVerseError::Unauthorized);
+    }
+    if !profiles::may_create_ideas(&caller) {
+        return Err(FundVerseError::NotRegistered);
+    }
+    let idea = new_pending_idea(
+        title,
+        description,
+        funding_goal,
+        legal_entity,
+        contact_info,
+        category,
+        business_registration,
+        caller,
+    )
+    .map_err(FundVerseError::from)?;
+    quotas::charge_idea(&caller, ic_cdk::api::time())?;
+    Ok(insert_idea(idea))
+}
+
+/// Partial idea edit: only the supplied fields change.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct UpdateIdeaPayload {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub funding_goal: Option<u64>,
+    pub legal_entity: Option<String>,
+    pub contact_info: Option<String>,
+    pub category: Option<String>,
+    pub business_registration: Option<u8>,
+}
+
+fn editable_idea(idea_id: u64) -> Result<Idea, String> {
+    let idea = get_idea(idea_id).ok_or_else(|| not_found(EntityRef::Idea(idea_id)))?;
+    permissions::may_edit_idea(&ic_cdk::caller(), &idea)?;
+    Ok(idea)
+}
+
+/// Update some fields of an idea (owner or controllers). Supplied fields get the same checks as create_idea.
+#[update]
+fn update_idea(idea_id: u64, payload: UpdateIdeaPayload) -> Result<(), String> {
+    let mut idea = editable_idea(idea_id)?;
+    if idea.is_draft() {
+        return Err("idea is a draft; edit it with update_idea_draft and send it with submit_idea".into());
+    }
+    // Resubmitting the stored category is no change, even if it has since been deactivated.
+    let category = payload.category.filter(|c| c.trim() != idea.category);
+    let supplied = [
+        (FieldId::Title, payload.title.is_some()),
+        (FieldId::Description, payload.description.is_some()),
+        (FieldId::FundingGoal, payload.funding_goal.is_some()),
+        (FieldId::LegalEntity, payload.legal_entity.is_some()),
+        (FieldId::ContactInfo, payload.contact_info.is_some()),
+        (FieldId::Category, category.is_some()),
+    ];
+    let text_changed = payload.title.is_some() || payload.description.is_some();
+    idea.title = payload.title.unwrap_or(idea.title);
+    idea.description = payload.description.unwrap_or(idea.description);
+    idea.funding_goal = payload.funding_goal.unwrap_or(idea.funding_goal);
+    idea.legal_entity = payload.legal_entity.unwrap_or(idea.legal_entity);
+    idea.contact_info = payload.contact_info.unwrap_or(idea.contact_info);
+    idea.category = category.clone().unwrap_or(idea.category);
+    idea.business_registration = payload.business_registration.unwrap_or(idea.business_registration);
+
+    let mut errors: Vec<FieldError> = validate_idea_fields(
+        &idea.title,
+        &idea.description,
+        idea.funding_goal,
+        &idea.legal_entity,
+        &idea.contact_info,
+        &idea.category,
+    )
+    .into_iter()
+    .filter(|e| supplied.iter().any(|(field, given)| *given && *field == e.field))
+    .collect();
+    if category.is_some() {
+        categories::canonicalize(&mut idea.category, &mut errors);
+    }
+    if !errors.is_empty() {
+        return Err(describe_field_errors(&errors));
+    }
+    if idea.funding_goal < idea.current_funding {
+        return Err(format!("funding_goal cannot be below the {} already raised", idea.current_funding));
+    }
+
+    idea.updated_at = ic_cdk::api::time();
+    if text_changed {
+        unindex_fingerprint(idea_id);
+        index_fingerprint(idea_id, fingerprint(&idea.title, &idea.description));
+    }
+    IDEAS.with(|ideas| ideas.borrow_mut().insert(idea_id, idea));
+    record_change(EntityRef::Idea(idea_id), ChangeKind::Updated);
+    Ok(())
+}
+
+// ------------- Idea drafts -------------
+
+/// Copy the supplied fields onto a draft. Only the length limits apply; the rest waits for
+/// `submit_idea`.
+fn apply_to_draft(idea: &mut Idea, payload: UpdateIdeaPayload) -> Result<(), FundVerseError> {
+    idea.title = payload.title.unwrap_or(std::mem::take(&mut idea.title));
+    idea.description = payload.description.unwrap_or(std::mem::take(&mut idea.description));
+    idea.funding_goal = payload.funding_goal.unwrap_or(idea.funding_goal);
+    idea.legal_entity = payload.legal_entity.unwrap_or(std::mem::take(&mut idea.legal_entity));
+    idea.contact_info = payload.contact_info.unwrap_or(std::mem::take(&mut idea.contact_info));
+    idea.category = payload.category.unwrap_or(std::mem::take(&mut idea.category));
+    idea.business_registration = payload.business_registration.unwrap_or(idea.business_registration);
+    let too_long: Vec<FieldError> = validate_idea_fields(
+        &idea.title,
+        &idea.description,
+        idea.funding_goal,
+        &idea.legal_entity,
+        &idea.contact_info,
+        &idea.category,
+    )
+    .into_iter()
+    .filter(|e| e.rule == RuleId::MaxLength)
+    .collect();
+    if !too_long.is_empty() {
+        return Err(too_long.into());
+    }
+    idea.updated_at = ic_cdk::api::time();
+    Ok(())
+}
+
+/// The caller's own draft, for editing or submitting.
+fn own_draft(idea_id: u64) -> Result<Idea, FundVerseError> {
+    let idea = get_idea(idea_id).ok_or(FundVerseError::NotFound(EntityRef::Idea(idea_id)))?;
+    if idea.owner != ic_cdk::caller() {
+        return Err(FundVerseError::Unauthorized);
+    }
+    if !idea.is_draft() {
+        return Err(FundVerseError::NotADraft { status: idea.status });
+    }
+    Ok(idea)
+}
+
+/// Save an unfinished idea as a draft (signed-in callers), to finish later with
+/// `update_idea_draft`. Every field is optional. Drafts count against the daily idea quota, are
+/// listed only by `get_my_ideas`, and can't have campaigns until submitted and approved.
+#[update]
+fn create_idea_draft(payload: UpdateIdeaPayload) -> Result<u64, FundVerseError> {
+    let caller = ic_cdk::caller();
+    if caller == Principal::anonymous() {
+        return Err(FundVerseError::Unauthorized);
+    }
+    if !profiles::may_create_ideas(&caller) {
+        return Err(FundVerseError::NotRegistered);
+    }
+    let now = ic_cdk::api::time();
+    let mut idea = Idea {
+        title: String::new(),
+        description: String::new(),
+        funding_goal: 0,
+        current_funding: 0,
+        legal_entity: String::new(),
+        status: IdeaStatus::Draft,
+        contact_info: String::new(),
+        category: String::new(),
+        business_registration: 0,
+        created_at: now,
+        updated_at: now,
+        doc_ids: vec![],
+        owner: caller,
+        external_ref: None,
+    };
+    apply_to_draft(&mut idea, payload)?;
+    quotas::charge_idea(&caller, now)?;
+    Ok(store_new_idea(idea))
+}
+
+/// Change some fields of one of the caller's drafts; fields left out keep their value.
+#[update]
+fn update_idea_draft(idea_id: u64, payload: UpdateIdeaPayload) -> Result<(), FundVerseError> {
+    let mut idea = own_draft(idea_id)?;
+    apply_to_draft(&mut idea, payload)?;
+    IDEAS.with(|ideas| ideas.borrow_mut().insert(idea_id, idea));
+    Ok(())
+}
+
+/// Send one of the caller's drafts for review. It gets every check `create_idea` makes, and on
+/// success becomes Pending, counting as created now. Ideas already submitted are refused.
+#[update]
+fn submit_idea(idea_id: u64) -> Result<(), FundVerseError> {
+    let draft = own_draft(idea_id)?;
+    let pending = new_pending_idea(
+        draft.title,
+        draft.description,
+        draft.funding_goal,
+        draft.legal_entity,
+        draft.contact_info,
+        draft.category,
+        draft.business_registration,
+        draft.owner,
+    )?;
+    let idea = Idea { doc_ids: draft.doc_ids, ..pending };
+    IDEAS.with(|ideas| ideas.borrow_mut().insert(idea_id, idea.clone()));
+    publish_idea(idea_id, &idea);
+    Ok(())
+}
+
+/// Delete an idea with its documents and discussion (owner or controllers).
+/// Refused once the idea has any campaign: campaigns keep their idea so contributions stay
+/// traceable, and content that must go anyway is taken down by an admin (`takedown_idea`).
+/// Drafts can always be deleted.
+#[update]
+fn delete_idea(idea_id: u64) -> Result<(), String> {
+    let idea = get_idea(idea_id).ok_or_else(|| not_found(EntityRef::Idea(idea_id)))?;
+    permissions::may_delete_idea(&ic_cdk::caller(), idea_id, &idea)?;
+
+    for doc_id in &idea.doc_ids {
+        if DOCS.with(|docs| docs.borrow_mut().remove(doc_id)).is_some() {
+            record_change(EntityRef::Doc(*doc_id), ChangeKind::Deleted);
+        }
+    }
+    remove_idea_records(idea_id, &idea, ic_cdk::api::time());
+    Ok(())
+}
+
+/// Remove an idea, its discussion and its index entries; docs and campaigns are the caller's.
+fn remove_idea_records(idea_id: u64, idea: &Idea, now: u64) {
+    for comment_id in remove_idea_comments(idea_id) {
+        record_change_at(now, EntityRef::Comment(comment_id), ChangeKind::Deleted);
+    }
+    unindex_fingerprint(idea_id);
+    DUPLICATE_FLAGS.with(|flags| flags.borrow_mut().remove(&idea_id));
+    IDEAS_BY_OWNER.with(|o| o.borrow_mut().remove(&(idea.owner, idea_id)));
+    badges::forget_idea(idea_id);
+    review_pipeline::forget_idea(idea_id);
+    IDEAS.with(|ideas| ideas.borrow_mut().remove(&idea_id));
+    record_change_at(now, EntityRef::Idea(idea_id), ChangeKind::Deleted);
+}
+
+fn is_successful(c: &Campaign, now_secs: u64) -> bool {
+    lifecycle(c, now_secs) == CampaignLifecycle::Completed
+}
+
+/// A new campaign may not go back to a round earlier than the idea's last successful one.
+/// Admins can override (e.g. to correct a mislabelled round).
+fn check_round_progression(idea_id: u64, round: &FundingRound) -> Result<(), String> {
+    let Some(rank) = round.rank() else { return Ok(()) };
+    if is_admin(&ic_cdk::api::caller()) {
+        return Ok(());
+    }
+    let now = now_secs();
+    let last_success = CAMPAIGNS.with(|store| {
+        store
+            .borrow()
+            .iter()
+            .map(|(_, c)| c)
+            .filter(|c| c.idea_id == idea_id && is_successful(c, now))
+            .filter_map(|c| c.round.clone().filter(|r| r.rank().is_some()))
+            .max_by_key(|r| r.rank())
+    });
+    match last_success {
+        Some(last) if last.rank() > Some(rank) => Err(format!(
+            "round {:?} is earlier than the last successful round {:?}",
+            round, last
+        )),
+        _ => Ok(()),
+    }
+}
+
+const MAX_DISPLAY_TITLE_LEN: usize = 80;
+const MAX_CAMPAIGN_TEASER_LEN: usize = 140;
+
+/// Trim optional card copy; blank means "use the idea's own".
+fn validate_copy(text: Option<String>, what: &str, max: usize) -> Result<Option<String>, String> {
+    let Some(text) = text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()) else {
+        return Ok(None);
+    };
+    if text.chars().count() > max {
+        return Err(format!("{} must be at most {} characters", what, max));
+    }
+    Ok(Some(text))
 }
 
 /// Create a Campaign linked to an existing Idea. Returns new campaign_id (Ok) or error (Err).
+/// With a `fiat_goal` the token goal is its amount at the cached exchange rate, and `goal` must
+/// be 0. `currency` (default ICP) is what `goal` and the pledges are in; it must match the idea's
+/// other campaigns, and fiat goals are only priced in ICP.
+#[update]
+#[allow(clippy::too_many_arguments)] // optional campaign settings, appended so old clients still work
+fn create_campaign(
+    idea_id: u64,
+    goal: u64,
+    end_date: u64,
+    round: Option<FundingRound>,
+    display_title: Option<String>,
+    teaser: Option<String>,
+    end_time: Option<EndTime>,
+    fiat_goal: Option<FiatGoalRequest>,
+    currency: Option<Currency>,
+) -> Result<u64, String> {
+    let caller = ic_cdk::caller();
+    let currency = currency.unwrap_or_default();
+    if fiat_goal.is_some() && currency != Currency::Icp {
+        return Err(format!("fiat goals are priced in ICP; set the goal in {} instead", currency));
+    }
+    let (fiat_goal, goal) = match fiat_goal {
+        Some(_) if goal != 0 => return Err("pass goal = 0 with a fiat_goal; the token goal is derived".into()),
+        Some(request) => fiat_goal::lock_at_creation(request, ic_cdk::api::time()).map(|(f, g)| (Some(f), g))?,
+        None => (None, goal),
+    };
+    if goal == 0 {
+        return Err("goal must be > 0".into());
+    }
+    check_end_date(end_date, now_secs())?;
+    let end_hour_utc = end_hour_for(end_time)?;
+    let display_title = validate_copy(display_title, "display_title", MAX_DISPLAY_TITLE_LEN)?;
+    let teaser = validate_copy(teaser, "teaser", MAX_CAMPAIGN_TEASER_LEN)?;
+    // ensure idea exists
+    let Some(idea) = get_idea(idea_id) else {
+        return Err(not_found(EntityRef::Idea(idea_id)));
+    };
+    permissions::may_create_campaign(&caller, &idea)?;
+    currency::check_idea(idea_id, currency)?;
+    if let Some(round) = &round {
+        check_round_progression(idea_id, round)?;
+    }
+    let external_ref = syndication::campaign_ref(&idea)?;
+
+    let id = CAMPAIGN_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store campaign counter");
+        id
+    });
+    CAMPAIGNS.with(|store| {
+        store.borrow_mut().insert(id, Campaign {
+            id,
+            idea_id,
+            amount_raised: 0,
+            goal,
+            end_date: snap_end_date(end_date, end_hour_utc),
+            risks: None,
+            risk_amendments: vec![],
+            round,
+            display_title,
+            teaser,
+            owner: Some(caller),
+            status: None,
+            refund_pending: None,
+            end_hour_utc,
+            requested_end_date: end_hour_utc.and(Some(end_date)),
+            launched_at: Some(now_secs()),
+            fiat_goal,
+            external_ref: external_ref.clone(),
+            currency: Some(currency),
+        })
+    });
+    CAMPAIGNS_BY_OWNER.with(|o| o.borrow_mut().insert((caller, id), ()));
+    if let Some(external_ref) = &external_ref {
+        syndication::bind_campaign_ref(&idea.owner, external_ref, id);
+    }
+    if let Some(campaign) = get_campaign(id) {
+        fiat_goal::record_creation_lock(&campaign, caller);
+    }
+    record_change(EntityRef::Campaign(id), ChangeKind::Created);
+    let filed_under = [EntityRef::Idea(idea_id), EntityRef::Campaign(id)];
+    activity::record_event(caller, EventKind::CampaignCreated, id, &filed_under);
+
+    Ok(id)
+}
+
+/// Return all campaign cards (title/category pulled from linked Idea).
+#[query]
+fn get_campaign_cards() -> Vec<CampaignCard> {
+    all_campaign_cards()
+}
+
+/// A campaign's card, or its tombstone once taken down. None only for an orphaned campaign.
+fn card_of(c: &Campaign) -> Option<CampaignCard> {
+    if takedown::is_campaign_taken_down(c) {
+        return Some(takedown::tombstone_card(c));
+    }
+    get_idea(c.idea_id).map(|idea| to_card(c, &idea))
+}
+
+fn all_campaign_cards() -> Vec<CampaignCard> {
+    CAMPAIGNS.with(|store| store.borrow().iter().filter_map(|(_, c)| card_of(&c)).collect())
+}
+
+const MAX_LISTING_PAGE: u64 = 100;
+
+/// Slice `matches` (already in id order) into a page. `limit` is capped at MAX_LISTING_PAGE;
+/// a zero limit returns no items but still reports the total.
+fn paginate<T>(matches: Vec<T>, offset: u64, limit: u64) -> PagedResult<T> {
+    let total = matches.len() as u64;
+    let items: Vec<T> = matches
+        .into_iter()
+        .skip(offset.min(total) as usize)
+        .take(limit.min(MAX_LISTING_PAGE) as usize)
+        .collect();
+    let end = offset.saturating_add(items.len() as u64);
+    PagedResult { items, total, next_offset: (end < total).then_some(end) }
+}
+
+/// Campaign cards a page at a time, ordered by campaign id.
+#[query]
+fn get_campaign_cards_paged(offset: u64, limit: u64) -> PagedResult<CampaignCard> {
+    paginate(all_campaign_cards(), offset, limit)
+}
+
+/// Like `get_campaign_cards_paged`, filtered by status; `total` counts matching campaigns only.
+#[query]
+fn get_campaign_cards_by_status_paged(status: CampaignStatus, offset: u64, limit: u64) -> PagedResult<CampaignCard> {
+    paginate(get_campaign_cards_by_status(status), offset, limit)
+}
+
+/// Put campaigns in `sort` order, dropping ended ones for `EndingSoon`. Ties go to the lower id
+/// (for `Newest`, the higher one), so a fixed state always pages the same way.
+fn sort_campaigns(mut campaigns: Vec<Campaign>, sort: SortBy, now: u64) -> Vec<Campaign> {
+    use std::cmp::Reverse;
+    match sort {
+        SortBy::Newest => campaigns.sort_by_key(|c| Reverse((c.launched_at.unwrap_or(0), c.id))),
+        SortBy::EndingSoon => {
+            campaigns.retain(|c| lifecycle(c, now) == CampaignLifecycle::Active);
+            campaigns.sort_by_key(|c| (effective_end_date(c, now), c.id));
+        }
+        SortBy::MostFunded => campaigns.sort_by_key(|c| (Reverse(c.amount_raised), c.id)),
+        // creation forbids a zero goal, but a zero goal must not rank as infinitely funded
+        SortBy::PercentFunded => campaigns
+            .sort_by_key(|c| (Reverse(if c.goal == 0 { 0 } else { percent_bps(c.amount_raised, c.goal) }), c.id)),
+    }
+    campaigns
+}
+
+/// Campaign cards a page at a time in `sort` order (see `SortBy`). Taken-down campaigns are left
+/// out, as in search.
+#[query]
+fn get_campaign_cards_sorted(sort: SortBy, offset: u64, limit: u64) -> PagedResult<CampaignCard> {
+    let campaigns: Vec<Campaign> = CAMPAIGNS.with(|store| {
+        store.borrow().iter().map(|(_, c)| c).filter(|c| !takedown::is_campaign_taken_down(c)).collect()
+    });
+    let cards = sort_campaigns(campaigns, sort, now_secs()).iter().filter_map(card_of).collect();
+    paginate(cards, offset, limit)
+}
+
+/// Ideas a page at a time, ordered by idea id. Unreadable (quarantined) records are skipped.
+#[query]
+fn list_ideas_paged(offset: u64, limit: u64) -> PagedResult<(u64, Idea)> {
+    let ideas: Vec<(u64, Idea)> =
+        IDEAS.with(|ideas| ideas.borrow().iter().filter(|(_, idea)| idea.is_listed()).collect());
+    paginate(ideas, offset, limit)
+}
+
+/// Case-insensitive substring match over title and description; `needle` must be lowercase.
+fn idea_matches(idea: &Idea, needle: &str, category: Option<&str>) -> bool {
+    category.is_none_or(|cat| same_category(&idea.category, cat))
+        && (needle.is_empty()
+            || idea.title.to_lowercase().contains(needle)
+            || idea.description.to_lowercase().contains(needle))
+}
+
+fn matching_ideas(query: &str, category: Option<&str>) -> Result<Vec<(u64, Idea)>, String> {
+    let needle = query.trim().to_lowercase();
+    let category = category.map(str::trim).filter(|c| !c.is_empty());
+    if needle.is_empty() && category.is_none() {
+        return Err("give a search query, a category or both".into());
+    }
+    Ok(IDEAS.with(|ideas| {
+        ideas
+            .borrow()
+            .iter()
+            .filter(|(_, idea)| idea.is_listed() && idea_matches(idea, &needle, category))
+            .collect()
+    }))
+}
+
+/// Search ideas by title and description, optionally within a category; an empty query with a
+/// category is a pure category filter. Paged like `list_ideas_paged`.
+#[query]
+fn search_ideas(
+    query: String,
+    category: Option<String>,
+    offset: u64,
+    limit: u64,
+) -> Result<PagedResult<(u64, Idea)>, String> {
+    matching_ideas(&query, category.as_deref()).map(|ideas| paginate(ideas, offset, limit))
+}
+
+/// Campaigns whose idea is in `category`. Campaigns whose idea no longer resolves are skipped.
+fn campaigns_in_category(category: &str) -> Vec<(Campaign, Idea)> {
+    CAMPAIGNS.with(|store| {
+        store
+            .borrow()
+            .iter()
+            .filter_map(|(_, c)| {
+                let idea = get_idea(c.idea_id)?;
+                same_category(&idea.category, category).then_some((c, idea))
+            })
+            .collect()
+    })
+}
+
+/// Cards for campaigns in a category (case-insensitive, through the linked idea), by campaign id.
+#[query]
+fn get_campaign_cards_by_category(category: String, offset: u64, limit: u64) -> PagedResult<CampaignCard> {
+    let page = paginate(campaigns_in_category(&category), offset, limit);
+    PagedResult {
+        items: page.items.iter().map(|(c, idea)| to_card(c, idea)).collect(),
+        total: page.total,
+        next_offset: page.next_offset,
+    }
+}
+
+///return docs with idea_id; a draft's docs only to its owner
+#[query]
+fn get_doc(doc_id: u64) -> Option<Doc> {
+    let doc = DOCS.with(|docs| docs.borrow().get(&doc_id))?;
+    match get_idea(doc.idea_id) {
+        Some(idea) if is_private_draft(&idea) => None,
+        _ => Some(doc),
+    }
+}
+
+/// All documents attached to an idea, in upload order. Ids without a stored doc are skipped, and
+/// a draft's docs are only listed to its owner.
+#[query]
+fn get_docs_by_idea(idea_id: u64) -> Vec<Doc> {
+    let Some(idea) = visible_idea(idea_id) else {
+        return vec![];
+    };
+    DOCS.with(|docs| {
+        let docs = docs.borrow();
+        idea.doc_ids.iter().filter_map(|id| docs.get(id)).collect()
+    })
+}
+
+/// Return cards filtered by status: Active, or Ended (completed, expired, cancelled or taken down).
+#[query]
+fn get_campaign_cards_by_status(status: CampaignStatus) -> Vec<CampaignCard> {
+    CAMPAIGNS.with(|store| {
+        store
+            .borrow()
+            .iter()
+            .filter_map(|(_, c)| card_of(&c))
+            .filter(|card| match status {
+                CampaignStatus::Active => card.status == CampaignLifecycle::Active,
+                CampaignStatus::Ended => card.status != CampaignLifecycle::Active, // cancelled, taken down included
+            })
+            .collect()
+    })
+}
+
+/// Fetch a single campaign joined with its Idea.
+#[query]
+fn get_campaign_with_idea(campaign_id: u64) -> Option<CampaignWithIdea> {
+    let c = get_campaign(campaign_id)?;
+    let idea = get_idea(c.idea_id)?;
+    Some(CampaignWithIdea {
+        campaign: to_card(&c, &idea),
+        idea,
+        risks: c.risks,
+        risk_amendments: c.risk_amendments,
+    })
+}
+
+const MAX_BATCH_IDS: usize = 100;
+
+fn check_batch(ids: &[u64]) -> Result<(), String> {
+    if ids.len() > MAX_BATCH_IDS {
+        return Err(format!("at most {} ids per call, got {}", MAX_BATCH_IDS, ids.len()));
+    }
+    Ok(())
+}
+
+/// Up to 100 docs in the order asked, None in place of ids without a doc or that `get_doc` would hide.
+#[query]
+fn get_docs(doc_ids: Vec<u64>) -> Result<Vec<Option<Doc>>, String> {
+    check_batch(&doc_ids)?;
+    Ok(doc_ids.into_iter().map(get_doc).collect())
+}
+
+/// Up to 100 ideas in the order asked, None in place of unknown ids and other users' drafts.
+#[query]
+fn get_ideas(ids: Vec<u64>) -> Result<Vec<Option<Idea>>, String> {
+    check_batch(&ids)?;
+    Ok(ids.into_iter().map(visible_idea).collect())
+}
+
+fn doc_meta(doc: Doc) -> DocMeta {
+    DocMeta {
+        id: doc.id,
+        size: doc.data.len() as u64,
+        name: doc.name,
+        content_type: doc.content_type,
+        uploaded_at: doc.uploaded_at,
+    }
+}
+
+/// How many contributions a campaign has, archived ones included.
+fn contribution_count(campaign_id: u64) -> u64 {
+    if let Some(cold) = archive::cold_campaign_contributions(campaign_id) {
+        return cold.len() as u64;
+    }
+    CAMPAIGN_CONTRIBUTIONS.with(|c| c.borrow().range((campaign_id, 0)..=(campaign_id, u64::MAX)).count() as u64)
+}
+
+/// The campaign detail page in one call: what `get_campaign_with_idea` returns plus the idea's
+/// doc metadata and the contribution count. None where `get_campaign_with_idea` is None.
+#[query]
+fn get_campaign_details(campaign_id: u64) -> Option<CampaignDetails> {
+    let CampaignWithIdea { campaign, idea, risks, risk_amendments } = get_campaign_with_idea(campaign_id)?;
+    let docs = DOCS.with(|docs| {
+        let docs = docs.borrow();
+        idea.doc_ids.iter().filter_map(|id| docs.get(id)).map(doc_meta).collect()
+    });
+    Some(CampaignDetails {
+        campaign,
+        idea,
+        risks,
+        risk_amendments,
+        docs,
+        contribution_count: contribution_count(campaign_id),
+    })
+}
+
+const MAX_RISKS_LEN: usize = 3000;
+
+fn validate_risks(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() || text.chars().count() > MAX_RISKS_LEN {
+        return Err(format!("risks text must be 1-{} characters", MAX_RISKS_LEN));
+    }
+    Ok(text.to_string())
+}
+
+fn with_campaign_mut<R>(campaign_id: u64, f: impl FnOnce(&mut Campaign) -> Result<R, String>) -> Result<R, String> {
+    CAMPAIGNS.with(|store| {
+        let mut store = store.borrow_mut();
+        let mut campaign = store
+            .get(&campaign_id)
+            .ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+        let result = f(&mut campaign)?;
+        store.insert(campaign_id, campaign);
+        Ok(result)
+    })
+}
+
+/// The campaign, if the caller owns it or is a controller.
+fn ensure_campaign_owner(campaign_id: u64) -> Result<Campaign, String> {
+    campaign_owned_by(&ic_cdk::caller(), campaign_id)
+}
+
+fn campaign_owned_by(caller: &Principal, campaign_id: u64) -> Result<Campaign, String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    permissions::may_manage_campaign(caller, &campaign)?;
+    Ok(campaign)
+}
+
+fn ensure_active(campaign: &Campaign) -> Result<(), String> {
+    Ok(permissions::running(campaign, now_secs())?)
+}
+
+/// Cancel an active campaign (owner or controllers). Contributions stay recorded; a campaign that
+/// had raised anything is flagged `refund_pending` until its backers have claimed their refunds.
+#[update]
+fn cancel_campaign(campaign_id: u64) -> Result<(), String> {
+    let campaign = ensure_campaign_owner(campaign_id)?;
+    ensure_active(&campaign)?;
+    with_campaign_mut(campaign_id, |c| {
+        c.status = Some(CampaignLifecycle::Cancelled);
+        c.refund_pending = Some(c.amount_raised > 0);
+        Ok(())
+    })?;
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// Move an active campaign's end date later (owner or controllers). Seconds since the epoch,
+/// snapped to the campaign's end hour like at creation.
+#[update]
+fn extend_campaign(campaign_id: u64, new_end_date: u64) -> Result<(), String> {
+    let campaign = ensure_campaign_owner(campaign_id)?;
+    ensure_active(&campaign)?;
+    check_end_date(new_end_date, now_secs())?;
+    let snapped = snap_end_date(new_end_date, campaign.end_hour_utc);
+    if snapped <= campaign.end_date {
+        return Err(format!("new_end_date must be after the current end date ({})", campaign.end_date));
+    }
+    with_campaign_mut(campaign_id, |c| {
+        c.end_date = snapped;
+        c.requested_end_date = c.end_hour_utc.and(Some(new_end_date));
+        Ok(())
+    })?;
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// Snap new campaigns' end dates to this hour (UTC) unless they pick their own; None keeps them
+/// exact (controllers only). Existing campaigns keep the rule they were created with.
+#[update]
+fn set_campaign_end_hour_utc(hour: Option<u8>) -> Result<(), String> {
+    ensure_controller()?;
+    if hour.is_some_and(|h| h >= 24) {
+        return Err("end hour must be 0-23 (UTC)".into());
+    }
+    CAMPAIGN_END_HOUR
+        .with(|h| h.borrow_mut().set(hour.unwrap_or(NO_END_HOUR)))
+        .map(|_| ())
+        .map_err(|e| format!("failed to store end hour: {:?}", e))
+}
+
+#[query]
+fn get_campaign_end_hour_utc() -> Option<u8> {
+    end_hour_for(None).unwrap_or(None)
+}
+
+/// Cancelled or taken-down campaigns that raised funds and still await refunds (admins).
+#[query]
+fn get_campaigns_pending_refund() -> Result<Vec<u64>, String> {
+    ensure_admin()?;
+    Ok(CAMPAIGNS.with(|store| {
+        store
+            .borrow()
+            .iter()
+            .filter(|(_, c)| c.refund_pending == Some(true))
+            .map(|(id, _)| id)
+            .collect()
+    }))
+}
+
+/// Set the risk disclosure before the campaign raises any funds (owner or controllers).
+#[update]
+fn set_campaign_risks(campaign_id: u64, text: String) -> Result<(), String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    permissions::may_edit_campaign(&ic_cdk::caller(), &campaign)?;
+    let text = validate_risks(&text)?;
+    with_campaign_mut(campaign_id, |c| {
+        c.risks = Some(text);
+        Ok(())
+    })?;
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// Replace the campaign headline and teaser before launch (owner or controllers). `None` falls back to the idea.
+#[update]
+fn set_campaign_copy(campaign_id: u64, display_title: Option<String>, teaser: Option<String>) -> Result<(), String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    permissions::may_edit_campaign(&ic_cdk::caller(), &campaign)?;
+    let display_title = validate_copy(display_title, "display_title", MAX_DISPLAY_TITLE_LEN)?;
+    let teaser = validate_copy(teaser, "teaser", MAX_CAMPAIGN_TEASER_LEN)?;
+    with_campaign_mut(campaign_id, |c| {
+        c.display_title = display_title;
+        c.teaser = teaser;
+        Ok(())
+    })?;
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// Append a timestamped amendment to the risk disclosure (owner or controllers). Amendments are never edited.
+#[update]
+fn amend_campaign_risks(campaign_id: u64, text: String) -> Result<(), String> {
+    ensure_campaign_owner(campaign_id)?;
+    let text = validate_risks(&text)?;
+    with_campaign_mut(campaign_id, |c| {
+        c.risk_amendments.push(RiskAmendment { text, added_at: ic_cdk::api::time() });
+        Ok(())
+    })?;
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// A draft the caller doesn't own, which must not be shown to them.
+fn is_private_draft(idea: &Idea) -> bool {
+    idea.is_draft() && idea.owner != ic_cdk::caller()
+}
+
+/// An idea as the caller may see it: drafts only to their owner.
+fn visible_idea(idea_id: u64) -> Option<Idea> {
+    get_idea(idea_id).filter(|idea| !is_private_draft(idea))
+}
+
+/// Convenience: fetch an idea by id. Drafts are only returned to their owner.
+#[query]
+fn get_idea_by_id(idea_id: u64) -> Option<Idea> {
+    visible_idea(idea_id)
+}
+
+/// Ideas created by the caller, drafts included, oldest first.
+#[query]
+fn get_my_ideas() -> Vec<(u64, Idea)> {
+    let caller = ic_cdk::caller();
+    let ids: Vec<u64> = IDEAS_BY_OWNER.with(|o| {
+        o.borrow()
+            .range((caller, 0)..=(caller, u64::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    });
+    ids.into_iter().filter_map(|id| get_idea(id).map(|idea| (id, idea))).collect()
+}
+
+/// Cards for the campaigns the caller created, oldest first.
+#[query]
+fn get_my_campaigns() -> Vec<CampaignCard> {
+    let caller = ic_cdk::caller();
+    let ids: Vec<u64> = CAMPAIGNS_BY_OWNER.with(|o| {
+        o.borrow()
+            .range((caller, 0)..=(caller, u64::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    });
+    ids.into_iter()
+        .filter_map(|id| {
+            card_of(&get_campaign(id)?)
+        })
+        .collect()
+}
+
+const TEASER_CHARS: usize = 140;
+const MAX_SUMMARY_PAGE: u64 = 100;
+
+/// Cut `text` to at most `TEASER_CHARS` characters, at a word boundary when there is one.
+fn teaser(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= TEASER_CHARS {
+        return text.to_string();
+    }
+    // leave room for the ellipsis; char_indices keeps multi-byte characters whole
+    let cut = text.char_indices().nth(TEASER_CHARS - 1).map(|(i, _)| i).unwrap_or(text.len());
+    let head = &text[..cut];
+    let head = match head.rfind(char::is_whitespace) {
+        Some(i) if i > 0 => &head[..i],
+        _ => head,
+    };
+    format!("{}…", head.trim_end())
+}
+
+fn to_summary(id: u64, idea: &Idea, successful_campaigns: &BTreeMap<u64, u64>) -> IdeaSummary {
+    IdeaSummary {
+        id,
+        title: idea.title.clone(),
+        teaser: teaser(&idea.description),
+        category: idea.category.clone(),
+        percent_funded: (percent_bps(idea.current_funding, idea.funding_goal) / 100).min(u32::MAX as u64) as u32,
+        comment_count: get_idea_comment_count(id),
+        badges: badges::idea_stats(id, idea, successful_campaigns.get(&id).copied().unwrap_or(0)).badges,
+    }
+}
+
+/// Slim, paginated idea listing for mobile list views. Start with `cursor = None`.
+#[query]
+fn get_idea_summaries(filter: Option<IdeaSummaryFilter>, cursor: Option<u64>, limit: u64) -> SummaryPage {
+    let filter = filter.unwrap_or_default();
+    let limit = limit.clamp(1, MAX_SUMMARY_PAGE) as usize;
+    let start = cursor.unwrap_or(0);
+    let mut items = Vec::with_capacity(limit);
+    let mut next_cursor = None;
+    let successful_campaigns = badges::successful_campaigns_by_idea();
+    IDEAS.with(|ideas| {
+        for (id, idea) in ideas.borrow().range(start..) {
+            if !idea.is_listed() {
+                continue;
+            }
+            if let Some(cat) = &filter.category {
+                if !idea.category.eq_ignore_ascii_case(cat) {
+                    continue;
+                }
+            }
+            if items.len() == limit {
+                next_cursor = Some(id);
+                break;
+            }
+            items.push(to_summary(id, &idea, &successful_campaigns));
+        }
+    });
+    SummaryPage { items, next_cursor }
+}
+
+/// An idea's campaigns grouped by funding round, in the order each round first appeared.
+#[query]
+fn get_idea_round_timeline(idea_id: u64) -> Vec<RoundTimelineEntry> {
+    let mut timeline: Vec<RoundTimelineEntry> = Vec::new();
+    CAMPAIGNS.with(|store| {
+        for (_, c) in store.borrow().iter().filter(|(_, c)| c.idea_id == idea_id) {
+            let entry = match timeline.iter_mut().position(|e| e.round == c.round) {
+                Some(i) => &mut timeline[i],
+                None => {
+                    timeline.push(RoundTimelineEntry {
+                        round: c.round.clone(),
+                        campaign_ids: vec![],
+                        total_raised: 0,
+                        total_goal: 0,
+                    });
+                    timeline.last_mut().expect("just pushed")
+                }
+            };
+            entry.campaign_ids.push(c.id);
+            entry.total_raised = entry.total_raised.saturating_add(c.amount_raised);
+            entry.total_goal = entry.total_goal.saturating_add(c.goal);
+        }
+    });
+    timeline
+}
+
+/// Cards for campaigns in a given round.
+#[query]
+fn get_campaign_cards_by_round(round: FundingRound) -> Vec<CampaignCard> {
+    CAMPAIGNS.with(|store| {
+        store
+            .borrow()
+            .iter()
+            .filter(|(_, c)| c.round.as_ref() == Some(&round))
+            .filter_map(|(_, c)| card_of(&c))
+            .collect()
+    })
+}
+
+/// Campaign counts per (category, round) for the browse sidebar.
+#[query]
+fn get_category_round_counts() -> Vec<CategoryRoundCount> {
+    let mut counts: std::collections::BTreeMap<(String, Option<FundingRound>), u64> = Default::default();
+    CAMPAIGNS.with(|store| {
+        for (_, c) in store.borrow().iter() {
+            if let Some(idea) = get_idea(c.idea_id) {
+                *counts.entry((idea.category, c.round.clone())).or_default() += 1;
+            }
+        }
+    });
+    counts
+        .into_iter()
+        .map(|((category, round), campaigns)| CategoryRoundCount { category, round, campaigns })
+        .collect()
+}
+
+const MAX_RESOLVE_REFS: usize = 100;
+
+fn resolve_ref(entity: EntityRef) -> Option<EntitySummary> {
+    let (label, parent) = match entity {
+        EntityRef::Idea(id) if takedown::is_taken_down(id) => (takedown::TAKEDOWN_NOTICE.to_string(), None),
+        EntityRef::Idea(id) => (visible_idea(id)?.title, None),
+        EntityRef::Campaign(id) => {
+            let c = get_campaign(id)?;
+            if takedown::is_campaign_taken_down(&c) {
+                (takedown::TAKEDOWN_NOTICE.to_string(), Some(EntityRef::Idea(c.idea_id)))
+            } else {
+                let idea_title = get_idea(c.idea_id)?.title;
+                (c.display_title.unwrap_or(idea_title), Some(EntityRef::Idea(c.idea_id)))
+            }
+        }
+        EntityRef::Doc(id) if takedown::is_held_doc(id) => (takedown::TAKEDOWN_NOTICE.to_string(), None),
+        EntityRef::Doc(id) => {
+            let d = get_doc(id)?;
+            (d.name, Some(EntityRef::Idea(d.idea_id)))
+        }
+        EntityRef::Comment(id) => {
+            let c = IDEA_COMMENTS.with(|m| m.borrow().get(&id))?;
+            (teaser(&c.text), Some(EntityRef::Idea(c.idea_id)))
+        }
+    };
+    Some(EntitySummary { entity, label, parent })
+}
+
+/// Resolve up to 100 references to link summaries, in order; None for anything missing.
+#[query]
+fn resolve_refs(refs: Vec<EntityRef>) -> Vec<Option<EntitySummary>> {
+    refs.into_iter().take(MAX_RESOLVE_REFS).map(resolve_ref).collect()
+}
+
+// ------------- Fund_Flow Integration Methods -------------
+
+/// Get campaign metadata for Fund_Flow canister
+#[query]
+fn get_campaign_meta(campaign_id: u64) -> Option<CampaignMeta> {
+    let now = now_secs();
+    get_campaign(campaign_id).map(|campaign| CampaignMeta {
+        campaign_id: campaign.id,
+        goal: campaign.goal,
+        amount_raised: campaign.amount_raised,
+        end_date_secs: effective_end_date(&campaign, now),
+        has_risks: campaign.risks.is_some() || !campaign.risk_amendments.is_empty(),
+        paused_reason: active_pause_reason(campaign.id, now),
+        cancelled: matches!(campaign.status, Some(CampaignLifecycle::Cancelled | CampaignLifecycle::TakenDown)),
+        currency: campaign.currency(),
+    })
+}
+
+/// Receive ICP contribution from Fund_Flow canister; anyone else is rejected.
+#[update]
+fn receive_icp_contribution(campaign_id: u64, amount_e8s: u64) -> Result<(), String> {
+    icp_ledger::ensure_fund_flow()?;
+    // Verify campaign exists
+    let Some(campaign) = get_campaign(campaign_id) else {
+        return Err(not_found(EntityRef::Campaign(campaign_id)));
+    };
+    currency::check_pledge(&campaign, Currency::Icp)?;
+    
+    // Update ICP contributions tracking
+    let icp_total = Amount::new(ICP_CONTRIBUTIONS.with(|c| c.borrow().get(&campaign_id)).unwrap_or(0))
+        .add(Amount::new(amount_e8s))?;
+    credit_campaign(&campaign, Amount::new(amount_e8s), ic_cdk::api::time())?;
+    ICP_CONTRIBUTIONS.with(|contributions| contributions.borrow_mut().insert(campaign_id, icp_total.get()));
+    Ok(())
+}
+
+/// The campaign's amount_raised and its idea's current_funding after `change` is applied to both,
+/// or the error if either total would overflow or go below zero. Nothing is written.
+fn totals_after(
+    campaign: &Campaign,
+    change: impl Fn(Amount) -> Result<Amount, AmountError>,
+) -> Result<(Amount, Option<Idea>), AmountError> {
+    let raised = change(Amount::new(campaign.amount_raised))?;
+    let idea = match get_idea(campaign.idea_id) {
+        Some(mut idea) => {
+            idea.current_funding = change(Amount::new(idea.current_funding))?.get();
+            Some(idea)
+        }
+        None => None,
+    };
+    Ok((raised, idea))
+}
+
+fn store_totals(campaign: &Campaign, (raised, idea): (Amount, Option<Idea>), now: u64) {
+    update_campaign_amount(campaign.id, raised.get());
+    if let Some(mut idea) = idea {
+        idea.updated_at = now;
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(campaign.idea_id, idea));
+    }
+    record_change_at(now, EntityRef::Campaign(campaign.id), ChangeKind::Updated);
+    record_change_at(now, EntityRef::Idea(campaign.idea_id), ChangeKind::Updated);
+}
+
+/// Add `amount` to the campaign's amount_raised and its idea's current_funding as of `now` (ns);
+/// neither changes if either would overflow.
+fn credit_campaign(campaign: &Campaign, amount: Amount, now: u64) -> Result<(), AmountError> {
+    let totals = totals_after(campaign, |total| total.add(amount))?;
+    let raised = totals.0.get();
+    store_totals(campaign, totals, now);
+    milestones::mark_reached(campaign.id, raised);
+    Ok(())
+}
+
+/// Take a refunded `amount` back off the campaign's amount_raised and its idea's current_funding.
+fn debit_campaign(campaign: &Campaign, amount: Amount, now: u64) -> Result<(), AmountError> {
+    store_totals(campaign, totals_after(campaign, |total| total.sub(amount))?, now);
+    Ok(())
+}
+
+// ------------- Contributions -------------
+
+/// Error prefix while a campaign's contributions are paused; the public reason follows.
+pub const CONTRIBUTIONS_PAUSED: &str = "CONTRIBUTIONS_PAUSED";
+
+/// Error prefix for an ICP pledge made without a ledger transfer; see `contribute_icp`.
+pub const LEDGER_TRANSFER_REQUIRED: &str = "LEDGER_TRANSFER_REQUIRED";
+
+/// Error prefix for a pledge to a campaign with a risks section, made without `ack_risks = true`.
+pub const RISK_ACK_REQUIRED: &str = "RISK_ACK_REQUIRED";
+
+const MAX_BASKET_ITEMS: usize = 5;
+
+/// Pledge `amount` to a campaign that is still open, from `channel` (Web or MobileApp; omitted
+/// => Unspecified). `currency` (default ICP) must be the campaign's. ICP is only credited against
+/// a ledger transfer, so ICP pledges here fail with LEDGER_TRANSFER_REQUIRED: send the ICP and call
+/// `contribute_icp`. Campaigns with a risks section need `ack_risks = Some(true)`, else
+/// RISK_ACK_REQUIRED. Returns the new contribution id.
+#[update]
+fn contribute(
+    campaign_id: u64,
+    amount: u64,
+    channel: Option<Channel>,
+    currency: Option<Currency>,
+    ack_risks: Option<bool>,
+) -> Result<u64, String> {
+    let pledge = Money { currency: currency.unwrap_or_default(), amount };
+    contribute_at(ic_cdk::caller(), ic_cdk::api::time(), campaign_id, pledge, channel, ack_risks)
+}
+
+/// `contribute` by `caller` at `now` (ns).
+fn contribute_at(
+    caller: Principal,
+    now: u64,
+    campaign_id: u64,
+    pledge: Money,
+    channel: Option<Channel>,
+    ack_risks: Option<bool>,
+) -> Result<u64, String> {
+    let channel = channels::channel_for(caller, channel)?;
+    check_unverified(pledge)?;
+    check_contribution(caller, now, campaign_id, pledge, Amount::default(), ack_risks)?;
+    Ok(record_contribution(caller, now, campaign_id, pledge, channel))
+}
+
+/// Pledges taken on the caller's word, without a transfer to check: never ICP.
+fn check_unverified(pledge: Money) -> Result<(), String> {
+    if pledge.currency == Currency::Icp {
+        return Err(format!(
+            "{}: ICP pledges must be sent to the ledger first and claimed with contribute_icp",
+            LEDGER_TRANSFER_REQUIRED
+        ));
+    }
+    Ok(())
+}
+
+/// `pending` is what the same call will credit before this pledge; the totals must fit both.
+fn check_contribution(
+    caller: Principal,
+    now: u64,
+    campaign_id: u64,
+    pledge: Money,
+    pending: Amount,
+    ack_risks: Option<bool>,
+) -> Result<(), String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    permissions::may_contribute(&caller, &campaign, to_secs(now))?;
+    check_risks_acked(&campaign, ack_risks)?;
+    currency::check_pledge(&campaign, pledge.currency)?;
+    let amount = pledge.amount;
+    if amount == 0 {
+        return Err("amount must be > 0".into());
+    }
+    let credit = pending.add(Amount::new(amount))?;
+    totals_after(&campaign, |total| total.add(credit))?;
+    Ok(())
+}
+
+/// Backers must acknowledge a campaign's risks section before pledging to it.
+fn check_risks_acked(campaign: &Campaign, ack_risks: Option<bool>) -> Result<(), String> {
+    if campaign.risks.is_some() && ack_risks != Some(true) {
+        return Err(format!(
+            "{}: acknowledge this campaign's risks (ack_risks = true) to contribute",
+            RISK_ACK_REQUIRED
+        ));
+    }
+    Ok(())
+}
+
+/// Record an already-checked pledge. Re-reads the campaign so a basket may hit one campaign twice.
+/// A campaign with risks was acknowledged at the check, so the time is recorded with the pledge.
+fn record_contribution(caller: Principal, now: u64, campaign_id: u64, pledge: Money, channel: Channel) -> u64 {
+    let amount = pledge.amount;
+    let campaign = get_campaign(campaign_id).expect("checked campaign exists");
+    let id = CONTRIBUTION_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store contribution counter");
+        id
+    });
+    let contribution = Contribution {
+        id,
+        campaign_id,
+        contributor: caller,
+        amount,
+        timestamp: now,
+        channel: Some(channel),
+        currency: Some(pledge.currency),
+        risks_acked_at: campaign.risks.is_some().then_some(now),
+    };
+    CONTRIBUTIONS.with(|c| c.borrow_mut().insert(id, contribution));
+    CAMPAIGN_CONTRIBUTIONS.with(|c| c.borrow_mut().insert((campaign_id, id), ()));
+    CONTRIBUTOR_CONTRIBUTIONS.with(|c| c.borrow_mut().insert((caller, id), ()));
+    badges::note_backer(campaign.idea_id, caller);
+    credit_campaign(&campaign, Amount::new(amount), now).expect("checked totals fit");
+    activity::append(now, caller, EventKind::ContributionReceived, id, &[EntityRef::Campaign(campaign_id)]);
+    id
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ContributionRequest {
+    pub campaign_id: u64,
+    pub amount: u64,
+    pub currency: Option<Currency>, // None => ICP
+    pub ack_risks: Option<bool>,    // required (true) when the campaign has a risks section
+}
+
+impl ContributionRequest {
+    fn pledge(&self) -> Money {
+        Money { currency: self.currency.unwrap_or_default(), amount: self.amount }
+    }
+}
+
+/// Pledge to up to 5 campaigns in one call, with one result per item in request order.
+/// Every item is checked before any is recorded; with `all_or_nothing` a single failed check
+/// records nothing, and the items that passed report that they were not executed. Each check
+/// counts every earlier passing item as pending, so no total can overflow between checks.
+/// `channel` applies to the whole basket, and ICP items fail, as for `contribute`.
+#[update]
+fn contribute_many(
+    items: Vec<ContributionRequest>,
+    all_or_nothing: bool,
+    channel: Option<Channel>,
+) -> Result<Vec<Result<u64, String>>, String> {
+    contribute_many_at(ic_cdk::caller(), ic_cdk::api::time(), items, all_or_nothing, channel)
+}
+
+/// `contribute_many` by `caller` at `now` (ns).
+fn contribute_many_at(
+    caller: Principal,
+    now: u64,
+    items: Vec<ContributionRequest>,
+    all_or_nothing: bool,
+    channel: Option<Channel>,
+) -> Result<Vec<Result<u64, String>>, String> {
+    if items.is_empty() || items.len() > MAX_BASKET_ITEMS {
+        return Err(format!("a basket holds 1-{} items", MAX_BASKET_ITEMS));
+    }
+    let channel = channels::channel_for(caller, channel)?;
+    let mut pending = Amount::default();
+    let checks: Vec<Result<(), String>> = items
+        .iter()
+        .map(|item| {
+            let check = check_unverified(item.pledge()).and_then(|_| {
+                check_contribution(caller, now, item.campaign_id, item.pledge(), pending, item.ack_risks)
+            });
+            if check.is_ok() {
+                pending = pending.add(Amount::new(item.amount)).unwrap_or(Amount::MAX);
+            }
+            check
+        })
+        .collect();
+    let failed: Vec<usize> = checks.iter().enumerate().filter(|(_, c)| c.is_err()).map(|(i, _)| i).collect();
+    if all_or_nothing && !failed.is_empty() {
+        let reason = format!("not executed: item {} failed", failed[0]);
+        return Ok(checks.into_iter().map(|c| c.and(Err(reason.clone()))).collect());
+    }
+    // Recording can't fail once checked, and nothing awaits, so the basket lands in one message.
+    Ok(items
+        .iter()
+        .zip(checks)
+        .map(|(item, check)| check.map(|_| record_contribution(caller, now, item.campaign_id, item.pledge(), channel)))
+        .collect())
+}
+
+fn contributions_by_id(ids: Vec<u64>) -> Vec<Contribution> {
+    CONTRIBUTIONS.with(|c| {
+        let c = c.borrow();
+        ids.into_iter().filter_map(|id| c.get(&id)).collect()
+    })
+}
+
+/// Every contribution to a campaign, oldest first.
+#[query]
+fn get_contributions_by_campaign(campaign_id: u64) -> Vec<Contribution> {
+    if let Some(cold) = archive::cold_campaign_contributions(campaign_id) {
+        return cold;
+    }
+    let ids = CAMPAIGN_CONTRIBUTIONS.with(|c| {
+        c.borrow()
+            .range((campaign_id, 0)..=(campaign_id, u64::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    });
+    contributions_by_id(ids)
+}
+
+/// The caller's own contributions, oldest first.
+#[query]
+fn get_my_contributions() -> Vec<Contribution> {
+    let caller = ic_cdk::caller();
+    let ids = CONTRIBUTOR_CONTRIBUTIONS.with(|c| {
+        c.borrow()
+            .range((caller, 0)..=(caller, u64::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    });
+    let mut contributions = contributions_by_id(ids);
+    contributions.extend(archive::cold_contributions_of(caller));
+    contributions.sort_by_key(|c| c.id);
+    contributions
+}
+
+// ------------- Contribution pauses -------------
+
+const FREE_PAUSE_SECS: u64 = 72 * 60 * 60; // pauses up to this total don't move the deadline
+const MAX_PAUSE_BUDGET_SECS: u64 = 14 * 24 * 60 * 60; // total pause time allowed per campaign
+const MAX_PAUSE_REASON_LEN: usize = 280;
+
+fn pause_log(campaign_id: u64) -> PauseLog {
+    CAMPAIGN_PAUSES.with(|p| p.borrow().get(&campaign_id)).unwrap_or_default()
+}
+
+fn active_pause_reason(campaign_id: u64, now_secs: u64) -> Option<String> {
+    pause_log(campaign_id).active(now_secs).map(|w| w.reason.clone())
+}
+
+/// The scheduled end date pushed back by however much cumulative pause time exceeds 72 hours,
+/// then snapped to the campaign's end hour again.
+fn effective_end_date(c: &Campaign, now_secs: u64) -> u64 {
+    let excess = pause_log(c.id).paused_secs(now_secs).saturating_sub(FREE_PAUSE_SECS);
+    snap_end_date(c.end_date.saturating_add(excess), c.end_hour_utc)
+}
+
+/// Stop accepting contributions for a while without cancelling (owner or controllers).
+/// Pauses draw on a 14-day budget per campaign.
+#[update]
+fn pause_contributions(campaign_id: u64, reason: String) -> Result<(), String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    let now = now_secs();
+    permissions::may_pause(&ic_cdk::caller(), &campaign, now)?;
+    let reason = reason.trim().to_string();
+    if reason.is_empty() || reason.chars().count() > MAX_PAUSE_REASON_LEN {
+        return Err(format!("reason must be 1-{} characters", MAX_PAUSE_REASON_LEN));
+    }
+    let mut log = pause_log(campaign_id);
+    let remaining = MAX_PAUSE_BUDGET_SECS.saturating_sub(log.paused_secs(now));
+    log.windows.push(PauseWindow { reason, started_at: now, ended_at: None, expires_at: now + remaining });
+    CAMPAIGN_PAUSES.with(|p| p.borrow_mut().insert(campaign_id, log));
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// End the current pause (owner or controllers). Pauses also end by themselves when the budget runs out.
+#[update]
+fn resume_contributions(campaign_id: u64) -> Result<(), String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    let now = now_secs();
+    permissions::may_resume(&ic_cdk::caller(), &campaign, now)?;
+    let mut log = pause_log(campaign_id);
+    if let Some(w) = log.windows.last_mut() {
+        w.ended_at = Some(now);
+    }
+    CAMPAIGN_PAUSES.with(|p| p.borrow_mut().insert(campaign_id, log));
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(())
+}
+
+/// Every pause window of a campaign, oldest first.
+#[query]
+fn get_campaign_pauses(campaign_id: u64) -> Vec<PauseWindow> {
+    pause_log(campaign_id).windows
+}
+
+/// Receive payout notification from Fund_Flow canister
+#[update]
+fn receive_payout(campaign_id: u64, total_amount: u64) -> Result<(), String> {
+    // This method is called when Fund_Flow releases funds to project owner
+    // For now, we just log the payout. In a real implementation, you might:
+    // - Transfer ICP to project owner's wallet
+    // - Update campaign status
+    // - Send notifications
+    
+    ic_cdk::println!("Payout received for campaign {}: {} e8s", campaign_id, total_amount);
+    
+    // Update campaign status or mark as completed
+    // You could add a status field to Campaign struct for this
+    
+    Ok(())
+}
+
+/// ICP (e8s) Fund_Flow relayed to a campaign; pledges claimed with contribute_icp are not included.
+#[query]
+fn get_icp_contribution(campaign_id: u64) -> u64 {
+    ICP_CONTRIBUTIONS.with(|contributions| {
+        contributions.borrow().get(&campaign_id).unwrap_or(0)
+    })
+}
+
+/// Get total funding (ICP + other methods) for a campaign. Relayed ICP is credited to
+/// amount_raised as it arrives, so that is the whole total.
+#[query]
+fn get_campaign_total_funding(campaign_id: u64) -> u64 {
+    get_campaign(campaign_id).map(|c| c.amount_raised).unwrap_or(0)
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct StateStats {
+    pub ideas: u64,
+    pub campaigns: u64,
+    pub docs: u64,
+    pub contributions: u64, // hot only; archived ones live in cold blocks
+    pub icp_funded_campaigns: u64,
+}
+
+/// Record counts, for checking that an upgrade kept every collection.
+#[query]
+fn get_state_stats() -> StateStats {
+    StateStats {
+        ideas: IDEAS.with(|m| m.borrow().len()),
+        campaigns: CAMPAIGNS.with(|m| m.borrow().len()),
+        docs: DOCS.with(|m| m.borrow().len()),
+        contributions: CONTRIBUTIONS.with(|m| m.borrow().len()),
+        icp_funded_campaigns: ICP_CONTRIBUTIONS.with(|m| m.borrow().len()),
+    }
+}
+
+// ------------- Platform statistics -------------
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PlatformStats {
+    pub total_ideas: u64, // quarantined records excluded
+    pub approved_ideas: u64,
+    pub total_campaigns: u64,
+    pub active_campaigns: u64,
+    pub totals: Vec<CurrencyTotals>, // one entry per currency with campaigns, in `Currency` order
+    pub raised_by_category: Vec<(String, Money)>, // by idea category, then currency; orphaned campaigns left out
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct CampaignProgress {
+    pub campaign_id: u64,
+    pub raised: u64,
+    pub goal: u64,
+    pub percent_bps: u64, // 10_000 = 100%; keeps going past the goal
+    pub backers: u64,     // distinct principals; ICP pledges relayed by Fund_Flow carry none
+    pub days_left: i64,   // as on the card; negative => ended
+    pub currency: Currency,
+}
+
+/// Raised as basis points of goal, pinned at u64::MAX for the rare share too large to represent.
+fn percent_bps(raised: u64, goal: u64) -> u64 {
+    Amount::new(raised).share_bps(Amount::new(goal)).unwrap_or(u64::MAX)
+}
+
+/// Fold one campaign into the running totals. Each field is a plain sum, so maintained counters
+/// updated at the same points can replace the full scan in `get_platform_stats` later.
+fn add_campaign_to_stats(
+    stats: &mut PlatformStats,
+    by_category: &mut BTreeMap<(String, Currency), u64>,
+    c: &Campaign,
+    category: Option<String>,
+    now: u64,
+) {
+    stats.total_campaigns += 1;
+    if lifecycle(c, now) == CampaignLifecycle::Active {
+        stats.active_campaigns += 1;
+    }
+    currency::add_to_totals(&mut stats.totals, c);
+    if let Some(category) = category {
+        let raised = by_category.entry((category, c.currency())).or_default();
+        *raised = raised.saturating_add(c.amount_raised);
+    }
+}
+
+/// Landing-page totals across every idea and campaign.
+#[query]
+fn get_platform_stats() -> PlatformStats {
+    let now = now_secs();
+    let mut stats = PlatformStats::default();
+    IDEAS.with(|ideas| {
+        for (_, idea) in ideas.borrow().iter().filter(|(_, i)| i.is_listed()) {
+            stats.total_ideas += 1;
+            if idea.status == IdeaStatus::Approved {
+                stats.approved_ideas += 1;
+            }
+        }
+    });
+    let mut by_category = BTreeMap::new();
+    CAMPAIGNS.with(|store| {
+        for (_, c) in store.borrow().iter() {
+            let category = get_idea(c.idea_id).map(|idea| idea.category);
+            add_campaign_to_stats(&mut stats, &mut by_category, &c, category, now);
+        }
+    });
+    stats.totals.sort_by_key(|t| t.currency);
+    stats.raised_by_category = by_category
+        .into_iter()
+        .map(|((category, currency), amount)| (category, Money { currency, amount }))
+        .collect();
+    stats
+}
+
+#[query]
+fn get_campaign_progress(campaign_id: u64) -> Option<CampaignProgress> {
+    let c = get_campaign(campaign_id)?;
+    let backers: BTreeSet<Principal> =
+        get_contributions_by_campaign(campaign_id).into_iter().map(|contribution| contribution.contributor).collect();
+    let now = now_secs();
+    Some(CampaignProgress {
+        campaign_id,
+        raised: c.amount_raised,
+        goal: c.goal,
+        percent_bps: percent_bps(c.amount_raised, c.goal),
+        backers: backers.len() as u64,
+        days_left: ends_in_secs(effective_end_date(&c, now), now).div_euclid(86_400),
+        currency: c.currency(),
+    })
+}
+
+// ------------- Idea discussion -------------
+
+const MAX_COMMENT_LEN: usize = 2000;
+const MAX_COMMENT_PAGE: u64 = 100;
+
+/// Public comments open once an idea is approved; before that only moderators (controllers) may post.
+fn can_comment(idea: &Idea, caller: &candid::Principal) -> bool {
+    idea.status == IdeaStatus::Approved || is_controller(caller)
+}
+
+fn page_of(index: &StableBTreeMap<(u64, u64), (), Memory>, key: u64, offset: u64, limit: u64) -> Vec<IdeaComment> {
+    let limit = limit.min(MAX_COMMENT_PAGE) as usize;
+    index
+        .range((key, 0)..=(key, u64::MAX))
+        .skip(offset as usize)
+        .take(limit)
+        .filter_map(|((_, id), _)| IDEA_COMMENTS.with(|c| c.borrow().get(&id)))
+        .collect()
+}
+
+fn bump_comment_count(idea_id: u64, delta: i64) {
+    IDEA_COMMENT_COUNTS.with(|counts| {
+        let mut counts = counts.borrow_mut();
+        let n = counts.get(&idea_id).unwrap_or(0).saturating_add_signed(delta);
+        if n == 0 {
+            counts.remove(&idea_id);
+        } else {
+            counts.insert(idea_id, n);
+        }
+    });
+}
+
+/// Drop every comment on an idea and its indexes. Returns the removed comment ids.
+fn remove_idea_comments(idea_id: u64) -> Vec<u64> {
+    let top: Vec<u64> = TOP_LEVEL_COMMENTS.with(|t| {
+        t.borrow()
+            .range((idea_id, 0)..=(idea_id, u64::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    });
+    let mut removed = Vec::new();
+    for parent in top {
+        let replies: Vec<u64> = COMMENT_REPLIES.with(|r| {
+            r.borrow()
+                .range((parent, 0)..=(parent, u64::MAX))
+                .map(|((_, id), _)| id)
+                .collect()
+        });
+        COMMENT_REPLIES.with(|r| {
+            let mut r = r.borrow_mut();
+            for id in &replies {
+                r.remove(&(parent, *id));
+            }
+        });
+        TOP_LEVEL_COMMENTS.with(|t| t.borrow_mut().remove(&(idea_id, parent)));
+        removed.extend(replies);
+        removed.push(parent);
+    }
+    IDEA_COMMENTS.with(|c| {
+        let mut c = c.borrow_mut();
+        for id in &removed {
+            c.remove(id);
+        }
+    });
+    IDEA_COMMENT_COUNTS.with(|counts| counts.borrow_mut().remove(&idea_id));
+    removed
+}
+
+/// Comment on an idea. `parent_id` replies to a top-level comment (one level of nesting only).
 #[update]
-fn create_campaign(idea_id: u64, goal: u64, end_date: u64) -> Result<u64, String> {
-    if goal == 0 {
-        return Err("goal must be > 0".into());
+fn post_idea_comment(idea_id: u64, text: String, parent_id: Option<u64>) -> Result<u64, String> {
+    let caller = ic_cdk::caller();
+    let idea = get_idea(idea_id).ok_or_else(|| not_found(EntityRef::Idea(idea_id)))?;
+    permissions::may_comment(&caller, &idea)?;
+    let text = text.trim().to_string();
+    if text.is_empty() || text.len() > MAX_COMMENT_LEN {
+        return Err(format!("comment must be 1-{} bytes", MAX_COMMENT_LEN));
+    }
+
+    let mut parent = match parent_id {
+        Some(pid) => {
+            let parent = IDEA_COMMENTS
+                .with(|c| c.borrow().get(&pid))
+                .ok_or_else(|| not_found(EntityRef::Comment(pid)))?;
+            if parent.idea_id != idea_id {
+                return Err("parent comment belongs to another idea".into());
+            }
+            if parent.parent_id.is_some() {
+                return Err("replies cannot be nested further".into());
+            }
+            Some(parent)
+        }
+        None => None,
+    };
+
+    let id = COMMENT_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store comment counter");
+        id
+    });
+    let comment = IdeaComment {
+        id,
+        idea_id,
+        parent_id,
+        author: caller,
+        text,
+        created_at: ic_cdk::api::time(),
+        reply_count: 0,
+    };
+    IDEA_COMMENTS.with(|c| {
+        let mut c = c.borrow_mut();
+        c.insert(id, comment);
+        if let Some(p) = parent.as_mut() {
+            p.reply_count += 1;
+            c.insert(p.id, p.clone());
+        }
+    });
+    match parent_id {
+        Some(pid) => COMMENT_REPLIES.with(|r| r.borrow_mut().insert((pid, id), ())),
+        None => TOP_LEVEL_COMMENTS.with(|t| t.borrow_mut().insert((idea_id, id), ())),
+    };
+    bump_comment_count(idea_id, 1);
+    record_change(EntityRef::Comment(id), ChangeKind::Created);
+    if let Some(pid) = parent_id {
+        record_change(EntityRef::Comment(pid), ChangeKind::Updated);
+    }
+    Ok(id)
+}
+
+/// Delete a comment (its author or a moderator). Deleting a top-level comment removes its replies.
+#[update]
+fn delete_idea_comment(comment_id: u64) -> Result<(), String> {
+    let caller = ic_cdk::caller();
+    let comment = IDEA_COMMENTS
+        .with(|c| c.borrow().get(&comment_id))
+        .ok_or_else(|| not_found(EntityRef::Comment(comment_id)))?;
+    permissions::may_delete_comment(&caller, &comment)?;
+
+    let removed = match comment.parent_id {
+        Some(pid) => {
+            COMMENT_REPLIES.with(|r| r.borrow_mut().remove(&(pid, comment_id)));
+            IDEA_COMMENTS.with(|c| {
+                let mut c = c.borrow_mut();
+                c.remove(&comment_id);
+                if let Some(mut parent) = c.get(&pid) {
+                    parent.reply_count = parent.reply_count.saturating_sub(1);
+                    c.insert(pid, parent);
+                }
+            });
+            record_change(EntityRef::Comment(comment_id), ChangeKind::Deleted);
+            record_change(EntityRef::Comment(pid), ChangeKind::Updated);
+            1
+        }
+        None => {
+            let replies: Vec<u64> = COMMENT_REPLIES.with(|r| {
+                r.borrow()
+                    .range((comment_id, 0)..=(comment_id, u64::MAX))
+                    .map(|((_, id), _)| id)
+                    .collect()
+            });
+            COMMENT_REPLIES.with(|r| {
+                let mut r = r.borrow_mut();
+                for id in &replies {
+                    r.remove(&(comment_id, *id));
+                }
+            });
+            IDEA_COMMENTS.with(|c| {
+                let mut c = c.borrow_mut();
+                for id in &replies {
+                    c.remove(id);
+                }
+                c.remove(&comment_id);
+            });
+            TOP_LEVEL_COMMENTS.with(|t| t.borrow_mut().remove(&(comment.idea_id, comment_id)));
+            for id in replies.iter().chain([&comment_id]) {
+                record_change(EntityRef::Comment(*id), ChangeKind::Deleted);
+            }
+            1 + replies.len() as i64
+        }
+    };
+    bump_comment_count(comment.idea_id, -removed);
+    Ok(())
+}
+
+/// Top-level comments on an idea, oldest first, each with its reply count.
+#[query]
+fn get_idea_comments(idea_id: u64, offset: u64, limit: u64) -> Vec<IdeaComment> {
+    TOP_LEVEL_COMMENTS.with(|t| page_of(&t.borrow(), idea_id, offset, limit))
+}
+
+/// Replies to a top-level comment, oldest first.
+#[query]
+fn get_comment_replies(comment_id: u64, offset: u64, limit: u64) -> Vec<IdeaComment> {
+    COMMENT_REPLIES.with(|r| page_of(&r.borrow(), comment_id, offset, limit))
+}
+
+/// Total comments (including replies) on an idea.
+#[query]
+fn get_idea_comment_count(idea_id: u64) -> u64 {
+    IDEA_COMMENT_COUNTS.with(|counts| counts.borrow().get(&idea_id).unwrap_or(0))
+}
+
+// ------------- Admin: dry runs for destructive operations -------------
+
+const CONFIRMATION_TTL_NS: u64 = 5 * 60 * 1_000_000_000;
+const DRY_RUN_SAMPLE: usize = 20;
+
+/// Each part is length-prefixed, so ("ab", "c") and ("a", "bc") give different digests.
+fn confirmation_digest(caller: Principal, op: &str, params: &[&str]) -> u64 {
+    let part = |h: u64, bytes: &[u8]| fnv1a(fnv1a(h, &(bytes.len() as u64).to_be_bytes()), bytes);
+    let h = params.iter().fold(part(0, op.as_bytes()), |h, p| part(h, p.as_bytes()));
+    part(h, caller.as_slice())
+}
+
+/// Issue a single-use token binding a later live run to this caller, operation and parameters.
+fn issue_confirmation(op: &str, params: &[&str]) -> String {
+    issue_confirmation_at(ic_cdk::api::caller(), ic_cdk::api::time(), op, params)
+}
+
+fn issue_confirmation_at(caller: Principal, now: u64, op: &str, params: &[&str]) -> String {
+    let digest = confirmation_digest(caller, op, params);
+    let seq = CONFIRMATION_SEQ.with(|s| {
+        let mut s = s.borrow_mut();
+        *s += 1;
+        *s
+    });
+    let token = format!("{:016x}{:016x}", digest, fnv1a(digest, &[now.to_be_bytes(), seq.to_be_bytes()].concat()));
+    CONFIRMATIONS.with(|c| {
+        let mut c = c.borrow_mut();
+        c.retain(|_, p| p.expires_at > now);
+        c.insert(token.clone(), PendingConfirmation { digest, expires_at: now + CONFIRMATION_TTL_NS });
+    });
+    token
+}
+
+/// Consume a token from `issue_confirmation`; fails unless it matches the same operation and parameters.
+fn redeem_confirmation(op: &str, params: &[&str], token: Option<String>) -> Result<(), String> {
+    redeem_confirmation_at(ic_cdk::api::caller(), ic_cdk::api::time(), op, params, token)
+}
+
+fn redeem_confirmation_at(
+    caller: Principal,
+    now: u64,
+    op: &str,
+    params: &[&str],
+    token: Option<String>,
+) -> Result<(), String> {
+    let token = token.ok_or_else(|| "run with dry_run = true first and pass back its confirmation_token".to_string())?;
+    let pending = CONFIRMATIONS
+        .with(|c| c.borrow_mut().remove(&token))
+        .ok_or_else(|| "unknown or already used confirmation token".to_string())?;
+    if pending.expires_at <= now {
+        return Err("confirmation token expired; dry-run again".into());
+    }
+    if pending.digest != confirmation_digest(caller, op, params) {
+        return Err("confirmation token was issued for different parameters".into());
+    }
+    Ok(())
+}
+
+fn dry_run_report(op: &str, params: &[&str], ids: &[u64]) -> DryRunReport {
+    DryRunReport {
+        dry_run: true,
+        affected: ids.len() as u64,
+        sample_ids: ids.iter().take(DRY_RUN_SAMPLE).copied().collect(),
+        confirmation_token: Some(issue_confirmation(op, params)),
+    }
+}
+
+// ------------- Admin: corrupt record repair -------------
+
+/// Add the undecodable ideas not yet recorded to CORRUPT_RECORDS, as detected at `now` (ns).
+/// Returns the newly recorded ones.
+fn quarantine_scan(now: u64) -> Vec<CorruptRecord> {
+    let found: Vec<CorruptRecord> = IDEAS.with(|map| {
+        map.borrow()
+            .iter()
+            .filter(|(key, _)| CORRUPT_RECORDS.with(|m| !m.borrow().contains_key(key)))
+            .filter_map(|(key, idea)| match idea.status {
+                IdeaStatus::Quarantined { bytes } => Some(CorruptRecord { key, bytes, detected_at: now }),
+                _ => None,
+            })
+            .collect()
+    });
+    CORRUPT_RECORDS.with(|m| {
+        let mut m = m.borrow_mut();
+        for record in &found {
+            m.insert(record.key, record.clone());
+        }
+    });
+    found
+}
+
+/// Run the quarantine scan and alert once per new record, in the activity log and the canister
+/// log. Reads every idea; runs after each upgrade, when schema changes can strand records.
+fn scan_for_corrupt_records() -> Vec<CorruptRecord> {
+    let found = quarantine_scan(ic_cdk::api::time());
+    for record in &found {
+        ic_cdk::println!("ALERT: idea {} could not be decoded and was quarantined", record.key);
+        let filed_under = [EntityRef::Idea(record.key)];
+        activity::record_event(ic_cdk::id(), EventKind::RecordQuarantined, record.key, &filed_under);
+    }
+    found
+}
+
+fn corrupt_records() -> Vec<CorruptRecord> {
+    CORRUPT_RECORDS.with(|m| m.borrow().iter().map(|(_, record)| record).collect())
+}
+
+/// Scan for idea records that fail to decode now (controllers only); the upgrade hook also does.
+/// Returns the ones not found before.
+#[update]
+fn scan_corrupt_records() -> Result<Vec<CorruptRecord>, String> {
+    ensure_controller()?;
+    Ok(scan_for_corrupt_records())
+}
+
+/// Idea records found undecodable by the last scans and not repaired since (controllers only).
+#[query]
+fn get_corrupt_records() -> Result<Vec<CorruptRecord>, String> {
+    ensure_controller()?;
+    Ok(corrupt_records())
+}
+
+/// Replace a corrupt idea record with bytes that decode under a known schema (controllers only).
+/// Dry-run first; the live run needs the dry run's confirmation token.
+#[update]
+fn repair_record(key: u64, bytes: Vec<u8>, dry_run: bool, confirmation_token: Option<String>) -> Result<DryRunReport, String> {
+    ensure_controller()?;
+    let is_corrupt = IDEAS.with(|map| map.borrow().get(&key)).is_some_and(|i| i.is_quarantined());
+    if !is_corrupt {
+        return Err("no corrupt record under this key".into());
+    }
+    let idea = decode_idea(&bytes).ok_or_else(|| "bytes do not decode as an Idea".to_string())?;
+    let key_param = key.to_string();
+    let bytes_param = format!("{:016x}", fnv1a(0, &bytes));
+    let params = [key_param.as_str(), bytes_param.as_str()];
+    if dry_run {
+        return Ok(dry_run_report("repair_record", &params, &[key]));
+    }
+    redeem_confirmation("repair_record", &params, confirmation_token)?;
+    IDEAS.with(|map| map.borrow_mut().insert(key, idea));
+    CORRUPT_RECORDS.with(|m| m.borrow_mut().remove(&key));
+    record_change(EntityRef::Idea(key), ChangeKind::Updated);
+    Ok(DryRunReport { dry_run: false, affected: 1, sample_ids: vec![key], confirmation_token: None })
+}
+
+// ------------- Admin: duplicate moderation -------------
+
+/// Ideas flagged as likely duplicates at submission, with the matched idea ids (controllers only).
+#[query]
+fn get_duplicate_flags() -> Result<Vec<DuplicateFlag>, String> {
+    ensure_controller()?;
+    Ok(DUPLICATE_FLAGS.with(|flags| flags.borrow().iter().map(|(_, f)| f).collect()))
+}
+
+/// Clear the duplicate flag on an idea once a moderator has looked at it (controllers only).
+#[update]
+fn dismiss_duplicate_flag(idea_id: u64) -> Result<(), String> {
+    ensure_controller()?;
+    DUPLICATE_FLAGS
+        .with(|flags| flags.borrow_mut().remove(&idea_id))
+        .map(|_| ())
+        .ok_or_else(|| "idea is not flagged".into())
+}
+
+/// Set the similarity percentage at which new ideas get flagged (controllers only).
+#[update]
+fn set_duplicate_threshold(pct: u8) -> Result<(), String> {
+    ensure_controller()?;
+    if pct == 0 || pct > 100 {
+        return Err("threshold must be between 1 and 100".into());
+    }
+    DUPLICATE_THRESHOLD_PCT
+        .with(|t| t.borrow_mut().set(pct))
+        .map(|_| ())
+        .map_err(|e| format!("failed to store threshold: {:?}", e))
+}
+
+// ------------- Admin: idea review -------------
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct InitArgs {
+    pub admins: Vec<Principal>,
+    pub ledger: Option<Principal>, // ICRC ledger for contribute_icp; kept across upgrades when None
+    pub fund_flow: Option<Principal>, // the canister allowed to call receive_icp_contribution; kept when None
+    pub require_profile: Option<bool>, // create_idea needs a user profile; kept across upgrades when None
+    pub event_retention: Option<u32>,  // activity events kept per idea and campaign; kept when None
+    pub quotas: Option<QuotaLimits>,   // per-caller limits on ideas and uploads; kept when None
+    pub expiry_sweep_secs: Option<u64>, // how often ended campaigns are finalized; kept when None
+}
+
+fn add_admins(args: Option<InitArgs>) {
+    for admin in args.map(|a| a.admins).unwrap_or_default() {
+        ADMINS.with(|a| a.borrow_mut().insert(admin, ()));
+    }
+}
+
+/// Grant review rights (admins and controllers).
+#[update]
+fn add_admin(principal: Principal) -> Result<(), String> {
+    ensure_admin()?;
+    if principal == Principal::anonymous() {
+        return Err("the anonymous principal cannot be an admin".into());
+    }
+    ADMINS.with(|a| a.borrow_mut().insert(principal, ()));
+    Ok(())
+}
+
+/// Revoke review rights (admins and controllers). Controllers stay admins regardless.
+#[update]
+fn remove_admin(principal: Principal) -> Result<(), String> {
+    ensure_admin()?;
+    ADMINS
+        .with(|a| a.borrow_mut().remove(&principal))
+        .map(|_| ())
+        .ok_or_else(|| "not an admin".into())
+}
+
+#[query]
+fn get_admins() -> Vec<Principal> {
+    ADMINS.with(|a| a.borrow().iter().map(|(p, _)| p).collect())
+}
+
+/// Move a pending idea to `status`, as decided by `caller` at `now` (ns).
+fn decide_idea(caller: Principal, now: u64, idea_id: u64, status: IdeaStatus) -> Result<(), String> {
+    let mut idea = get_idea(idea_id).ok_or_else(|| not_found(EntityRef::Idea(idea_id)))?;
+    permissions::may_review_idea(&caller, &idea)?;
+    let approved = status == IdeaStatus::Approved;
+    idea.status = status;
+    idea.updated_at = now;
+    IDEAS.with(|ideas| ideas.borrow_mut().insert(idea_id, idea));
+    record_change_at(now, EntityRef::Idea(idea_id), ChangeKind::Updated);
+    if approved {
+        activity::append(now, caller, EventKind::IdeaApproved, idea_id, &[EntityRef::Idea(idea_id)]);
+    }
+    Ok(())
+}
+
+/// Approve a pending idea (admins), which opens it for campaigns and public comments. Ideas in
+/// a category with a review pipeline are approved by signing off its last stage instead.
+#[update]
+fn approve_idea(idea_id: u64) -> Result<(), String> {
+    approve_idea_at(ic_cdk::caller(), ic_cdk::api::time(), idea_id)
+}
+
+/// `approve_idea` by `caller` at `now` (ns).
+fn approve_idea_at(caller: Principal, now: u64, idea_id: u64) -> Result<(), String> {
+    check_admin(&caller)?;
+    if get_idea(idea_id).is_some_and(|idea| review_pipeline::reviews_in_stages(idea_id, &idea)) {
+        return Err("idea is reviewed in stages; signing off its last stage approves it".into());
+    }
+    decide_idea(caller, now, idea_id, IdeaStatus::Approved)
+}
+
+/// Reject a pending idea with a reason the creator can see (admins).
+#[update]
+fn reject_idea(idea_id: u64, reason: String) -> Result<(), String> {
+    reject_idea_at(ic_cdk::caller(), ic_cdk::api::time(), idea_id, reason)
+}
+
+/// `reject_idea` by `caller` at `now` (ns).
+fn reject_idea_at(caller: Principal, now: u64, idea_id: u64, reason: String) -> Result<(), String> {
+    let reason = reason.trim().to_string();
+    if reason.is_empty() || reason.len() > MAX_REJECTION_REASON_LEN {
+        return Err(format!("a rejection reason of 1-{} bytes is required", MAX_REJECTION_REASON_LEN));
+    }
+    decide_idea(caller, now, idea_id, IdeaStatus::Rejected { reason })
+}
+
+/// Ideas in a review state, for the admin dashboard (admins). `Rejected` matches any reason;
+/// drafts stay private to their owners.
+#[query]
+fn get_ideas_by_status(status: IdeaStatus) -> Result<Vec<(u64, Idea)>, String> {
+    ensure_admin()?;
+    if status == IdeaStatus::Draft {
+        return Err("drafts are private to their owners".into());
+    }
+    Ok(IDEAS.with(|map| {
+        map.borrow()
+            .iter()
+            .filter(|(_, idea)| idea.status.same_kind(&status))
+            .collect()
+    }))
+}
+
+// ------------- Admin: category migration -------------
+
+const MIGRATION_CHUNK: usize = 500;
+
+fn same_category(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+fn campaigns_for_ideas(idea_ids: &[u64]) -> u64 {
+    CAMPAIGNS.with(|store| {
+        store.borrow().iter().filter(|(_, c)| idea_ids.contains(&c.idea_id)).count() as u64
+    })
+}
+
+/// Re-categorize every idea in `from` to `to` (controllers only). `from` may be any stored text,
+/// matched in any case; `to` must be an active category and is stored as its slug.
+/// Works in chunks: call again until `done` is true; an interrupted run resumes where it stopped.
+/// Starting a live run needs the confirmation token from a dry run; continuing one does not.
+#[update]
+fn migrate_category(
+    from: String,
+    to: String,
+    dry_run: bool,
+    confirmation_token: Option<String>,
+) -> Result<MigrationReport, String> {
+    ensure_controller()?;
+    if from.trim().is_empty() || to.trim().is_empty() {
+        return Err("both categories must be provided".into());
+    }
+    let to = categories::canonical_slug(&to).map_err(|e| e.to_string())?;
+    if from.trim() == to {
+        return Err("source and target category are the same".into());
+    }
+
+    if dry_run {
+        let ids: Vec<u64> = IDEAS.with(|map| {
+            map.borrow()
+                .iter()
+                .filter(|(_, idea)| {
+                    !idea.is_quarantined() && same_category(&idea.category, &from) && idea.category != to
+                })
+                .map(|(id, _)| id)
+                .collect()
+        });
+        let preview = dry_run_report("migrate_category", &[from.trim(), &to], &ids);
+        return Ok(MigrationReport {
+            ideas_moved: preview.affected,
+            campaigns_moved: campaigns_for_ideas(&ids),
+            from,
+            to,
+            dry_run,
+            done: true,
+            sample_ids: preview.sample_ids,
+            confirmation_token: preview.confirmation_token,
+        });
+    }
+
+    let mut state = CATEGORY_MIGRATION.with(|m| m.borrow().get().clone());
+    if state.active && !(same_category(&state.from, &from) && state.to == to) {
+        return Err(format!(
+            "migration from '{}' to '{}' is still in progress; finish it first",
+            state.from, state.to
+        ));
+    }
+    if !state.active {
+        redeem_confirmation("migrate_category", &[from.trim(), &to], confirmation_token)?;
+        state = CategoryMigration { active: true, from: from.clone(), to: to.clone(), ..Default::default() };
+    }
+
+    let batch: Vec<(u64, Idea)> = IDEAS.with(|map| {
+        map.borrow()
+            .range(state.next_idea_id..)
+            .take(MIGRATION_CHUNK)
+            .collect()
+    });
+    let done = batch.len() < MIGRATION_CHUNK;
+    let now = ic_cdk::api::time();
+    let mut moved: Vec<u64> = Vec::new();
+    for (id, mut idea) in batch.iter().cloned() {
+        if idea.is_quarantined() || !same_category(&idea.category, &from) || idea.category == to {
+            continue;
+        }
+        idea.category = to.clone();
+        idea.updated_at = now;
+        IDEAS.with(|map| map.borrow_mut().insert(id, idea));
+        record_change(EntityRef::Idea(id), ChangeKind::Updated);
+        moved.push(id);
+    }
+
+    state.ideas_moved += moved.len() as u64;
+    state.campaigns_moved += campaigns_for_ideas(&moved);
+    state.next_idea_id = batch.last().map(|(id, _)| id + 1).unwrap_or(state.next_idea_id);
+    let report = MigrationReport {
+        from,
+        to,
+        dry_run,
+        ideas_moved: state.ideas_moved,
+        campaigns_moved: state.campaigns_moved,
+        done,
+        sample_ids: vec![],
+        confirmation_token: None,
+    };
+    let next = if done { CategoryMigration::default() } else { state };
+    CATEGORY_MIGRATION
+        .with(|m| m.borrow_mut().set(next))
+        .map_err(|e| format!("failed to store migration cursor: {:?}", e))?;
+    Ok(report)
+}
+
+// ------------- HTTP: embeddable campaign widget -------------
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub streaming_strategy: Option<StreamingStrategy>, // docs over one chunk only
+}
+
+// Public, contact-free view of a campaign for partner sites. Only add fields here
+// that are safe to show on third-party pages.
+#[derive(serde::Serialize)]
+struct EmbedCampaign {
+    id: u64,
+    title: String,
+    percent_funded: u64,
+    amount_raised: u64,
+    goal: u64,
+    currency: String, // e.g. "ICP"; the amounts are in its smallest unit
+    days_left: i64,
+    link: String,
+}
+
+const EMBED_MAX_AGE_SECS: u64 = 30;
+
+fn campaign_link(campaign_id: u64) -> String {
+    // dfx exports canister ids at build time; fall back to a relative link for local builds.
+    match option_env!("CANISTER_ID_FUNDVERSE_FRONTEND") {
+        Some(frontend) => format!("https://{}.icp0.io/?campaign={}", frontend, campaign_id),
+        None => format!("/?campaign={}", campaign_id),
+    }
+}
+
+fn to_embed(card: &CampaignCard) -> EmbedCampaign {
+    let percent_funded = if card.goal == 0 { 0 } else { percent_bps(card.amount_raised, card.goal) / 100 };
+    EmbedCampaign {
+        id: card.id,
+        title: card.title.clone(),
+        percent_funded,
+        amount_raised: card.amount_raised,
+        goal: card.goal,
+        currency: card.currency.to_string(),
+        days_left: card.days_left,
+        link: campaign_link(card.id),
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn embed_html(e: &EmbedCampaign) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title>\
+<style>body{{font-family:sans-serif;margin:8px}}.bar{{background:#eee;height:8px;border-radius:4px}}\
+.fill{{background:#4f46e5;height:8px;border-radius:4px;width:{width}%}}</style></head><body>\
+<a href=\"{link}\" target=\"_blank\" rel=\"noopener\"><strong>{title}</strong></a>\
+<div class=\"bar\"><div class=\"fill\"></div></div>\
+<p>{percent}% funded &middot; {raised} of {goal} {currency} &middot; {days} days left</p></body></html>",
+        title = html_escape(&e.title),
+        link = html_escape(&e.link),
+        width = e.percent_funded.min(100),
+        percent = e.percent_funded,
+        raised = e.amount_raised,
+        goal = e.goal,
+        currency = html_escape(&e.currency),
+        days = e.days_left.max(0),
+    )
+}
+
+fn embed_headers(content_type: &str) -> Vec<(String, String)> {
+    vec![
+        ("Content-Type".into(), content_type.into()),
+        ("Access-Control-Allow-Origin".into(), "*".into()),
+        ("Access-Control-Allow-Methods".into(), "GET, OPTIONS".into()),
+        ("Cache-Control".into(), format!("public, max-age={}", EMBED_MAX_AGE_SECS)),
+    ]
+}
+
+fn http_error(status_code: u16, message: &str) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: embed_headers("text/plain; charset=utf-8"),
+        body: message.as_bytes().to_vec(),
+        streaming_strategy: None,
+    }
+}
+
+/// Serve the embeddable campaign widget:
+/// `GET /embed/campaign/{id}.json` (data) and `GET /embed/campaign/{id}` (self-contained HTML),
+/// plus `GET /.well-known/build-info` (same as `get_build_info`), `GET /docs/{id}`,
+/// `GET /ideas/{id}/docs` and `GET /partners/{principal}/campaigns/{external ref}.json` (the
+/// widget data of a syndicated idea's latest campaign, the ref percent-encoded).
+#[query]
+fn http_request(req: HttpRequest) -> HttpResponse {
+    if req.method.eq_ignore_ascii_case("OPTIONS") {
+        return HttpResponse {
+            status_code: 204,
+            headers: embed_headers("text/plain"),
+            body: vec![],
+            streaming_strategy: None,
+        };
+    }
+    if !req.method.eq_ignore_ascii_case("GET") {
+        return http_error(405, "method not allowed");
+    }
+
+    let path = req.url.split('?').next().unwrap_or("");
+    if path == "/.well-known/build-info" {
+        return HttpResponse {
+            status_code: 200,
+            headers: embed_headers("application/json"),
+            body: serde_json::to_vec(&build_info::get_build_info()).unwrap_or_default(),
+            streaming_strategy: None,
+        };
+    }
+    if let Some(id) = path.strip_prefix("/docs/") {
+        return serve_doc(id);
     }
-    // ensure idea exists
-    let Some(_idea) = get_idea(idea_id) else {
-        return Err("idea_id not found".into());
+    if let Some(id) = path.strip_prefix("/ideas/").and_then(|rest| rest.strip_suffix("/docs")) {
+        return serve_doc_index(id);
+    }
+    if let Some(rest) = path.strip_prefix("/partners/") {
+        return match syndication::campaign_id_for_path(rest) {
+            Some(campaign_id) => serve_embed(campaign_id, true),
+            None => http_error(404, "not found"),
+        };
+    }
+    let Some(rest) = path.strip_prefix("/embed/campaign/") else {
+        return http_error(404, "not found");
     };
+    let (id_part, as_json) = match rest.strip_suffix(".json") {
+        Some(id) => (id, true),
+        None => (rest, false),
+    };
+    match id_part.parse::<u64>() {
+        Ok(campaign_id) => serve_embed(campaign_id, as_json),
+        Err(_) => http_error(404, "not found"),
+    }
+}
 
-    let id = CAMPAIGNS.with(|store| {
-        let mut vec = store.borrow_mut();
-        let new_id = (vec.len() as u64) + 1;
-        vec.push(Campaign {
-            id: new_id,
-            idea_id,
-            amount_raised: 0,
-            goal,
-            end_date,
-        });
-        new_id
-    });
+fn serve_embed(campaign_id: u64, as_json: bool) -> HttpResponse {
+    if get_campaign(campaign_id).is_some_and(|c| takedown::is_campaign_taken_down(&c)) {
+        return http_error(451, takedown::TAKEDOWN_NOTICE);
+    }
+    let Some(CampaignWithIdea { campaign: card, .. }) = get_campaign_with_idea(campaign_id) else {
+        return http_error(404, &not_found(EntityRef::Campaign(campaign_id)));
+    };
 
-    Ok(id)
+    let embed = to_embed(&card);
+    if as_json {
+        HttpResponse {
+            status_code: 200,
+            headers: embed_headers("application/json"),
+            body: serde_json::to_vec(&embed).unwrap_or_default(),
+            streaming_strategy: None,
+        }
+    } else {
+        HttpResponse {
+            status_code: 200,
+            headers: embed_headers("text/html; charset=utf-8"),
+            body: embed_html(&embed).into_bytes(),
+            streaming_strategy: None,
+        }
+    }
 }
 
-/// Return all campaign cards (title/category pulled from linked Idea).
-#[query]
-fn get_campaign_cards() -> Vec<CampaignCard> {
-    CAMPAIGNS.with(|store| {
-        store
-            .borrow()
-            .iter()
-            .filter_map(|c| get_idea(c.idea_id).map(|idea| to_card(c, &idea)))
-            .collect()
-    })
-}
+// ------------- HTTP: uploaded documents -------------
 
-///return docs with idea_id
-#[query]
-fn get_doc(doc_id: u64) -> Option<Doc> {
-    DOCS.with(|docs| docs.borrow().get(&doc_id).cloned())
+candid::define_function!(pub StreamingCallback : (StreamingToken) -> (StreamingCallbackHttpResponse) query);
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct StreamingToken {
+    pub doc_id: u64,
+    pub offset: u64, // byte offset of the next chunk
 }
 
-/// Return cards filtered by status (Active/Ended).
-#[query]
-fn get_campaign_cards_by_status(status: CampaignStatus) -> Vec<CampaignCard> {
-    let now = now_secs() as i64;
-    CAMPAIGNS.with(|store| {
-        store
-            .borrow()
-            .iter()
-            .filter_map(|c| get_idea(c.idea_id).map(|idea| to_card(c, &idea)))
-            .filter(|card| match status {
-                CampaignStatus::Active => card.days_left >= 0 && (card.end_date as i64) >= now,
-                CampaignStatus::Ended => card.days_left < 0 || (card.end_date as i64) < now,
-            })
-            .collect()
-    })
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum StreamingStrategy {
+    Callback { callback: StreamingCallback, token: StreamingToken },
 }
 
-/// Fetch a single campaign joined with its Idea.
-#[query]
-fn get_campaign_with_idea(campaign_id: u64) -> Option<CampaignWithIdea> {
-    CAMPAIGNS.with(|store| {
-        store
-            .borrow()
-            .iter()
-            .find(|c| c.id == campaign_id)
-            .and_then(|c| get_idea(c.idea_id).map(|idea| CampaignWithIdea {
-                campaign: to_card(c, &idea),
-                idea,
-            }))
-    })
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct StreamingCallbackHttpResponse {
+    pub body: Vec<u8>,
+    pub token: Option<StreamingToken>, // None => last chunk
 }
 
-/// Convenience: fetch an idea by id
-#[query]
-fn get_idea_by_id(idea_id: u64) -> Option<Idea> {
-    get_idea(idea_id)
+// Same bound as upload chunks, so every chunk fits in one reply.
+const DOC_HTTP_CHUNK_BYTES: u64 = MAX_DOC_CHUNK_BYTES;
+const DOC_MAX_AGE_SECS: u64 = 24 * 60 * 60; // docs never change; a deleted one may stay cached a day
+
+#[derive(serde::Serialize)]
+struct DocIndexEntry {
+    id: u64,
+    name: String,
+    content_type: String,
+    size: u64,
+    uploaded_at: u64,
+    url: String,
 }
 
-// ------------- Fund_Flow Integration Methods -------------
+/// One slice of a doc's bytes from `offset`, with the token for the next slice if any.
+fn doc_chunk(doc: &Doc, offset: u64) -> (Vec<u8>, Option<StreamingToken>) {
+    let len = doc.data.len() as u64;
+    let start = offset.min(len);
+    let end = start.saturating_add(DOC_HTTP_CHUNK_BYTES).min(len);
+    let next = (end < len).then_some(StreamingToken { doc_id: doc.id, offset: end });
+    (doc.data[start as usize..end as usize].to_vec(), next)
+}
 
-/// Get campaign metadata for Fund_Flow canister
-#[query]
-fn get_campaign_meta(campaign_id: u64) -> Option<CampaignMeta> {
-    get_campaign(campaign_id).map(|campaign| CampaignMeta {
-        campaign_id: campaign.id,
-        goal: campaign.goal,
-        amount_raised: campaign.amount_raised,
-        end_date_secs: campaign.end_date,
-    })
+// Quoted ASCII fallback for old browsers, plus the RFC 5987 form carrying the real name.
+fn content_disposition(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+        .collect();
+    let encoded: String = name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("inline; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
 }
 
-/// Receive ICP contribution from Fund_Flow canister
-#[update]
-fn receive_icp_contribution(campaign_id: u64, amount_e8s: u64) -> Result<(), String> {
-    // Verify campaign exists
-    let Some(campaign) = get_campaign(campaign_id) else {
-        return Err("Campaign not found".into());
+/// `GET /docs/{id}`: the stored bytes with their content type. Uploaders pick the content type,
+/// so the response is sandboxed and never sniffed: an uploaded page can't run as this canister.
+fn serve_doc(id_part: &str) -> HttpResponse {
+    let doc_id = id_part.parse::<u64>().ok();
+    if doc_id.is_some_and(takedown::is_held_doc) {
+        return http_error(451, takedown::TAKEDOWN_NOTICE);
+    }
+    let Some(doc) = doc_id.and_then(get_doc) else {
+        return http_error(404, "not found");
     };
-    
-    // Update ICP contributions tracking
-    ICP_CONTRIBUTIONS.with(|contributions| {
-        let mut contributions = contributions.borrow_mut();
-        let current = contributions.get(&campaign_id).unwrap_or(&0).clone();
-        contributions.insert(campaign_id, current + amount_e8s);
-    });
-    
-    // Update campaign amount raised
-    let new_amount = campaign.amount_raised + amount_e8s;
-    update_campaign_amount(campaign_id, new_amount);
-    
-    // Update the idea's current funding as well
-    IDEAS.with(|ideas| {
-        if let Some(mut idea) = ideas.borrow_mut().get(&campaign.idea_id) {
-            idea.current_funding = idea.current_funding.saturating_add(amount_e8s);
-            idea.updated_at = ic_cdk::api::time();
-            ideas.borrow_mut().insert(campaign.idea_id, idea);
-        }
+    let (body, token) = doc_chunk(&doc, 0);
+    let headers = vec![
+        ("Content-Type".into(), doc.content_type.clone()),
+        ("Content-Disposition".into(), content_disposition(&doc.name)),
+        ("Content-Length".into(), doc.data.len().to_string()),
+        ("Content-Security-Policy".into(), "sandbox".into()),
+        ("X-Content-Type-Options".into(), "nosniff".into()),
+        ("Access-Control-Allow-Origin".into(), "*".into()),
+        ("Cache-Control".into(), format!("public, max-age={}", DOC_MAX_AGE_SECS)),
+    ];
+    let streaming_strategy = token.map(|token| StreamingStrategy::Callback {
+        callback: StreamingCallback::new(ic_cdk::id(), "http_request_streaming_callback".into()),
+        token,
     });
-    
-    Ok(())
+    HttpResponse { status_code: 200, headers, body, streaming_strategy }
 }
 
-/// Receive payout notification from Fund_Flow canister
-#[update]
-fn receive_payout(campaign_id: u64, total_amount: u64) -> Result<(), String> {
-    // This method is called when Fund_Flow releases funds to project owner
-    // For now, we just log the payout. In a real implementation, you might:
-    // - Transfer ICP to project owner's wallet
-    // - Update campaign status
-    // - Send notifications
-    
-    ic_cdk::println!("Payout received for campaign {}: {} e8s", campaign_id, total_amount);
-    
-    // Update campaign status or mark as completed
-    // You could add a status field to Campaign struct for this
-    
-    Ok(())
+/// `GET /ideas/{id}/docs`: JSON list of an idea's docs with their `/docs/{id}` links.
+fn serve_doc_index(id_part: &str) -> HttpResponse {
+    if id_part.parse::<u64>().is_ok_and(takedown::is_taken_down) {
+        return http_error(451, takedown::TAKEDOWN_NOTICE);
+    }
+    let Some(idea_id) = id_part.parse::<u64>().ok().filter(|id| visible_idea(*id).is_some()) else {
+        return http_error(404, "not found");
+    };
+    let entries: Vec<DocIndexEntry> = get_docs_by_idea(idea_id)
+        .into_iter()
+        .map(|d| DocIndexEntry {
+            id: d.id,
+            size: d.data.len() as u64,
+            url: format!("/docs/{}", d.id),
+            name: d.name,
+            content_type: d.content_type,
+            uploaded_at: d.uploaded_at,
+        })
+        .collect();
+    HttpResponse {
+        status_code: 200,
+        headers: embed_headers("application/json"),
+        body: serde_json::to_vec(&entries).unwrap_or_default(),
+        streaming_strategy: None,
+    }
 }
 
-/// Get ICP contribution amount for a campaign
+/// The rest of a doc too large for one `http_request` reply; called by the HTTP gateway.
 #[query]
-fn get_icp_contribution(campaign_id: u64) -> u64 {
-    ICP_CONTRIBUTIONS.with(|contributions| {
-        contributions.borrow().get(&campaign_id).unwrap_or(&0).clone()
-    })
+fn http_request_streaming_callback(token: StreamingToken) -> StreamingCallbackHttpResponse {
+    match get_doc(token.doc_id) {
+        Some(doc) => {
+            let (body, token) = doc_chunk(&doc, token.offset);
+            StreamingCallbackHttpResponse { body, token }
+        }
+        None => StreamingCallbackHttpResponse { body: vec![], token: None },
+    }
 }
 
-/// Get total funding (ICP + other methods) for a campaign
-#[query]
-fn get_campaign_total_funding(campaign_id: u64) -> u64 {
-    let campaign_amount = get_campaign(campaign_id).map(|c| c.amount_raised).unwrap_or(0);
-    let icp_amount = get_icp_contribution(campaign_id);
-    campaign_amount + icp_amount
+#[init]
+fn init(args: Option<InitArgs>) {
+    icp_ledger::configure(args.as_ref().and_then(|a| a.ledger), args.as_ref().and_then(|a| a.fund_flow));
+    profiles::configure(args.as_ref().and_then(|a| a.require_profile));
+    activity::configure(args.as_ref().and_then(|a| a.event_retention));
+    quotas::configure(args.as_ref().and_then(|a| a.quotas.clone()));
+    expiry::configure(args.as_ref().and_then(|a| a.expiry_sweep_secs));
+    add_admins(args);
+    categories::seed_defaults();
+    build_info::schedule_module_hash_refresh();
+    archive::schedule_archiving();
+    expiry::schedule_sweeps();
+    interface::remember_current_interface();
 }
 
-
-
+/// Admins passed on upgrade are added to the existing list.
+#[post_upgrade]
+fn post_upgrade(args: Option<InitArgs>) {
+    icp_ledger::configure(args.as_ref().and_then(|a| a.ledger), args.as_ref().and_then(|a| a.fund_flow));
+    profiles::configure(args.as_ref().and_then(|a| a.require_profile));
+    activity::configure(args.as_ref().and_then(|a| a.event_retention));
+    quotas::configure(args.as_ref().and_then(|a| a.quotas.clone()));
+    expiry::configure(args.as_ref().and_then(|a| a.expiry_sweep_secs));
+    add_admins(args);
+    categories::seed_defaults();
+    build_info::schedule_module_hash_refresh();
+    archive::schedule_archiving();
+    expiry::schedule_sweeps();
+    interface::remember_current_interface();
+    scan_for_corrupt_records();
+}
 
 // Export Candid for tooling & UI integration
 ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid_parser::utils::{service_equal, CandidSource};
+
+    // Guards against accidental interface changes: update the .did alongside the code.
+    #[test]
+    fn candid_interface_matches_committed_did() {
+        let exported = __export_service();
+        let did = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("FundVerse_backend.did");
+        service_equal(CandidSource::Text(&exported), CandidSource::File(&did))
+            .expect("FundVerse_backend.did is out of sync with the exported interface");
+    }
+
+    #[test]
+    fn idea_validation_reports_every_violation_at_once() {
+        let errors = validate_idea_fields("", "pitch", 0, "", "me@example.com", "");
+        let fields: Vec<FieldId> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec![FieldId::Title, FieldId::FundingGoal, FieldId::LegalEntity, FieldId::Category]);
+        assert_eq!(errors[1], FieldError { field: FieldId::FundingGoal, rule: RuleId::MinValue, limit: Some(1), actual: Some(0) });
+        assert_eq!(
+            describe_field_errors(&errors),
+            "Invalid input: title is required; funding_goal must be at least 1; legal_entity is required; category is required"
+        );
+        assert!(validate_idea_fields("t", "d", 1, "l", "c@example.com", "x").is_empty());
+        assert_eq!(validate_idea_fields("  ", "d", 1, "l", "c@example.com", "x")[0].rule, RuleId::Required);
+    }
+
+    #[test]
+    fn contact_info_must_be_an_email_phone_number_or_https_link() {
+        let good = ["me@example.com", " me@mail.example.org ", "+254 712 345 678", "(555) 010-0199", "https://eco.example"];
+        for ok in good {
+            assert!(is_contact(ok), "{:?}", ok);
+        }
+        let bad_ones = ["me", "me@", "@example.com", "me@example", "me@example..com", "a b@example.com", "12345"];
+        for bad in bad_ones.into_iter().chain(["555-CALL-NOW", "http://eco.example", "https://"]) {
+            assert!(!is_contact(bad), "{:?}", bad);
+        }
+        assert!(is_category("Arts & Crafts") && is_category("Food/Drink") && is_category("Énergie"));
+        assert!(!is_category("<script>") && !is_category("tech;drop"));
+    }
+
+    #[test]
+    fn idea_errors_map_onto_typed_variants() {
+        let errors = |title, goal, contact| validate_idea_fields(title, "d", goal, "l", contact, "x");
+        assert_eq!(
+            FundVerseError::from(errors("", 1, "c@example.com")),
+            FundVerseError::EmptyField { field: FieldId::Title }
+        );
+        assert_eq!(FundVerseError::from(errors("t", 0, "c@example.com")), FundVerseError::InvalidFundingGoal);
+        let FundVerseError::ValidationError { message, errors } = FundVerseError::from(errors("", 0, "nope")) else {
+            panic!("several problems are reported together");
+        };
+        let fields: Vec<FieldId> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, [FieldId::Title, FieldId::FundingGoal, FieldId::ContactInfo]);
+        assert!(message.ends_with("contact_info must be an email address, a phone number or an https:// link"));
+        assert_eq!(String::from(FundVerseError::NotFound(EntityRef::Idea(7))), "idea 7 not found");
+    }
+
+    #[test]
+    fn idea_text_limits_accept_the_limit_and_reject_one_more() {
+        for (field, max) in IDEA_TEXT_LIMITS {
+            let check = |len: usize| {
+                let v = |f: FieldId| if f == field { "a".repeat(len) } else { "x".to_string() };
+                let contact = match field {
+                    FieldId::ContactInfo => format!("{}@example.com", "a".repeat(len - 12)),
+                    _ => "x@example.com".to_string(),
+                };
+                validate_idea_fields(
+                    &v(FieldId::Title),
+                    &v(FieldId::Description),
+                    1,
+                    &v(FieldId::LegalEntity),
+                    &contact,
+                    &v(FieldId::Category),
+                )
+            };
+            assert!(check(max).is_empty(), "{:?} at its limit", field);
+            let over = FieldError { field, rule: RuleId::MaxLength, limit: Some(max as u64), actual: Some(max as u64 + 1) };
+            assert_eq!(check(max + 1), vec![over]);
+        }
+    }
+
+    #[test]
+    fn long_ideas_with_many_docs_store_and_round_trip() {
+        let idea = Idea {
+            title: "Solar kiosks".into(),
+            description: "Two paragraphs about the kiosks. ".repeat(160), // ~5 KB
+            funding_goal: u64::MAX,
+            current_funding: u64::MAX,
+            legal_entity: "x".repeat(idea_text_limit(FieldId::LegalEntity)),
+            status: IdeaStatus::Rejected { reason: "x".repeat(MAX_REJECTION_REASON_LEN) },
+            contact_info: "founder@example.com".into(),
+            doc_ids: (1..=50).map(|id| u64::MAX - id).collect(),
+            category: "energy".into(),
+            business_registration: u8::MAX,
+            created_at: u64::MAX,
+            updated_at: u64::MAX,
+            owner: Principal::from_slice(&[0xFF; 29]),
+            external_ref: None,
+        };
+        assert!(idea.description.len() > 5_000 && idea.description.len() <= idea_text_limit(FieldId::Description));
+        let bytes = idea.to_bytes().into_owned();
+        assert!(bytes.len() > 5_000, "{} bytes", bytes.len());
+
+        let mut map: StableBTreeMap<u64, Idea, DefaultMemoryImpl> = StableBTreeMap::init(DefaultMemoryImpl::default());
+        for id in 0..20 {
+            map.insert(id, idea.clone());
+        }
+        let stored = map.get(&7).expect("stored");
+        assert_eq!((stored.description, stored.doc_ids), (idea.description, idea.doc_ids));
+        assert_eq!(map.get(&19).unwrap().to_bytes().as_ref(), bytes.as_slice());
+        assert_eq!(Idea::from_bytes(Cow::Borrowed(&bytes)).to_bytes().as_ref(), bytes.as_slice());
+    }
+
+    #[test]
+    fn confirmation_digests_keep_parameter_boundaries() {
+        let admin = Principal::from_slice(&[1]);
+        let digest = |params: &[&str]| confirmation_digest(admin, "migrate_category", params);
+        assert_ne!(digest(&["ab", "c"]), digest(&["a", "bc"]));
+        assert_ne!(digest(&["abc"]), digest(&["ab", "c"]));
+        assert_ne!(digest(&["", "x"]), digest(&["x", ""]));
+        assert_eq!(digest(&["ab", "c"]), digest(&["ab", "c"]));
+        let other_admin = Principal::from_slice(&[2]);
+        assert_ne!(digest(&["ab", "c"]), confirmation_digest(other_admin, "migrate_category", &["ab", "c"]));
+    }
+
+    #[test]
+    fn campaigns_with_risks_need_an_explicit_acknowledgment() {
+        let plain = test_campaign(1, 1, 0);
+        let risky = Campaign { risks: Some("Supply delays".into()), ..test_campaign(2, 1, 0) };
+        for ack_risks in [None, Some(false), Some(true)] {
+            assert!(check_risks_acked(&plain, ack_risks).is_ok());
+        }
+        for ack_risks in [None, Some(false)] {
+            assert!(check_risks_acked(&risky, ack_risks).unwrap_err().starts_with(RISK_ACK_REQUIRED));
+        }
+        assert!(check_risks_acked(&risky, Some(true)).is_ok());
+    }
+
+    #[test]
+    fn total_funding_counts_relayed_icp_once_even_near_u64_max() {
+        let campaign = Campaign { amount_raised: u64::MAX - 1, ..test_campaign(1, 1, 0) };
+        CAMPAIGNS.with(|store| store.borrow_mut().insert(1, campaign));
+        ICP_CONTRIBUTIONS.with(|c| c.borrow_mut().insert(1, u64::MAX - 1));
+        assert_eq!(get_campaign_total_funding(1), u64::MAX - 1);
+        assert_eq!(get_campaign_total_funding(2), 0);
+    }
+
+    fn usd(amount: u64) -> Money {
+        Money { currency: Currency::UsdCents, amount }
+    }
+
+    #[test]
+    fn pledges_are_checked_and_recorded_as_of_the_given_time() {
+        let (backer, now) = (Principal::from_slice(&[7]), 1_700_000_000 * 1_000_000_000);
+        let open = Campaign { currency: Some(Currency::UsdCents), ..test_campaign(1, 1, to_secs(now) + 60) };
+        let risky = Campaign { id: 2, risks: Some("Supply delays".into()), ..open.clone() };
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(1, test_idea("Solar Kiosk", "Kiosks", "energy")));
+        CAMPAIGNS.with(|store| {
+            store.borrow_mut().insert(1, open);
+            store.borrow_mut().insert(2, risky);
+        });
+
+        let id = contribute_at(backer, now, 1, usd(40), None, None).unwrap();
+        let pledge = CONTRIBUTIONS.with(|c| c.borrow().get(&id)).unwrap();
+        assert_eq!((pledge.contributor, pledge.timestamp, pledge.risks_acked_at), (backer, now, None));
+        assert_eq!(get_campaign(1).unwrap().amount_raised, 40);
+        assert_eq!(get_idea(1).map(|i| (i.current_funding, i.updated_at)), Some((40, now)));
+
+        let unacked = contribute_at(backer, now, 2, usd(10), None, None).unwrap_err();
+        assert!(unacked.starts_with(RISK_ACK_REQUIRED), "{}", unacked);
+        let id = contribute_at(backer, now, 2, usd(10), None, Some(true)).unwrap();
+        assert_eq!(CONTRIBUTIONS.with(|c| c.borrow().get(&id)).unwrap().risks_acked_at, Some(now));
+
+        let icp = Money { currency: Currency::Icp, amount: 5 };
+        assert!(contribute_at(backer, now, 1, icp, None, None).unwrap_err().starts_with(LEDGER_TRANSFER_REQUIRED));
+        assert!(contribute_at(Principal::anonymous(), now, 1, usd(5), None, None).is_err());
+        assert!(contribute_at(backer, now, 1, usd(0), None, None).is_err());
+        let last_second = now + 60 * 1_000_000_000;
+        assert!(contribute_at(backer, last_second, 1, usd(5), None, None).is_ok());
+        let ended = contribute_at(backer, last_second + 1_000_000_000, 1, usd(5), None, None).unwrap_err();
+        assert_eq!(ended, "campaign has ended");
+        assert_eq!(get_idea(1).unwrap().current_funding, 55, "both campaigns count towards the idea");
+    }
+
+    #[test]
+    fn baskets_are_checked_whole_before_anything_is_recorded() {
+        let (backer, now) = (Principal::from_slice(&[7]), 1_700_000_000 * 1_000_000_000);
+        let campaign = Campaign { currency: Some(Currency::UsdCents), ..test_campaign(1, 1, to_secs(now) + 60) };
+        CAMPAIGNS.with(|store| store.borrow_mut().insert(1, campaign));
+        let item = |campaign_id| ContributionRequest {
+            campaign_id,
+            amount: 10,
+            currency: Some(Currency::UsdCents),
+            ack_risks: None,
+        };
+
+        let results = contribute_many_at(backer, now, vec![item(1), item(9)], true, None).unwrap();
+        assert_eq!(results[0], Err("not executed: item 1 failed".to_string()));
+        assert_eq!(results[1], Err("campaign 9 not found".to_string()));
+        assert_eq!(get_campaign(1).unwrap().amount_raised, 0);
+
+        let results = contribute_many_at(backer, now, vec![item(1), item(9), item(1)], false, None).unwrap();
+        assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok());
+        assert_eq!(get_campaign(1).unwrap().amount_raised, 20);
+        assert!(contribute_many_at(backer, now, vec![], false, None).is_err());
+    }
+
+    #[test]
+    fn admins_decide_each_pending_idea_once() {
+        let (admin, stranger, now) = (Principal::from_slice(&[1]), Principal::from_slice(&[2]), 5_000_000_000);
+        ADMINS.with(|a| a.borrow_mut().insert(admin, ()));
+        IDEAS.with(|ideas| {
+            let mut ideas = ideas.borrow_mut();
+            for id in [1, 2] {
+                ideas.insert(id, Idea { status: IdeaStatus::Pending, ..test_idea("Solar Kiosk", "Kiosks", "energy") });
+            }
+        });
+
+        assert_eq!(approve_idea_at(stranger, now, 1), Err("only admins can call this method".to_string()));
+        approve_idea_at(admin, now, 1).unwrap();
+        assert_eq!(get_idea(1).map(|i| (i.status, i.updated_at)), Some((IdeaStatus::Approved, now)));
+        assert!(approve_idea_at(admin, now, 1).unwrap_err().starts_with("idea is not pending review"));
+        assert!(reject_idea_at(admin, now, 1, "Duplicate".into()).is_err(), "approved ideas stay approved");
+
+        assert!(reject_idea_at(admin, now, 2, "   ".into()).is_err(), "a reason is required");
+        assert!(reject_idea_at(stranger, now, 2, "Duplicate".into()).is_err());
+        reject_idea_at(admin, now, 2, " Duplicate ".into()).unwrap();
+        assert_eq!(get_idea(2).unwrap().status, IdeaStatus::Rejected { reason: "Duplicate".into() });
+        assert_eq!(approve_idea_at(admin, now, 3), Err("idea 3 not found".to_string()));
+    }
+
+    #[test]
+    fn pages_are_capped_and_chain_through_next_offset() {
+        let page = paginate((0..250u64).collect(), 0, 1_000);
+        assert_eq!((page.items.len(), page.total, page.next_offset), (100, 250, Some(100)));
+        let last = paginate((0..250u64).collect(), 200, 100);
+        assert_eq!((last.items[0], last.items.len(), last.next_offset), (200, 50, None));
+        let empty = paginate((0..250u64).collect(), 10, 0);
+        assert_eq!((empty.items.len(), empty.total, empty.next_offset), (0, 250, Some(10)));
+        let past_end = paginate((0..3u64).collect(), 10, 5);
+        assert_eq!((past_end.items.len(), past_end.next_offset), (0, None));
+    }
+
+    pub(crate) fn test_idea(title: &str, description: &str, category: &str) -> Idea {
+        Idea {
+            title: title.into(),
+            description: description.into(),
+            funding_goal: 100,
+            current_funding: 0,
+            legal_entity: "LLC".into(),
+            status: IdeaStatus::Approved,
+            contact_info: "a@b.c".into(),
+            doc_ids: vec![],
+            category: category.into(),
+            business_registration: 1,
+            created_at: 0,
+            updated_at: 0,
+            owner: Principal::anonymous(),
+            external_ref: None,
+        }
+    }
+
+    #[test]
+    fn garbage_idea_bytes_are_skipped_by_listings_and_reported_once_scanned() {
+        let garbage = vec![0xde, 0xad, 0xbe, 0xef];
+        // Same memory as IDEAS, written before IDEAS is first touched on this thread.
+        let mut raw: StableBTreeMap<u64, Vec<u8>, Memory> =
+            StableBTreeMap::init(MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(0))));
+        raw.insert(2, garbage.clone());
+        IDEAS.with(|ideas| {
+            let mut ideas = ideas.borrow_mut();
+            ideas.insert(1, test_idea("Solar Kiosk", "Phone charging", "Energy"));
+            ideas.insert(3, test_idea("Wind Farm", "Turbines", "Energy"));
+        });
+
+        let listed: Vec<u64> = list_ideas_paged(0, 10).items.into_iter().map(|(id, _)| id).collect();
+        assert_eq!(listed, [1, 3]);
+        assert!(get_idea(2).is_none());
+        assert!(get_idea(3).is_some());
+        assert!(corrupt_records().is_empty(), "reads must not record anything");
+
+        let found = quarantine_scan(42);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].key, &found[0].bytes, found[0].detected_at), (2, &garbage, 42));
+        assert!(quarantine_scan(43).is_empty(), "already recorded");
+        let reported: Vec<(u64, Vec<u8>)> = corrupt_records().into_iter().map(|r| (r.key, r.bytes)).collect();
+        assert_eq!(reported, [(2, garbage)]);
+    }
+
+    #[test]
+    fn search_matches_title_and_description_in_any_case() {
+        IDEAS.with(|ideas| {
+            let mut ideas = ideas.borrow_mut();
+            ideas.insert(1, test_idea("Solar Kiosk", "Off-grid phone CHARGING", "Energy"));
+            ideas.insert(2, test_idea("Eco Bottles", "Recycled solar-dried plastic", "Environment"));
+            ideas.insert(3, test_idea("Wind Farm", "Turbines", "energy"));
+        });
+        let ids = |query: &str, category: Option<&str>| -> Vec<u64> {
+            matching_ideas(query, category).unwrap().into_iter().map(|(id, _)| id).collect()
+        };
+        assert_eq!(ids("sOLAR", None), vec![1, 2]);
+        assert_eq!(ids("  charging ", None), vec![1]);
+        assert_eq!(ids("solar", Some(" ENERGY ")), vec![1]);
+        assert_eq!(ids("", Some("Energy")), vec![1, 3]);
+        assert!(ids("hydro", None).is_empty());
+        assert!(matching_ideas("  ", None).is_err());
+        assert!(matching_ideas("", Some(" ")).is_err());
+    }
+
+    #[test]
+    fn category_cards_skip_campaigns_whose_idea_is_gone() {
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(1, test_idea("Solar Kiosk", "Kiosks", "Energy")));
+        let campaign = |id, idea_id| test_campaign(id, idea_id, 0);
+        CAMPAIGNS.with(|store| {
+            let mut store = store.borrow_mut();
+            store.insert(1, campaign(1, 1));
+            store.insert(2, campaign(2, 99)); // idea 99 was never stored (or was deleted)
+        });
+        let found: Vec<u64> = campaigns_in_category("energy").iter().map(|(c, _)| c.id).collect();
+        assert_eq!(found, vec![1]);
+        assert!(campaigns_in_category("Environment").is_empty());
+    }
+
+    #[test]
+    fn only_taken_down_campaigns_may_outlive_their_idea() {
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(1, test_idea("Solar Kiosk", "Kiosks", "Energy")));
+        let campaign = |id, idea_id, status| Campaign { amount_raised: 50, status, ..test_campaign(id, idea_id, 0) };
+        CAMPAIGNS.with(|store| {
+            let mut store = store.borrow_mut();
+            store.insert(1, campaign(1, 1, None));
+            store.insert(2, campaign(2, 7, Some(CampaignLifecycle::TakenDown)));
+            store.insert(3, campaign(3, 8, Some(CampaignLifecycle::Expired)));
+        });
+        assert_eq!(takedown::orphaned_campaigns(), vec![3]);
+        assert_eq!(lifecycle(&campaign(2, 7, Some(CampaignLifecycle::TakenDown)), 1), CampaignLifecycle::TakenDown);
+        let title = resolve_ref(EntityRef::Campaign(2)).map(|s| s.label);
+        assert_eq!(title.as_deref(), Some(takedown::TAKEDOWN_NOTICE));
+        assert!(resolve_ref(EntityRef::Campaign(3)).is_none());
+    }
+
+    #[test]
+    fn platform_stats_sum_per_currency_saturating_and_progress_runs_past_the_goal() {
+        let now = 1_700_000_000;
+        let campaign = |amount_raised, goal, end_date, currency| Campaign {
+            amount_raised,
+            goal,
+            currency,
+            ..test_campaign(1, 1, end_date)
+        };
+        let mut stats = PlatformStats::default();
+        let mut by_category = BTreeMap::new();
+        let energy = || Some("Energy".to_string());
+        let ckbtc = Some(Currency::CkBtc);
+        add_campaign_to_stats(&mut stats, &mut by_category, &campaign(40, 100, now + 10, None), energy(), now);
+        add_campaign_to_stats(&mut stats, &mut by_category, &campaign(u64::MAX, 100, now - 10, None), energy(), now);
+        add_campaign_to_stats(&mut stats, &mut by_category, &campaign(5, 50, now + 10, None), None, now); // idea gone
+        add_campaign_to_stats(&mut stats, &mut by_category, &campaign(3, 8, now + 10, ckbtc), energy(), now);
+        assert_eq!((stats.total_campaigns, stats.active_campaigns), (4, 3));
+        let totals: Vec<_> = stats.totals.iter().map(|t| (t.currency, t.campaigns, t.raised, t.goal)).collect();
+        assert_eq!(totals, [(Currency::Icp, 3, u64::MAX, 250), (Currency::CkBtc, 1, 3, 8)], "never summed across");
+        let energy = "Energy".to_string();
+        assert_eq!(
+            by_category.into_iter().collect::<Vec<_>>(),
+            [((energy.clone(), Currency::Icp), u64::MAX), ((energy, Currency::CkBtc), 3)]
+        );
+
+        assert_eq!(percent_bps(40, 100), 4_000);
+        assert_eq!(percent_bps(250, 100), 25_000);
+        assert_eq!(percent_bps(7, 0), 70_000);
+        assert_eq!(percent_bps(u64::MAX, 1), u64::MAX);
+    }
+
+    #[test]
+    fn totals_refuse_to_wrap_on_either_the_campaign_or_its_idea() {
+        let mut idea = test_idea("Solar Kiosk", "Kiosks", "Energy");
+        idea.current_funding = u64::MAX - 10;
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(5, idea));
+        let campaign = |idea_id, amount_raised| Campaign { amount_raised, ..test_campaign(1, idea_id, 0) };
+        let credit = |c: &Campaign, n| totals_after(c, |total| total.add(Amount::new(n))).map(|(raised, _)| raised.get());
+        let debit = |c: &Campaign, n| totals_after(c, |total| total.sub(Amount::new(n))).map(|(raised, _)| raised.get());
+
+        assert_eq!(credit(&campaign(5, 0), 10), Ok(10));
+        assert_eq!(credit(&campaign(5, 0), 11), Err(AmountError::Overflow)); // the idea's total
+        assert_eq!(credit(&campaign(99, u64::MAX - 1), 1), Ok(u64::MAX)); // idea gone: campaign only
+        assert_eq!(credit(&campaign(99, u64::MAX), 1), Err(AmountError::Overflow));
+        assert_eq!(debit(&campaign(5, 7), 7), Ok(0));
+        assert_eq!(debit(&campaign(5, 7), 8), Err(AmountError::Underflow));
+    }
+
+    #[test]
+    fn campaigns_stay_open_through_their_last_second() {
+        let now = 1_700_000_000;
+        // (end_date, ends_in_secs, days_left)
+        for (end, ends_in, days) in [(now - 1, -1, -1), (now, 0, 0), (now + 1, 1, 0), (now + 86_400, 86_400, 1)] {
+            assert_eq!(ends_in_secs(end, now), ends_in, "end {}", end);
+            assert_eq!(ends_in_secs(end, now).div_euclid(86_400), days, "end {}", end);
+        }
+        assert!(check_end_date(now + 1, now).is_ok());
+        assert!(check_end_date(now, now).is_err());
+        assert!(check_end_date(now * 1_000, now).unwrap_err().contains("milliseconds"));
+        assert!(check_end_date(now * 1_000_000_000, now).unwrap_err().contains("milliseconds"));
+    }
+
+    #[test]
+    fn lifecycle_is_derived_from_the_clock_except_for_cancellation() {
+        let now = 1_700_000_000;
+        let campaign = |end_date, amount_raised, status| Campaign {
+            amount_raised,
+            status,
+            ..test_campaign(7, 1, end_date)
+        };
+        assert_eq!(lifecycle(&campaign(now, 0, None), now), CampaignLifecycle::Active);
+        assert_eq!(lifecycle(&campaign(now - 1, 100, None), now), CampaignLifecycle::Completed);
+        assert_eq!(lifecycle(&campaign(now - 1, 99, None), now), CampaignLifecycle::Expired);
+        let cancelled = Some(CampaignLifecycle::Cancelled);
+        assert_eq!(lifecycle(&campaign(now + 60, 0, cancelled), now), CampaignLifecycle::Cancelled);
+        assert_eq!(lifecycle(&campaign(now - 1, 100, cancelled), now), CampaignLifecycle::Cancelled);
+        // finalized outcomes hold even after refunds lower the amount raised
+        let expired = Some(CampaignLifecycle::Expired);
+        assert_eq!(lifecycle(&campaign(now - 1, 0, expired), now), CampaignLifecycle::Expired);
+        let completed = Some(CampaignLifecycle::Completed);
+        assert_eq!(lifecycle(&campaign(now - 1, 40, completed), now), CampaignLifecycle::Completed);
+    }
+
+    #[test]
+    fn sorted_listings_break_ties_by_id_and_ending_soon_skips_ended_campaigns() {
+        let now = 1_700_000_000;
+        let campaign = |id, amount_raised, goal, end_date, launched_at| Campaign {
+            amount_raised,
+            goal,
+            launched_at,
+            ..test_campaign(id, 1, end_date)
+        };
+        let campaigns = vec![
+            campaign(1, 50, 100, now + 300, None),
+            campaign(2, 50, 0, now + 100, Some(now - 10)), // zero goal: not "infinitely" funded
+            campaign(3, 80, 1_000, now - 1, Some(now - 10)),
+            campaign(4, 50, 50, now + 100, Some(now)),
+        ];
+        let ids = |sort| sort_campaigns(campaigns.clone(), sort, now).iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids(SortBy::Newest), [4, 3, 2, 1]);
+        assert_eq!(ids(SortBy::EndingSoon), [2, 4, 1], "3 has ended");
+        assert_eq!(ids(SortBy::MostFunded), [3, 1, 2, 4]);
+        assert_eq!(ids(SortBy::PercentFunded), [4, 1, 3, 2]);
+    }
+
+    #[test]
+    fn backers_count_once_per_idea_and_badges_need_every_threshold() {
+        let mut idea = test_idea("Solar Kiosk", "Kiosks", "energy");
+        let backer = |n: u8| Principal::from_slice(&[n; 29]);
+        for n in 0..100 {
+            badges::note_backer(1, backer(n));
+            badges::note_backer(1, backer(n));
+        }
+        badges::note_backer(2, backer(0));
+        idea.current_funding = 9_999;
+        let stats = badges::idea_stats(1, &idea, 1);
+        assert_eq!(stats.lifetime_backers, 100);
+        assert_eq!(stats.badges, vec![IdeaBadge::Backers(100)]);
+
+        idea.current_funding = 10_000;
+        let all = vec![IdeaBadge::Raised(10_000), IdeaBadge::FullyFunded(2), IdeaBadge::Backers(100)];
+        assert_eq!(badges::idea_stats(1, &idea, 2).badges, all);
+        assert_eq!(badges::idea_stats(2, &idea, 0).lifetime_backers, 1);
+
+        badges::forget_idea(1);
+        assert_eq!(badges::idea_stats(1, &idea, 0).lifetime_backers, 0);
+        assert_eq!(badges::idea_stats(2, &idea, 0).lifetime_backers, 1);
+    }
+
+    #[test]
+    fn teaser_keeps_short_text_and_cuts_long_text_at_a_word() {
+        assert_eq!(teaser("  Short pitch.  "), "Short pitch.");
+        let long = "word ".repeat(60);
+        let t = teaser(&long);
+        assert!(t.ends_with("word…"), "{}", t);
+        assert!(t.chars().count() <= TEASER_CHARS);
+    }
+
+    #[test]
+    fn teaser_does_not_split_multibyte_characters() {
+        let arabic = "مشروع ".repeat(40);
+        let t = teaser(&arabic);
+        assert!(t.chars().count() <= TEASER_CHARS);
+        assert!(t.ends_with("مشروع…"), "{}", t);
+        let no_spaces = "é".repeat(300);
+        assert_eq!(teaser(&no_spaces).chars().count(), TEASER_CHARS);
+    }
+
+    #[test]
+    fn summary_encodes_small() {
+        let summary = IdeaSummary {
+            id: u64::MAX,
+            title: "A reasonably long campaign title for a startup".into(),
+            teaser: teaser(&"lorem ipsum ".repeat(30)),
+            category: "Environment".into(),
+            percent_funded: 250,
+            comment_count: 1_000,
+            badges: vec![IdeaBadge::Raised(10_000), IdeaBadge::FullyFunded(2), IdeaBadge::Backers(100)],
+        };
+        let one = Encode!(&vec![summary.clone()]).unwrap().len();
+        let many = Encode!(&vec![summary; 101]).unwrap().len();
+        assert!((many - one) / 100 < 300, "per-item size {}", (many - one) / 100);
+    }
+
+    #[test]
+    fn ideas_stored_before_ownership_decode_with_anonymous_owner() {
+        let legacy = IdeaV1 {
+            title: "Solar Kiosk".into(),
+            description: "Kiosks".into(),
+            funding_goal: 100,
+            current_funding: 0,
+            legal_entity: "LLC".into(),
+            status: Some("approved".into()),
+            contact_info: "a@b.c".into(),
+            category: "energy".into(),
+            business_registration: 1,
+            created_at: 1,
+            updated_at: 2,
+            doc_ids: vec![7],
+        };
+        let idea = decode_idea(&Encode!(&legacy).unwrap()).expect("legacy idea decodes");
+        assert_eq!(idea.owner, Principal::anonymous());
+        assert_eq!(idea.doc_ids, vec![7]);
+        assert_eq!(idea.status, IdeaStatus::Approved);
+    }
+
+    #[test]
+    fn free_text_statuses_map_onto_the_enum() {
+        assert_eq!(IdeaStatus::from_legacy(Some("pending".into())), IdeaStatus::Pending);
+        assert_eq!(IdeaStatus::from_legacy(Some("Approved".into())), IdeaStatus::Approved);
+        assert_eq!(
+            IdeaStatus::from_legacy(Some("rejected".into())),
+            IdeaStatus::Rejected { reason: String::new() }
+        );
+        assert_eq!(IdeaStatus::from_legacy(Some("under review".into())), IdeaStatus::Pending);
+        assert_eq!(IdeaStatus::from_legacy(None), IdeaStatus::Pending);
+    }
+
+    #[test]
+    fn end_dates_snap_forward_to_the_utc_hour() {
+        let dst_weekend = 1_774_742_400; // 2026-03-29 00:00 UTC, when Europe moves its clocks
+        let five_pm = dst_weekend - 86_400 + 17 * 3_600;
+        assert_eq!(snap_end_date(five_pm - 1, Some(17)), five_pm);
+        assert_eq!(snap_end_date(five_pm, Some(17)), five_pm);
+        assert_eq!(snap_end_date(five_pm + 1, Some(17)), five_pm + 86_400); // the day of the switch
+        assert_eq!(snap_end_date(dst_weekend + 1, Some(0)), dst_weekend + 86_400);
+        assert_eq!(snap_end_date(five_pm + 1, None), five_pm + 1);
+        for end in (five_pm - 90_000..five_pm + 90_000).step_by(997) {
+            let snapped = snap_end_date(end, Some(17));
+            assert!(snapped >= end && snapped - end < 86_400 && snapped % 86_400 == 17 * 3_600, "end {}", end);
+        }
+        assert!(end_hour_for(Some(EndTime::HourUtc(24))).is_err());
+        assert_eq!(end_hour_for(Some(EndTime::Exact)), Ok(None));
+    }
+
+    #[test]
+    fn pause_extensions_land_on_the_campaign_end_hour() {
+        let five_pm = 1_774_717_200;
+        let campaign = Campaign {
+            end_hour_utc: Some(17),
+            requested_end_date: Some(five_pm - 600),
+            ..test_campaign(9, 1, five_pm)
+        };
+        let paused = |secs| PauseLog {
+            windows: vec![PauseWindow { reason: "audit".into(), started_at: 0, ended_at: Some(secs), expires_at: secs }],
+        };
+        CAMPAIGN_PAUSES.with(|p| p.borrow_mut().insert(9, paused(FREE_PAUSE_SECS)));
+        assert_eq!(effective_end_date(&campaign, five_pm), five_pm);
+        CAMPAIGN_PAUSES.with(|p| p.borrow_mut().insert(9, paused(FREE_PAUSE_SECS + 3_600)));
+        assert_eq!(effective_end_date(&campaign, five_pm), five_pm + 86_400);
+    }
+
+    #[test]
+    fn docs_stream_in_reply_sized_chunks_with_a_safe_filename() {
+        let doc = |len: u64| Doc {
+            id: 3,
+            idea_id: 1,
+            name: "deck.pdf".into(),
+            content_type: "application/pdf".into(),
+            data: vec![7; len as usize],
+            uploaded_at: 0,
+        };
+        assert_eq!(doc_chunk(&doc(10), 0), (vec![7; 10], None));
+        let big = doc(2 * DOC_HTTP_CHUNK_BYTES + 5);
+        let (first, token) = doc_chunk(&big, 0);
+        assert_eq!(first.len() as u64, DOC_HTTP_CHUNK_BYTES);
+        let token = token.unwrap();
+        let (second, token) = doc_chunk(&big, token.offset);
+        assert_eq!(second.len() as u64, DOC_HTTP_CHUNK_BYTES);
+        let (last, token) = doc_chunk(&big, token.unwrap().offset);
+        assert_eq!((last.len(), token), (5, None));
+
+        assert_eq!(content_disposition("deck v2.pdf"), "inline; filename=\"deck v2.pdf\"; filename*=UTF-8''deck%20v2.pdf");
+        assert_eq!(
+            content_disposition("خطة\"a.pdf"),
+            "inline; filename=\"____a.pdf\"; filename*=UTF-8''%D8%AE%D8%B7%D8%A9%22a.pdf"
+        );
+    }
+
+    #[test]
+    fn pause_time_counts_closed_open_and_expired_windows() {
+        let window = |started_at, ended_at, expires_at| PauseWindow {
+            reason: "legal review".into(),
+            started_at,
+            ended_at,
+            expires_at,
+        };
+        let mut log = PauseLog { windows: vec![window(100, Some(160), 1_000)] };
+        assert_eq!(log.paused_secs(500), 60);
+        assert!(log.active(500).is_none());
+
+        log.windows.push(window(500, None, 800));
+        assert!(log.active(600).is_some());
+        assert_eq!(log.paused_secs(600), 160);
+        // an open pause stops counting (and ends) once the budget left at its start runs out
+        assert!(log.active(900).is_none());
+        assert_eq!(log.paused_secs(900), 360);
+    }
+
+    pub(crate) fn test_campaign(id: u64, idea_id: u64, end_date: u64) -> Campaign {
+        Campaign {
+            id,
+            idea_id,
+            amount_raised: 0,
+            goal: 100,
+            end_date,
+            risks: None,
+            risk_amendments: vec![],
+            round: None,
+            display_title: None,
+            teaser: None,
+            owner: None,
+            status: None,
+            refund_pending: None,
+            end_hour_utc: None,
+            requested_end_date: None,
+            launched_at: None,
+            fiat_goal: None,
+            external_ref: None,
+            currency: None,
+        }
+    }
+
+    #[test]
+    fn cards_count_whole_days_left_and_end_after_the_last_second() {
+        let now = 1_700_000_000;
+        let idea = test_idea("Solar Kiosk", "Kiosks", "energy");
+        let campaign = test_campaign(7, 1, now + 2 * 86_400 + 5);
+        let card = card_at(&campaign, &idea, now);
+        assert_eq!((card.days_left, card.ends_in_secs, card.status), (2, 2 * 86_400 + 5, CampaignLifecycle::Active));
+        assert_eq!((card.title.as_str(), card.idea_title.as_str()), ("Solar Kiosk", "Solar Kiosk"));
+        let last_second = card_at(&campaign, &idea, campaign.end_date);
+        assert_eq!((last_second.days_left, last_second.status), (0, CampaignLifecycle::Active));
+        let ended = card_at(&campaign, &idea, campaign.end_date + 1);
+        assert_eq!((ended.days_left, ended.status), (-1, CampaignLifecycle::Expired));
+        let titled = Campaign { display_title: Some("Kiosks for every village".into()), ..campaign.clone() };
+        assert_eq!(card_at(&titled, &idea, now).title, "Kiosks for every village");
+
+        assert!(card_of(&campaign).is_none(), "idea 1 was never stored: no card to join");
+    }
+
+    #[test]
+    fn idea_ids_are_never_reused_after_deletes() {
+        let ids: Vec<u64> = (0..3).map(|_| store_new_idea(test_idea("a", "b", "energy"))).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        IDEAS.with(|ideas| ideas.borrow_mut().remove(&3));
+        assert_eq!(store_new_idea(test_idea("a", "b", "energy")), 4, "the deleted last id stays used");
+        IDEAS.with(|ideas| ideas.borrow_mut().remove(&2));
+        assert_eq!(store_new_idea(test_idea("a", "b", "energy")), 5);
+        // before the counter, ids were len + 1: keys past the counter are skipped too
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(9, test_idea("a", "b", "energy")));
+        assert_eq!(store_new_idea(test_idea("a", "b", "energy")), 10);
+    }
+
+    #[test]
+    fn attaching_and_detaching_docs_keeps_doc_ids_in_step_with_stored_docs() {
+        let now = 1_700_000_000 * 1_000_000_000;
+        let idea = Idea { doc_ids: vec![99], ..test_idea("Solar Kiosk", "Kiosks", "energy") }; // 99 was lost
+        IDEAS.with(|ideas| ideas.borrow_mut().insert(1, idea));
+        let attach = |name: &str| {
+            attach_doc(1, get_idea(1).unwrap(), name.into(), "application/pdf".into(), vec![1], now)
+        };
+        let (deck, plan) = (attach("deck.pdf"), attach("plan.pdf"));
+        assert_eq!(get_idea(1).unwrap().doc_ids, [deck, plan], "the lost id was dropped");
+        assert_eq!(DOCS.with(|d| d.borrow().get(&plan)).map(|d| (d.idea_id, d.uploaded_at)), Some((1, now)));
+
+        assert!(detach_doc(deck, 1, get_idea(1)));
+        assert_eq!(get_idea(1).unwrap().doc_ids, [plan]);
+        assert!(DOCS.with(|d| !d.borrow().contains_key(&deck)));
+        assert_eq!(attach("budget.pdf"), plan + 1, "doc ids aren't reused either");
+
+        IDEAS.with(|ideas| ideas.borrow_mut().remove(&1));
+        assert!(!detach_doc(plan, 1, get_idea(1)), "no idea left to update");
+        assert!(DOCS.with(|d| !d.borrow().contains_key(&plan)));
+        assert!(get_idea(1).is_none(), "detaching doesn't bring the idea back");
+    }
+
+    #[test]
+    fn campaigns_and_docs_round_trip_through_storable() {
+        let campaign = Campaign {
+            risks: Some("Supply delays".into()),
+            risk_amendments: vec![RiskAmendment { text: "Panels cost more".into(), added_at: 5 }],
+            round: Some(FundingRound::Custom("Bridge".into())),
+            owner: Some(Principal::from_slice(&[1])),
+            status: Some(CampaignLifecycle::Cancelled),
+            refund_pending: Some(true),
+            end_hour_utc: Some(15),
+            launched_at: Some(1_700_000_000),
+            currency: Some(Currency::CkBtc),
+            ..test_campaign(7, 1, 1_800_000_000)
+        };
+        let bytes = campaign.to_bytes().into_owned();
+        let decoded = Campaign::from_bytes(Cow::Borrowed(&bytes));
+        assert_eq!(decoded.to_bytes().as_ref(), bytes.as_slice());
+        assert_eq!((decoded.status, decoded.currency), (campaign.status, campaign.currency));
+        assert_eq!((decoded.round, decoded.risk_amendments[0].added_at), (campaign.round, 5));
+
+        let doc = Doc {
+            id: 3,
+            idea_id: 1,
+            name: "deck.pdf".into(),
+            content_type: "application/pdf".into(),
+            data: (0..=255).collect(),
+            uploaded_at: u64::MAX,
+        };
+        let bytes = doc.to_bytes().into_owned();
+        let decoded = Doc::from_bytes(Cow::Borrowed(&bytes));
+        assert_eq!((decoded.id, decoded.name, decoded.data), (doc.id, doc.name, doc.data));
+        assert_eq!(Doc::from_bytes(Cow::Borrowed(&bytes)).to_bytes().as_ref(), bytes.as_slice());
+    }
+}
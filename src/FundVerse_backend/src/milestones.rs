@@ -0,0 +1,217 @@
+//! Milestones on campaigns: funding thresholds the owner promises to deliver against.
+//!
+//! The owner adds milestones while the campaign runs, each with a target no higher than the goal
+//! and above the previous milestone's. A milestone is Reached as soon as `amount_raised` gets to
+//! its target, so `credit_campaign` marks milestones for every pledge, ICP transfers included. A
+//! refund that drops the total back below a target leaves the milestone Reached: backers were
+//! told about it. The owner marks a Reached milestone Delivered once the promised work is done.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{
+    ensure_campaign_owner, now_secs, permissions, record_change, ChangeKind, EntityRef, Memory, MAX_END_DATE_SECS,
+    MEMORY_MANAGER,
+};
+
+pub(crate) const MAX_MILESTONES: usize = 20; // per campaign
+pub(crate) const MAX_MILESTONE_TITLE_LEN: usize = 80; // chars
+pub(crate) const MAX_MILESTONE_DESCRIPTION_LEN: usize = 1_000; // chars
+
+thread_local! {
+    static MILESTONES: RefCell<StableBTreeMap<u64, Milestone, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(65)))
+        )
+    );
+
+    // (campaign_id, milestone_id) -> (), in the order they were added, which is target order
+    static MILESTONES_BY_CAMPAIGN: RefCell<StableBTreeMap<(u64, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(66)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MilestoneStatus {
+    Planned,
+    Reached,   // amount_raised got to target_amount
+    Delivered, // the owner says the promised work is done
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Milestone {
+    pub id: u64,
+    pub campaign_id: u64,
+    pub title: String,
+    pub description: String,
+    pub target_amount: u64, // of amount_raised, in the campaign's token units
+    pub due_date: u64,      // seconds since Unix epoch
+    pub status: MilestoneStatus,
+}
+
+impl Storable for Milestone {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Milestone"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode Milestone")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+fn campaign_milestones(campaign_id: u64) -> Vec<Milestone> {
+    let ids: Vec<u64> = MILESTONES_BY_CAMPAIGN.with(|m| {
+        m.borrow().range((campaign_id, 0)..=(campaign_id, u64::MAX)).map(|((_, id), _)| id).collect()
+    });
+    MILESTONES.with(|m| {
+        let m = m.borrow();
+        ids.into_iter().filter_map(|id| m.get(&id)).collect()
+    })
+}
+
+/// Check a new milestone against the campaign's goal and the milestones it already has.
+fn validate_milestone(
+    existing: &[Milestone],
+    goal: u64,
+    title: &str,
+    description: &str,
+    target_amount: u64,
+    due_date: u64,
+    now: u64,
+) -> Result<(), String> {
+    if existing.len() >= MAX_MILESTONES {
+        return Err(format!("a campaign can have at most {} milestones", MAX_MILESTONES));
+    }
+    if title.is_empty() || title.chars().count() > MAX_MILESTONE_TITLE_LEN {
+        return Err(format!("milestone title must be 1-{} characters", MAX_MILESTONE_TITLE_LEN));
+    }
+    if description.chars().count() > MAX_MILESTONE_DESCRIPTION_LEN {
+        return Err(format!("milestone description exceeds {} characters", MAX_MILESTONE_DESCRIPTION_LEN));
+    }
+    if target_amount == 0 || target_amount > goal {
+        return Err(format!("target_amount must be 1-{} (the campaign goal)", goal));
+    }
+    if let Some(last) = existing.last() {
+        if target_amount <= last.target_amount {
+            return Err(format!("target_amount must be above the last milestone's ({})", last.target_amount));
+        }
+    }
+    if due_date > MAX_END_DATE_SECS {
+        return Err("due_date looks like milliseconds or nanoseconds; pass seconds since the Unix epoch".into());
+    }
+    if due_date <= now {
+        return Err("due_date must be in the future".into());
+    }
+    Ok(())
+}
+
+/// Flip the campaign's Planned milestones with targets up to `amount_raised` to Reached.
+pub(crate) fn mark_reached(campaign_id: u64, amount_raised: u64) {
+    let reached: Vec<Milestone> = campaign_milestones(campaign_id)
+        .into_iter()
+        .filter(|m| m.status == MilestoneStatus::Planned && m.target_amount <= amount_raised)
+        .collect();
+    MILESTONES.with(|m| {
+        let mut m = m.borrow_mut();
+        for milestone in reached {
+            m.insert(milestone.id, Milestone { status: MilestoneStatus::Reached, ..milestone });
+        }
+    });
+}
+
+/// Add a milestone to a running campaign (owner). Targets must rise from one milestone to the
+/// next and stay within the goal. Returns the milestone, already Reached if the campaign has raised
+/// its target.
+#[update]
+fn add_milestone(
+    campaign_id: u64,
+    title: String,
+    description: String,
+    target_amount: u64,
+    due_date: u64,
+) -> Result<Milestone, String> {
+    let campaign = ensure_campaign_owner(campaign_id)?;
+    let now = now_secs();
+    permissions::running(&campaign, now)?;
+    let (title, description) = (title.trim().to_string(), description.trim().to_string());
+    let existing = campaign_milestones(campaign_id);
+    validate_milestone(&existing, campaign.goal, &title, &description, target_amount, due_date, now)?;
+    let id = MILESTONES.with(|m| m.borrow().last_key_value().map_or(0, |(id, _)| id + 1));
+    let status =
+        if campaign.amount_raised >= target_amount { MilestoneStatus::Reached } else { MilestoneStatus::Planned };
+    let milestone = Milestone { id, campaign_id, title, description, target_amount, due_date, status };
+    MILESTONES.with(|m| m.borrow_mut().insert(id, milestone.clone()));
+    MILESTONES_BY_CAMPAIGN.with(|m| m.borrow_mut().insert((campaign_id, id), ()));
+    record_change(EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+    Ok(milestone)
+}
+
+/// A campaign's milestones, lowest target first.
+#[query]
+fn get_milestones(campaign_id: u64) -> Vec<Milestone> {
+    campaign_milestones(campaign_id)
+}
+
+/// Mark a Reached milestone Delivered (the campaign's owner), also after the campaign has ended.
+#[update]
+fn mark_milestone_delivered(milestone_id: u64) -> Result<Milestone, String> {
+    let milestone = MILESTONES
+        .with(|m| m.borrow().get(&milestone_id))
+        .ok_or_else(|| format!("milestone {} not found", milestone_id))?;
+    ensure_campaign_owner(milestone.campaign_id)?;
+    match milestone.status {
+        MilestoneStatus::Reached => {}
+        MilestoneStatus::Planned => return Err("milestone hasn't reached its funding target yet".into()),
+        MilestoneStatus::Delivered => return Err("milestone is already delivered".into()),
+    }
+    let milestone = Milestone { status: MilestoneStatus::Delivered, ..milestone };
+    MILESTONES.with(|m| m.borrow_mut().insert(milestone_id, milestone.clone()));
+    record_change(EntityRef::Campaign(milestone.campaign_id), ChangeKind::Updated);
+    Ok(milestone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn milestone(id: u64, target_amount: u64) -> Milestone {
+        Milestone {
+            id,
+            campaign_id: 0,
+            title: format!("m{}", id),
+            description: String::new(),
+            target_amount,
+            due_date: 2_000,
+            status: MilestoneStatus::Planned,
+        }
+    }
+
+    #[test]
+    fn milestone_targets_rise_stay_within_the_goal_and_are_capped() {
+        let check = |existing: &[Milestone], target| {
+            validate_milestone(existing, 1_000, "Prototype", "", target, 2_000, 1_000)
+        };
+        assert!(check(&[], 1_000).is_ok(), "a milestone at the goal itself");
+        assert!(check(&[], 0).is_err() && check(&[], 1_001).is_err());
+        let existing = [milestone(0, 250), milestone(1, 500)];
+        assert!(check(&existing, 501).is_ok());
+        assert!(check(&existing, 500).is_err(), "strictly above the last target");
+        assert!(check(&existing, 300).is_err());
+
+        let full: Vec<Milestone> = (0..MAX_MILESTONES as u64).map(|i| milestone(i, i + 1)).collect();
+        assert!(check(&full, 999).unwrap_err().contains("at most"));
+
+        assert!(validate_milestone(&[], 1_000, "", "", 500, 2_000, 1_000).is_err());
+        assert!(validate_milestone(&[], 1_000, "Prototype", "", 500, 1_000, 1_000).is_err(), "due in the past");
+        assert!(validate_milestone(&[], 1_000, "Prototype", "", 500, 2_000_000_000_000, 1_000).is_err(), "ms");
+    }
+}
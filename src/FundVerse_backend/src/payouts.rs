@@ -0,0 +1,285 @@
+//! The end of a campaign: finalization, backer refunds and the owner's withdrawal.
+//!
+//! Finalizing stores the outcome the clock already implies (goal met or not), so later refunds
+//! can't change it. Backers of failed, cancelled or taken-down campaigns claim back their own
+//! pledges; the owner of a successful one withdraws what it raised. No tokens move yet: every
+//! claim is written to a payout ledger, and each contribution (or campaign, for withdrawals) can
+//! be claimed once.
+//! ICP pledges relayed by Fund_Flow carry no backer and are settled there, not here: they count
+//! towards the campaign's total, but are left out of what the owner withdraws here and of the
+//! refunds that must be paid before a taken-down campaign stops being refund-pending.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{
+    activity, campaign_owned_by, debit_campaign, get_campaign, get_contributions_by_campaign, get_icp_contribution,
+    lifecycle, not_found, record_change_at, to_secs, with_campaign_mut, Amount, Campaign, CampaignLifecycle, ChangeKind,
+    EntityRef, EventKind, Memory, MEMORY_MANAGER,
+};
+
+thread_local! {
+    // (campaign_id, payout_id) -> payout
+    static PAYOUTS: RefCell<StableBTreeMap<(u64, u64), Payout, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(46)))
+        )
+    );
+
+    static PAYOUT_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(47))),
+            0,
+        ).expect("init payout counter")
+    );
+
+    // contribution_id -> payout_id that refunded it
+    static REFUNDED_CONTRIBUTIONS: RefCell<StableBTreeMap<u64, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(48)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CampaignOutcome {
+    Successful, // goal met: the owner may withdraw
+    Failed,     // goal missed: backers may claim refunds
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayoutKind {
+    Refund,
+    Withdrawal,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Payout {
+    pub id: u64,
+    pub campaign_id: u64,
+    pub kind: PayoutKind,
+    pub to: Principal,
+    pub amount: u64,
+    pub contribution_ids: Vec<u64>, // refunded pledges; empty for withdrawals
+    pub created_at: u64,            // ns
+}
+
+impl Storable for Payout {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Payout"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode Payout")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Store the outcome of a campaign ended by `now` (ns) and log it as done by `caller`.
+/// Already-final campaigns return their stored outcome.
+pub(crate) fn finalize(caller: Principal, now: u64, campaign_id: u64) -> Result<CampaignOutcome, String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    let outcome = match lifecycle(&campaign, to_secs(now)) {
+        CampaignLifecycle::Active => return Err("campaign has not ended yet".into()),
+        CampaignLifecycle::Cancelled | CampaignLifecycle::TakenDown => {
+            return Err("campaign was stopped early; backers can claim refunds".into())
+        }
+        CampaignLifecycle::Completed => CampaignOutcome::Successful,
+        CampaignLifecycle::Expired => CampaignOutcome::Failed,
+    };
+    if campaign.status.is_none() {
+        with_campaign_mut(campaign_id, |c| {
+            c.status = Some(match outcome {
+                CampaignOutcome::Successful => CampaignLifecycle::Completed,
+                CampaignOutcome::Failed => CampaignLifecycle::Expired,
+            });
+            Ok(())
+        })?;
+        record_change_at(now, EntityRef::Campaign(campaign_id), ChangeKind::Updated);
+        let kind = match outcome {
+            CampaignOutcome::Successful => EventKind::CampaignCompleted,
+            CampaignOutcome::Failed => EventKind::CampaignExpired,
+        };
+        let filed_under = [EntityRef::Idea(campaign.idea_id), EntityRef::Campaign(campaign_id)];
+        activity::append(now, caller, kind, campaign_id, &filed_under);
+    }
+    Ok(outcome)
+}
+
+fn campaign_payouts(campaign_id: u64) -> Vec<Payout> {
+    PAYOUTS.with(|p| {
+        p.borrow()
+            .range((campaign_id, 0)..=(campaign_id, u64::MAX))
+            .map(|(_, payout)| payout)
+            .collect()
+    })
+}
+
+fn record_payout(
+    now: u64,
+    campaign_id: u64,
+    kind: PayoutKind,
+    to: Principal,
+    amount: u64,
+    contribution_ids: Vec<u64>,
+) -> Payout {
+    let id = PAYOUT_COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        let id = c.get() + 1;
+        c.set(id).expect("store payout counter");
+        id
+    });
+    REFUNDED_CONTRIBUTIONS.with(|r| {
+        let mut r = r.borrow_mut();
+        for contribution_id in &contribution_ids {
+            r.insert(*contribution_id, id);
+        }
+    });
+    let payout = Payout { id, campaign_id, kind, to, amount, contribution_ids, created_at: now };
+    PAYOUTS.with(|p| p.borrow_mut().insert((campaign_id, id), payout.clone()));
+    payout
+}
+
+/// The part of a campaign's total the backend settles: everything but the ICP Fund_Flow relayed.
+fn held_here(campaign: &Campaign) -> Result<Amount, String> {
+    Ok(Amount::new(campaign.amount_raised).sub(Amount::new(get_icp_contribution(campaign.id)))?)
+}
+
+/// Settle an ended campaign as Successful or Failed; anyone may call it once the (pause-adjusted)
+/// end date has passed. Repeating it returns the same outcome.
+#[update]
+fn finalize_campaign(campaign_id: u64) -> Result<CampaignOutcome, String> {
+    finalize(ic_cdk::caller(), ic_cdk::api::time(), campaign_id)
+}
+
+/// Reclaim every unrefunded pledge the caller made to a failed or cancelled campaign, finalizing
+/// it first if needed. The campaign and idea totals drop by the refunded amount.
+#[update]
+fn claim_refund(campaign_id: u64) -> Result<Payout, String> {
+    claim_refund_at(ic_cdk::caller(), ic_cdk::api::time(), campaign_id)
+}
+
+/// `claim_refund` by `caller` at `now` (ns).
+fn claim_refund_at(caller: Principal, now: u64, campaign_id: u64) -> Result<Payout, String> {
+    let campaign = get_campaign(campaign_id).ok_or_else(|| not_found(EntityRef::Campaign(campaign_id)))?;
+    let stopped = matches!(campaign.status, Some(CampaignLifecycle::Cancelled | CampaignLifecycle::TakenDown));
+    if !stopped && finalize(caller, now, campaign_id)? != CampaignOutcome::Failed {
+        return Err("campaign met its goal; pledges are not refundable".into());
+    }
+    let pledges: Vec<(u64, u64)> = get_contributions_by_campaign(campaign_id)
+        .into_iter()
+        .filter(|c| c.contributor == caller)
+        .filter(|c| !REFUNDED_CONTRIBUTIONS.with(|r| r.borrow().contains_key(&c.id)))
+        .map(|c| (c.id, c.amount))
+        .collect();
+    if pledges.is_empty() {
+        return Err("nothing to refund".into());
+    }
+    let amount = pledges.iter().try_fold(Amount::default(), |sum, (_, amount)| sum.add(Amount::new(*amount)))?;
+    debit_campaign(&campaign, amount, now)?;
+    let ids = pledges.into_iter().map(|(id, _)| id).collect();
+    let payout = record_payout(now, campaign_id, PayoutKind::Refund, caller, amount.get(), ids);
+    if campaign.refund_pending == Some(true) && held_here(&campaign)? == amount {
+        with_campaign_mut(campaign_id, |c| {
+            c.refund_pending = Some(false);
+            Ok(())
+        })?;
+    }
+    Ok(payout)
+}
+
+/// Mark everything a successful campaign raised here as withdrawn to its owner (owner or
+/// controllers), finalizing it first if needed. Totals are kept, since the goal was met.
+/// ICP relayed by Fund_Flow is paid out by Fund_Flow and is not included.
+#[update]
+fn withdraw_raised(campaign_id: u64) -> Result<Payout, String> {
+    withdraw_raised_at(ic_cdk::caller(), ic_cdk::api::time(), campaign_id)
+}
+
+/// `withdraw_raised` by `caller` at `now` (ns).
+fn withdraw_raised_at(caller: Principal, now: u64, campaign_id: u64) -> Result<Payout, String> {
+    let campaign = campaign_owned_by(&caller, campaign_id)?;
+    let Some(owner) = campaign.owner else {
+        return Err("campaign has no owner to withdraw to".into());
+    };
+    if finalize(caller, now, campaign_id)? != CampaignOutcome::Successful {
+        return Err("campaign missed its goal; backers are refunded instead".into());
+    }
+    if campaign_payouts(campaign_id).iter().any(|p| p.kind == PayoutKind::Withdrawal) {
+        return Err("funds were already withdrawn".into());
+    }
+    let amount = held_here(&campaign)?;
+    if amount == Amount::default() {
+        return Err("nothing to withdraw here; ICP relayed by Fund_Flow is paid out there".into());
+    }
+    Ok(record_payout(now, campaign_id, PayoutKind::Withdrawal, owner, amount.get(), vec![]))
+}
+
+/// Refunds and withdrawals recorded for a campaign, oldest first.
+#[query]
+fn get_campaign_payouts(campaign_id: u64) -> Vec<Payout> {
+    campaign_payouts(campaign_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_campaign;
+    use crate::{contribute_at, Currency, Money, CAMPAIGNS, ICP_CONTRIBUTIONS};
+
+    #[test]
+    fn relayed_icp_is_left_to_fund_flow() {
+        // 300 e8s relayed by Fund_Flow and 400 pledged here with contribute_icp.
+        let mixed = Campaign { amount_raised: 700, ..test_campaign(1, 1, 0) };
+        ICP_CONTRIBUTIONS.with(|c| c.borrow_mut().insert(1, 300));
+        assert_eq!(held_here(&mixed), Ok(Amount::new(400)));
+
+        let relayed_only = Campaign { amount_raised: 300, ..test_campaign(2, 1, 0) };
+        ICP_CONTRIBUTIONS.with(|c| c.borrow_mut().insert(2, 300));
+        assert_eq!(held_here(&relayed_only), Ok(Amount::default()));
+
+        let direct_only = Campaign { amount_raised: 250, ..test_campaign(3, 1, 0) };
+        assert_eq!(held_here(&direct_only), Ok(Amount::new(250)));
+    }
+
+    #[test]
+    fn backers_of_missed_goals_are_refunded_and_owners_of_met_ones_withdraw() {
+        let (owner, backer) = (Principal::from_slice(&[1]), Principal::from_slice(&[2]));
+        let ends = 1_700_000_000;
+        let (during, after) = (ends * 1_000_000_000, (ends + 1) * 1_000_000_000);
+        let open = |id| Campaign {
+            owner: Some(owner),
+            currency: Some(Currency::UsdCents),
+            ..test_campaign(id, 1, ends)
+        };
+        CAMPAIGNS.with(|store| {
+            store.borrow_mut().insert(1, open(1));
+            store.borrow_mut().insert(2, open(2));
+        });
+        let usd = |amount| Money { currency: Currency::UsdCents, amount };
+        contribute_at(backer, during, 1, usd(30), None, None).unwrap();
+        contribute_at(backer, during, 2, usd(100), None, None).unwrap();
+
+        assert_eq!(claim_refund_at(backer, during, 1).unwrap_err(), "campaign has not ended yet");
+        let refund = claim_refund_at(backer, after, 1).unwrap();
+        assert_eq!((refund.kind, refund.to, refund.amount, refund.created_at), (PayoutKind::Refund, backer, 30, after));
+        let missed = get_campaign(1).unwrap();
+        assert_eq!((missed.status, missed.amount_raised), (Some(CampaignLifecycle::Expired), 0));
+        assert_eq!(claim_refund_at(backer, after, 1).unwrap_err(), "nothing to refund");
+        assert!(withdraw_raised_at(owner, after, 1).is_err());
+
+        assert!(claim_refund_at(backer, after, 2).is_err(), "the goal was met");
+        assert!(withdraw_raised_at(backer, after, 2).is_err(), "only the owner withdraws");
+        let withdrawal = withdraw_raised_at(owner, after, 2).unwrap();
+        assert_eq!((withdrawal.kind, withdrawal.to, withdrawal.amount), (PayoutKind::Withdrawal, owner, 100));
+        assert_eq!(withdraw_raised_at(owner, after, 2).unwrap_err(), "funds were already withdrawn");
+        assert_eq!(get_campaign(2).unwrap().amount_raised, 100, "totals are kept once the goal is met");
+        assert_eq!(campaign_payouts(2).len(), 1);
+    }
+}
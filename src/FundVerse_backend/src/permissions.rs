@@ -0,0 +1,296 @@
+//! What a caller may do to an idea, campaign, doc or comment, for the frontend's buttons.
+//!
+//! Each `may_*` guard is the check its update endpoint runs, so `get_permissions` can't drift
+//! from what the endpoints enforce. A guard covers who is calling and the entity's state; input
+//! checks (text lengths, amounts, dates) stay in the endpoints. A denial carries a reason code
+//! for the UI and the message the endpoint returns.
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk_macros::query;
+
+use super::{
+    active_pause_reason, can_comment, can_edit_idea, effective_end_date, get_campaign, get_doc, get_idea, is_admin,
+    is_controller, lifecycle, now_secs, pause_log, Campaign, CampaignLifecycle, EntityRef, FundVerseError, Idea,
+    IdeaComment, IdeaStatus, CAMPAIGNS, CONTRIBUTIONS_PAUSED, IDEA_COMMENTS, MAX_PAUSE_BUDGET_SECS,
+};
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DenialReason {
+    SignInRequired,
+    NotOwner,
+    NotAdmin,
+    WrongStatus,  // e.g. not approved yet, already ended
+    Frozen,       // a corrupt idea record or a taken-down campaign
+    HasCampaigns, // ideas with campaigns can't be deleted
+    HasBackers,   // launched campaigns can't be edited
+}
+
+pub(crate) struct Denial {
+    pub reason: DenialReason,
+    pub message: String,
+}
+
+impl From<Denial> for String {
+    fn from(d: Denial) -> Self {
+        d.message
+    }
+}
+
+fn deny(reason: DenialReason, message: impl Into<String>) -> Result<(), Denial> {
+    Err(Denial { reason, message: message.into() })
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Permission {
+    pub allowed: bool,
+    pub denied_because: Option<DenialReason>,
+}
+
+impl From<Result<(), Denial>> for Permission {
+    fn from(check: Result<(), Denial>) -> Self {
+        match check {
+            Ok(()) => Permission { allowed: true, denied_because: None },
+            Err(d) => Permission { allowed: false, denied_because: Some(d.reason) },
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct IdeaPermissions {
+    pub edit: Permission,            // update_idea
+    pub delete: Permission,          // delete_idea
+    pub attach_docs: Permission,     // upload_doc, begin_doc_upload
+    pub create_campaign: Permission, // create_campaign
+    pub comment: Permission,         // post_idea_comment
+    pub review: Permission,          // approve_idea, reject_idea
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignPermissions {
+    pub edit: Permission,          // set_campaign_copy, set_campaign_risks
+    pub amend_risks: Permission,   // amend_campaign_risks
+    pub cancel: Permission,        // cancel_campaign
+    pub extend: Permission,        // extend_campaign
+    pub pause: Permission,         // pause_contributions
+    pub resume: Permission,        // resume_contributions
+    pub contribute: Permission,    // contribute
+    pub add_milestone: Permission, // add_milestone
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DocPermissions {
+    pub replace: Permission, // replace_doc
+    pub delete: Permission,  // delete_doc
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CommentPermissions {
+    pub delete: Permission, // delete_idea_comment
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum Permissions {
+    Idea(IdeaPermissions),
+    Campaign(CampaignPermissions),
+    Doc(DocPermissions),
+    Comment(CommentPermissions),
+}
+
+fn signed_in(caller: &Principal, action: &str) -> Result<(), Denial> {
+    if *caller == Principal::anonymous() {
+        return deny(DenialReason::SignInRequired, format!("sign in to {}", action));
+    }
+    Ok(())
+}
+
+pub(crate) fn may_edit_idea(caller: &Principal, idea: &Idea) -> Result<(), Denial> {
+    if idea.is_quarantined() {
+        return deny(DenialReason::Frozen, "idea record is corrupt; repair it first");
+    }
+    if !can_edit_idea(idea, caller) {
+        return deny(DenialReason::NotOwner, "only the idea's owner can change it");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_delete_idea(caller: &Principal, idea_id: u64, idea: &Idea) -> Result<(), Denial> {
+    may_edit_idea(caller, idea)?;
+    if idea.is_draft() {
+        return Ok(()); // never public, so nothing can depend on it
+    }
+    if CAMPAIGNS.with(|store| store.borrow().iter().any(|(_, c)| c.idea_id == idea_id)) {
+        return deny(DenialReason::HasCampaigns, "idea has campaigns and can't be deleted");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_attach_docs(caller: &Principal, idea: &Idea) -> Result<(), Denial> {
+    if !can_edit_idea(idea, caller) {
+        return deny(DenialReason::NotOwner, "only the idea's owner can attach documents");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_create_campaign(caller: &Principal, idea: &Idea) -> Result<(), Denial> {
+    signed_in(caller, "create a campaign")?;
+    if !can_edit_idea(idea, caller) {
+        return deny(DenialReason::NotOwner, "only the idea's owner can create a campaign for it");
+    }
+    if idea.status != IdeaStatus::Approved {
+        return deny(DenialReason::WrongStatus, "idea must be approved before it can have a campaign");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_comment(caller: &Principal, idea: &Idea) -> Result<(), Denial> {
+    signed_in(caller, "comment")?;
+    if !can_comment(idea, caller) {
+        return deny(DenialReason::WrongStatus, "comments open once the idea is approved");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_review_idea(caller: &Principal, idea: &Idea) -> Result<(), Denial> {
+    if !is_admin(caller) {
+        return deny(DenialReason::NotAdmin, "only admins can call this method");
+    }
+    if idea.status != IdeaStatus::Pending {
+        return deny(DenialReason::WrongStatus, format!("idea is not pending review ({:?})", idea.status));
+    }
+    Ok(())
+}
+
+pub(crate) fn may_manage_campaign(caller: &Principal, campaign: &Campaign) -> Result<(), Denial> {
+    if campaign.owner != Some(*caller) && !is_controller(caller) {
+        return deny(DenialReason::NotOwner, "only the campaign's owner can change it");
+    }
+    Ok(())
+}
+
+pub(crate) fn running(campaign: &Campaign, now: u64) -> Result<(), Denial> {
+    match lifecycle(campaign, now) {
+        CampaignLifecycle::Active => Ok(()),
+        CampaignLifecycle::TakenDown => deny(DenialReason::Frozen, "campaign is TakenDown, not Active"),
+        other => deny(DenialReason::WrongStatus, format!("campaign is {:?}, not Active", other)),
+    }
+}
+
+/// Cancelling and extending: the owner, while the campaign runs.
+pub(crate) fn may_change_schedule(caller: &Principal, campaign: &Campaign, now: u64) -> Result<(), Denial> {
+    may_manage_campaign(caller, campaign)?;
+    running(campaign, now)
+}
+
+/// Headline, teaser and risks can change until the first pledge.
+pub(crate) fn may_edit_campaign(caller: &Principal, campaign: &Campaign) -> Result<(), Denial> {
+    may_manage_campaign(caller, campaign)?;
+    if campaign.amount_raised > 0 {
+        return deny(DenialReason::HasBackers, "campaign has launched and can no longer be edited");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_pause(caller: &Principal, campaign: &Campaign, now: u64) -> Result<(), Denial> {
+    may_manage_campaign(caller, campaign)?;
+    if now > effective_end_date(campaign, now) {
+        return deny(DenialReason::WrongStatus, "campaign has ended");
+    }
+    let log = pause_log(campaign.id);
+    if log.active(now).is_some() {
+        return deny(DenialReason::WrongStatus, "contributions are already paused");
+    }
+    if log.paused_secs(now) >= MAX_PAUSE_BUDGET_SECS {
+        return deny(DenialReason::WrongStatus, "pause budget for this campaign is used up");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_resume(caller: &Principal, campaign: &Campaign, now: u64) -> Result<(), Denial> {
+    may_manage_campaign(caller, campaign)?;
+    if pause_log(campaign.id).active(now).is_none() {
+        return deny(DenialReason::WrongStatus, "contributions are not paused");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_contribute(caller: &Principal, campaign: &Campaign, now: u64) -> Result<(), Denial> {
+    signed_in(caller, "contribute")?;
+    match lifecycle(campaign, now) {
+        CampaignLifecycle::Active => {}
+        CampaignLifecycle::Cancelled => return deny(DenialReason::WrongStatus, "campaign was cancelled"),
+        CampaignLifecycle::TakenDown => {
+            let notice = super::takedown::TAKEDOWN_NOTICE.to_lowercase();
+            return deny(DenialReason::Frozen, format!("campaign was {}", notice));
+        }
+        CampaignLifecycle::Completed | CampaignLifecycle::Expired => {
+            return deny(DenialReason::WrongStatus, "campaign has ended")
+        }
+    }
+    if let Some(reason) = active_pause_reason(campaign.id, now) {
+        return deny(DenialReason::WrongStatus, format!("{}: {}", CONTRIBUTIONS_PAUSED, reason));
+    }
+    Ok(())
+}
+
+/// Docs belong to their idea's owner; admins may change any, including docs whose idea is gone.
+pub(crate) fn may_change_doc(caller: &Principal, idea: Option<&Idea>) -> Result<(), Denial> {
+    if !is_admin(caller) && idea.is_none_or(|idea| idea.owner != *caller) {
+        return deny(DenialReason::NotOwner, "only the idea's owner or an admin can change its documents");
+    }
+    Ok(())
+}
+
+pub(crate) fn may_delete_comment(caller: &Principal, comment: &IdeaComment) -> Result<(), Denial> {
+    if comment.author != *caller && !is_controller(caller) {
+        return deny(DenialReason::NotOwner, "only the author or a moderator can delete this comment");
+    }
+    Ok(())
+}
+
+fn permissions(caller: &Principal, entity: EntityRef, now: u64) -> Option<Permissions> {
+    Some(match entity {
+        EntityRef::Idea(id) => {
+            let idea = get_idea(id)?;
+            Permissions::Idea(IdeaPermissions {
+                edit: may_edit_idea(caller, &idea).into(),
+                delete: may_delete_idea(caller, id, &idea).into(),
+                attach_docs: may_attach_docs(caller, &idea).into(),
+                create_campaign: may_create_campaign(caller, &idea).into(),
+                comment: may_comment(caller, &idea).into(),
+                review: may_review_idea(caller, &idea).into(),
+            })
+        }
+        EntityRef::Campaign(id) => {
+            let campaign = get_campaign(id)?;
+            Permissions::Campaign(CampaignPermissions {
+                edit: may_edit_campaign(caller, &campaign).into(),
+                amend_risks: may_manage_campaign(caller, &campaign).into(),
+                cancel: may_change_schedule(caller, &campaign, now).into(),
+                extend: may_change_schedule(caller, &campaign, now).into(),
+                pause: may_pause(caller, &campaign, now).into(),
+                resume: may_resume(caller, &campaign, now).into(),
+                contribute: may_contribute(caller, &campaign, now).into(),
+                add_milestone: may_change_schedule(caller, &campaign, now).into(),
+            })
+        }
+        EntityRef::Doc(id) => {
+            let doc = get_doc(id)?;
+            let idea = get_idea(doc.idea_id);
+            Permissions::Doc(DocPermissions {
+                replace: may_change_doc(caller, idea.as_ref()).into(),
+                delete: may_change_doc(caller, idea.as_ref()).into(),
+            })
+        }
+        EntityRef::Comment(id) => {
+            let comment = IDEA_COMMENTS.with(|c| c.borrow().get(&id))?;
+            Permissions::Comment(CommentPermissions { delete: may_delete_comment(caller, &comment).into() })
+        }
+    })
+}
+
+/// What the caller may do to an entity right now, each denial with its reason. Input checks are
+/// left out: an allowed action can still fail on bad arguments, but never on who is asking.
+#[query]
+fn get_permissions(entity: EntityRef) -> Result<Permissions, FundVerseError> {
+    permissions(&ic_cdk::caller(), entity, now_secs()).ok_or(FundVerseError::NotFound(entity))
+}
@@ -0,0 +1,231 @@
+//! User profiles, one per principal, so the frontend can greet a signed-in user and show who is
+//! behind an idea.
+//!
+//! Registering is explicit and happens once; the anonymous principal can't have a profile. A
+//! profile's email is shown only to its owner and to admins. `role` is derived from the admin
+//! list when a profile is read, so granting or revoking admin rights needs no profile update.
+//! With `require_profile` set in the init or upgrade args, `create_idea` refuses callers without
+//! a profile; it is off by default so the demo works without signing up.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{
+    get_idea, is_admin, is_contact, FieldError, FieldId, FundVerseError, Idea, IdeaStatus, Memory, RuleId,
+    IDEAS_BY_OWNER, MEMORY_MANAGER,
+};
+
+pub(crate) const MAX_DISPLAY_NAME_LEN: usize = 50; // chars
+pub(crate) const MAX_EMAIL_LEN: usize = 100; // bytes
+pub(crate) const MAX_BIO_LEN: usize = 500; // chars
+
+thread_local! {
+    static PROFILES: RefCell<StableBTreeMap<Principal, UserProfile, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(61)))
+        )
+    );
+
+    static REQUIRE_PROFILE: RefCell<StableCell<bool, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(62))),
+            false,
+        ).expect("init require_profile flag")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserRole {
+    Member,
+    Admin,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct UserProfile {
+    pub principal: Principal,
+    pub display_name: String,
+    pub email: Option<String>, // None when read by someone other than the owner or an admin
+    pub bio: String,
+    pub created_at: u64, // ns
+    pub role: UserRole,
+}
+
+impl Storable for UserProfile {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode UserProfile"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode UserProfile")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Fields to change; None leaves a field as it is.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct ProfileUpdate {
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    pub bio: Option<String>,
+}
+
+/// Turn the profile check for `create_idea` on or off; None keeps the stored setting.
+pub(crate) fn configure(require_profile: Option<bool>) {
+    if let Some(required) = require_profile {
+        REQUIRE_PROFILE.with(|r| r.borrow_mut().set(required)).expect("store require_profile flag");
+    }
+}
+
+/// Whether `principal` may create ideas as far as profiles are concerned.
+pub(crate) fn may_create_ideas(principal: &Principal) -> bool {
+    !REQUIRE_PROFILE.with(|r| *r.borrow().get()) || PROFILES.with(|p| p.borrow().contains_key(principal))
+}
+
+fn signed_in_caller() -> Result<Principal, FundVerseError> {
+    let caller = ic_cdk::caller();
+    if caller == Principal::anonymous() {
+        return Err(FundVerseError::Unauthorized);
+    }
+    Ok(caller)
+}
+
+fn validate_profile(display_name: &str, email: &str, bio: &str) -> Vec<FieldError> {
+    let error = |field, rule, limit: Option<usize>, actual: Option<usize>| FieldError {
+        field,
+        rule,
+        limit: limit.map(|l| l as u64),
+        actual: actual.map(|a| a as u64),
+    };
+    let mut errors = Vec::new();
+    let name_len = display_name.chars().count();
+    if display_name.is_empty() {
+        errors.push(error(FieldId::DisplayName, RuleId::Required, None, None));
+    } else if name_len > MAX_DISPLAY_NAME_LEN {
+        errors.push(error(FieldId::DisplayName, RuleId::MaxLength, Some(MAX_DISPLAY_NAME_LEN), Some(name_len)));
+    }
+    if email.is_empty() {
+        errors.push(error(FieldId::Email, RuleId::Required, None, None));
+    } else if email.len() > MAX_EMAIL_LEN {
+        errors.push(error(FieldId::Email, RuleId::MaxLength, Some(MAX_EMAIL_LEN), Some(email.len())));
+    } else if !email.contains('@') || !is_contact(email) {
+        errors.push(error(FieldId::Email, RuleId::Format, None, None));
+    }
+    let bio_len = bio.chars().count();
+    if bio_len > MAX_BIO_LEN {
+        errors.push(error(FieldId::Bio, RuleId::MaxLength, Some(MAX_BIO_LEN), Some(bio_len)));
+    }
+    errors
+}
+
+/// The profile as `viewer` may see it, with its role brought up to date.
+fn as_seen_by(mut profile: UserProfile, viewer: &Principal) -> UserProfile {
+    profile.role = if is_admin(&profile.principal) { UserRole::Admin } else { UserRole::Member };
+    if *viewer != profile.principal && !is_admin(viewer) {
+        profile.email = None;
+    }
+    profile
+}
+
+fn stored_profile(principal: &Principal) -> Option<UserProfile> {
+    PROFILES.with(|p| p.borrow().get(principal))
+}
+
+/// Create the caller's profile. Fails with AlreadyRegistered if the caller has one.
+#[update]
+fn register_user(display_name: String, email: String, bio: String) -> Result<UserProfile, FundVerseError> {
+    let caller = signed_in_caller()?;
+    if stored_profile(&caller).is_some() {
+        return Err(FundVerseError::AlreadyRegistered);
+    }
+    let (display_name, email, bio) = (display_name.trim(), email.trim(), bio.trim());
+    let errors = validate_profile(display_name, email, bio);
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    let profile = UserProfile {
+        principal: caller,
+        display_name: display_name.to_string(),
+        email: Some(email.to_string()),
+        bio: bio.to_string(),
+        created_at: ic_cdk::api::time(),
+        role: UserRole::Member,
+    };
+    PROFILES.with(|p| p.borrow_mut().insert(caller, profile.clone()));
+    Ok(as_seen_by(profile, &caller))
+}
+
+/// Change the caller's profile; fields left as None keep their value.
+#[update]
+fn update_profile(update: ProfileUpdate) -> Result<UserProfile, FundVerseError> {
+    let caller = signed_in_caller()?;
+    let mut profile = stored_profile(&caller).ok_or(FundVerseError::NotRegistered)?;
+    let trimmed = |value: Option<String>, current: &str| value.map_or(current.to_string(), |v| v.trim().to_string());
+    let display_name = trimmed(update.display_name, &profile.display_name);
+    let email = trimmed(update.email, profile.email.as_deref().unwrap_or(""));
+    let bio = trimmed(update.bio, &profile.bio);
+    let errors = validate_profile(&display_name, &email, &bio);
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    profile.display_name = display_name;
+    profile.email = Some(email);
+    profile.bio = bio;
+    PROFILES.with(|p| p.borrow_mut().insert(caller, profile.clone()));
+    Ok(as_seen_by(profile, &caller))
+}
+
+/// The caller's own profile, None if they haven't registered.
+#[query]
+fn get_my_profile() -> Option<UserProfile> {
+    let caller = ic_cdk::caller();
+    stored_profile(&caller).map(|p| as_seen_by(p, &caller))
+}
+
+#[query]
+fn get_profile(principal: Principal) -> Option<UserProfile> {
+    stored_profile(&principal).map(|p| as_seen_by(p, &ic_cdk::caller()))
+}
+
+/// A user's approved ideas, oldest first: what they have published.
+#[query]
+fn get_published_ideas(principal: Principal) -> Vec<(u64, Idea)> {
+    let ids: Vec<u64> = IDEAS_BY_OWNER.with(|o| {
+        o.borrow().range((principal, 0)..=(principal, u64::MAX)).map(|((_, id), _)| id).collect()
+    });
+    ids.into_iter()
+        .filter_map(|id| get_idea(id).map(|idea| (id, idea)))
+        .filter(|(_, idea)| idea.status == IdeaStatus::Approved)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_need_a_name_and_an_email_and_cap_every_field() {
+        assert!(validate_profile("Ada", "ada@example.com", "").is_empty());
+        let fields = |errors: Vec<FieldError>| errors.iter().map(|e| (e.field, e.rule)).collect::<Vec<_>>();
+        assert_eq!(
+            fields(validate_profile("", "+1 555 010 9999", "")),
+            [(FieldId::DisplayName, RuleId::Required), (FieldId::Email, RuleId::Format)],
+            "a phone number is contact info, not an email"
+        );
+        let name = "é".repeat(MAX_DISPLAY_NAME_LEN); // counted in chars
+        assert!(validate_profile(&name, "ada@example.com", &"b".repeat(MAX_BIO_LEN)).is_empty());
+        assert_eq!(
+            fields(validate_profile(&format!("{}x", name), "", &"b".repeat(MAX_BIO_LEN + 1))),
+            [
+                (FieldId::DisplayName, RuleId::MaxLength),
+                (FieldId::Email, RuleId::Required),
+                (FieldId::Bio, RuleId::MaxLength),
+            ]
+        );
+    }
+}
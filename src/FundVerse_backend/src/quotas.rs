@@ -0,0 +1,305 @@
+//! Per-caller quotas on idea creation and doc uploads, so one script can't fill stable memory for
+//! free.
+//!
+//! Ideas and uploaded bytes are counted over a rolling 24 hours: each creation or upload is kept
+//! with its time and stops counting a day later. Docs per idea and the size of a single doc are
+//! checked against what is stored. A chunked upload counts its declared size when it begins, so
+//! aborted uploads still use up the day's bytes. Limits come from `quotas` in the init or upgrade
+//! args or from `set_quota_limits`; admins are exempt from all of them.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{ensure_admin, is_admin, FundVerseError, Idea, Memory, MAX_DOC_UPLOAD_BYTES, MEMORY_MANAGER};
+
+const WINDOW_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+thread_local! {
+    static QUOTA_LIMITS: RefCell<StableCell<QuotaLimits, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(71))),
+            QuotaLimits::default(),
+        ).expect("init quota limits")
+    );
+
+    static QUOTA_USAGE: RefCell<StableBTreeMap<Principal, Usage, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(72)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotaKind {
+    IdeasPerDay,
+    UploadBytesPerDay,
+    DocsPerIdea,
+    DocSize,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct QuotaLimits {
+    pub ideas_per_day: u64,
+    pub upload_bytes_per_day: u64,
+    pub docs_per_idea: u64,
+    pub max_doc_bytes: u64, // at most the 50 MiB a chunked upload can hold
+}
+
+impl Default for QuotaLimits {
+    fn default() -> Self {
+        QuotaLimits {
+            ideas_per_day: 25,
+            upload_bytes_per_day: 200 * 1024 * 1024,
+            docs_per_idea: 50,
+            max_doc_bytes: MAX_DOC_UPLOAD_BYTES,
+        }
+    }
+}
+
+impl Storable for QuotaLimits {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode QuotaLimits"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode QuotaLimits")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// What a caller did within the last window, oldest first.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+struct Usage {
+    ideas: Vec<u64>,          // creation times, ns
+    uploads: Vec<(u64, u64)>, // (time in ns, bytes)
+}
+
+impl Storable for Usage {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode quota usage"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode quota usage")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Usage {
+    /// Drop what happened a full window before `now`.
+    fn expire(&mut self, now: u64) {
+        let counts = |at: u64| now.saturating_sub(at) < WINDOW_NS;
+        self.ideas.retain(|at| counts(*at));
+        self.uploads.retain(|(at, _)| counts(*at));
+    }
+
+    fn bytes(&self) -> u64 {
+        self.uploads.iter().map(|(_, bytes)| bytes).sum()
+    }
+
+    /// When the oldest counted entry stops counting; None when nothing counts.
+    fn resets_at(&self) -> Option<u64> {
+        let oldest_idea = self.ideas.first().copied();
+        let oldest_upload = self.uploads.first().map(|(at, _)| *at);
+        oldest_idea.into_iter().chain(oldest_upload).min().map(|at| at + WINDOW_NS)
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct QuotaUsage {
+    pub exempt: bool, // admins aren't limited
+    pub ideas_created: u64,
+    pub bytes_uploaded: u64,
+    pub resets_at: Option<u64>, // ns; when the oldest counted idea or upload stops counting
+    pub limits: QuotaLimits,
+}
+
+fn validate_limits(limits: &QuotaLimits) -> Result<(), String> {
+    if limits.ideas_per_day == 0 || limits.upload_bytes_per_day == 0 || limits.docs_per_idea == 0 {
+        return Err("quota limits must be > 0".into());
+    }
+    if limits.max_doc_bytes == 0 || limits.max_doc_bytes > MAX_DOC_UPLOAD_BYTES {
+        return Err(format!("max_doc_bytes must be 1-{}", MAX_DOC_UPLOAD_BYTES));
+    }
+    Ok(())
+}
+
+fn store_limits(limits: QuotaLimits) {
+    QUOTA_LIMITS.with(|l| l.borrow_mut().set(limits)).expect("store quota limits");
+}
+
+/// Replace the limits; None keeps the stored ones. Invalid limits fail the install or upgrade.
+pub(crate) fn configure(limits: Option<QuotaLimits>) {
+    if let Some(limits) = limits {
+        if let Err(e) = validate_limits(&limits) {
+            ic_cdk::trap(&format!("invalid quotas: {}", e));
+        }
+        store_limits(limits);
+    }
+}
+
+fn limits() -> QuotaLimits {
+    QUOTA_LIMITS.with(|l| l.borrow().get().clone())
+}
+
+fn exceeded(quota: QuotaKind, limit: u64, retry_at: Option<u64>) -> FundVerseError {
+    FundVerseError::QuotaExceeded { quota, limit, retry_at }
+}
+
+/// Count one idea at `now`, or fail if the window's ideas are used up.
+fn take_idea(usage: &mut Usage, limits: &QuotaLimits, now: u64) -> Result<(), FundVerseError> {
+    usage.expire(now);
+    if usage.ideas.len() as u64 >= limits.ideas_per_day {
+        return Err(exceeded(QuotaKind::IdeasPerDay, limits.ideas_per_day, usage.resets_at()));
+    }
+    usage.ideas.push(now);
+    Ok(())
+}
+
+/// Count `bytes` uploaded at `now`, or fail if the doc is too large or the window's bytes are used up.
+fn take_bytes(usage: &mut Usage, limits: &QuotaLimits, bytes: u64, now: u64) -> Result<(), FundVerseError> {
+    if bytes > limits.max_doc_bytes {
+        return Err(exceeded(QuotaKind::DocSize, limits.max_doc_bytes, None));
+    }
+    usage.expire(now);
+    if usage.bytes().saturating_add(bytes) > limits.upload_bytes_per_day {
+        return Err(exceeded(QuotaKind::UploadBytesPerDay, limits.upload_bytes_per_day, usage.resets_at()));
+    }
+    usage.uploads.push((now, bytes));
+    Ok(())
+}
+
+/// Run `take` on the caller's usage at `now` (ns) and store the result; admins skip it.
+fn charge<F>(caller: &Principal, now: u64, take: F) -> Result<(), FundVerseError>
+where
+    F: FnOnce(&mut Usage, &QuotaLimits, u64) -> Result<(), FundVerseError>,
+{
+    if is_admin(caller) {
+        return Ok(());
+    }
+    let mut usage = QUOTA_USAGE.with(|u| u.borrow().get(caller)).unwrap_or_default();
+    take(&mut usage, &limits(), now)?;
+    QUOTA_USAGE.with(|u| u.borrow_mut().insert(*caller, usage));
+    Ok(())
+}
+
+/// Count a new idea against the caller's quota. Call once the idea is valid, right before storing it.
+pub(crate) fn charge_idea(caller: &Principal, now: u64) -> Result<(), FundVerseError> {
+    charge(caller, now, take_idea)
+}
+
+/// Check the idea has room for another doc (admins aren't limited).
+pub(crate) fn check_doc_count(caller: &Principal, idea: &Idea) -> Result<(), FundVerseError> {
+    let limit = limits().docs_per_idea;
+    if !is_admin(caller) && idea.doc_ids.len() as u64 >= limit {
+        return Err(exceeded(QuotaKind::DocsPerIdea, limit, None));
+    }
+    Ok(())
+}
+
+/// Count `bytes` of doc data against the caller's quota, after checking the doc's size.
+pub(crate) fn charge_bytes(caller: &Principal, bytes: u64, now: u64) -> Result<(), FundVerseError> {
+    charge(caller, now, |usage, limits, now| take_bytes(usage, limits, bytes, now))
+}
+
+/// A new doc of `bytes` on `idea`: room on the idea, then the caller's bytes.
+pub(crate) fn charge_upload(caller: &Principal, idea: &Idea, bytes: u64, now: u64) -> Result<(), FundVerseError> {
+    check_doc_count(caller, idea)?;
+    charge_bytes(caller, bytes, now)
+}
+
+/// Change the quota limits (admins). Usage already counted stays counted.
+#[update]
+fn set_quota_limits(limits: QuotaLimits) -> Result<(), String> {
+    ensure_admin()?;
+    validate_limits(&limits)?;
+    store_limits(limits);
+    Ok(())
+}
+
+/// The caller's usage in the current window and the limits, so the frontend can warn early.
+#[query]
+fn get_my_quota_usage() -> QuotaUsage {
+    usage_at(&ic_cdk::caller(), ic_cdk::api::time())
+}
+
+/// `caller`'s usage in the window ending at `now` (ns).
+fn usage_at(caller: &Principal, now: u64) -> QuotaUsage {
+    let mut usage = QUOTA_USAGE.with(|u| u.borrow().get(caller)).unwrap_or_default();
+    usage.expire(now);
+    QuotaUsage {
+        exempt: is_admin(caller),
+        ideas_created: usage.ideas.len() as u64,
+        bytes_uploaded: usage.bytes(),
+        resets_at: usage.resets_at(),
+        limits: limits(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_NS: u64 = 60 * 60 * 1_000_000_000;
+
+    #[test]
+    fn usage_rolls_off_a_day_after_it_happened() {
+        let limits = QuotaLimits { ideas_per_day: 2, upload_bytes_per_day: 100, docs_per_idea: 5, max_doc_bytes: 80 };
+        let mut usage = Usage::default();
+        let start = 1_000 * WINDOW_NS;
+        take_idea(&mut usage, &limits, start).unwrap();
+        take_idea(&mut usage, &limits, start + 6 * HOUR_NS).unwrap();
+        let err = take_idea(&mut usage, &limits, start + 23 * HOUR_NS).unwrap_err();
+        let retry_at = Some(start + WINDOW_NS);
+        assert_eq!(err, FundVerseError::QuotaExceeded { quota: QuotaKind::IdeasPerDay, limit: 2, retry_at });
+        take_idea(&mut usage, &limits, start + 24 * HOUR_NS).unwrap();
+        assert_eq!(usage.ideas, [start + 6 * HOUR_NS, start + 24 * HOUR_NS], "the first idea stopped counting");
+
+        take_bytes(&mut usage, &limits, 60, start).unwrap();
+        let too_big = take_bytes(&mut usage, &limits, 81, start).unwrap_err();
+        assert!(matches!(too_big, FundVerseError::QuotaExceeded { quota: QuotaKind::DocSize, limit: 80, .. }));
+        assert!(take_bytes(&mut usage, &limits, 41, start + HOUR_NS).is_err());
+        take_bytes(&mut usage, &limits, 40, start + HOUR_NS).unwrap();
+        take_bytes(&mut usage, &limits, 60, start + WINDOW_NS).unwrap();
+        assert_eq!(usage.bytes(), 100);
+
+        let mut later = usage.clone();
+        later.expire(start + 3 * WINDOW_NS);
+        assert_eq!((later.bytes(), later.resets_at()), (0, None), "a quiet day resets everything");
+    }
+
+    #[test]
+    fn callers_are_charged_as_of_the_given_time_and_admins_are_exempt() {
+        let (admin, creator) = (Principal::from_slice(&[1]), Principal::from_slice(&[2]));
+        crate::ADMINS.with(|a| a.borrow_mut().insert(admin, ()));
+        store_limits(QuotaLimits { ideas_per_day: 1, upload_bytes_per_day: 100, docs_per_idea: 1, max_doc_bytes: 80 });
+        let now = 1_000 * WINDOW_NS;
+
+        charge_idea(&creator, now).unwrap();
+        assert!(charge_idea(&creator, now + HOUR_NS).is_err());
+        let usage = usage_at(&creator, now + HOUR_NS);
+        assert_eq!((usage.exempt, usage.ideas_created, usage.resets_at), (false, 1, Some(now + WINDOW_NS)));
+        assert_eq!(usage_at(&creator, now + WINDOW_NS).ideas_created, 0);
+        charge_idea(&creator, now + WINDOW_NS).unwrap();
+
+        let full = Idea { doc_ids: vec![1], ..crate::tests::test_idea("Solar Kiosk", "Kiosks", "energy") };
+        let err = charge_upload(&creator, &full, 10, now).unwrap_err();
+        assert!(matches!(err, FundVerseError::QuotaExceeded { quota: QuotaKind::DocsPerIdea, .. }));
+        charge_bytes(&creator, 80, now).unwrap();
+        assert_eq!(usage_at(&creator, now).bytes_uploaded, 80, "a failed upload charges nothing");
+
+        for _ in 0..3 {
+            charge_idea(&admin, now).unwrap();
+            charge_upload(&admin, &full, 80, now).unwrap();
+        }
+        let usage = usage_at(&admin, now);
+        assert_eq!((usage.exempt, usage.ideas_created, usage.bytes_uploaded), (true, 0, 0));
+    }
+}
@@ -0,0 +1,480 @@
+//! Staged idea review for categories that need more than one approve/reject decision.
+//!
+//! Admins give a category an ordered list of stages (e.g. Completeness, Legal, Final), each with
+//! a checklist. Moderators tick the current stage's items with `complete_review_item` and sign
+//! the stage off once every item is ticked; signing off the last stage approves the idea, and
+//! `approve_idea` refuses ideas reviewed in stages. Rejecting stays possible at any stage.
+//! Reverting a stage drops its sign-off and reopens every later stage. Categories without a
+//! pipeline keep the single approve/reject decision.
+//!
+//! Each idea under review keeps its own copy of the pipeline, taken when its review starts.
+//! Editing a category's pipeline moves those copies onto the new one only where the edit leaves
+//! alone every stage the idea already signed off; ideas past a changed stage finish on the
+//! pipeline they started with. Moving an idea to another category restarts its review.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{
+    decide_idea, ensure_admin, get_idea, not_found, EntityRef, Idea, IdeaStatus, Memory, IDEAS, MEMORY_MANAGER,
+};
+
+const MAX_REVIEW_STAGES: usize = 10;
+const MAX_CHECKLIST_ITEMS: usize = 20; // per stage
+const MAX_REVIEW_LABEL_LEN: usize = 200; // bytes; stage names, item ids and labels
+const MAX_REVIEW_NOTE_LEN: usize = 1_000; // bytes
+
+thread_local! {
+    // trimmed, lowercased category -> pipeline
+    static REVIEW_PIPELINES: RefCell<StableBTreeMap<String, ReviewPipeline, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(59)))
+        )
+    );
+
+    // idea_id -> its copy of the pipeline with progress; kept after the decision
+    static IDEA_REVIEWS: RefCell<StableBTreeMap<u64, IdeaReview, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(60)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChecklistItem {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReviewStage {
+    pub name: String,
+    pub items: Vec<ChecklistItem>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReviewPipeline {
+    pub category: String,
+    pub stages: Vec<ReviewStage>,
+    pub updated_at: u64, // ns
+}
+
+impl Storable for ReviewPipeline {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode ReviewPipeline"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode ReviewPipeline")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Who ticked an item or signed off a stage, and when.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReviewCheck {
+    pub by: Principal,
+    pub at: u64, // ns
+    pub note: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReviewItem {
+    pub id: String,
+    pub label: String,
+    pub completed: Option<ReviewCheck>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct StageReview {
+    pub name: String,
+    pub items: Vec<ReviewItem>,
+    pub signed_off: Option<ReviewCheck>,
+}
+
+impl StageReview {
+    fn open(stage: &ReviewStage) -> Self {
+        StageReview {
+            name: stage.name.clone(),
+            items: stage
+                .items
+                .iter()
+                .map(|item| ReviewItem { id: item.id.clone(), label: item.label.clone(), completed: None })
+                .collect(),
+            signed_off: None,
+        }
+    }
+
+    fn definition(&self) -> ReviewStage {
+        ReviewStage {
+            name: self.name.clone(),
+            items: self.items.iter().map(|i| ChecklistItem { id: i.id.clone(), label: i.label.clone() }).collect(),
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct IdeaReview {
+    pub idea_id: u64,
+    pub category: String,
+    pub stages: Vec<StageReview>,
+}
+
+impl Storable for IdeaReview {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode IdeaReview"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode IdeaReview")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl IdeaReview {
+    fn start(idea_id: u64, category: &str, stages: &[ReviewStage]) -> Self {
+        IdeaReview { idea_id, category: category.to_string(), stages: stages.iter().map(StageReview::open).collect() }
+    }
+
+    /// How many stages are signed off; they are always the first ones.
+    fn signed_off(&self) -> usize {
+        self.stages.iter().take_while(|s| s.signed_off.is_some()).count()
+    }
+
+    fn current_stage(&self) -> Option<&StageReview> {
+        self.stages.get(self.signed_off())
+    }
+}
+
+/// A pending idea in the moderation queue. `review` is None where the category has no pipeline.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReviewQueueEntry {
+    pub idea_id: u64,
+    pub title: String,
+    pub category: String,
+    pub submitted_at: u64, // ns
+    pub current_stage: Option<String>,
+    pub review: Option<IdeaReview>,
+}
+
+fn category_key(category: &str) -> String {
+    category.trim().to_lowercase()
+}
+
+fn pipeline_for(category: &str) -> Option<ReviewPipeline> {
+    REVIEW_PIPELINES.with(|p| p.borrow().get(&category_key(category)))
+}
+
+/// The idea's review, or a fresh one from its category's pipeline if it hasn't started (or the
+/// idea changed category since). None when the category reviews in a single decision.
+fn review_of(idea_id: u64, idea: &Idea) -> Option<IdeaReview> {
+    match IDEA_REVIEWS.with(|r| r.borrow().get(&idea_id)) {
+        Some(review) if category_key(&review.category) == category_key(&idea.category) => Some(review),
+        _ => pipeline_for(&idea.category).map(|p| IdeaReview::start(idea_id, &idea.category, &p.stages)),
+    }
+}
+
+/// Whether a pending idea can only be approved by signing off its review stages.
+pub(crate) fn reviews_in_stages(idea_id: u64, idea: &Idea) -> bool {
+    idea.status == IdeaStatus::Pending && review_of(idea_id, idea).is_some()
+}
+
+pub(crate) fn forget_idea(idea_id: u64) {
+    IDEA_REVIEWS.with(|r| r.borrow_mut().remove(&idea_id));
+}
+
+/// `review` moved onto an edited pipeline, or None if the edit changes a stage it already
+/// signed off (or leaves it nothing to sign off). Ticks carry over for items that keep their id
+/// in a stage that keeps its name and position.
+pub(crate) fn apply_edit(review: &IdeaReview, stages: &[ReviewStage]) -> Option<IdeaReview> {
+    let done = review.signed_off();
+    let before: Vec<ReviewStage> = review.stages.iter().map(StageReview::definition).collect();
+    let unchanged = before.iter().zip(stages).take_while(|(a, b)| a == b).count();
+    if before == stages || unchanged < done || stages.len() <= done {
+        return None;
+    }
+    let mut next = IdeaReview::start(review.idea_id, &review.category, stages);
+    for (stage, old) in next.stages.iter_mut().zip(&review.stages) {
+        if stage.name != old.name {
+            continue;
+        }
+        stage.signed_off = old.signed_off.clone();
+        for item in &mut stage.items {
+            item.completed = old.items.iter().find(|o| o.id == item.id).and_then(|o| o.completed.clone());
+        }
+    }
+    Some(next)
+}
+
+/// Trimmed copies of the stages, or why they don't make a pipeline.
+fn validate_pipeline(stages: Vec<ReviewStage>) -> Result<Vec<ReviewStage>, String> {
+    if stages.len() > MAX_REVIEW_STAGES {
+        return Err(format!("a pipeline has at most {} stages", MAX_REVIEW_STAGES));
+    }
+    let text = |value: &str, what: &str| {
+        let value = value.trim();
+        if value.is_empty() || value.len() > MAX_REVIEW_LABEL_LEN {
+            return Err(format!("{} must be 1-{} bytes", what, MAX_REVIEW_LABEL_LEN));
+        }
+        Ok(value.to_string())
+    };
+    let mut valid: Vec<ReviewStage> = Vec::with_capacity(stages.len());
+    for stage in stages {
+        let name = text(&stage.name, "stage name")?;
+        if valid.iter().any(|s| s.name == name) {
+            return Err(format!("stage '{}' appears twice", name));
+        }
+        if stage.items.is_empty() || stage.items.len() > MAX_CHECKLIST_ITEMS {
+            return Err(format!("stage '{}' needs 1-{} checklist items", name, MAX_CHECKLIST_ITEMS));
+        }
+        let mut items: Vec<ChecklistItem> = Vec::with_capacity(stage.items.len());
+        for item in stage.items {
+            let id = text(&item.id, "checklist item id")?;
+            if items.iter().any(|i| i.id == id) {
+                return Err(format!("item '{}' appears twice in stage '{}'", id, name));
+            }
+            items.push(ChecklistItem { id, label: text(&item.label, "checklist item label")? });
+        }
+        valid.push(ReviewStage { name, items });
+    }
+    Ok(valid)
+}
+
+fn check(note: Option<String>) -> Result<ReviewCheck, String> {
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    if note.as_ref().is_some_and(|n| n.len() > MAX_REVIEW_NOTE_LEN) {
+        return Err(format!("note must be at most {} bytes", MAX_REVIEW_NOTE_LEN));
+    }
+    Ok(ReviewCheck { by: ic_cdk::caller(), at: ic_cdk::api::time(), note })
+}
+
+/// The review of a pending idea reviewed in stages, for an admin about to act on it.
+fn pending_review(idea_id: u64) -> Result<IdeaReview, String> {
+    ensure_admin()?;
+    let idea = get_idea(idea_id).ok_or_else(|| not_found(EntityRef::Idea(idea_id)))?;
+    if idea.status != IdeaStatus::Pending {
+        return Err(format!("idea is not pending review ({:?})", idea.status));
+    }
+    review_of(idea_id, &idea).ok_or_else(|| "idea's category has no review pipeline; use approve_idea".into())
+}
+
+fn stage_index(review: &IdeaReview, stage: &str) -> Result<usize, String> {
+    review.stages.iter().position(|s| s.name == stage).ok_or_else(|| format!("no review stage named '{}'", stage))
+}
+
+/// The index of `stage` if it is the one currently under review.
+fn current_stage_index(review: &IdeaReview, stage: &str) -> Result<usize, String> {
+    let index = stage_index(review, stage)?;
+    let current = review.signed_off();
+    if index < current {
+        return Err(format!("stage '{}' is already signed off; revert it first", stage));
+    }
+    if index > current {
+        return Err(format!("stage '{}' opens once '{}' is signed off", stage, review.stages[current].name));
+    }
+    Ok(index)
+}
+
+fn save(review: IdeaReview) -> IdeaReview {
+    IDEA_REVIEWS.with(|r| r.borrow_mut().insert(review.idea_id, review.clone()));
+    review
+}
+
+/// Set a category's review pipeline (admins); an empty list removes it. Reviews in progress move
+/// to the new pipeline unless it changes a stage they already signed off, and finish on their own
+/// copy when it is removed. Returns how many moved.
+#[update]
+fn set_review_pipeline(category: String, stages: Vec<ReviewStage>) -> Result<u64, String> {
+    ensure_admin()?;
+    let key = category_key(&category);
+    if key.is_empty() {
+        return Err("give a category".into());
+    }
+    let stages = validate_pipeline(stages)?;
+    REVIEW_PIPELINES.with(|p| {
+        let mut p = p.borrow_mut();
+        if stages.is_empty() {
+            p.remove(&key);
+        } else {
+            let category = category.trim().to_string();
+            let pipeline = ReviewPipeline { category, stages: stages.clone(), updated_at: ic_cdk::api::time() };
+            p.insert(key.clone(), pipeline);
+        }
+    });
+
+    let in_progress: Vec<IdeaReview> = IDEA_REVIEWS.with(|r| {
+        r.borrow().iter().map(|(_, review)| review).filter(|review| category_key(&review.category) == key).collect()
+    });
+    let mut moved = 0;
+    for review in in_progress {
+        let pending = get_idea(review.idea_id)
+            .is_some_and(|idea| idea.status == IdeaStatus::Pending && category_key(&idea.category) == key);
+        if let Some(next) = apply_edit(&review, &stages).filter(|_| pending) {
+            save(next);
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+#[query]
+fn get_review_pipeline(category: String) -> Option<ReviewPipeline> {
+    pipeline_for(&category)
+}
+
+#[query]
+fn get_review_pipelines() -> Vec<ReviewPipeline> {
+    REVIEW_PIPELINES.with(|p| p.borrow().iter().map(|(_, pipeline)| pipeline).collect())
+}
+
+/// An idea's staged review, including one not started yet (admins). None for ideas whose
+/// category reviews in a single decision.
+#[query]
+fn get_idea_review(idea_id: u64) -> Result<Option<IdeaReview>, String> {
+    ensure_admin()?;
+    let idea = get_idea(idea_id).ok_or_else(|| not_found(EntityRef::Idea(idea_id)))?;
+    Ok(review_of(idea_id, &idea))
+}
+
+/// Pending ideas, oldest first, with their progress through the review stages (admins).
+#[query]
+fn get_review_queue() -> Result<Vec<ReviewQueueEntry>, String> {
+    ensure_admin()?;
+    let pending: Vec<(u64, Idea)> = IDEAS.with(|map| {
+        map.borrow().iter().filter(|(_, idea)| idea.status == IdeaStatus::Pending).collect()
+    });
+    Ok(pending
+        .into_iter()
+        .map(|(idea_id, idea)| {
+            let review = review_of(idea_id, &idea);
+            ReviewQueueEntry {
+                idea_id,
+                current_stage: review.as_ref().and_then(|r| r.current_stage()).map(|s| s.name.clone()),
+                review,
+                title: idea.title,
+                category: idea.category,
+                submitted_at: idea.created_at,
+            }
+        })
+        .collect())
+}
+
+/// Tick a checklist item of the stage an idea is currently in (admins). Ticking it again
+/// replaces the note.
+#[update]
+fn complete_review_item(
+    idea_id: u64,
+    stage: String,
+    item_id: String,
+    note: Option<String>,
+) -> Result<IdeaReview, String> {
+    let mut review = pending_review(idea_id)?;
+    let index = current_stage_index(&review, &stage)?;
+    let check = check(note)?;
+    let item = review.stages[index]
+        .items
+        .iter_mut()
+        .find(|i| i.id == item_id)
+        .ok_or_else(|| format!("stage '{}' has no checklist item '{}'", stage, item_id))?;
+    item.completed = Some(check);
+    Ok(save(review))
+}
+
+/// Sign off the stage an idea is currently in once all its items are ticked (admins). Signing
+/// off the last stage approves the idea.
+#[update]
+fn sign_off_review_stage(idea_id: u64, stage: String, note: Option<String>) -> Result<IdeaReview, String> {
+    let mut review = pending_review(idea_id)?;
+    let index = current_stage_index(&review, &stage)?;
+    let unticked: Vec<&str> =
+        review.stages[index].items.iter().filter(|i| i.completed.is_none()).map(|i| i.id.as_str()).collect();
+    if !unticked.is_empty() {
+        return Err(format!("stage '{}' has unticked items: {}", stage, unticked.join(", ")));
+    }
+    review.stages[index].signed_off = Some(check(note)?);
+    let last = index + 1 == review.stages.len();
+    let review = save(review);
+    if last {
+        decide_idea(ic_cdk::caller(), ic_cdk::api::time(), idea_id, IdeaStatus::Approved)?;
+    }
+    Ok(review)
+}
+
+/// Withdraw a stage's sign-off (admins), reopening it and every later stage. Ticks are kept.
+#[update]
+fn revert_review_stage(idea_id: u64, stage: String) -> Result<IdeaReview, String> {
+    let mut review = pending_review(idea_id)?;
+    let index = stage_index(&review, &stage)?;
+    if review.stages[index].signed_off.is_none() {
+        return Err(format!("stage '{}' is not signed off", stage));
+    }
+    for later in &mut review.stages[index..] {
+        later.signed_off = None;
+    }
+    Ok(save(review))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(name: &str, items: &[&str]) -> ReviewStage {
+        let item = |id: &&str| ChecklistItem { id: id.to_string(), label: format!("Check {}", id) };
+        ReviewStage { name: name.into(), items: items.iter().map(item).collect() }
+    }
+
+    fn ticked() -> Option<ReviewCheck> {
+        Some(ReviewCheck { by: Principal::anonymous(), at: 1, note: None })
+    }
+
+    /// A review with `done` stages signed off and every item ticked up to and including the next.
+    fn review(stages: &[ReviewStage], done: usize) -> IdeaReview {
+        let mut review = IdeaReview::start(7, "Health", stages);
+        for (i, s) in review.stages.iter_mut().enumerate().take(done + 1) {
+            s.items.iter_mut().for_each(|item| item.completed = ticked());
+            if i < done {
+                s.signed_off = ticked();
+            }
+        }
+        review
+    }
+
+    #[test]
+    fn edits_reach_reviews_only_before_the_changed_stage() {
+        let old = [stage("Completeness", &["docs"]), stage("Legal", &["kyc", "license"]), stage("Final", &["ok"])];
+        let mut legal_changed = old.clone();
+        legal_changed[1].items.push(ChecklistItem { id: "aml".into(), label: "AML screening".into() });
+
+        // In Legal: the edit lands, keeping Completeness signed off and the ticks Legal kept.
+        let moved = apply_edit(&review(&old, 1), &legal_changed).expect("not past Legal");
+        assert_eq!(moved.signed_off(), 1);
+        let legal: Vec<bool> = moved.stages[1].items.iter().map(|i| i.completed.is_some()).collect();
+        assert_eq!(legal, [true, true, false]);
+        assert!(moved.stages[2].items[0].completed.is_none());
+
+        // In Final: Legal is already signed off, so the review keeps the old pipeline.
+        assert_eq!(apply_edit(&review(&old, 2), &legal_changed), None);
+        assert_eq!(apply_edit(&review(&old, 1), &old), None, "nothing changed");
+        assert_eq!(apply_edit(&review(&old, 1), &old[..1]), None, "nothing left to sign off");
+        assert_eq!(apply_edit(&review(&old, 0), &[]), None, "pipeline removed");
+    }
+
+    #[test]
+    fn pipelines_are_trimmed_and_need_unique_named_stages_with_items() {
+        let padded = ReviewStage { name: " Legal ".into(), ..stage("x", &["kyc"]) };
+        assert_eq!(validate_pipeline(vec![padded]).unwrap()[0].name, "Legal");
+        assert!(validate_pipeline(vec![stage("Legal", &[])]).is_err());
+        assert!(validate_pipeline(vec![stage("Legal", &["a"]), stage("Legal", &["b"])]).is_err());
+        assert!(validate_pipeline(vec![stage("Legal", &["kyc", "kyc"])]).is_err());
+        assert!(validate_pipeline(vec![stage(" ", &["kyc"])]).is_err());
+        assert!(validate_pipeline(vec![stage("Legal", &["kyc"]); MAX_REVIEW_STAGES + 1]).is_err());
+        assert_eq!(validate_pipeline(vec![]), Ok(vec![]));
+    }
+}
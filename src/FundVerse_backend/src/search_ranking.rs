@@ -0,0 +1,278 @@
+//! Ranked campaign search.
+//!
+//! A campaign matches when the query is a substring of its title (the display title or the idea
+//! title), its tags (the idea category, the only tag ideas carry) or its description (the idea
+//! description or the campaign teaser). A match scores the text weight of its best matching
+//! field, plus boosts for
+//! - recency: full on launch day, fading to none by the end of the recency window;
+//! - funded: percent of the goal raised, capped at 100%;
+//! - velocity: share of the goal raised within the trending window, capped at 100%;
+//!
+//! and results are ordered by score with ties broken by campaign id, so a fixed state always
+//! ranks the same way. Scores are integers in weight × basis points: a title match at weight 100
+//! is worth 1_000_000. The weights live in stable memory and controllers tune them with
+//! `set_search_weights`.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableCell, Storable};
+
+use super::{
+    ensure_admin, ensure_controller, get_contributions_by_campaign, get_idea, now_secs, paginate, percent_bps,
+    takedown, to_card, Campaign, CampaignCard, Idea, Memory, PagedResult, CAMPAIGNS, MEMORY_MANAGER,
+};
+
+const FULL_BPS: u64 = 10_000;
+const DAY_SECS: u64 = 86_400;
+
+thread_local! {
+    static SEARCH_WEIGHTS: RefCell<StableCell<SearchWeights, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(56))),
+            SearchWeights::default(),
+        ).expect("init search weights")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct SearchWeights {
+    pub title: u64,
+    pub tags: u64,
+    pub description: u64,
+    pub recency: u64,
+    pub recency_window_days: u64,
+    pub funded: u64,
+    pub velocity: u64,
+    pub velocity_window_days: u64,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        SearchWeights {
+            title: 100,
+            tags: 60,
+            description: 30,
+            recency: 40,
+            recency_window_days: 60,
+            funded: 30,
+            velocity: 50,
+            velocity_window_days: 7,
+        }
+    }
+}
+
+impl Storable for SearchWeights {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode SearchWeights"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode SearchWeights")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// How a result's score was made up, for tuning the weights.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    pub text: u64,
+    pub recency: u64,
+    pub funded: u64,
+    pub velocity: u64,
+    pub total: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignSearchHit {
+    pub card: CampaignCard,
+    pub score: Option<ScoreBreakdown>, // only on admin debug searches
+}
+
+/// What scoring looks at for one campaign; text fields are lowercase.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Candidate {
+    pub title: String,
+    pub tags: String,
+    pub description: String,
+    pub launched_at: Option<u64>, // seconds
+    pub raised: u64,
+    pub goal: u64,
+    pub raised_recently: u64, // within the velocity window
+}
+
+fn candidate(w: &SearchWeights, c: &Campaign, idea: &Idea, now: u64) -> Candidate {
+    let since_ns = now.saturating_sub(w.velocity_window_days.saturating_mul(DAY_SECS)).saturating_mul(1_000_000_000);
+    let raised_recently = get_contributions_by_campaign(c.id)
+        .iter()
+        .filter(|p| p.timestamp >= since_ns)
+        .fold(0u64, |sum, p| sum.saturating_add(p.amount));
+    let title = match &c.display_title {
+        Some(display) => format!("{}\n{}", display, idea.title),
+        None => idea.title.clone(),
+    };
+    Candidate {
+        title: title.to_lowercase(),
+        tags: idea.category.to_lowercase(),
+        description: format!("{}\n{}", idea.description, c.teaser.as_deref().unwrap_or("")).to_lowercase(),
+        launched_at: c.launched_at,
+        raised: c.amount_raised,
+        goal: c.goal,
+        raised_recently,
+    }
+}
+
+/// Score one campaign against a lowercase, non-empty `needle`; None if no field matches.
+pub(crate) fn score(w: &SearchWeights, needle: &str, c: &Candidate, now: u64) -> Option<ScoreBreakdown> {
+    let text_weight = [(&c.title, w.title), (&c.tags, w.tags), (&c.description, w.description)]
+        .into_iter()
+        .filter(|(field, _)| field.contains(needle))
+        .map(|(_, weight)| weight)
+        .max()?;
+    let window = w.recency_window_days.saturating_mul(DAY_SECS).max(1);
+    let recency_bps = c.launched_at.map_or(0, |launched| {
+        let age = now.saturating_sub(launched);
+        (window.saturating_sub(age) as u128 * FULL_BPS as u128 / window as u128) as u64
+    });
+    let funded_bps = percent_bps(c.raised, c.goal).min(FULL_BPS);
+    let velocity_bps = percent_bps(c.raised_recently, c.goal).min(FULL_BPS);
+
+    let text = text_weight.saturating_mul(FULL_BPS);
+    let recency = w.recency.saturating_mul(recency_bps);
+    let funded = w.funded.saturating_mul(funded_bps);
+    let velocity = w.velocity.saturating_mul(velocity_bps);
+    let total = text.saturating_add(recency).saturating_add(funded).saturating_add(velocity);
+    Some(ScoreBreakdown { text, recency, funded, velocity, total })
+}
+
+/// Every matching campaign with its card and score, best first.
+fn ranked(query: &str) -> Result<Vec<(CampaignCard, ScoreBreakdown)>, String> {
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return Err("give a search query".into());
+    }
+    let w = SEARCH_WEIGHTS.with(|w| w.borrow().get().clone());
+    let now = now_secs();
+    let campaigns: Vec<Campaign> = CAMPAIGNS.with(|store| {
+        store.borrow().iter().filter(|(_, c)| !takedown::is_campaign_taken_down(c)).map(|(_, c)| c).collect()
+    });
+    let mut hits: Vec<(u64, CampaignCard, ScoreBreakdown)> = campaigns
+        .iter()
+        .filter_map(|c| {
+            let idea = get_idea(c.idea_id)?;
+            let breakdown = score(&w, &needle, &candidate(&w, c, &idea, now), now)?;
+            Some((c.id, to_card(c, &idea), breakdown))
+        })
+        .collect();
+    hits.sort_by(|(a_id, _, a), (b_id, _, b)| b.total.cmp(&a.total).then(a_id.cmp(b_id)));
+    Ok(hits.into_iter().map(|(_, card, breakdown)| (card, breakdown)).collect())
+}
+
+/// Search campaigns by title, tags and description, best match first (see the module docs for
+/// the ranking). `debug` adds each result's score breakdown and is for admins only.
+#[query]
+fn search_campaigns(
+    query: String,
+    debug: bool,
+    offset: u64,
+    limit: u64,
+) -> Result<PagedResult<CampaignSearchHit>, String> {
+    if debug {
+        ensure_admin()?;
+    }
+    let hits = ranked(&query)?
+        .into_iter()
+        .map(|(card, breakdown)| CampaignSearchHit { card, score: debug.then_some(breakdown) })
+        .collect();
+    Ok(paginate(hits, offset, limit))
+}
+
+#[query]
+fn get_search_weights() -> SearchWeights {
+    SEARCH_WEIGHTS.with(|w| w.borrow().get().clone())
+}
+
+/// Retune search ranking (controllers only); applies to the next search.
+#[update]
+fn set_search_weights(weights: SearchWeights) -> Result<(), String> {
+    ensure_controller()?;
+    if weights.recency_window_days == 0 || weights.velocity_window_days == 0 {
+        return Err("search windows must be at least one day".into());
+    }
+    SEARCH_WEIGHTS
+        .with(|w| w.borrow_mut().set(weights))
+        .map(|_| ())
+        .map_err(|e| format!("failed to store search weights: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn fixture(title: &str, tags: &str, description: &str) -> Candidate {
+        Candidate {
+            title: title.into(),
+            tags: tags.into(),
+            description: description.into(),
+            goal: 1_000,
+            ..Candidate::default()
+        }
+    }
+
+    #[test]
+    fn text_matches_weigh_title_over_tags_over_description() {
+        let w = SearchWeights::default();
+        let in_title = fixture("solar kiosk", "energy", "phone charging");
+        let in_tags = fixture("kiosk", "solar", "phone charging");
+        let in_description = fixture("kiosk", "energy", "solar phone charging");
+        let text = |c: &Candidate| score(&w, "solar", c, NOW).map(|s| s.text);
+        assert_eq!(text(&in_title), Some(1_000_000));
+        assert_eq!(text(&in_tags), Some(600_000));
+        assert_eq!(text(&in_description), Some(300_000));
+        assert_eq!(text(&fixture("solar kiosk", "solar", "solar")), Some(1_000_000), "best field only");
+        assert_eq!(score(&w, "wind", &in_title, NOW), None);
+    }
+
+    #[test]
+    fn boosts_scale_with_recency_funding_and_velocity() {
+        let w = SearchWeights::default();
+        let today = Candidate { launched_at: Some(NOW), raised: 500, raised_recently: 250, ..fixture("solar", "", "") };
+        assert_eq!(
+            score(&w, "solar", &today, NOW),
+            Some(ScoreBreakdown {
+                text: 1_000_000,
+                recency: 400_000,
+                funded: 150_000,
+                velocity: 125_000,
+                total: 1_675_000,
+            })
+        );
+
+        let half_window = Candidate { launched_at: Some(NOW - 30 * DAY_SECS), ..today.clone() };
+        assert_eq!(score(&w, "solar", &half_window, NOW).unwrap().recency, 200_000);
+        let two_years = Candidate { launched_at: Some(NOW - 730 * DAY_SECS), ..today.clone() };
+        assert_eq!(score(&w, "solar", &two_years, NOW).unwrap().recency, 0);
+        let unknown_launch = Candidate { launched_at: None, ..today.clone() };
+        assert_eq!(score(&w, "solar", &unknown_launch, NOW).unwrap().recency, 0);
+
+        let overfunded = Candidate { raised: 5_000, raised_recently: 5_000, ..today.clone() };
+        let s = score(&w, "solar", &overfunded, NOW).unwrap();
+        assert_eq!((s.funded, s.velocity), (300_000, 500_000), "both capped at the goal");
+    }
+
+    #[test]
+    fn a_hot_new_campaign_outranks_an_old_dead_one_on_the_same_field() {
+        let w = SearchWeights::default();
+        let dead = Candidate { launched_at: Some(NOW - 730 * DAY_SECS), raised: 40, ..fixture("solar", "", "") };
+        let hot = Candidate { launched_at: Some(NOW - DAY_SECS), raised: 300, raised_recently: 300, ..dead.clone() };
+        assert!(score(&w, "solar", &hot, NOW).unwrap().total > score(&w, "solar", &dead, NOW).unwrap().total);
+        let max = SearchWeights { title: u64::MAX, recency: u64::MAX, ..w };
+        assert_eq!(score(&max, "solar", &hot, NOW).unwrap().total, u64::MAX, "saturates");
+    }
+}
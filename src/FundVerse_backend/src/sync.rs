@@ -0,0 +1,150 @@
+//! Change feed for read-only replicas (e.g. an analytics canister).
+//!
+//! Every mutation of an idea, campaign, doc or comment appends a `ChangeRecord` under the next
+//! global version. Versions come from a single stable counter, so the feed has no gaps; a replica
+//! polls `get_changes_since` and fetches full entities through the regular getters.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, StableCell, Storable};
+
+use super::{ensure_controller, fnv1a, get_campaign, get_doc, EntityRef, Memory, IDEAS, IDEA_COMMENTS, MEMORY_MANAGER};
+
+const DEFAULT_HORIZON_NS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000; // 30 days
+const PRUNE_PER_WRITE: usize = 8; // keeps each mutation's extra work bounded
+pub(crate) const MAX_BATCH: u32 = 1000;
+
+thread_local! {
+    static CHANGES: RefCell<StableBTreeMap<u64, ChangeRecord, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(14)))
+        )
+    );
+
+    static VERSION: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(15))),
+            0,
+        ).expect("init sync version")
+    );
+
+    static HORIZON_NS: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(16))),
+            DEFAULT_HORIZON_NS,
+        ).expect("init sync horizon")
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ChangeRecord {
+    pub version: u64,
+    pub entity: EntityRef,
+    pub kind: ChangeKind,
+    pub value_hash: u64, // FNV-1a of the entity's candid encoding; 0 when deleted
+    pub at: u64,
+}
+
+impl Storable for ChangeRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode ChangeRecord"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode ChangeRecord")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ChangeBatch {
+    pub changes: Vec<ChangeRecord>, // ascending versions, all > the requested one
+    pub current_version: u64,
+    pub resync_required: bool, // records after the requested version were pruned
+}
+
+fn value_hash(entity: EntityRef) -> u64 {
+    let bytes = match entity {
+        EntityRef::Idea(id) => IDEAS.with(|m| m.borrow().get(&id)).map(|i| i.to_bytes().into_owned()),
+        EntityRef::Campaign(id) => get_campaign(id).map(|c| Encode!(&c).expect("encode Campaign")),
+        EntityRef::Doc(id) => get_doc(id).map(|d| Encode!(&d).expect("encode Doc")),
+        EntityRef::Comment(id) => IDEA_COMMENTS.with(|m| m.borrow().get(&id)).map(|c| c.to_bytes().into_owned()),
+    };
+    bytes.map(|b| fnv1a(0, &b)).unwrap_or(0)
+}
+
+/// Append a change for `entity`. Call after the mutation, outside any borrow of the entity's store.
+pub(crate) fn record_change(entity: EntityRef, kind: ChangeKind) {
+    record_change_at(ic_cdk::api::time(), entity, kind);
+}
+
+/// `record_change` as of `now` (ns).
+pub(crate) fn record_change_at(now: u64, entity: EntityRef, kind: ChangeKind) {
+    let value_hash = if kind == ChangeKind::Deleted { 0 } else { value_hash(entity) };
+    let version = VERSION.with(|v| {
+        let mut v = v.borrow_mut();
+        let next = v.get() + 1;
+        v.set(next).expect("store sync version");
+        next
+    });
+    let horizon = HORIZON_NS.with(|h| *h.borrow().get());
+    CHANGES.with(|c| {
+        let mut c = c.borrow_mut();
+        c.insert(version, ChangeRecord { version, entity, kind, value_hash, at: now });
+        let expired: Vec<u64> = c
+            .iter()
+            .take(PRUNE_PER_WRITE)
+            .take_while(|(_, r)| now.saturating_sub(r.at) > horizon)
+            .map(|(k, _)| k)
+            .collect();
+        for k in expired {
+            c.remove(&k);
+        }
+    });
+}
+
+#[query]
+fn get_current_version() -> u64 {
+    VERSION.with(|v| *v.borrow().get())
+}
+
+/// Changes with version > `version`, oldest first, at most `limit` (capped at 1000).
+#[query]
+fn get_changes_since(version: u64, limit: u32) -> ChangeBatch {
+    let current_version = get_current_version();
+    let limit = limit.clamp(1, MAX_BATCH) as usize;
+    CHANGES.with(|c| {
+        let c = c.borrow();
+        let oldest = c.first_key_value().map(|(k, _)| k).unwrap_or(current_version + 1);
+        if version < current_version && version + 1 < oldest {
+            return ChangeBatch { changes: vec![], current_version, resync_required: true };
+        }
+        let changes = c.range(version.saturating_add(1)..).take(limit).map(|(_, r)| r).collect();
+        ChangeBatch { changes, current_version, resync_required: false }
+    })
+}
+
+/// How long change records are kept (controllers only).
+#[update]
+fn set_sync_horizon(secs: u64) -> Result<(), String> {
+    ensure_controller()?;
+    if secs == 0 {
+        return Err("horizon must be > 0".into());
+    }
+    HORIZON_NS
+        .with(|h| h.borrow_mut().set(secs.saturating_mul(1_000_000_000)))
+        .map(|_| ())
+        .map_err(|e| format!("failed to store horizon: {:?}", e))
+}
@@ -0,0 +1,307 @@
+//! Idea syndication: registered partner canisters (e.g. incubators) push vetted ideas in
+//! programmatically. Syndicated ideas go through the same validation and land in "pending".
+//!
+//! A partner's external ids form its namespace: each names one idea, and syndicating the same id
+//! again updates that idea while it is still pending. Campaigns on a syndicated idea carry its
+//! external id too, so partners can look them up without keeping our ids. The id resolves to the
+//! idea's latest campaign; a new campaign can take it over once the previous one has ended.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{
+    card_of, dry_run_report, ensure_controller, fingerprint, get_campaign, get_idea, index_fingerprint, insert_idea,
+    lifecycle, new_pending_idea, now_secs, record_change, redeem_confirmation, unindex_fingerprint, CampaignCard,
+    CampaignLifecycle, ChangeKind, DryRunReport, EntityRef, FieldError, Idea, IdeaStatus, Memory, IDEAS,
+    MEMORY_MANAGER,
+};
+
+pub(crate) const MAX_EXTERNAL_ID_LEN: usize = 64;
+
+thread_local! {
+    static PARTNERS: RefCell<StableBTreeMap<Principal, Partner, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(12)))
+        )
+    );
+
+    // "<partner principal>/<external id>" -> idea_id
+    static SYNDICATED: RefCell<StableBTreeMap<String, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(13)))
+        )
+    );
+
+    // "<partner principal>/<external id>" -> the syndicated idea's latest campaign
+    static CAMPAIGN_REFS: RefCell<StableBTreeMap<String, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(63)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Partner {
+    pub name: String,
+    pub quota: u64, // total ideas the partner may submit
+    pub used: u64,
+    pub registered_at: u64,
+}
+
+impl Storable for Partner {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Partner"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode Partner")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SyndicatedIdea {
+    pub external_id: String, // the partner's own id, unique per partner
+    pub title: String,
+    pub description: String,
+    pub funding_goal: u64,
+    pub legal_entity: String,
+    pub contact_info: String,
+    pub category: String,
+    pub business_registration: u8,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum SyndicationError {
+    NotRegistered,
+    QuotaExhausted { quota: u64 },
+    DuplicateExternalId { idea_id: u64 }, // the id names an idea that was already reviewed
+    InvalidInput(String),
+    ValidationFailed(Vec<FieldError>), // every invalid idea field, not just the first
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SyndicatedIdeaStatus {
+    pub external_id: String,
+    pub idea_id: u64,
+    pub status: Option<IdeaStatus>, // None if the idea is gone or its record is unreadable
+}
+
+fn index_key(partner: &Principal, external_id: &str) -> String {
+    format!("{}/{}", partner.to_text(), external_id)
+}
+
+/// Register a partner canister or update its name/quota (controllers only). Usage so far is kept.
+#[update]
+fn register_syndication_partner(partner: Principal, name: String, quota: u64) -> Result<(), String> {
+    ensure_controller()?;
+    let now = ic_cdk::api::time();
+    PARTNERS.with(|p| {
+        let mut p = p.borrow_mut();
+        let used = p.get(&partner).map(|existing| existing.used).unwrap_or(0);
+        p.insert(partner, Partner { name, quota, used, registered_at: now });
+    });
+    Ok(())
+}
+
+/// Stop accepting submissions from a partner (controllers only). Its ideas stay.
+/// Dry-run first; the live run needs the dry run's confirmation token.
+#[update]
+fn remove_syndication_partner(
+    partner: Principal,
+    dry_run: bool,
+    confirmation_token: Option<String>,
+) -> Result<DryRunReport, String> {
+    ensure_controller()?;
+    if !PARTNERS.with(|p| p.borrow().contains_key(&partner)) {
+        return Err("partner not registered".into());
+    }
+    let partner_text = partner.to_text();
+    let params = [partner_text.as_str()];
+    if dry_run {
+        return Ok(dry_run_report("remove_syndication_partner", &params, &[]));
+    }
+    redeem_confirmation("remove_syndication_partner", &params, confirmation_token)?;
+    PARTNERS.with(|p| p.borrow_mut().remove(&partner));
+    Ok(DryRunReport { dry_run: false, affected: 1, sample_ids: vec![], confirmation_token: None })
+}
+
+pub(crate) fn is_partner(principal: &Principal) -> bool {
+    PARTNERS.with(|p| p.borrow().contains_key(principal))
+}
+
+#[query]
+fn get_syndication_partners() -> Result<Vec<(Principal, Partner)>, String> {
+    ensure_controller()?;
+    Ok(PARTNERS.with(|p| p.borrow().iter().collect()))
+}
+
+/// The external id a new campaign on `idea` carries, if the idea was syndicated. Fails if a
+/// running campaign still holds it.
+pub(crate) fn campaign_ref(idea: &Idea) -> Result<Option<String>, String> {
+    let Some(external_ref) = &idea.external_ref else {
+        return Ok(None);
+    };
+    let held_by = CAMPAIGN_REFS.with(|r| r.borrow().get(&index_key(&idea.owner, external_ref)));
+    if let Some(campaign) = held_by.and_then(get_campaign) {
+        if lifecycle(&campaign, now_secs()) == CampaignLifecycle::Active {
+            return Err(format!("external ref '{}' belongs to running campaign {}", external_ref, campaign.id));
+        }
+    }
+    Ok(Some(external_ref.clone()))
+}
+
+pub(crate) fn bind_campaign_ref(partner: &Principal, external_ref: &str, campaign_id: u64) {
+    CAMPAIGN_REFS.with(|r| r.borrow_mut().insert(index_key(partner, external_ref), campaign_id));
+}
+
+fn campaign_by_ref(partner: &Principal, external_ref: &str) -> Option<CampaignCard> {
+    let campaign_id = CAMPAIGN_REFS.with(|r| r.borrow().get(&index_key(partner, external_ref.trim())))?;
+    get_campaign(campaign_id).as_ref().and_then(card_of)
+}
+
+/// Resolve `/partners/{principal}/campaigns/{external id}.json` (the id percent-encoded).
+pub(crate) fn campaign_id_for_path(path: &str) -> Option<u64> {
+    let (partner, rest) = path.split_once("/campaigns/")?;
+    let partner = Principal::from_text(partner).ok()?;
+    let external_ref = percent_decode(rest.strip_suffix(".json")?)?;
+    CAMPAIGN_REFS.with(|r| r.borrow().get(&index_key(&partner, &external_ref)))
+}
+
+fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Apply a re-syndicated payload to the pending idea its external id already names.
+fn resyndicate(idea_id: u64, existing: Idea, payload: SyndicatedIdea) -> Result<u64, SyndicationError> {
+    if existing.status != IdeaStatus::Pending {
+        return Err(SyndicationError::DuplicateExternalId { idea_id });
+    }
+    let fresh = new_pending_idea(
+        payload.title,
+        payload.description,
+        payload.funding_goal,
+        payload.legal_entity,
+        payload.contact_info,
+        payload.category,
+        payload.business_registration,
+        existing.owner,
+    )
+    .map_err(SyndicationError::ValidationFailed)?;
+    let idea = Idea {
+        created_at: existing.created_at,
+        doc_ids: existing.doc_ids,
+        external_ref: existing.external_ref,
+        ..fresh
+    };
+    unindex_fingerprint(idea_id);
+    index_fingerprint(idea_id, fingerprint(&idea.title, &idea.description));
+    IDEAS.with(|ideas| ideas.borrow_mut().insert(idea_id, idea));
+    record_change(EntityRef::Idea(idea_id), ChangeKind::Updated);
+    Ok(idea_id)
+}
+
+/// Create a pending idea on behalf of the calling partner canister, or update the one syndicated
+/// earlier under the same external id while it is still pending (which uses no quota).
+#[update]
+fn syndicate_idea(payload: SyndicatedIdea) -> Result<u64, SyndicationError> {
+    let partner = ic_cdk::caller();
+    let mut record = PARTNERS
+        .with(|p| p.borrow().get(&partner))
+        .ok_or(SyndicationError::NotRegistered)?;
+    let external_id = payload.external_id.trim().to_string();
+    if external_id.is_empty() || external_id.len() > MAX_EXTERNAL_ID_LEN {
+        return Err(SyndicationError::InvalidInput(format!(
+            "external_id must be 1-{} bytes",
+            MAX_EXTERNAL_ID_LEN
+        )));
+    }
+    let key = index_key(&partner, &external_id);
+    if let Some(idea_id) = SYNDICATED.with(|s| s.borrow().get(&key)) {
+        if let Some(existing) = get_idea(idea_id) {
+            return resyndicate(idea_id, existing, payload);
+        }
+    }
+    if record.used >= record.quota {
+        return Err(SyndicationError::QuotaExhausted { quota: record.quota });
+    }
+
+    let mut idea = new_pending_idea(
+        payload.title,
+        payload.description,
+        payload.funding_goal,
+        payload.legal_entity,
+        payload.contact_info,
+        payload.category,
+        payload.business_registration,
+        partner,
+    )
+    .map_err(SyndicationError::ValidationFailed)?;
+    idea.external_ref = Some(external_id);
+    let idea_id = insert_idea(idea);
+
+    SYNDICATED.with(|s| s.borrow_mut().insert(key, idea_id));
+    record.used += 1;
+    PARTNERS.with(|p| p.borrow_mut().insert(partner, record));
+    Ok(idea_id)
+}
+
+/// Review status of every idea the calling partner has submitted.
+#[query]
+fn get_syndicated_ideas_status() -> Vec<SyndicatedIdeaStatus> {
+    let prefix = index_key(&ic_cdk::caller(), "");
+    let entries: Vec<(String, u64)> = SYNDICATED.with(|s| {
+        s.borrow()
+            .range(prefix.clone()..)
+            .take_while(|(k, _)| k.starts_with(&prefix))
+            .collect()
+    });
+    entries
+        .into_iter()
+        .map(|(key, idea_id)| SyndicatedIdeaStatus {
+            external_id: key[prefix.len()..].to_string(),
+            idea_id,
+            status: get_idea(idea_id).map(|i| i.status),
+        })
+        .collect()
+}
+
+/// The card of the campaign a partner's external id currently resolves to (see the module docs).
+#[query]
+fn get_campaign_by_external_ref(partner: Principal, external_ref: String) -> Option<CampaignCard> {
+    campaign_by_ref(&partner, &external_ref)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ref_paths_decode_percent_escapes() {
+        assert_eq!(percent_decode("inc%2F42%20solar").as_deref(), Some("inc/42 solar"));
+        assert_eq!(percent_decode("caf%C3%A9").as_deref(), Some("café"));
+        assert_eq!(percent_decode("plain-ref_1").as_deref(), Some("plain-ref_1"));
+        for bad in ["50%", "%2", "%zz", "%FF"] {
+            assert_eq!(percent_decode(bad), None, "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Legal takedowns: removing an idea without stranding the money and records around it.
+//!
+//! `delete_idea` is for owners and refuses ideas that have campaigns. A takedown is the admin
+//! path for content that has to go regardless: in one message the idea and its comments are
+//! removed, every campaign of the idea becomes `TakenDown` with refunds queued and its updates and
+//! comments dropped, and its docs move to a legal hold readable by admins only. Public reads then
+//! show a tombstone ("Removed for legal reasons") in place of the idea, its campaigns and docs. Outside a takedown no campaign
+//! may reference a missing idea; `get_orphaned_campaigns` reports any that do.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+
+use super::{
+    campaign_feed, check_admin, ends_in_secs, ensure_admin, get_idea, issue_confirmation_at, not_found, now_secs,
+    record_change_at, redeem_confirmation_at, remove_idea_records, Campaign, CampaignCard, CampaignLifecycle,
+    ChangeKind, Doc, EntityRef, Memory, CAMPAIGNS, DOCS, IDEAS, MEMORY_MANAGER,
+};
+
+/// Shown publicly wherever taken-down content used to be.
+pub(crate) const TAKEDOWN_NOTICE: &str = "Removed for legal reasons";
+
+pub(crate) const MAX_TAKEDOWN_REASON_LEN: usize = 1000; // bytes; admins only
+
+thread_local! {
+    // idea_id -> what was taken down, and why
+    static TAKEDOWNS: RefCell<StableBTreeMap<u64, Takedown, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(51)))
+        )
+    );
+
+    // doc_id -> doc kept for legal hold, out of every public read
+    static HELD_DOCS: RefCell<StableBTreeMap<u64, Doc, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(52)))
+        )
+    );
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Takedown {
+    pub idea_id: u64,
+    pub title: Option<String>, // None if the idea was already gone (orphaned campaigns adopted)
+    pub reason: String,        // admins only
+    pub taken_down_by: Principal,
+    pub taken_down_at: u64, // ns
+    pub campaign_ids: Vec<u64>,
+    pub held_doc_ids: Vec<u64>,
+}
+
+/// What `takedown_idea` did, or on a dry run what it would do.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TakedownReport {
+    pub dry_run: bool,
+    pub takedown: Takedown, // on a dry run, what would be recorded: the campaigns stopped, docs held
+    pub confirmation_token: Option<String>, // issued by dry runs only
+}
+
+impl Storable for Takedown {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).expect("encode Takedown"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).expect("decode Takedown")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// The public side of a takedown.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct TakedownNotice {
+    pub idea_id: u64,
+    pub notice: String,
+    pub taken_down_at: u64, // ns
+}
+
+pub(crate) fn is_taken_down(idea_id: u64) -> bool {
+    TAKEDOWNS.with(|t| t.borrow().contains_key(&idea_id))
+}
+
+pub(crate) fn is_held_doc(doc_id: u64) -> bool {
+    HELD_DOCS.with(|d| d.borrow().contains_key(&doc_id))
+}
+
+pub(crate) fn is_campaign_taken_down(c: &Campaign) -> bool {
+    c.status == Some(CampaignLifecycle::TakenDown)
+}
+
+/// What a taken-down campaign shows in listings: its totals (backers need them for refunds) and
+/// nothing from the removed idea.
+pub(crate) fn tombstone_card(c: &Campaign) -> CampaignCard {
+    let now = now_secs();
+    let remaining = ends_in_secs(c.end_date, now);
+    CampaignCard {
+        id: c.id,
+        idea_id: c.idea_id,
+        title: TAKEDOWN_NOTICE.to_string(),
+        idea_title: TAKEDOWN_NOTICE.to_string(),
+        teaser: None,
+        category: String::new(),
+        amount_raised: c.amount_raised,
+        goal: c.goal,
+        end_date: c.end_date,
+        days_left: remaining.div_euclid(86_400),
+        ends_in_secs: remaining,
+        status: CampaignLifecycle::TakenDown,
+        round: c.round.clone(),
+        paused: false,
+        pause_reason: None,
+        end_hour_utc: c.end_hour_utc,
+        requested_end_date: c.requested_end_date,
+        partners: vec![],
+        fiat_goal: c.fiat_goal.clone(),
+        currency: c.currency(),
+    }
+}
+
+/// Campaigns pointing at an idea that doesn't exist, other than taken-down ones. Should be empty.
+pub(crate) fn orphaned_campaigns() -> Vec<u64> {
+    CAMPAIGNS.with(|store| {
+        store
+            .borrow()
+            .iter()
+            .filter(|(_, c)| !is_campaign_taken_down(c) && !IDEAS.with(|i| i.borrow().contains_key(&c.idea_id)))
+            .map(|(id, _)| id)
+            .collect()
+    })
+}
+
+/// Take an idea down for legal reasons (admins). Its campaigns stop and queue refunds for
+/// everything raised, its docs go to legal hold and the idea and its comments are removed. Also
+/// accepts the id of an idea that is already gone, to settle campaigns it left behind.
+/// Dry-run first: it lists the campaigns and docs the takedown would touch, and the live run
+/// needs its confirmation token.
+#[update]
+fn takedown_idea(
+    idea_id: u64,
+    reason: String,
+    dry_run: bool,
+    confirmation_token: Option<String>,
+) -> Result<TakedownReport, String> {
+    takedown_idea_at(ic_cdk::caller(), ic_cdk::api::time(), idea_id, reason, dry_run, confirmation_token)
+}
+
+/// `takedown_idea` by `caller` at `now` (ns).
+fn takedown_idea_at(
+    caller: Principal,
+    now: u64,
+    idea_id: u64,
+    reason: String,
+    dry_run: bool,
+    confirmation_token: Option<String>,
+) -> Result<TakedownReport, String> {
+    check_admin(&caller)?;
+    let reason = reason.trim().to_string();
+    if reason.is_empty() || reason.len() > MAX_TAKEDOWN_REASON_LEN {
+        return Err(format!("reason must be 1-{} bytes", MAX_TAKEDOWN_REASON_LEN));
+    }
+    if is_taken_down(idea_id) {
+        return Err("idea was already taken down".into());
+    }
+    let idea = get_idea(idea_id);
+    let campaigns: Vec<Campaign> = CAMPAIGNS.with(|store| {
+        store.borrow().iter().filter(|(_, c)| c.idea_id == idea_id).map(|(_, c)| c).collect()
+    });
+    if idea.is_none() && campaigns.is_empty() {
+        return Err(not_found(EntityRef::Idea(idea_id)));
+    }
+    let ids = idea_id.to_string();
+    if dry_run {
+        let doc_ids = idea.iter().flat_map(|i| i.doc_ids.iter().copied());
+        let held_doc_ids = doc_ids.filter(|id| DOCS.with(|docs| docs.borrow().contains_key(id))).collect();
+        let takedown = takedown_record(caller, now, idea_id, idea.map(|i| i.title), reason, &campaigns, held_doc_ids);
+        let confirmation_token = Some(issue_confirmation_at(caller, now, "takedown_idea", &[&ids]));
+        return Ok(TakedownReport { dry_run, takedown, confirmation_token });
+    }
+    redeem_confirmation_at(caller, now, "takedown_idea", &[&ids], confirmation_token)?;
+
+    for mut c in campaigns.iter().cloned() {
+        c.status = Some(CampaignLifecycle::TakenDown);
+        c.refund_pending = Some(c.amount_raised > 0);
+        CAMPAIGNS.with(|store| store.borrow_mut().insert(c.id, c));
+    }
+    for c in &campaigns {
+        campaign_feed::forget_campaign(c.id);
+        record_change_at(now, EntityRef::Campaign(c.id), ChangeKind::Updated);
+    }
+    let mut held_doc_ids = vec![];
+    for doc_id in idea.iter().flat_map(|i| i.doc_ids.iter()) {
+        if let Some(doc) = DOCS.with(|docs| docs.borrow_mut().remove(doc_id)) {
+            HELD_DOCS.with(|held| held.borrow_mut().insert(*doc_id, doc));
+            record_change_at(now, EntityRef::Doc(*doc_id), ChangeKind::Deleted);
+            held_doc_ids.push(*doc_id);
+        }
+    }
+    if let Some(idea) = &idea {
+        remove_idea_records(idea_id, idea, now);
+    }
+
+    let takedown = takedown_record(caller, now, idea_id, idea.map(|i| i.title), reason, &campaigns, held_doc_ids);
+    TAKEDOWNS.with(|t| t.borrow_mut().insert(idea_id, takedown.clone()));
+    Ok(TakedownReport { dry_run, takedown, confirmation_token: None })
+}
+
+fn takedown_record(
+    caller: Principal,
+    now: u64,
+    idea_id: u64,
+    title: Option<String>,
+    reason: String,
+    campaigns: &[Campaign],
+    held_doc_ids: Vec<u64>,
+) -> Takedown {
+    Takedown {
+        idea_id,
+        title,
+        reason,
+        taken_down_by: caller,
+        taken_down_at: now,
+        campaign_ids: campaigns.iter().map(|c| c.id).collect(),
+        held_doc_ids,
+    }
+}
+
+/// The public tombstone for a taken-down idea; None if it wasn't taken down.
+#[query]
+fn get_takedown_notice(idea_id: u64) -> Option<TakedownNotice> {
+    TAKEDOWNS.with(|t| t.borrow().get(&idea_id)).map(|t| TakedownNotice {
+        idea_id,
+        notice: TAKEDOWN_NOTICE.to_string(),
+        taken_down_at: t.taken_down_at,
+    })
+}
+
+/// Every takedown with its reason (admins).
+#[query]
+fn get_takedowns() -> Result<Vec<Takedown>, String> {
+    ensure_admin()?;
+    Ok(TAKEDOWNS.with(|t| t.borrow().iter().map(|(_, t)| t).collect()))
+}
+
+/// A doc on legal hold (admins).
+#[query]
+fn get_held_doc(doc_id: u64) -> Result<Option<Doc>, String> {
+    ensure_admin()?;
+    Ok(HELD_DOCS.with(|d| d.borrow().get(&doc_id)))
+}
+
+/// Campaigns referencing a missing idea outside a takedown (admins). Empty unless records were
+/// removed some other way; `takedown_idea` on the missing idea's id settles them.
+#[query]
+fn get_orphaned_campaigns() -> Result<Vec<u64>, String> {
+    ensure_admin()?;
+    Ok(orphaned_campaigns())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_campaign, test_idea};
+    use crate::{Idea, ADMINS};
+
+    #[test]
+    fn takedowns_run_only_with_the_token_of_their_dry_run() {
+        let (admin, other_admin, now) = (Principal::from_slice(&[1]), Principal::from_slice(&[2]), 5_000_000_000);
+        ADMINS.with(|a| {
+            a.borrow_mut().insert(admin, ());
+            a.borrow_mut().insert(other_admin, ());
+        });
+        let doc = Doc {
+            id: 4,
+            idea_id: 1,
+            name: "deck.pdf".into(),
+            content_type: "application/pdf".into(),
+            data: vec![1],
+            uploaded_at: 0,
+        };
+        DOCS.with(|d| d.borrow_mut().insert(4, doc));
+        let idea = Idea { doc_ids: vec![4, 9], ..test_idea("Solar Kiosk", "Kiosks", "energy") }; // 9 is gone
+        IDEAS.with(|i| i.borrow_mut().insert(1, idea));
+        CAMPAIGNS.with(|c| c.borrow_mut().insert(3, Campaign { amount_raised: 50, ..test_campaign(3, 1, 10) }));
+        let take_down = |by, dry_run, token| takedown_idea_at(by, now, 1, "Court order".into(), dry_run, token);
+
+        let preview = take_down(admin, true, None).unwrap();
+        assert_eq!((preview.takedown.campaign_ids, preview.takedown.held_doc_ids), (vec![3], vec![4]));
+        assert!(get_idea(1).is_some() && DOCS.with(|d| d.borrow().contains_key(&4)), "a dry run changes nothing");
+        assert!(take_down(admin, false, None).is_err());
+        let stolen = take_down(other_admin, false, preview.confirmation_token).unwrap_err();
+        assert_eq!(stolen, "confirmation token was issued for different parameters");
+
+        let token = take_down(admin, true, None).unwrap().confirmation_token;
+        let report = take_down(admin, false, token).unwrap();
+        assert_eq!((report.dry_run, report.takedown.taken_down_by, report.takedown.taken_down_at), (false, admin, now));
+        let campaign = CAMPAIGNS.with(|c| c.borrow().get(&3)).unwrap();
+        assert_eq!((campaign.status, campaign.refund_pending), (Some(CampaignLifecycle::TakenDown), Some(true)));
+        assert!(get_idea(1).is_none() && HELD_DOCS.with(|h| h.borrow().contains_key(&4)));
+        assert_eq!(get_takedown_notice(1).map(|n| n.taken_down_at), Some(now));
+        assert_eq!(take_down(admin, true, None).unwrap_err(), "idea was already taken down");
+    }
+}
@@ -0,0 +1,128 @@
+//! Watchlists: campaigns a signed-in user follows, kept on-chain so they follow the user across
+//! devices.
+//!
+//! Follows are keyed by (principal, campaign), so a user's watchlist is one range read; each
+//! campaign's follower count is kept alongside and moved by follow and unfollow, so reading it
+//! never scans watchlists. Following again, or unfollowing a campaign not followed, changes nothing.
+
+use std::cell::RefCell;
+
+use candid::Principal;
+use ic_cdk_macros::{query, update};
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::StableBTreeMap;
+
+use super::{card_of, get_campaign, CampaignCard, EntityRef, FundVerseError, Memory, MEMORY_MANAGER};
+
+thread_local! {
+    // (follower, campaign_id) -> ()
+    static FOLLOWS: RefCell<StableBTreeMap<(Principal, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(75)))
+        )
+    );
+
+    // campaign_id -> followers; campaigns nobody follows have no entry
+    static FOLLOWER_COUNTS: RefCell<StableBTreeMap<u64, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(76)))
+        )
+    );
+}
+
+fn signed_in_caller() -> Result<Principal, FundVerseError> {
+    let caller = ic_cdk::caller();
+    if caller == Principal::anonymous() {
+        return Err(FundVerseError::Unauthorized);
+    }
+    Ok(caller)
+}
+
+fn follow(follower: Principal, campaign_id: u64) {
+    if FOLLOWS.with(|f| f.borrow_mut().insert((follower, campaign_id), ())).is_none() {
+        FOLLOWER_COUNTS.with(|c| {
+            let mut c = c.borrow_mut();
+            let count = c.get(&campaign_id).unwrap_or(0);
+            c.insert(campaign_id, count + 1);
+        });
+    }
+}
+
+fn unfollow(follower: Principal, campaign_id: u64) {
+    if FOLLOWS.with(|f| f.borrow_mut().remove(&(follower, campaign_id))).is_some() {
+        FOLLOWER_COUNTS.with(|c| {
+            let mut c = c.borrow_mut();
+            match c.get(&campaign_id).unwrap_or(0) {
+                0 | 1 => c.remove(&campaign_id),
+                count => c.insert(campaign_id, count - 1),
+            };
+        });
+    }
+}
+
+fn followed(follower: Principal) -> Vec<u64> {
+    FOLLOWS.with(|f| {
+        f.borrow()
+            .range((follower, 0)..=(follower, u64::MAX))
+            .map(|((_, campaign_id), _)| campaign_id)
+            .collect()
+    })
+}
+
+fn follower_count(campaign_id: u64) -> u64 {
+    FOLLOWER_COUNTS.with(|c| c.borrow().get(&campaign_id).unwrap_or(0))
+}
+
+/// Add a campaign to the caller's watchlist (signed-in callers). Following it again is a no-op.
+#[update]
+fn follow_campaign(campaign_id: u64) -> Result<(), FundVerseError> {
+    let caller = signed_in_caller()?;
+    get_campaign(campaign_id).ok_or(FundVerseError::NotFound(EntityRef::Campaign(campaign_id)))?;
+    follow(caller, campaign_id);
+    Ok(())
+}
+
+/// Remove a campaign from the caller's watchlist; a no-op if it isn't on it.
+#[update]
+fn unfollow_campaign(campaign_id: u64) -> Result<(), FundVerseError> {
+    unfollow(signed_in_caller()?, campaign_id);
+    Ok(())
+}
+
+/// Cards for the campaigns the caller follows, by campaign id. Campaigns that no longer resolve
+/// to a card are left out; taken-down ones show their tombstone.
+#[query]
+fn get_my_watchlist() -> Vec<CampaignCard> {
+    followed(ic_cdk::caller()).into_iter().filter_map(get_campaign).filter_map(|c| card_of(&c)).collect()
+}
+
+/// How many users follow a campaign; 0 for unknown campaigns.
+#[query]
+fn get_follower_count(campaign_id: u64) -> u64 {
+    follower_count(campaign_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follower_counts_move_once_per_follow_and_unfollow() {
+        let (ada, bob) = (Principal::from_slice(&[1]), Principal::from_slice(&[2]));
+        follow(ada, 7);
+        follow(ada, 7); // already following
+        follow(bob, 7);
+        follow(ada, 3);
+        assert_eq!((follower_count(7), follower_count(3)), (2, 1));
+        assert_eq!(followed(ada), [3, 7]);
+
+        unfollow(bob, 7);
+        unfollow(bob, 7); // no longer following
+        unfollow(bob, 3); // never followed
+        assert_eq!((follower_count(7), follower_count(3)), (1, 1));
+        unfollow(ada, 3);
+        assert_eq!(follower_count(3), 0);
+        assert!(FOLLOWER_COUNTS.with(|c| !c.borrow().contains_key(&3)), "no zero entries");
+        assert!(followed(bob).is_empty());
+    }
+}
@@ -0,0 +1,360 @@
+//! Shared pocket-ic harness for the FundVerse_backend end-to-end suite.
+//!
+//! Needs a pocket-ic server binary (`POCKET_IC_BIN`) and a release wasm build:
+//! `cargo build -p FundVerse_backend --target wasm32-unknown-unknown --release`.
+//! Override the wasm location with `FUNDVERSE_BACKEND_WASM`.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use candid::utils::{ArgumentDecoder, ArgumentEncoder};
+use candid::{CandidType, Deserialize, Principal};
+use pocket_ic::{query_candid_as, update_candid_as, PocketIc};
+
+pub const DAY_SECS: u64 = 86_400;
+
+/// Deterministic, non-anonymous test principal.
+pub fn principal(n: u8) -> Principal {
+    Principal::from_slice(&[0xFE, n, 0x01])
+}
+
+pub fn controller() -> Principal {
+    principal(0)
+}
+
+pub fn backer(n: u8) -> Principal {
+    principal(100 + n)
+}
+
+/// Stands in for the Fund_Flow canister, the only caller `receive_icp_contribution` accepts.
+pub fn fund_flow() -> Principal {
+    principal(250)
+}
+
+fn wasm_path() -> PathBuf {
+    std::env::var_os("FUNDVERSE_BACKEND_WASM")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../../target/wasm32-unknown-unknown/release/FundVerse_backend.wasm")
+        })
+}
+
+pub fn backend_wasm() -> Vec<u8> {
+    let path = wasm_path();
+    std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "could not read {} ({}); build it with `cargo build -p FundVerse_backend --target wasm32-unknown-unknown --release`",
+            path.display(),
+            e
+        )
+    })
+}
+
+// ---- Mirrors of the candid types the tests decode (only the fields they need) ----
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CampaignCard {
+    pub id: u64,
+    pub idea_id: u64,
+    pub title: String,
+    pub idea_title: String,
+    pub category: String,
+    pub amount_raised: u64,
+    pub goal: u64,
+    pub end_date: u64,
+    pub days_left: i64,
+    pub ends_in_secs: i64,
+    pub requested_end_date: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Currency {
+    Icp,
+    CkBtc,
+    UsdCents,
+}
+
+/// The currency `TestEnv::create_campaign` raises in, whose pledges `contribute` records as sent.
+pub const USD: Option<Currency> = Some(Currency::UsdCents);
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Channel {
+    Unspecified,
+    Web,
+    Embed,
+    PartnerApi,
+    MobileApp,
+}
+
+#[derive(CandidType)]
+pub enum EndTime {
+    Exact,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct FiatGoalRequest {
+    pub currency: String,
+    pub amount_cents: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Takedown {
+    pub campaign_ids: Vec<u64>,
+    pub held_doc_ids: Vec<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TakedownReport {
+    pub dry_run: bool,
+    pub takedown: Takedown,
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum FundingRound {
+    PreSeed,
+    Seed,
+    SeriesA,
+    SeriesB,
+    SeriesC,
+    Custom(String),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum CampaignStatus {
+    Active,
+    Ended,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Idea {
+    pub title: String,
+    pub description: String,
+    pub funding_goal: u64,
+    pub current_funding: u64,
+    pub status: IdeaStatus,
+    pub category: String,
+    pub doc_ids: Vec<u64>,
+    pub owner: Principal,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum IdeaStatus {
+    Draft,
+    Pending,
+    Approved,
+    Rejected { reason: String },
+    Quarantined { bytes: Vec<u8> },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Doc {
+    pub id: u64,
+    pub idea_id: u64,
+    pub name: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub uploaded_at: u64,
+}
+
+/// Arguments for `create_idea`, with valid defaults so scenarios only override what they test.
+#[derive(Clone, Debug)]
+pub struct IdeaArgs {
+    pub title: String,
+    pub description: String,
+    pub funding_goal: u64,
+    pub legal_entity: String,
+    pub contact_info: String,
+    pub category: String,
+    pub business_registration: u8,
+}
+
+impl Default for IdeaArgs {
+    fn default() -> Self {
+        IdeaArgs {
+            title: "Eco Bottles".into(),
+            description: "Reusable bottles made from recycled ocean plastic.".into(),
+            funding_goal: 50_000,
+            legal_entity: "Eco Bottles LLC".into(),
+            contact_info: "founder@ecobottles.example".into(),
+            category: "Environment".into(),
+            business_registration: 1,
+        }
+    }
+}
+
+impl IdeaArgs {
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn funding_goal(mut self, funding_goal: u64) -> Self {
+        self.funding_goal = funding_goal;
+        self
+    }
+
+    pub fn category(mut self, category: &str) -> Self {
+        self.category = category.into();
+        self
+    }
+}
+
+pub struct TestEnv {
+    pub pic: PocketIc,
+    pub canister: Principal,
+}
+
+impl TestEnv {
+    /// Fresh replica with the backend installed by `controller()`.
+    pub fn new() -> Self {
+        let pic = PocketIc::new();
+        let canister = pic.create_canister_with_settings(Some(controller()), None);
+        pic.add_cycles(canister, 2_000_000_000_000);
+        pic.install_canister(canister, backend_wasm(), candid::encode_args(()).unwrap(), Some(controller()));
+        TestEnv { pic, canister }
+    }
+
+    pub fn upgrade(&self) {
+        self.pic
+            .upgrade_canister(self.canister, backend_wasm(), candid::encode_args(()).unwrap(), Some(controller()))
+            .expect("upgrade failed");
+    }
+
+    pub fn now_secs(&self) -> u64 {
+        self.pic.get_time().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    pub fn advance_days(&self, days: u64) {
+        self.pic.advance_time(Duration::from_secs(days * DAY_SECS));
+        self.pic.tick();
+    }
+
+    pub fn update<I: ArgumentEncoder, O: for<'a> ArgumentDecoder<'a>>(&self, sender: Principal, method: &str, args: I) -> O {
+        update_candid_as(&self.pic, self.canister, sender, method, args)
+            .unwrap_or_else(|e| panic!("{} failed: {:?}", method, e))
+    }
+
+    pub fn query<I: ArgumentEncoder, O: for<'a> ArgumentDecoder<'a>>(&self, sender: Principal, method: &str, args: I) -> O {
+        query_candid_as(&self.pic, self.canister, sender, method, args)
+            .unwrap_or_else(|e| panic!("{} failed: {:?}", method, e))
+    }
+
+    pub fn create_idea(&self, sender: Principal, a: IdeaArgs) -> u64 {
+        let (id,): (Result<u64, candid::Reserved>,) = self.update(
+            sender,
+            "create_idea",
+            (a.title, a.description, a.funding_goal, a.legal_entity, a.contact_info, a.category, a.business_registration),
+        );
+        id.expect("valid idea")
+    }
+
+    /// A campaign raising in USD cents, so backers can pledge with plain `contribute` calls; ICP
+    /// pledges need a ledger transfer (or `relay_icp`).
+    pub fn create_campaign(&self, sender: Principal, idea_id: u64, goal: u64, end_date_secs: u64) -> Result<u64, String> {
+        self.create_campaign_in(sender, idea_id, goal, end_date_secs, Currency::UsdCents)
+    }
+
+    pub fn create_campaign_in(
+        &self,
+        sender: Principal,
+        idea_id: u64,
+        goal: u64,
+        end_date_secs: u64,
+        currency: Currency,
+    ) -> Result<u64, String> {
+        let (res,): (Result<u64, String>,) = self.update(
+            sender,
+            "create_campaign",
+            (
+                idea_id,
+                goal,
+                end_date_secs,
+                None::<FundingRound>,
+                None::<String>,
+                None::<String>,
+                None::<EndTime>,
+                None::<FiatGoalRequest>,
+                Some(currency),
+            ),
+        );
+        res
+    }
+
+    /// Approve an idea as `controller()`, which always counts as an admin.
+    pub fn approve_idea(&self, idea_id: u64) {
+        let (res,): (Result<(), String>,) = self.update(controller(), "approve_idea", (idea_id,));
+        res.expect("approve_idea failed");
+    }
+
+    /// Call `takedown_idea` once as given: `dry_run`, or live with a dry run's token.
+    pub fn takedown(
+        &self,
+        sender: Principal,
+        idea_id: u64,
+        reason: &str,
+        dry_run: bool,
+        token: Option<String>,
+    ) -> Result<TakedownReport, String> {
+        let (res,): (Result<TakedownReport, String>,) =
+            self.update(sender, "takedown_idea", (idea_id, reason.to_string(), dry_run, token));
+        res
+    }
+
+    /// Take an idea down as `controller()`: a dry run, then the live run with its token.
+    pub fn take_down_idea(&self, idea_id: u64, reason: &str) -> TakedownReport {
+        let preview = self.takedown(controller(), idea_id, reason, true, None).expect("takedown dry run failed");
+        self.takedown(controller(), idea_id, reason, false, preview.confirmation_token).expect("takedown failed")
+    }
+
+    pub fn upload_doc(&self, sender: Principal, idea_id: u64, name: &str, data: Vec<u8>) -> Option<u64> {
+        let (res,): (Option<u64>,) =
+            self.update(sender, "upload_doc", (idea_id, name.to_string(), "application/pdf".to_string(), data, 0u64));
+        res
+    }
+
+    /// Pledge USD cents to a campaign from `create_campaign`.
+    pub fn contribute(&self, sender: Principal, campaign_id: u64, amount: u64) -> Result<u64, String> {
+        let (res,): (Result<u64, String>,) =
+            self.update(sender, "contribute", (campaign_id, amount, None::<Channel>, USD));
+        res
+    }
+
+    /// Credit ICP to a campaign the way Fund_Flow does once a transfer has settled.
+    pub fn relay_icp(&self, campaign_id: u64, amount_e8s: u64) {
+        let (res,): (Result<(), String>,) = self.update(controller(), "set_fund_flow_canister", (fund_flow(),));
+        res.expect("set_fund_flow_canister failed");
+        let (res,): (Result<(), String>,) =
+            self.update(fund_flow(), "receive_icp_contribution", (campaign_id, amount_e8s));
+        res.expect("receive_icp_contribution failed");
+    }
+
+    pub fn campaign_cards(&self) -> Vec<CampaignCard> {
+        let (cards,): (Vec<CampaignCard>,) = self.query(Principal::anonymous(), "get_campaign_cards", ());
+        cards
+    }
+
+    pub fn campaign_cards_by_status(&self, status: CampaignStatus) -> Vec<CampaignCard> {
+        let (cards,): (Vec<CampaignCard>,) =
+            self.query(Principal::anonymous(), "get_campaign_cards_by_status", (status,));
+        cards
+    }
+
+    pub fn idea(&self, idea_id: u64) -> Option<Idea> {
+        let (idea,): (Option<Idea>,) = self.query(Principal::anonymous(), "get_idea_by_id", (idea_id,));
+        idea
+    }
+
+    pub fn doc(&self, doc_id: u64) -> Option<Doc> {
+        let (doc,): (Option<Doc>,) = self.query(Principal::anonymous(), "get_doc", (doc_id,));
+        doc
+    }
+}
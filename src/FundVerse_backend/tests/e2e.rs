@@ -0,0 +1,2504 @@
+//! End-to-end scenarios against the real wasm in pocket-ic.
+//! Run with `cargo test -p FundVerse_backend --test e2e -- --ignored` (see `common` for setup).
+
+mod common;
+
+use common::*;
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn idea_to_campaign_card() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default().title("Solar Kiosk"));
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 7 * DAY_SECS;
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+
+    let cards = env.campaign_cards();
+    assert_eq!(cards.len(), 1);
+    let card = &cards[0];
+    assert_eq!(card.id, campaign_id);
+    assert_eq!(card.idea_id, idea_id);
+    assert_eq!(card.title, "Solar Kiosk");
+    assert_eq!(card.idea_title, "Solar Kiosk");
+    assert_eq!(card.goal, 10_000);
+    assert!((6..=7).contains(&card.days_left), "days_left = {}", card.days_left);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct CardPage {
+    items: Vec<CampaignCard>,
+    total: u64,
+    next_offset: Option<u64>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn campaign_cards_page_in_id_order() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 7 * DAY_SECS;
+    let ids: Vec<u64> = (0..3).map(|_| env.create_campaign(principal(1), idea_id, 10_000, end).unwrap()).collect();
+
+    let page = |offset: u64, limit: u64| -> CardPage {
+        let (page,): (CardPage,) = env.query(principal(2), "get_campaign_cards_paged", (offset, limit));
+        page
+    };
+    let first = page(0, 2);
+    assert_eq!(first.items.iter().map(|c| c.id).collect::<Vec<_>>(), ids[..2]);
+    assert_eq!((first.total, first.next_offset), (3, Some(2)));
+    let second = page(2, 2);
+    assert_eq!(second.items.iter().map(|c| c.id).collect::<Vec<_>>(), ids[2..]);
+    assert_eq!(second.next_offset, None);
+    assert!(page(0, 0).items.is_empty());
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn campaign_for_unknown_idea_is_rejected() {
+    let env = TestEnv::new();
+    let end = env.now_secs() + DAY_SECS;
+    assert!(env.create_campaign(principal(1), 42, 10_000, end).is_err());
+    assert!(env.campaign_cards().is_empty());
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn only_the_idea_owner_can_open_a_campaign() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    let end = env.now_secs() + DAY_SECS;
+    assert!(env.create_campaign(principal(1), idea_id, 10_000, end).is_err()); // still pending review
+    env.approve_idea(idea_id);
+    assert!(env.create_campaign(principal(2), idea_id, 10_000, end).is_err());
+    assert!(env.create_campaign(candid::Principal::anonymous(), idea_id, 10_000, end).is_err());
+    assert!(env.create_campaign(principal(1), idea_id, 10_000, end).is_ok());
+    assert_eq!(env.idea(idea_id).unwrap().owner, principal(1));
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn campaign_moves_to_ended_after_deadline() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 3 * DAY_SECS;
+    assert!(env.create_campaign(principal(1), idea_id, 10_000, end * 1_000_000_000).is_err()); // ns, not secs
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+
+    let active = env.campaign_cards_by_status(CampaignStatus::Active);
+    assert!(active.iter().any(|c| c.id == campaign_id));
+
+    env.advance_days(4);
+    let active = env.campaign_cards_by_status(CampaignStatus::Active);
+    let ended = env.campaign_cards_by_status(CampaignStatus::Ended);
+    assert!(!active.iter().any(|c| c.id == campaign_id));
+    let card = ended.iter().find(|c| c.id == campaign_id).expect("campaign should have ended");
+    assert!(card.ends_in_secs < 0 && card.days_left < 0);
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn contributions_raise_campaign_and_idea_totals() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 2 * DAY_SECS;
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+
+    env.contribute(backer(1), campaign_id, 300).unwrap();
+    env.contribute(backer(2), campaign_id, 200).unwrap();
+    assert!(env.contribute(backer(1), campaign_id, 0).is_err());
+    assert!(env.contribute(backer(1), campaign_id + 1, 100).is_err());
+
+    assert_eq!(env.campaign_cards()[0].amount_raised, 500);
+    assert_eq!(env.idea(idea_id).unwrap().current_funding, 500);
+
+    env.advance_days(3);
+    assert!(env.contribute(backer(1), campaign_id, 100).is_err());
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn cancelled_campaigns_refuse_contributions_and_await_refunds() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 2 * DAY_SECS;
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    env.contribute(backer(1), campaign_id, 300).unwrap();
+
+    let (res,): (Result<(), String>,) = env.update(principal(1), "extend_campaign", (campaign_id, end - 60));
+    assert!(res.is_err(), "extending must move the end date later");
+    let (res,): (Result<(), String>,) = env.update(backer(1), "cancel_campaign", (campaign_id,));
+    assert!(res.is_err(), "only the owner can cancel");
+    let (res,): (Result<(), String>,) = env.update(principal(1), "extend_campaign", (campaign_id, end + DAY_SECS));
+    res.unwrap();
+    let (res,): (Result<(), String>,) = env.update(principal(1), "cancel_campaign", (campaign_id,));
+    res.unwrap();
+
+    assert!(env.contribute(backer(2), campaign_id, 100).is_err());
+    let (res,): (Result<(), String>,) = env.update(principal(1), "extend_campaign", (campaign_id, end + 2 * DAY_SECS));
+    assert!(res.is_err(), "cancelled campaigns can't be extended");
+    let ended = env.campaign_cards_by_status(CampaignStatus::Ended);
+    assert!(ended.iter().any(|c| c.id == campaign_id));
+    let (pending,): (Result<Vec<u64>, String>,) = env.query(controller(), "get_campaigns_pending_refund", ());
+    assert_eq!(pending.unwrap(), vec![campaign_id]);
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn end_dates_snap_to_the_platform_hour_unless_exact() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let (res,): (Result<(), String>,) = env.update(controller(), "set_campaign_end_hour_utc", (Some(17u8),));
+    res.unwrap();
+
+    let requested = env.now_secs() + 3 * DAY_SECS;
+    let snapped_id = env.create_campaign(principal(1), idea_id, 10_000, requested).unwrap();
+    let (res,): (Result<u64, String>,) = env.update(
+        principal(1),
+        "create_campaign",
+        (idea_id, 10_000u64, requested, None::<FundingRound>, None::<String>, None::<String>, Some(EndTime::Exact)),
+    );
+    let exact_id = res.unwrap();
+
+    let cards = env.campaign_cards();
+    let snapped = cards.iter().find(|c| c.id == snapped_id).unwrap();
+    assert_eq!(snapped.end_date % DAY_SECS, 17 * 3_600);
+    assert!(snapped.end_date >= requested && snapped.end_date - requested < DAY_SECS);
+    assert_eq!(snapped.requested_end_date, Some(requested));
+    let exact = cards.iter().find(|c| c.id == exact_id).unwrap();
+    assert_eq!((exact.end_date, exact.requested_end_date), (requested, None));
+
+    let (res,): (Result<(), String>,) = env.update(principal(1), "extend_campaign", (snapped_id, snapped.end_date + 60));
+    res.unwrap();
+    let extended = env.campaign_cards().into_iter().find(|c| c.id == snapped_id).unwrap();
+    assert_eq!(extended.end_date, snapped.end_date + DAY_SECS);
+}
+
+#[derive(candid::CandidType)]
+struct ContributionRequest {
+    campaign_id: u64,
+    amount: u64,
+    currency: Option<Currency>,
+    ack_risks: Option<bool>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn basket_checkout_reports_each_item() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 7 * DAY_SECS;
+    let a = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    let b = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    let checkout = |items: &[(u64, u64)], all_or_nothing: bool| -> Vec<Result<u64, String>> {
+        let items: Vec<ContributionRequest> = items
+            .iter()
+            .map(|&(campaign_id, amount)| ContributionRequest { campaign_id, amount, currency: USD, ack_risks: None })
+            .collect();
+        let (res,): (Result<Vec<Result<u64, String>>, String>,) =
+            env.update(backer(1), "contribute_many", (items, all_or_nothing));
+        res.unwrap()
+    };
+    let raised = |id: u64| env.campaign_cards().into_iter().find(|c| c.id == id).unwrap().amount_raised;
+
+    let results = checkout(&[(a, 100), (b + 1, 100)], true);
+    assert!(results[0].as_ref().unwrap_err().starts_with("not executed"));
+    assert!(results[1].is_err());
+    assert_eq!(raised(a), 0);
+
+    let results = checkout(&[(a, 100), (b + 1, 100), (a, 50), (b, 25)], false);
+    assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok() && results[3].is_ok());
+    assert_eq!((raised(a), raised(b)), (150, 25));
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+struct Contribution {
+    id: u64,
+    campaign_id: u64,
+    contributor: candid::Principal,
+    amount: u64,
+    risks_acked_at: Option<u64>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn pledges_to_risky_campaigns_need_an_acknowledgment() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 7 * DAY_SECS;
+    let risky = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    let plain = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    let (res,): (Result<(), String>,) =
+        env.update(principal(1), "set_campaign_risks", (risky, "Supply delays".to_string()));
+    res.unwrap();
+    let pledge = |campaign_id: u64, ack_risks: Option<bool>| {
+        let (res,): (Result<u64, String>,) =
+            env.update(backer(1), "contribute", (campaign_id, 100u64, None::<Channel>, USD, ack_risks));
+        res
+    };
+
+    for ack_risks in [None, Some(false)] {
+        assert!(pledge(risky, ack_risks).unwrap_err().starts_with("RISK_ACK_REQUIRED"));
+    }
+    assert!(env.contribute(backer(1), risky, 100).unwrap_err().starts_with("RISK_ACK_REQUIRED"));
+    let basket = vec![ContributionRequest { campaign_id: risky, amount: 100, currency: USD, ack_risks: None }];
+    let (res,): (Result<Vec<Result<u64, String>>, String>,) =
+        env.update(backer(1), "contribute_many", (basket, false));
+    assert!(res.unwrap()[0].as_ref().unwrap_err().starts_with("RISK_ACK_REQUIRED"));
+
+    let acked = pledge(risky, Some(true)).unwrap();
+    let unasked = pledge(plain, None).unwrap();
+    let (contributions,): (Vec<Contribution>,) = env.query(backer(1), "get_my_contributions", ());
+    let acked_at = |id| contributions.iter().find(|c| c.id == id).unwrap().risks_acked_at;
+    assert!(acked_at(acked).is_some());
+    assert_eq!(acked_at(unasked), None, "nothing to acknowledge without a risks section");
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn archived_contributions_stay_readable() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + DAY_SECS;
+    let old = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    let recent = env.create_campaign(principal(1), idea_id, 10_000, end + 30 * DAY_SECS).unwrap();
+    env.contribute(backer(1), old, 300).unwrap();
+    env.contribute(backer(2), old, 200).unwrap();
+    env.contribute(backer(1), recent, 50).unwrap();
+
+    let by_campaign = |id: u64| -> Vec<Contribution> {
+        let (res,): (Vec<Contribution>,) = env.query(backer(1), "get_contributions_by_campaign", (id,));
+        res
+    };
+    let mine = || -> Vec<Contribution> {
+        let (res,): (Vec<Contribution>,) = env.query(backer(1), "get_my_contributions", ());
+        res
+    };
+    let (before_old, before_mine) = (by_campaign(old), mine());
+
+    let (res,): (Result<(), String>,) = env.update(controller(), "set_contribution_archive_age", (2 * DAY_SECS,));
+    res.unwrap();
+    env.advance_days(5); // `old` ended 4 days ago; `recent` is still open
+
+    assert_eq!(by_campaign(old), before_old);
+    assert_eq!(mine(), before_mine);
+    assert_eq!(mine().iter().map(|c| c.campaign_id).collect::<Vec<_>>(), vec![old, recent]);
+    let card = env.campaign_cards().into_iter().find(|c| c.id == old).unwrap();
+    assert_eq!(card.amount_raised, 500);
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn chunked_upload_assembles_out_of_order_chunks() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    let (upload,): (Result<u64, String>,) = env.update(
+        principal(1),
+        "begin_doc_upload",
+        (idea_id, "deck.pdf".to_string(), "application/pdf".to_string(), 6u64),
+    );
+    let upload = upload.unwrap();
+    let chunk = |index: u32, bytes: Vec<u8>| -> Result<(), String> {
+        let (res,): (Result<(), String>,) = env.update(principal(1), "upload_doc_chunk", (upload, index, bytes));
+        res
+    };
+    chunk(1, vec![4, 5, 6]).unwrap();
+    assert!(chunk(1, vec![4, 5, 6]).is_err(), "duplicate index accepted");
+    chunk(0, vec![1, 2, 3]).unwrap();
+
+    let (doc_id,): (Result<u64, String>,) = env.update(principal(1), "finish_doc_upload", (upload,));
+    let doc = env.doc(doc_id.unwrap()).unwrap();
+    assert_eq!(doc.data, vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[derive(candid::CandidType, Clone, Default)]
+struct UpdateIdeaPayload {
+    title: Option<String>,
+    description: Option<String>,
+    funding_goal: Option<u64>,
+    legal_entity: Option<String>,
+    contact_info: Option<String>,
+    category: Option<String>,
+    business_registration: Option<u8>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn owner_can_edit_and_delete_an_idea() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    let doc_id = env.upload_doc(principal(1), idea_id, "deck.pdf", vec![1]).unwrap();
+
+    let rename = |sender, title: &str| -> Result<(), String> {
+        let payload = UpdateIdeaPayload { title: Some(title.into()), ..Default::default() };
+        let (res,): (Result<(), String>,) = env.update(sender, "update_idea", (idea_id, payload));
+        res
+    };
+    assert!(rename(principal(2), "Hijacked").is_err());
+    assert!(rename(principal(1), "").is_err());
+    rename(principal(1), "Eco Bottles 2").unwrap();
+    assert_eq!(env.idea(idea_id).unwrap().title, "Eco Bottles 2");
+
+    let (res,): (Result<(), String>,) = env.update(principal(1), "delete_idea", (idea_id,));
+    res.unwrap();
+    assert!(env.idea(idea_id).is_none());
+    assert!(env.doc(doc_id).is_none());
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn state_survives_upgrade() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let doc_id = env.upload_doc(principal(1), idea_id, "deck.pdf", vec![1, 2, 3]).unwrap();
+    let end = env.now_secs() + 7 * DAY_SECS;
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    let icp_campaign_id = env.create_campaign_in(principal(1), idea_id, 10_000, end, Currency::Icp).unwrap();
+    env.relay_icp(icp_campaign_id, 250);
+    let (before,): (StateStats,) = env.query(principal(1), "get_state_stats", ());
+
+    env.upgrade();
+
+    let (after,): (StateStats,) = env.query(principal(1), "get_state_stats", ());
+    assert_eq!(after, before);
+    assert_eq!(after.icp_funded_campaigns, 1);
+    let (icp,): (u64,) = env.query(principal(1), "get_icp_contribution", (icp_campaign_id,));
+    assert_eq!(icp, 250);
+    let idea = env.idea(idea_id).expect("idea lost on upgrade");
+    assert_eq!(idea.doc_ids, vec![doc_id]);
+    assert!(env.campaign_cards().iter().any(|c| c.id == campaign_id), "campaign lost on upgrade");
+    let doc = env.doc(doc_id).expect("doc lost on upgrade");
+    assert_eq!(doc.data, vec![1, 2, 3]);
+    assert!(env.create_idea(principal(1), IdeaArgs::default()) > idea_id, "ids must not be reused");
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+struct StateStats {
+    ideas: u64,
+    campaigns: u64,
+    docs: u64,
+    contributions: u64,
+    icp_funded_campaigns: u64,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+enum SyndicationError {
+    NotRegistered,
+    QuotaExhausted { quota: u64 },
+    DuplicateExternalId { idea_id: u64 },
+    InvalidInput(String),
+}
+
+#[derive(candid::CandidType)]
+struct SyndicatedIdea {
+    external_id: String,
+    title: String,
+    description: String,
+    funding_goal: u64,
+    legal_entity: String,
+    contact_info: String,
+    category: String,
+    business_registration: u8,
+}
+
+fn syndicated(external_id: &str) -> SyndicatedIdea {
+    let a = IdeaArgs::default().title(external_id);
+    SyndicatedIdea {
+        external_id: external_id.into(),
+        title: a.title,
+        description: a.description,
+        funding_goal: a.funding_goal,
+        legal_entity: a.legal_entity,
+        contact_info: a.contact_info,
+        category: a.category,
+        business_registration: a.business_registration,
+    }
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn syndication_errors_are_distinct() {
+    let env = TestEnv::new();
+    let partner = principal(50);
+    let syndicate = |who, id: &str| -> Result<u64, SyndicationError> {
+        let (res,): (Result<u64, SyndicationError>,) = env.update(who, "syndicate_idea", (syndicated(id),));
+        res
+    };
+
+    assert_eq!(syndicate(partner, "ext-1"), Err(SyndicationError::NotRegistered));
+
+    let (res,): (Result<(), String>,) =
+        env.update(controller(), "register_syndication_partner", (partner, "Incubator".to_string(), 2u64));
+    res.unwrap();
+
+    let idea_id = syndicate(partner, "ext-1").unwrap();
+    assert_eq!(env.idea(idea_id).unwrap().title, "ext-1");
+    let revised = SyndicatedIdea { title: "ext-1, revised".into(), ..syndicated("ext-1") };
+    let (res,): (Result<u64, SyndicationError>,) = env.update(partner, "syndicate_idea", (revised,));
+    assert_eq!(res, Ok(idea_id), "a pending idea is updated in place");
+    assert_eq!(env.idea(idea_id).unwrap().title, "ext-1, revised");
+    env.approve_idea(idea_id);
+    assert_eq!(syndicate(partner, "ext-1"), Err(SyndicationError::DuplicateExternalId { idea_id }));
+    syndicate(partner, "ext-2").unwrap();
+    assert_eq!(syndicate(partner, "ext-3"), Err(SyndicationError::QuotaExhausted { quota: 2 }), "updates are free");
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn partners_find_campaigns_by_their_external_ref() {
+    let env = TestEnv::new();
+    let partner = principal(50);
+    let (res,): (Result<(), String>,) =
+        env.update(controller(), "register_syndication_partner", (partner, "Incubator".to_string(), 5u64));
+    res.unwrap();
+    let (idea_id,): (Result<u64, SyndicationError>,) =
+        env.update(partner, "syndicate_idea", (syndicated("inc/42 solar"),));
+    let idea_id = idea_id.unwrap();
+    env.approve_idea(idea_id);
+    let lookup = |external_ref: &str| {
+        let (card,): (Option<CampaignCard>,) =
+            env.query(backer(1), "get_campaign_by_external_ref", (partner, external_ref.to_string()));
+        card.map(|c| c.id)
+    };
+    assert_eq!(lookup("inc/42 solar"), None, "no campaign yet");
+
+    let end = env.now_secs() + 7 * DAY_SECS;
+    let first = env.create_campaign(partner, idea_id, 10_000, end).unwrap();
+    assert_eq!(lookup("inc/42 solar"), Some(first));
+    assert_eq!(lookup("inc/42 other"), None);
+    assert!(env.create_campaign(partner, idea_id, 10_000, end).unwrap_err().contains("running campaign"));
+
+    let req = HttpRequest {
+        method: "GET".into(),
+        url: format!("/partners/{}/campaigns/inc%2F42%20solar.json", partner),
+        headers: vec![],
+        body: vec![],
+    };
+    let (res,): (HttpResponse,) = env.query(backer(1), "http_request", (req,));
+    assert_eq!(res.status_code, 200);
+    assert!(String::from_utf8(res.body).unwrap().contains(&format!("\"id\":{}", first)));
+
+    let (res,): (Result<(), String>,) = env.update(partner, "cancel_campaign", (first,));
+    res.unwrap();
+    let second = env.create_campaign(partner, idea_id, 20_000, end).unwrap();
+    assert_eq!(lookup("inc/42 solar"), Some(second), "the ref moves to the newest campaign");
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct TextLimit {
+    field: String,
+    max: u64,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct Capabilities {
+    max_doc_bytes: u64,
+    max_doc_chunk_bytes: u64,
+    max_doc_chunks: u32,
+    max_refs_per_batch: u64,
+    text_limits: Vec<TextLimit>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Copy, Debug, PartialEq)]
+enum EntityRef {
+    Idea(u64),
+    Campaign(u64),
+    Doc(u64),
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn every_advertised_limit_is_enforced() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let campaign_id = env.create_campaign(owner, idea_id, 10_000, env.now_secs() + 7 * DAY_SECS).unwrap();
+    let partner = principal(50);
+    let (res,): (Result<(), String>,) =
+        env.update(controller(), "register_syndication_partner", (partner, "Incubator".to_string(), 10u64));
+    res.unwrap();
+    let (res,): (Result<candid::Reserved, candid::Reserved>,) =
+        env.update(owner, "register_user", ("Founder".to_string(), "founder@example.com".to_string(), String::new()));
+    assert!(res.is_ok());
+
+    let (caps,): (Capabilities,) = env.query(owner, "get_capabilities", ());
+    for TextLimit { field, max } in &caps.text_limits {
+        // Over the limit first, so the exact-limit call doesn't change what the next one sees.
+        for len in [*max + 1, *max] {
+            let text = match field.as_str() {
+                "idea.contact_info" | "profile.email" => format!("{}@example.com", "a".repeat(len as usize - 12)),
+                "partner.url" => format!("https://{}", "a".repeat(len as usize - 8)),
+                _ => "a".repeat(len as usize),
+            };
+            let accepted = match field.as_str() {
+                "idea.title" | "idea.description" | "idea.legal_entity" | "idea.contact_info" | "idea.category" => {
+                    if field == "idea.category" {
+                        // Ideas take registered categories only; slugs share the field's limit.
+                        let (_,): (Result<Category, String>,) =
+                            env.update(controller(), "add_category", (text.clone(), "Long".to_string()));
+                    }
+                    let mut p = UpdateIdeaPayload::default();
+                    *match field.as_str() {
+                        "idea.title" => &mut p.title,
+                        "idea.description" => &mut p.description,
+                        "idea.legal_entity" => &mut p.legal_entity,
+                        "idea.contact_info" => &mut p.contact_info,
+                        _ => &mut p.category,
+                    } = Some(text);
+                    let (res,): (Result<(), String>,) = env.update(owner, "update_idea", (idea_id, p));
+                    res.is_ok()
+                }
+                "idea.rejection_reason" => {
+                    let pending = env.create_idea(owner, IdeaArgs::default().title(&format!("Pending {}", len)));
+                    let (res,): (Result<(), String>,) = env.update(controller(), "reject_idea", (pending, text));
+                    res.is_ok()
+                }
+                "campaign.display_title" | "campaign.teaser" => {
+                    let (title, teaser) =
+                        if field == "campaign.teaser" { (None, Some(text)) } else { (Some(text), None) };
+                    let (res,): (Result<(), String>,) =
+                        env.update(owner, "set_campaign_copy", (campaign_id, title, teaser));
+                    res.is_ok()
+                }
+                "campaign.risks" => {
+                    let (res,): (Result<(), String>,) = env.update(owner, "set_campaign_risks", (campaign_id, text));
+                    res.is_ok()
+                }
+                "campaign.pause_reason" => {
+                    let (res,): (Result<(), String>,) =
+                        env.update(owner, "pause_contributions", (campaign_id, text));
+                    res.is_ok()
+                }
+                "comment.text" => {
+                    let (res,): (Result<u64, String>,) =
+                        env.update(owner, "post_idea_comment", (idea_id, text, None::<u64>));
+                    res.is_ok()
+                }
+                "syndication.external_id" => {
+                    let (res,): (Result<u64, SyndicationError>,) =
+                        env.update(partner, "syndicate_idea", (syndicated(&text),));
+                    res.is_ok()
+                }
+                "campaign_update.title" | "campaign_update.body" => {
+                    let (title, body) = if field == "campaign_update.title" {
+                        (text, "body".to_string())
+                    } else {
+                        ("title".to_string(), text)
+                    };
+                    let (res,): (Result<u64, String>,) =
+                        env.update(owner, "post_campaign_update", (campaign_id, title, body));
+                    res.is_ok()
+                }
+                "campaign_comment.text" => {
+                    let (res,): (Result<u64, String>,) =
+                        env.update(backer(1), "post_campaign_comment", (campaign_id, text));
+                    res.is_ok()
+                }
+                "partner.name" | "partner.url" => {
+                    let (name, url) = if field == "partner.name" {
+                        (text, "https://partner.example".to_string())
+                    } else {
+                        ("Partner".to_string(), text)
+                    };
+                    let args = (name, None::<u64>, url, None::<candid::Principal>);
+                    let (res,): (Result<u64, String>,) = env.update(controller(), "register_marketing_partner", args);
+                    res.is_ok()
+                }
+                "takedown.reason" => {
+                    // The reason is checked before anything else, so a dry run will do.
+                    let doomed = env.create_idea(owner, IdeaArgs::default().title(&format!("Doomed {}", len)));
+                    env.takedown(controller(), doomed, &text, true, None).is_ok()
+                }
+                "profile.display_name" | "profile.email" | "profile.bio" => {
+                    let mut update = ProfileUpdate::default();
+                    *match field.as_str() {
+                        "profile.display_name" => &mut update.display_name,
+                        "profile.email" => &mut update.email,
+                        _ => &mut update.bio,
+                    } = Some(text);
+                    let (res,): (Result<candid::Reserved, candid::Reserved>,) =
+                        env.update(owner, "update_profile", (update,));
+                    res.is_ok()
+                }
+                "milestone.title" | "milestone.description" => {
+                    // The target is the text length, so the accepted 80-char title's milestone sits
+                    // below the description's: targets must rise.
+                    let (title, description) = if field == "milestone.title" {
+                        (text, String::new())
+                    } else {
+                        ("Prototype".to_string(), text)
+                    };
+                    let args = (campaign_id, title, description, len, env.now_secs() + DAY_SECS);
+                    let (res,): (Result<candid::Reserved, String>,) = env.update(owner, "add_milestone", args);
+                    res.is_ok()
+                }
+                "category.display_name" => {
+                    let slug = format!("named-{}", len);
+                    let (res,): (Result<Category, String>,) = env.update(controller(), "add_category", (slug, text));
+                    res.is_ok()
+                }
+                other => panic!("no check for advertised limit {}", other),
+            };
+            assert_eq!(accepted, len == *max, "{} at {} (limit {})", field, len, max);
+        }
+    }
+
+    let begin = |size: u64| -> Result<u64, String> {
+        let (res,): (Result<u64, String>,) = env.update(
+            owner,
+            "begin_doc_upload",
+            (idea_id, "deck.pdf".to_string(), "application/pdf".to_string(), size),
+        );
+        res
+    };
+    assert!(begin(caps.max_doc_bytes + 1).is_err());
+    let upload_id = begin(caps.max_doc_bytes).unwrap();
+    let chunk = |index: u32, len: u64| -> Result<(), String> {
+        let (res,): (Result<(), String>,) =
+            env.update(owner, "upload_doc_chunk", (upload_id, index, vec![0u8; len as usize]));
+        res
+    };
+    assert!(chunk(0, caps.max_doc_chunk_bytes + 1).is_err());
+    chunk(0, caps.max_doc_chunk_bytes).unwrap();
+    assert!(chunk(caps.max_doc_chunks, 1).is_err());
+    chunk(caps.max_doc_chunks - 1, 1).unwrap();
+    let chunk_len = caps.max_doc_chunk_bytes as usize;
+    assert!(env.upload_doc(owner, idea_id, "big.pdf", vec![0; chunk_len + 1]).is_none());
+    assert!(env.upload_doc(owner, idea_id, "big.pdf", vec![0; chunk_len]).is_some());
+
+    let refs: Vec<EntityRef> = (0..=caps.max_refs_per_batch).map(EntityRef::Idea).collect();
+    let (resolved,): (Vec<Option<candid::Reserved>>,) = env.query(owner, "resolve_refs", (refs,));
+    assert_eq!(resolved.len() as u64, caps.max_refs_per_batch);
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct BuildInfo {
+    interface_hash: String,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+enum Compatibility {
+    Identical,
+    Additive,
+    Breaking,
+    UnknownHash,
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct CompatReport {
+    compatibility: Compatibility,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn pinned_interface_hash_survives_an_upgrade() {
+    let env = TestEnv::new();
+    let (info,): (BuildInfo,) = env.query(backer(1), "get_build_info", ());
+    let check = |hash: &str| {
+        let (report,): (CompatReport,) = env.query(backer(1), "check_client_compatibility", (hash.to_string(),));
+        report.compatibility
+    };
+    assert_eq!(check(&info.interface_hash), Compatibility::Identical);
+    assert_eq!(check("not-a-deployed-hash"), Compatibility::UnknownHash);
+
+    env.upgrade();
+    assert_eq!(check(&info.interface_hash), Compatibility::Identical);
+    let (did,): (String,) = env.query(backer(1), "get_candid_interface", ());
+    assert!(did.contains("check_client_compatibility"));
+}
+
+#[derive(candid::CandidType)]
+struct HttpRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone)]
+struct StreamingToken {
+    doc_id: u64,
+    offset: u64,
+}
+
+candid::define_function!(StreamingCallback : (StreamingToken) -> (StreamingCallbackHttpResponse) query);
+
+#[derive(candid::CandidType, candid::Deserialize)]
+enum StreamingStrategy {
+    Callback { callback: StreamingCallback, token: StreamingToken },
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct HttpResponse {
+    status_code: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    streaming_strategy: Option<StreamingStrategy>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct StreamingCallbackHttpResponse {
+    body: Vec<u8>,
+    token: Option<StreamingToken>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn owners_replace_and_delete_docs_and_the_idea_follows() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    let wrong = env.upload_doc(owner, idea_id, "wrong.pdf", vec![1]).unwrap();
+    let deck = env.upload_doc(owner, idea_id, "deck.pdf", vec![2]).unwrap();
+    let now_ns = env.now_secs() * 1_000_000_000;
+    assert!(env.doc(deck).unwrap().uploaded_at >= now_ns, "stamped by the canister, not the client's 0");
+
+    let replace = |who, doc_id: u64| {
+        let file = ("deck-v2.pdf".to_string(), "application/pdf".to_string(), vec![3u8]);
+        let (res,): (Result<(), String>,) = env.update(who, "replace_doc", (doc_id, file.0, file.1, file.2));
+        res
+    };
+    let delete = |who, doc_id: u64| {
+        let (res,): (Result<(), String>,) = env.update(who, "delete_doc", (doc_id,));
+        res
+    };
+    assert!(replace(backer(1), deck).is_err());
+    assert!(delete(backer(1), wrong).is_err());
+    replace(owner, deck).unwrap();
+    let replaced = env.doc(deck).unwrap();
+    assert_eq!((replaced.id, replaced.name.as_str(), replaced.data), (deck, "deck-v2.pdf", vec![3]));
+
+    delete(owner, wrong).unwrap();
+    assert!(env.doc(wrong).is_none());
+    assert_eq!(env.idea(idea_id).unwrap().doc_ids, vec![deck]);
+    assert_eq!(delete(owner, wrong), Err(format!("doc {} not found", wrong)));
+    assert!(replace(owner, 999).unwrap_err().contains("not found"));
+    delete(controller(), deck).unwrap();
+    assert!(env.idea(idea_id).unwrap().doc_ids.is_empty());
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn docs_are_served_over_http_and_stream_when_large() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    let get = |url: String| {
+        let req = HttpRequest { method: "GET".into(), url, headers: vec![], body: vec![] };
+        let (res,): (HttpResponse,) = env.query(backer(1), "http_request", (req,));
+        res
+    };
+    let header = |res: &HttpResponse, name: &str| {
+        res.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone())
+    };
+
+    let small = env.upload_doc(owner, idea_id, "deck.pdf", vec![1, 2, 3]).unwrap();
+    let res = get(format!("/docs/{}", small));
+    assert_eq!((res.status_code, res.body.clone()), (200, vec![1, 2, 3]));
+    assert!(res.streaming_strategy.is_none());
+    assert!(header(&res, "Content-Disposition").unwrap().contains("filename=\"deck.pdf\""));
+    assert_eq!(get("/docs/999".into()).status_code, 404);
+
+    let size: u64 = 4_500_000;
+    let (upload,): (Result<u64, String>,) =
+        env.update(owner, "begin_doc_upload", (idea_id, "video.mp4".to_string(), "video/mp4".to_string(), size));
+    let upload = upload.unwrap();
+    for (i, part) in (0..size).map(|b| (b % 251) as u8).collect::<Vec<_>>().chunks(1_500_000).enumerate() {
+        let (res,): (Result<(), String>,) = env.update(owner, "upload_doc_chunk", (upload, i as u32, part.to_vec()));
+        res.unwrap();
+    }
+    let (big,): (Result<u64, String>,) = env.update(owner, "finish_doc_upload", (upload,));
+    let big = big.unwrap();
+
+    let res = get(format!("/docs/{}", big));
+    assert_eq!(header(&res, "Content-Type").as_deref(), Some("video/mp4"));
+    let mut body = res.body;
+    let Some(StreamingStrategy::Callback { mut token, .. }) = res.streaming_strategy else {
+        panic!("a 4.5 MB doc must stream");
+    };
+    loop {
+        let (chunk,): (StreamingCallbackHttpResponse,) =
+            env.query(backer(1), "http_request_streaming_callback", (token,));
+        body.extend(chunk.body);
+        match chunk.token {
+            Some(next) => token = next,
+            None => break,
+        }
+    }
+    assert_eq!(body.len() as u64, size);
+    assert!(body.iter().enumerate().all(|(i, b)| *b == (i % 251) as u8));
+
+    let index = get(format!("/ideas/{}/docs", idea_id));
+    let listed: serde_json::Value = serde_json::from_slice(&index.body).unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    assert_eq!(get("/ideas/999/docs".into()).status_code, 404);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+struct PartnerRef {
+    partner_id: u64,
+    name: String,
+    logo_doc_id: Option<u64>,
+    url: String,
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct PartneredCard {
+    id: u64,
+    partners: Vec<PartnerRef>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn partner_chips_show_only_while_approved() {
+    let env = TestEnv::new();
+    let (owner, partner) = (principal(1), principal(9));
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let campaign_id = env.create_campaign(owner, idea_id, 10_000, env.now_secs() + 5 * DAY_SECS).unwrap();
+
+    let (res,): (Result<u64, String>,) = env.update(
+        controller(),
+        "register_marketing_partner",
+        ("Cairo Accelerator".to_string(), None::<u64>, "https://accel.example".to_string(), Some(partner)),
+    );
+    let partner_id = res.unwrap();
+    let call = |sender, method: &str| {
+        let (res,): (Result<(), String>,) = env.update(sender, method, (campaign_id, partner_id));
+        res
+    };
+    let chips = || {
+        let (cards,): (Vec<PartneredCard>,) = env.query(backer(1), "get_campaign_cards", ());
+        cards.into_iter().find(|c| c.id == campaign_id).unwrap().partners
+    };
+
+    assert!(call(backer(1), "request_campaign_partner").is_err());
+    call(owner, "request_campaign_partner").unwrap();
+    assert!(chips().is_empty(), "requests don't display until approved");
+    assert!(call(owner, "approve_campaign_partner").is_err());
+    call(partner, "approve_campaign_partner").unwrap();
+    assert_eq!(chips().len(), 1);
+    let (listed,): (Vec<u64>,) = env.query(backer(1), "get_partner_campaigns", (partner_id,));
+    assert_eq!(listed, vec![campaign_id]);
+
+    call(partner, "remove_campaign_partner").unwrap();
+    assert!(chips().is_empty());
+    call(owner, "request_campaign_partner").unwrap();
+    call(controller(), "approve_campaign_partner").unwrap();
+    call(owner, "remove_campaign_partner").unwrap();
+    assert!(chips().is_empty());
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+enum CampaignOutcome {
+    Successful,
+    Failed,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct Payout {
+    to: candid::Principal,
+    amount: u64,
+    contribution_ids: Vec<u64>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn ended_campaigns_pay_out_once_to_backers_or_the_owner() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 2 * DAY_SECS;
+    let failed_id = env.create_campaign(principal(1), idea_id, 10_000, end).unwrap();
+    let funded_id = env.create_campaign(principal(1), idea_id, 500, end).unwrap();
+    env.contribute(backer(1), failed_id, 300).unwrap();
+    env.contribute(backer(1), failed_id, 200).unwrap();
+    env.contribute(backer(2), failed_id, 100).unwrap();
+    env.contribute(backer(2), funded_id, 500).unwrap();
+    let finalize = |campaign_id: u64| {
+        let (res,): (Result<CampaignOutcome, String>,) = env.update(backer(3), "finalize_campaign", (campaign_id,));
+        res
+    };
+    let payout = |sender, method: &str, campaign_id: u64| {
+        let (res,): (Result<Payout, String>,) = env.update(sender, method, (campaign_id,));
+        res
+    };
+
+    assert!(finalize(failed_id).is_err(), "still running");
+    assert!(payout(backer(1), "claim_refund", failed_id).is_err());
+    env.advance_days(3);
+    assert_eq!(finalize(failed_id).unwrap(), CampaignOutcome::Failed);
+    assert_eq!(finalize(failed_id).unwrap(), CampaignOutcome::Failed);
+
+    let refund = payout(backer(1), "claim_refund", failed_id).unwrap();
+    assert_eq!((refund.to, refund.amount, refund.contribution_ids.len()), (backer(1), 500, 2));
+    assert!(payout(backer(1), "claim_refund", failed_id).is_err(), "double claim");
+    assert!(payout(principal(1), "withdraw_raised", failed_id).is_err());
+    let card = env.campaign_cards().into_iter().find(|c| c.id == failed_id).unwrap();
+    assert_eq!(card.amount_raised, 100);
+
+    assert!(payout(backer(2), "claim_refund", funded_id).is_err(), "goal met");
+    assert!(payout(backer(2), "withdraw_raised", funded_id).is_err(), "owner only");
+    let withdrawal = payout(principal(1), "withdraw_raised", funded_id).unwrap();
+    assert_eq!((withdrawal.to, withdrawal.amount), (principal(1), 500));
+    assert!(payout(principal(1), "withdraw_raised", funded_id).is_err(), "double withdrawal");
+    assert_eq!(finalize(funded_id).unwrap(), CampaignOutcome::Successful);
+
+    env.upgrade();
+    assert!(payout(backer(1), "claim_refund", failed_id).is_err(), "ledger survives upgrades");
+    let (ledger,): (Vec<Payout>,) = env.query(backer(3), "get_campaign_payouts", (failed_id,));
+    assert_eq!(ledger.len(), 1);
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn relayed_icp_is_not_paid_out_again() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 2 * DAY_SECS;
+    let relayed = env.create_campaign_in(principal(1), idea_id, 500, end, Currency::Icp).unwrap();
+    env.relay_icp(relayed, 500);
+    env.advance_days(3);
+
+    let (res,): (Result<Payout, String>,) = env.update(principal(1), "withdraw_raised", (relayed,));
+    assert!(res.unwrap_err().contains("paid out there"), "Fund_Flow settles relayed ICP");
+    let (ledger,): (Vec<Payout>,) = env.query(principal(1), "get_campaign_payouts", (relayed,));
+    assert!(ledger.is_empty());
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct TakedownNotice {
+    notice: String,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn takedowns_leave_tombstones_and_queue_refunds() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    let doc_id = env.upload_doc(principal(1), idea_id, "deck.pdf", vec![1, 2, 3]).unwrap();
+    env.approve_idea(idea_id);
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, env.now_secs() + 5 * DAY_SECS).unwrap();
+    env.contribute(backer(1), campaign_id, 300).unwrap();
+
+    let (res,): (Result<(), String>,) = env.update(principal(1), "delete_idea", (idea_id,));
+    assert!(res.is_err(), "ideas with campaigns can't simply be deleted");
+    let takedown = |sender, idea_id, dry_run, token| env.takedown(sender, idea_id, "court order 12/3", dry_run, token);
+    assert!(takedown(principal(1), idea_id, true, None).is_err(), "admins only");
+    assert!(takedown(controller(), idea_id, false, None).is_err(), "dry-run first");
+    let preview = takedown(controller(), idea_id, true, None).unwrap();
+    assert!(preview.dry_run);
+    assert_eq!((preview.takedown.campaign_ids, preview.takedown.held_doc_ids), (vec![campaign_id], vec![doc_id]));
+    assert!(env.idea(idea_id).is_some() && env.doc(doc_id).is_some(), "a dry run takes nothing down");
+    let other = env.create_idea(principal(1), IdeaArgs::default().title("Someone else"));
+    assert!(takedown(controller(), other, false, preview.confirmation_token).is_err(), "tokens name one idea");
+    assert!(env.idea(other).is_some());
+    env.take_down_idea(idea_id, "court order 12/3");
+
+    assert!(env.idea(idea_id).is_none());
+    assert!(env.doc(doc_id).is_none());
+    let (held,): (Result<Option<Doc>, String>,) = env.query(controller(), "get_held_doc", (doc_id,));
+    assert_eq!(held.unwrap().unwrap().name, "deck.pdf");
+    let (held,): (Result<Option<Doc>, String>,) = env.query(backer(1), "get_held_doc", (doc_id,));
+    assert!(held.is_err());
+    let card = env.campaign_cards().into_iter().find(|c| c.id == campaign_id).expect("tombstone card");
+    assert_eq!((card.title.as_str(), card.amount_raised), ("Removed for legal reasons", 300));
+    let (notice,): (Option<TakedownNotice>,) = env.query(backer(1), "get_takedown_notice", (idea_id,));
+    assert_eq!(notice.unwrap().notice, "Removed for legal reasons");
+
+    assert!(env.contribute(backer(2), campaign_id, 100).is_err());
+    let (pending,): (Result<Vec<u64>, String>,) = env.query(controller(), "get_campaigns_pending_refund", ());
+    assert_eq!(pending.unwrap(), vec![campaign_id]);
+    let (refund,): (Result<Payout, String>,) = env.update(backer(1), "claim_refund", (campaign_id,));
+    assert_eq!(refund.unwrap().amount, 300);
+    let (orphans,): (Result<Vec<u64>, String>,) = env.query(controller(), "get_orphaned_campaigns", ());
+    assert!(orphans.unwrap().is_empty());
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct CampaignUpdate {
+    title: String,
+}
+
+#[derive(candid::CandidType, candid::Deserialize)]
+struct CampaignComment {
+    author: candid::Principal,
+    text: String,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn campaign_feeds_take_owner_updates_and_signed_in_comments() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, env.now_secs() + 5 * DAY_SECS).unwrap();
+    let post_update = |sender, title: &str, body: &str| {
+        let (res,): (Result<u64, String>,) =
+            env.update(sender, "post_campaign_update", (campaign_id, title.to_string(), body.to_string()));
+        res
+    };
+    let comment = |sender, text: String| {
+        let (res,): (Result<u64, String>,) = env.update(sender, "post_campaign_comment", (campaign_id, text));
+        res
+    };
+
+    post_update(principal(1), "Prototype done", "Shipping next week").unwrap();
+    post_update(principal(1), "Shipped", "Thanks, everyone").unwrap();
+    assert!(post_update(backer(1), "Not mine", "...").is_err(), "owner only");
+    assert!(post_update(principal(1), "Too long", &"x".repeat(10_001)).is_err());
+    let (updates,): (Vec<CampaignUpdate>,) = env.query(backer(1), "get_campaign_updates", (campaign_id,));
+    let titles: Vec<&str> = updates.iter().map(|u| u.title.as_str()).collect();
+    assert_eq!(titles, ["Shipped", "Prototype done"]);
+
+    comment(backer(1), "  When does it ship?  ".to_string()).unwrap();
+    assert!(comment(candid::Principal::anonymous(), "hi".to_string()).is_err());
+    assert!(comment(backer(1), "x".repeat(1_001)).is_err());
+    let (comments,): (Vec<CampaignComment>,) =
+        env.query(backer(2), "get_campaign_comments", (campaign_id, 0u64, 10u64));
+    assert_eq!(comments.len(), 1);
+    assert_eq!((comments[0].author, comments[0].text.as_str()), (backer(1), "When does it ship?"));
+
+    env.take_down_idea(idea_id, "court order");
+    let (updates,): (Vec<CampaignUpdate>,) = env.query(backer(1), "get_campaign_updates", (campaign_id,));
+    assert!(updates.is_empty());
+    assert!(comment(backer(1), "still there?".to_string()).is_err());
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+struct ChannelStats {
+    channel: Channel,
+    contributions: u64,
+    amount: u64,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn pledges_are_tagged_by_channel_and_implicit_channels_cant_be_claimed() {
+    let env = TestEnv::new();
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let campaign_id = env.create_campaign(principal(1), idea_id, 10_000, env.now_secs() + 5 * DAY_SECS).unwrap();
+    let contribute = |sender, amount: u64, channel: Option<Channel>| {
+        let (res,): (Result<u64, String>,) = env.update(sender, "contribute", (campaign_id, amount, channel, USD));
+        res
+    };
+
+    assert!(contribute(backer(1), 10, Some(Channel::Embed)).is_err());
+    assert!(contribute(backer(1), 10, Some(Channel::PartnerApi)).is_err());
+    contribute(backer(1), 10, Some(Channel::Web)).unwrap();
+    contribute(backer(2), 20, Some(Channel::MobileApp)).unwrap();
+    env.contribute(backer(3), campaign_id, 30).unwrap(); // no channel
+
+    let partner = principal(9);
+    let (res,): (Result<(), String>,) =
+        env.update(controller(), "register_syndication_partner", (partner, "Partner".to_string(), 10u64));
+    res.unwrap();
+    contribute(partner, 40, Some(Channel::Web)).unwrap();
+
+    let stats = |sender| {
+        let (res,): (Result<Vec<ChannelStats>, String>,) =
+            env.query(sender, "get_campaign_channel_stats", (campaign_id,));
+        res
+    };
+    assert!(stats(backer(1)).is_err(), "owners only");
+    let amounts: Vec<(Channel, u64)> = stats(principal(1)).unwrap().iter().map(|s| (s.channel, s.amount)).collect();
+    assert_eq!(
+        amounts,
+        [
+            (Channel::Unspecified, 30),
+            (Channel::Web, 10),
+            (Channel::Embed, 0),
+            (Channel::PartnerApi, 40),
+            (Channel::MobileApp, 20),
+        ]
+    );
+    let (platform,): (Result<Vec<ChannelStats>, String>,) = env.query(controller(), "get_platform_channel_stats", ());
+    assert_eq!(platform.unwrap().iter().map(|s| s.contributions).sum::<u64>(), 4);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct FiatGoal {
+    currency: String,
+    amount_cents: u64,
+    locked_rate: u64,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct FiatCard {
+    goal: u64,
+    fiat_goal: Option<FiatGoal>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct GoalRateLock {
+    previous_rate: Option<u64>,
+    rate: u64,
+    goal: u64,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn fiat_goals_lock_a_rate_and_refresh_it_mid_campaign() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let set_rate = |rate: u64| {
+        let (res,): (Result<(), String>,) = env.update(controller(), "set_exchange_rate", ("usd".to_string(), rate));
+        res.unwrap();
+    };
+    let create = |goal: u64| {
+        let fiat = FiatGoalRequest { currency: "USD".into(), amount_cents: 5_000_000 };
+        let end_date = env.now_secs() + 30 * DAY_SECS;
+        let (res,): (Result<u64, String>,) = env.update(
+            owner,
+            "create_campaign",
+            (idea_id, goal, end_date, None::<FundingRound>, None::<String>, None::<String>, None::<EndTime>, Some(fiat)),
+        );
+        res
+    };
+    let card = |campaign_id: u64| {
+        let (card,): (Option<CampaignWithIdea<FiatCard>>,) =
+            env.query(owner, "get_campaign_with_idea", (campaign_id,));
+        card.unwrap().campaign
+    };
+    let refresh = |sender, campaign_id: u64| {
+        let (res,): (Result<FiatGoal, String>,) = env.update(sender, "refresh_goal_rate", (campaign_id,));
+        res
+    };
+
+    assert!(create(0).is_err(), "no cached rate yet");
+    set_rate(20_000_000); // 0.2 ICP per dollar, in e8s
+    assert!(create(1).is_err(), "the token goal is derived");
+    let campaign_id = create(0).unwrap();
+    let locked = card(campaign_id);
+    assert_eq!(locked.goal, 1_000_000_000_000);
+    let fiat = locked.fiat_goal.unwrap();
+    assert_eq!((fiat.currency.as_str(), fiat.amount_cents, fiat.locked_rate), ("USD", 5_000_000, 20_000_000));
+
+    set_rate(10_000_000);
+    assert!(refresh(owner, campaign_id).is_err(), "at most once a day");
+    env.advance_days(2);
+    set_rate(10_000_000);
+    env.relay_icp(campaign_id, 250_000_000_000);
+    assert!(refresh(owner, campaign_id).is_err(), "backers pledged; admins only now");
+    assert_eq!(refresh(controller(), campaign_id).unwrap().locked_rate, 10_000_000);
+    assert_eq!(card(campaign_id).goal, 500_000_000_000);
+
+    let (progress,): (Option<Progress>,) = env.query(owner, "get_campaign_progress", (campaign_id,));
+    assert_eq!(progress.unwrap().percent_bps, 5_000, "percent in tokens: 2,500 of 5,000 ICP");
+    let (history,): (Vec<GoalRateLock>,) = env.query(owner, "get_goal_rate_history", (campaign_id,));
+    let locks: Vec<(Option<u64>, u64, u64)> = history.iter().map(|l| (l.previous_rate, l.rate, l.goal)).collect();
+    assert_eq!(locks, [(None, 20_000_000, 1_000_000_000_000), (Some(20_000_000), 10_000_000, 500_000_000_000)]);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct CampaignWithIdea<C> {
+    campaign: C,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct Progress {
+    percent_bps: u64,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn long_ideas_take_fifty_docs_and_survive_an_upgrade() {
+    let env = TestEnv::new();
+    let description = "Two paragraphs about the bottles. ".repeat(160); // ~5 KB
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default().description(&description));
+    let doc_ids: Vec<u64> = (0..50)
+        .map(|n| env.upload_doc(principal(1), idea_id, &format!("doc-{}.pdf", n), vec![n as u8]).expect("stored"))
+        .collect();
+
+    env.upgrade();
+    let idea = env.idea(idea_id).unwrap();
+    assert_eq!((idea.description, idea.doc_ids), (description, doc_ids.clone()));
+    assert_eq!(env.doc(doc_ids[49]).unwrap().data, vec![49]);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug)]
+struct ChecklistItem {
+    id: String,
+    label: String,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug)]
+struct ReviewStage {
+    name: String,
+    items: Vec<ChecklistItem>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct ReviewCheck {
+    note: Option<String>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct ReviewItem {
+    id: String,
+    completed: Option<ReviewCheck>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct StageReview {
+    name: String,
+    items: Vec<ReviewItem>,
+    signed_off: Option<ReviewCheck>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct IdeaReview {
+    stages: Vec<StageReview>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct ReviewQueueEntry {
+    idea_id: u64,
+    current_stage: Option<String>,
+}
+
+fn review_stage(name: &str, items: &[&str]) -> ReviewStage {
+    let item = |id: &&str| ChecklistItem { id: id.to_string(), label: format!("Check {}", id) };
+    ReviewStage { name: name.into(), items: items.iter().map(item).collect() }
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn staged_review_approves_on_the_last_sign_off() {
+    let env = TestEnv::new();
+    let set_pipeline = |stages: Vec<ReviewStage>| {
+        let (res,): (Result<u64, String>,) = env.update(controller(), "set_review_pipeline", ("Healthcare", stages));
+        res.unwrap()
+    };
+    let pipeline = vec![review_stage("Completeness", &["docs"]), review_stage("Legal", &["license"])];
+    set_pipeline(pipeline.clone());
+    let idea_id = env.create_idea(principal(1), IdeaArgs::default().category("healthcare"));
+    let other_id = env.create_idea(principal(2), IdeaArgs::default().category("HealthCare"));
+    let plain_id = env.create_idea(principal(3), IdeaArgs::default());
+
+    let tick = |idea_id: u64, stage: &str, item: &str| {
+        let (res,): (Result<IdeaReview, String>,) = env.update(
+            controller(),
+            "complete_review_item",
+            (idea_id, stage.to_string(), item.to_string(), Some("checked".to_string())),
+        );
+        res
+    };
+    let sign_off = |idea_id: u64, stage: &str| {
+        let (res,): (Result<IdeaReview, String>,) =
+            env.update(controller(), "sign_off_review_stage", (idea_id, stage.to_string(), None::<String>));
+        res
+    };
+    let approved = |idea_id: u64| env.idea(idea_id).unwrap().status == IdeaStatus::Approved;
+
+    let (res,): (Result<(), String>,) = env.update(controller(), "approve_idea", (idea_id,));
+    assert!(res.is_err(), "staged ideas aren't approved in one step");
+    assert!(tick(idea_id, "Legal", "license").is_err(), "Legal opens after Completeness");
+    assert!(sign_off(idea_id, "Completeness").is_err(), "docs not ticked");
+    let args = (idea_id, "Completeness".to_string(), "docs".to_string(), None::<String>);
+    let (res,): (Result<IdeaReview, String>,) = env.update(principal(1), "complete_review_item", args);
+    assert!(res.is_err(), "admins only");
+
+    tick(idea_id, "Completeness", "docs").unwrap();
+    sign_off(idea_id, "Completeness").unwrap();
+    tick(other_id, "Completeness", "docs").unwrap();
+    sign_off(other_id, "Completeness").unwrap();
+    tick(other_id, "Legal", "license").unwrap();
+
+    let (queue,): (Result<Vec<ReviewQueueEntry>, String>,) = env.query(controller(), "get_review_queue", ());
+    let stages: Vec<(u64, Option<String>)> = queue.unwrap().into_iter().map(|e| (e.idea_id, e.current_stage)).collect();
+    let legal = Some("Legal".to_string());
+    assert_eq!(stages, [(idea_id, legal.clone()), (other_id, legal), (plain_id, None)]);
+
+    // Reverting Completeness reopens it and closes Legal again; the ticks stay.
+    let (res,): (Result<IdeaReview, String>,) =
+        env.update(controller(), "revert_review_stage", (other_id, "Completeness".to_string()));
+    let review = res.unwrap();
+    assert!(review.stages.iter().all(|s| s.signed_off.is_none()));
+    assert!(review.stages[1].items[0].completed.as_ref().is_some_and(|c| c.note.as_deref() == Some("checked")));
+
+    // Adding a Legal item reaches both: neither has signed Legal off.
+    let mut edited = pipeline.clone();
+    edited[1].items.push(ChecklistItem { id: "aml".into(), label: "AML screening".into() });
+    assert_eq!(set_pipeline(edited), 2);
+    tick(idea_id, "Legal", "license").unwrap();
+    assert!(sign_off(idea_id, "Legal").is_err(), "aml not ticked");
+    tick(idea_id, "Legal", "aml").unwrap();
+    let review = sign_off(idea_id, "Legal").unwrap();
+    let shape: Vec<(&str, usize)> = review.stages.iter().map(|s| (s.name.as_str(), s.items.len())).collect();
+    assert_eq!(shape, [("Completeness", 1), ("Legal", 2)]);
+    assert!(approved(idea_id));
+    assert!(!approved(other_id));
+
+    // Ideas outside the pipeline's category keep the one-step decision.
+    env.approve_idea(plain_id);
+    assert!(approved(plain_id));
+    let (review,): (Result<Option<IdeaReview>, String>,) = env.query(controller(), "get_idea_review", (plain_id,));
+    assert!(review.unwrap().is_none());
+}
+
+#[derive(candid::CandidType, Default)]
+struct ProfileUpdate {
+    display_name: Option<String>,
+    email: Option<String>,
+    bio: Option<String>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+enum UserRole {
+    Member,
+    Admin,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct UserProfile {
+    principal: candid::Principal,
+    display_name: String,
+    email: Option<String>,
+    role: UserRole,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+enum FundVerseError {
+    Unauthorized,
+    AlreadyRegistered,
+    NotRegistered,
+    QuotaExceeded { quota: QuotaKind, limit: u64, retry_at: Option<u64> },
+    NotFound(EntityRef),
+    NotADraft { status: IdeaStatus },
+}
+
+#[derive(candid::CandidType, Default)]
+struct InitArgs {
+    admins: Vec<candid::Principal>,
+    ledger: Option<candid::Principal>,
+    require_profile: Option<bool>,
+    event_retention: Option<u32>,
+    quotas: Option<QuotaLimits>,
+    expiry_sweep_secs: Option<u64>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn profiles_register_once_and_can_gate_idea_creation() {
+    let env = TestEnv::new();
+    let register = |sender, name: &str| {
+        let (res,): (Result<UserProfile, FundVerseError>,) =
+            env.update(sender, "register_user", (name.to_string(), "ada@example.com".to_string(), String::new()));
+        res
+    };
+    let profile_of = |viewer, principal| {
+        let (profile,): (Option<UserProfile>,) = env.query(viewer, "get_profile", (principal,));
+        profile
+    };
+
+    assert_eq!(register(candid::Principal::anonymous(), "Anon").unwrap_err(), FundVerseError::Unauthorized);
+    let ada = register(principal(1), "Ada").unwrap();
+    assert_eq!((ada.principal, ada.role), (principal(1), UserRole::Member));
+    assert_eq!(register(principal(1), "Ada again").unwrap_err(), FundVerseError::AlreadyRegistered);
+
+    let update = ProfileUpdate { display_name: Some("Ada L.".into()), ..ProfileUpdate::default() };
+    let (res,): (Result<UserProfile, FundVerseError>,) = env.update(principal(1), "update_profile", (update,));
+    assert_eq!(res.unwrap().email.as_deref(), Some("ada@example.com"));
+    let (res,): (Result<UserProfile, FundVerseError>,) =
+        env.update(principal(2), "update_profile", (ProfileUpdate::default(),));
+    assert_eq!(res.unwrap_err(), FundVerseError::NotRegistered);
+
+    let (mine,): (Option<UserProfile>,) = env.query(principal(1), "get_my_profile", ());
+    assert_eq!(mine.unwrap().display_name, "Ada L.");
+    assert_eq!(profile_of(principal(2), principal(1)).unwrap().email, None, "emails stay private");
+    assert!(profile_of(controller(), principal(1)).unwrap().email.is_some());
+    let (res,): (Result<(), String>,) = env.update(controller(), "add_admin", (principal(1),));
+    res.unwrap();
+    assert_eq!(profile_of(principal(2), principal(1)).unwrap().role, UserRole::Admin);
+
+    // Published ideas are the approved ones.
+    let published = env.create_idea(principal(1), IdeaArgs::default());
+    env.create_idea(principal(1), IdeaArgs::default().title("Still in review"));
+    env.approve_idea(published);
+    let (ideas,): (Vec<(u64, Idea)>,) = env.query(principal(2), "get_published_ideas", (principal(1),));
+    assert_eq!(ideas.iter().map(|(id, _)| *id).collect::<Vec<_>>(), [published]);
+
+    let args = InitArgs { require_profile: Some(true), ..InitArgs::default() };
+    env.pic
+        .upgrade_canister(env.canister, backend_wasm(), candid::encode_one(Some(args)).unwrap(), Some(controller()))
+        .expect("upgrade failed");
+    let create = |sender| {
+        let a = IdeaArgs::default();
+        let (res,): (Result<u64, FundVerseError>,) = env.update(
+            sender,
+            "create_idea",
+            (a.title, a.description, a.funding_goal, a.legal_entity, a.contact_info, a.category, a.business_registration),
+        );
+        res
+    };
+    assert_eq!(create(principal(2)).unwrap_err(), FundVerseError::NotRegistered);
+    assert!(create(principal(1)).is_ok());
+    env.upgrade(); // no args: the requirement stays on
+    assert_eq!(create(principal(2)).unwrap_err(), FundVerseError::NotRegistered);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Copy, Debug, PartialEq)]
+enum FlagState {
+    Off,
+    AdminsOnly,
+    Percentage(u8),
+    On,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn feature_flags_roll_out_by_principal_and_survive_an_upgrade() {
+    let env = TestEnv::new();
+    let set = |who, name: &str, state| {
+        let (res,): (Result<(), String>,) = env.update(who, "set_feature_flag", (name.to_string(), state));
+        res
+    };
+    let enabled = |who| {
+        let (on,): (bool,) = env.query(who, "is_feature_enabled", ("matching_pools".to_string(),));
+        on
+    };
+    assert!(set(backer(1), "matching_pools", FlagState::On).is_err(), "admins only");
+    assert!(set(controller(), "Matching Pools", FlagState::On).is_err());
+    assert!(set(controller(), "matching_pools", FlagState::Percentage(101)).is_err());
+    assert!(!enabled(backer(1)), "unset flags are off");
+
+    set(controller(), "matching_pools", FlagState::AdminsOnly).unwrap();
+    assert!(enabled(controller()) && !enabled(backer(1)));
+    set(controller(), "matching_pools", FlagState::Percentage(50)).unwrap();
+    let rolled_out: Vec<bool> = (1..=20).map(|i| enabled(backer(i))).collect();
+    assert!(rolled_out.contains(&true) && rolled_out.contains(&false), "{:?}", rolled_out);
+
+    env.upgrade();
+    assert_eq!((1..=20).map(|i| enabled(backer(i))).collect::<Vec<_>>(), rolled_out, "same callers after upgrade");
+    let (flags,): (Result<Vec<(String, FlagState)>, String>,) = env.query(controller(), "get_feature_flags", ());
+    assert_eq!(flags.unwrap(), [("matching_pools".to_string(), FlagState::Percentage(50))]);
+    let (flags,): (Result<Vec<(String, FlagState)>, String>,) = env.query(backer(1), "get_feature_flags", ());
+    assert!(flags.is_err());
+    set(controller(), "matching_pools", FlagState::On).unwrap();
+    assert!(enabled(backer(1)));
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Copy, Debug, PartialEq)]
+enum DenialReason {
+    SignInRequired,
+    NotOwner,
+    NotAdmin,
+    WrongStatus,
+    Frozen,
+    HasCampaigns,
+    HasBackers,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct Permission {
+    allowed: bool,
+    denied_because: Option<DenialReason>,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct IdeaPermissions {
+    edit: Permission,
+    delete: Permission,
+    attach_docs: Permission,
+    create_campaign: Permission,
+    comment: Permission,
+    review: Permission,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct CampaignPermissions {
+    edit: Permission,
+    amend_risks: Permission,
+    cancel: Permission,
+    extend: Permission,
+    pause: Permission,
+    resume: Permission,
+    contribute: Permission,
+    add_milestone: Permission,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct DocPermissions {
+    replace: Permission,
+    delete: Permission,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct CommentPermissions {
+    delete: Permission,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+enum Permissions {
+    Idea(IdeaPermissions),
+    Campaign(CampaignPermissions),
+    Doc(DocPermissions),
+    Comment(CommentPermissions),
+}
+
+/// The call succeeds exactly when its permission said it would (all calls pass valid arguments).
+fn agrees<T>(permission: &Permission, result: Result<T, String>, action: &str) {
+    match result {
+        Ok(_) => assert!(permission.allowed, "{} succeeded but was denied: {:?}", action, permission),
+        Err(e) => assert!(!permission.allowed, "{} was allowed but failed: {}", action, e),
+    }
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn permissions_match_what_the_endpoints_allow() {
+    let env = TestEnv::new();
+    let (owner, stranger, anonymous) = (principal(1), principal(2), candid::Principal::anonymous());
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    let doc_id = env.upload_doc(owner, idea_id, "deck.pdf", vec![1]).unwrap();
+    let permissions = |who, entity| {
+        let (res,): (Result<Permissions, candid::Reserved>,) = env.query(who, "get_permissions", (entity,));
+        res.expect("entity exists")
+    };
+    let idea_permissions = |who| match permissions(who, EntityRef::Idea(idea_id)) {
+        Permissions::Idea(p) => p,
+        other => panic!("{:?}", other),
+    };
+    let comment = |who| {
+        let (res,): (Result<u64, String>,) =
+            env.update(who, "post_idea_comment", (idea_id, "Hi".to_string(), None::<u64>));
+        res
+    };
+    let ok = |res: Result<(), String>| res.map(|_| 0u64);
+    let edit = |who| {
+        let payload = UpdateIdeaPayload { title: Some("Retitled".into()), ..Default::default() };
+        let (res,): (Result<(), String>,) = env.update(who, "update_idea", (idea_id, payload));
+        ok(res)
+    };
+    let attach = |who| env.upload_doc(who, idea_id, "more.pdf", vec![2]).ok_or_else(|| "refused".to_string());
+    let campaign = |who| env.create_campaign(who, idea_id, 10_000, env.now_secs() + 7 * DAY_SECS);
+    let review = |who| {
+        let (res,): (Result<(), String>,) = env.update(who, "approve_idea", (idea_id,));
+        ok(res)
+    };
+    let delete = |who| {
+        let (res,): (Result<(), String>,) = env.update(who, "delete_idea", (idea_id,));
+        ok(res)
+    };
+
+    for who in [stranger, anonymous] {
+        let p = idea_permissions(who);
+        assert_eq!(p.edit.denied_because, Some(DenialReason::NotOwner));
+        assert_eq!(p.review.denied_because, Some(DenialReason::NotAdmin));
+        agrees(&p.edit, edit(who), "edit");
+        agrees(&p.attach_docs, attach(who), "attach_docs");
+        agrees(&p.create_campaign, campaign(who), "create_campaign");
+        agrees(&p.comment, comment(who), "comment");
+        agrees(&p.review, review(who), "review");
+        agrees(&p.delete, delete(who), "delete");
+    }
+    assert_eq!(idea_permissions(anonymous).comment.denied_because, Some(DenialReason::SignInRequired));
+
+    let p = idea_permissions(owner);
+    assert_eq!(p.create_campaign.denied_because, Some(DenialReason::WrongStatus), "not approved yet");
+    agrees(&p.edit, edit(owner), "edit");
+    agrees(&p.attach_docs, attach(owner), "attach_docs");
+    agrees(&p.create_campaign, campaign(owner), "create_campaign");
+    agrees(&p.comment, comment(owner), "comment");
+    agrees(&p.review, review(owner), "review");
+    agrees(&idea_permissions(controller()).review, review(controller()), "review");
+
+    let p = idea_permissions(owner);
+    agrees(&p.comment, comment(owner), "comment");
+    let campaign_id = campaign(owner);
+    agrees(&p.create_campaign, campaign_id.clone(), "create_campaign");
+    let campaign_id = campaign_id.unwrap();
+    let p = idea_permissions(owner);
+    assert_eq!(p.delete.denied_because, Some(DenialReason::HasCampaigns));
+    agrees(&p.delete, delete(owner), "delete");
+
+    let campaign_permissions = |who| match permissions(who, EntityRef::Campaign(campaign_id)) {
+        Permissions::Campaign(p) => p,
+        other => panic!("{:?}", other),
+    };
+    let set_copy = |who| {
+        let (res,): (Result<(), String>,) =
+            env.update(who, "set_campaign_copy", (campaign_id, Some("Headline".to_string()), None::<String>));
+        ok(res)
+    };
+    let amend = |who| {
+        let (res,): (Result<(), String>,) =
+            env.update(who, "amend_campaign_risks", (campaign_id, "New risk".to_string()));
+        ok(res)
+    };
+    let pause = |who| {
+        let (res,): (Result<(), String>,) = env.update(who, "pause_contributions", (campaign_id, "Audit".to_string()));
+        ok(res)
+    };
+    let resume = |who| {
+        let (res,): (Result<(), String>,) = env.update(who, "resume_contributions", (campaign_id,));
+        ok(res)
+    };
+    let extend = |who| {
+        let (res,): (Result<(), String>,) =
+            env.update(who, "extend_campaign", (campaign_id, env.now_secs() + 30 * DAY_SECS));
+        ok(res)
+    };
+    let cancel = |who| {
+        let (res,): (Result<(), String>,) = env.update(who, "cancel_campaign", (campaign_id,));
+        ok(res)
+    };
+    let add_milestone = |who, target: u64| {
+        let args = (campaign_id, "Prototype".to_string(), String::new(), target, env.now_secs() + DAY_SECS);
+        let (res,): (Result<candid::Reserved, String>,) = env.update(who, "add_milestone", args);
+        res.map(|_| 0u64)
+    };
+
+    let p = campaign_permissions(stranger);
+    assert_eq!(p.cancel.denied_because, Some(DenialReason::NotOwner));
+    for (permission, result, action) in [
+        (&p.edit, set_copy(stranger), "edit"),
+        (&p.amend_risks, amend(stranger), "amend_risks"),
+        (&p.pause, pause(stranger), "pause"),
+        (&p.resume, resume(stranger), "resume"),
+        (&p.extend, extend(stranger), "extend"),
+        (&p.cancel, cancel(stranger), "cancel"),
+        (&p.add_milestone, add_milestone(stranger, 1_000), "add_milestone"),
+    ] {
+        agrees(permission, result, action);
+    }
+    agrees(&campaign_permissions(owner).edit, set_copy(owner), "edit");
+    agrees(&campaign_permissions(anonymous).contribute, env.contribute(anonymous, campaign_id, 10), "contribute");
+    agrees(&campaign_permissions(stranger).contribute, env.contribute(stranger, campaign_id, 10), "contribute");
+
+    let p = campaign_permissions(owner);
+    assert_eq!(p.edit.denied_because, Some(DenialReason::HasBackers));
+    assert_eq!(p.resume.denied_because, Some(DenialReason::WrongStatus), "not paused");
+    agrees(&p.edit, set_copy(owner), "edit");
+    agrees(&p.amend_risks, amend(owner), "amend_risks");
+    agrees(&p.resume, resume(owner), "resume");
+    agrees(&p.pause, pause(owner), "pause");
+    let p = campaign_permissions(stranger);
+    assert_eq!(p.contribute.denied_because, Some(DenialReason::WrongStatus), "paused");
+    agrees(&p.contribute, env.contribute(stranger, campaign_id, 10), "contribute");
+    agrees(&campaign_permissions(owner).resume, resume(owner), "resume");
+    let p = campaign_permissions(owner);
+    agrees(&p.extend, extend(owner), "extend");
+    agrees(&p.add_milestone, add_milestone(owner, 1_000), "add_milestone");
+    agrees(&p.cancel, cancel(owner), "cancel");
+    let p = campaign_permissions(owner);
+    assert_eq!(p.cancel.denied_because, Some(DenialReason::WrongStatus));
+    agrees(&p.add_milestone, add_milestone(owner, 2_000), "add_milestone");
+
+    let doc_permissions = |who| match permissions(who, EntityRef::Doc(doc_id)) {
+        Permissions::Doc(p) => p,
+        other => panic!("{:?}", other),
+    };
+    let replace = |who| {
+        let (res,): (Result<(), String>,) = env.update(
+            who,
+            "replace_doc",
+            (doc_id, "deck-v2.pdf".to_string(), "application/pdf".to_string(), vec![3u8]),
+        );
+        ok(res)
+    };
+    let delete_doc = |who| {
+        let (res,): (Result<(), String>,) = env.update(who, "delete_doc", (doc_id,));
+        ok(res)
+    };
+    for who in [stranger, owner] {
+        let p = doc_permissions(who);
+        agrees(&p.replace, replace(who), "replace_doc");
+        agrees(&p.delete, delete_doc(who), "delete_doc");
+    }
+    let (missing,): (Result<Permissions, candid::Reserved>,) =
+        env.query(owner, "get_permissions", (EntityRef::Doc(doc_id),));
+    assert!(missing.is_err(), "deleted docs are not found");
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+enum MilestoneStatus {
+    Planned,
+    Reached,
+    Delivered,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct Milestone {
+    id: u64,
+    campaign_id: u64,
+    target_amount: u64,
+    status: MilestoneStatus,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn milestones_are_reached_by_pledges_and_delivered_by_the_owner() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let campaign_id = env.create_campaign(owner, idea_id, 1_000, env.now_secs() + 7 * DAY_SECS).unwrap();
+    let add = |who, target: u64| -> Result<Milestone, String> {
+        let args = (campaign_id, format!("At {}", target), String::new(), target, env.now_secs() + 30 * DAY_SECS);
+        let (res,): (Result<Milestone, String>,) = env.update(who, "add_milestone", args);
+        res
+    };
+    let statuses = || -> Vec<MilestoneStatus> {
+        let (milestones,): (Vec<Milestone>,) = env.query(owner, "get_milestones", (campaign_id,));
+        milestones.into_iter().map(|m| m.status).collect()
+    };
+
+    assert!(add(principal(2), 250).is_err(), "owner only");
+    let first = add(owner, 250).unwrap();
+    assert_eq!((first.campaign_id, first.status), (campaign_id, MilestoneStatus::Planned));
+    let second = add(owner, 600).unwrap();
+    assert!(add(owner, 600).is_err(), "targets must rise");
+    assert!(add(owner, 1_001).is_err(), "targets stay within the goal");
+
+    env.contribute(backer(1), campaign_id, 200).unwrap();
+    assert_eq!(statuses(), [MilestoneStatus::Planned, MilestoneStatus::Planned]);
+    env.contribute(backer(2), campaign_id, 100).unwrap();
+    assert_eq!(statuses(), [MilestoneStatus::Reached, MilestoneStatus::Planned]);
+    assert!(add(owner, 300).unwrap_err().contains("above"), "below the last target");
+    let third = add(owner, 800).unwrap();
+    assert_eq!(third.target_amount, 800);
+
+    let deliver = |who, milestone_id: u64| -> Result<Milestone, String> {
+        let (res,): (Result<Milestone, String>,) = env.update(who, "mark_milestone_delivered", (milestone_id,));
+        res
+    };
+    assert!(deliver(owner, second.id).is_err(), "not reached yet");
+    assert!(deliver(principal(2), first.id).is_err(), "owner only");
+    assert_eq!(deliver(owner, first.id).unwrap().status, MilestoneStatus::Delivered);
+    assert!(deliver(owner, first.id).is_err(), "delivered once");
+
+    env.contribute(backer(1), campaign_id, 700).unwrap();
+    assert_eq!(statuses(), [MilestoneStatus::Delivered, MilestoneStatus::Reached, MilestoneStatus::Reached]);
+    env.upgrade();
+    assert_eq!(statuses(), [MilestoneStatus::Delivered, MilestoneStatus::Reached, MilestoneStatus::Reached]);
+    assert!(deliver(owner, third.id).is_ok());
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq, Clone, Copy)]
+enum EventKind {
+    IdeaCreated,
+    IdeaApproved,
+    CampaignCreated,
+    ContributionReceived,
+    DocUploaded,
+    CampaignCompleted,
+    CampaignExpired,
+    RecordQuarantined,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct Event {
+    id: u64,
+    actor: candid::Principal,
+    kind: EventKind,
+    entity_id: u64,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct EventPage {
+    items: Vec<Event>,
+    total: u64,
+    next_offset: Option<u64>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn activity_is_logged_per_entity_in_call_order() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let doc_id = env.upload_doc(owner, idea_id, "deck.pdf", vec![1]).unwrap();
+    let campaign_id = env.create_campaign(owner, idea_id, 1_000, env.now_secs() + 7 * DAY_SECS).unwrap();
+    let pledge = env.contribute(backer(1), campaign_id, 100).unwrap();
+
+    let feed = |method: &str, entity_id: u64, offset: u64| -> EventPage {
+        let (page,): (EventPage,) = env.query(owner, method, (entity_id, offset, 10u64));
+        page
+    };
+    let kinds = |page: &EventPage| page.items.iter().map(|e| (e.kind, e.entity_id)).collect::<Vec<_>>();
+    let idea_feed = feed("get_events_for_idea", idea_id, 0);
+    assert_eq!(
+        kinds(&idea_feed),
+        [
+            (EventKind::CampaignCreated, campaign_id),
+            (EventKind::DocUploaded, doc_id),
+            (EventKind::IdeaApproved, idea_id),
+            (EventKind::IdeaCreated, idea_id),
+        ]
+    );
+    assert_eq!(idea_feed.items[2].actor, controller(), "approved by the admin");
+    let campaign_feed = feed("get_events_for_campaign", campaign_id, 0);
+    assert_eq!(
+        kinds(&campaign_feed),
+        [(EventKind::ContributionReceived, pledge), (EventKind::CampaignCreated, campaign_id)]
+    );
+    assert_eq!(campaign_feed.items[0].actor, backer(1));
+    let (recent,): (Vec<Event>,) = env.query(owner, "get_recent_events", (2u64,));
+    let recent_kinds: Vec<EventKind> = recent.iter().map(|e| e.kind).collect();
+    assert_eq!(recent_kinds, [EventKind::ContributionReceived, EventKind::CampaignCreated]);
+    assert!(recent[0].id > recent[1].id);
+    assert!(env.contribute(backer(1), campaign_id, 0).is_err());
+    assert_eq!(feed("get_events_for_campaign", campaign_id, 0).total, 2, "failed calls leave no event");
+
+    let args = InitArgs { event_retention: Some(2), ..InitArgs::default() };
+    env.pic
+        .upgrade_canister(env.canister, backend_wasm(), candid::encode_one(Some(args)).unwrap(), Some(controller()))
+        .expect("upgrade failed");
+    assert_eq!(feed("get_events_for_idea", idea_id, 0).total, 4, "trimmed on the next event");
+    env.upload_doc(owner, idea_id, "budget.pdf", vec![2]).unwrap();
+    let idea_feed = feed("get_events_for_idea", idea_id, 0);
+    assert_eq!(idea_feed.total, 2);
+    assert_eq!(idea_feed.items[1].kind, EventKind::CampaignCreated);
+    let page = feed("get_events_for_idea", idea_id, 1);
+    assert_eq!((page.items.len(), page.next_offset), (1, None));
+    assert_eq!(feed("get_events_for_campaign", campaign_id, 0).total, 2, "other entities keep theirs");
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, Clone, Copy, PartialEq)]
+enum ExportEntity {
+    Ideas,
+    Campaigns,
+    Docs,
+    Contributions,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct ImportIssue {
+    entity: ExportEntity,
+    id: u64,
+    message: String,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+enum ImportError {
+    Unauthorized(String),
+    Invalid(Vec<ImportIssue>),
+    Unconfirmed(String),
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct ImportReport {
+    dry_run: bool,
+    ideas: u64,
+    campaigns: u64,
+    docs: u64,
+    contributions: u64,
+    skipped: Vec<(ExportEntity, u64)>,
+    confirmation_token: Option<String>,
+}
+
+/// Export bundles are passed from one canister to another as untyped candid values, typed by the
+/// committed .did, so the test needs no full mirrors of Idea and Campaign.
+struct Bundles {
+    types: candid::TypeEnv,
+    actor: candid::types::Type,
+}
+
+impl Bundles {
+    fn new() -> Self {
+        let did = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("FundVerse_backend.did");
+        let (types, actor) = candid_parser::utils::CandidSource::File(&did).load().expect("load .did");
+        Bundles { types, actor: actor.expect("service") }
+    }
+
+    fn export(&self, env: &TestEnv, method: &str, args: Vec<u8>) -> candid::IDLValue {
+        let rets = &self.types.get_method(&self.actor, method).unwrap().rets;
+        let reply = match env.pic.query_call(env.canister, controller(), method, args).unwrap() {
+            pocket_ic::WasmResult::Reply(bytes) => candid::IDLArgs::from_bytes_with_types(&bytes, &self.types, rets),
+            pocket_ic::WasmResult::Reject(e) => panic!("{} rejected: {}", method, e),
+        };
+        match reply.unwrap().args.remove(0) {
+            candid::IDLValue::Variant(candid::types::value::VariantValue(ok, 0)) => ok.val,
+            other => panic!("{} failed: {}", method, other),
+        }
+    }
+
+    fn import(
+        &self,
+        env: &TestEnv,
+        sender: candid::Principal,
+        bundle: &candid::IDLValue,
+        mode: &str,
+        dry_run: bool,
+        token: Option<String>,
+    ) -> Result<ImportReport, ImportError> {
+        let mode = candid_parser::parse_idl_value(&format!("variant {{ {} }}", mode)).unwrap();
+        let token =
+            token.map_or(candid::IDLValue::None, |t| candid::IDLValue::Opt(Box::new(candid::IDLValue::Text(t))));
+        let params = &self.types.get_method(&self.actor, "import_data").unwrap().args;
+        let args = [bundle.clone(), mode, candid::IDLValue::Bool(dry_run), token];
+        let args = candid::IDLArgs::new(&args).to_bytes_with_types(&self.types, params).unwrap();
+        match env.pic.update_call(env.canister, sender, "import_data", args).unwrap() {
+            pocket_ic::WasmResult::Reply(bytes) => candid::decode_one(&bytes).unwrap(),
+            pocket_ic::WasmResult::Reject(e) => panic!("import_data rejected: {}", e),
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn exports_restore_into_another_canister_whole_or_in_chunks() {
+    let source = TestEnv::new();
+    let owner = principal(1);
+    let draft = source.create_idea(owner, IdeaArgs::default().title("Still in review"));
+    let idea_id = source.create_idea(owner, IdeaArgs::default().title("Solar Kiosk"));
+    source.approve_idea(idea_id);
+    let doc_id = source.upload_doc(owner, idea_id, "deck.pdf", vec![7; 64]).unwrap();
+    let campaign_id = source.create_campaign(owner, idea_id, 1_000, source.now_secs() + 7 * DAY_SECS).unwrap();
+    source.contribute(backer(1), campaign_id, 250).unwrap();
+
+    let bundles = Bundles::new();
+    let (res,): (Result<candid::Reserved, String>,) = source.query(owner, "export_data", (true,));
+    assert!(res.is_err(), "admins only");
+    let bundle = bundles.export(&source, "export_data", candid::encode_one(true).unwrap());
+
+    let target = TestEnv::new();
+    let replace = |sender, dry_run, token| bundles.import(&target, sender, &bundle, "ReplaceAll", dry_run, token);
+    assert!(matches!(replace(owner, true, None), Err(ImportError::Unauthorized(_))));
+    assert!(matches!(replace(controller(), false, None), Err(ImportError::Unconfirmed(_))), "dry-run first");
+    let preview = replace(controller(), true, None).unwrap();
+    assert!(preview.dry_run && target.campaign_cards().is_empty(), "a dry run writes nothing");
+    assert_eq!((preview.ideas, preview.campaigns, preview.docs, preview.contributions), (2, 1, 1, 1));
+    let report = replace(controller(), false, preview.confirmation_token.clone()).unwrap();
+    assert_eq!((report.ideas, report.campaigns, report.docs, report.contributions), (2, 1, 1, 1));
+    assert!(!report.dry_run);
+    assert!(matches!(replace(controller(), false, preview.confirmation_token), Err(ImportError::Unconfirmed(_))));
+    assert_eq!(target.idea(idea_id).unwrap().title, "Solar Kiosk");
+    assert_eq!(target.idea(draft).unwrap().status, IdeaStatus::Pending);
+    assert_eq!(target.doc(doc_id).unwrap().data, vec![7; 64]);
+    let cards = target.campaign_cards();
+    assert_eq!((cards.len(), cards[0].id, cards[0].amount_raised), (1, campaign_id, 250));
+    let (pledges,): (Vec<Contribution>,) = target.query(backer(1), "get_contributions_by_campaign", (campaign_id,));
+    assert_eq!(pledges.len(), 1);
+    assert!(target.create_idea(owner, IdeaArgs::default().title("After the import")) > idea_id, "no id collisions");
+
+    let again = bundles.import(&target, controller(), &bundle, "MergeSkipExisting", false, None).unwrap();
+    assert_eq!((again.ideas, again.campaigns, again.skipped.len()), (0, 0, 5), "everything was already there");
+
+    let chunk = |entity: ExportEntity| {
+        let args = candid::encode_args((entity, None::<u64>, 100u64, false)).unwrap();
+        bundles.export(&source, "export_data_chunk", args)
+    };
+    let seeded = TestEnv::new();
+    match bundles.import(&seeded, controller(), &chunk(ExportEntity::Campaigns), "MergeSkipExisting", false, None) {
+        Err(ImportError::Invalid(issues)) => {
+            assert_eq!(issues.len(), 1);
+            assert_eq!((issues[0].entity, issues[0].id), (ExportEntity::Campaigns, campaign_id));
+            assert!(issues[0].message.contains("not found"), "{}", issues[0].message);
+        }
+        other => panic!("campaigns without their ideas were accepted: {:?}", other),
+    }
+    assert!(seeded.campaign_cards().is_empty(), "nothing imported");
+    for entity in [ExportEntity::Ideas, ExportEntity::Docs, ExportEntity::Campaigns, ExportEntity::Contributions] {
+        bundles.import(&seeded, controller(), &chunk(entity), "MergeSkipExisting", false, None).unwrap();
+    }
+    assert_eq!(seeded.campaign_cards()[0].amount_raised, 250);
+    assert!(seeded.doc(doc_id).unwrap().data.is_empty(), "chunks were exported without doc bytes");
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Copy, Debug, PartialEq)]
+enum QuotaKind {
+    IdeasPerDay,
+    UploadBytesPerDay,
+    DocsPerIdea,
+    DocSize,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+struct QuotaLimits {
+    ideas_per_day: u64,
+    upload_bytes_per_day: u64,
+    docs_per_idea: u64,
+    max_doc_bytes: u64,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct QuotaUsage {
+    exempt: bool,
+    ideas_created: u64,
+    bytes_uploaded: u64,
+    resets_at: Option<u64>,
+    limits: QuotaLimits,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn quotas_limit_ideas_and_uploads_per_day_and_reset_after_it() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let limits = QuotaLimits { ideas_per_day: 2, upload_bytes_per_day: 100, docs_per_idea: 2, max_doc_bytes: 80 };
+    let (res,): (Result<(), String>,) = env.update(owner, "set_quota_limits", (limits.clone(),));
+    assert!(res.is_err(), "admins only");
+    let (res,): (Result<(), String>,) = env.update(controller(), "set_quota_limits", (limits.clone(),));
+    res.unwrap();
+    let create = |sender| {
+        let a = IdeaArgs::default();
+        let (res,): (Result<u64, FundVerseError>,) = env.update(
+            sender,
+            "create_idea",
+            (a.title, a.description, a.funding_goal, a.legal_entity, a.contact_info, a.category, a.business_registration),
+        );
+        res
+    };
+    let usage = |sender| {
+        let (usage,): (QuotaUsage,) = env.query(sender, "get_my_quota_usage", ());
+        usage
+    };
+    let begin_upload = |idea_id: u64, total_size: u64| {
+        let (res,): (Result<u64, String>,) = env.update(
+            owner,
+            "begin_doc_upload",
+            (idea_id, "big.pdf".to_string(), "application/pdf".to_string(), total_size),
+        );
+        res
+    };
+
+    let idea_id = create(owner).unwrap();
+    create(owner).unwrap();
+    match create(owner).unwrap_err() {
+        FundVerseError::QuotaExceeded { quota: QuotaKind::IdeasPerDay, limit: 2, retry_at } => {
+            assert!(retry_at.unwrap() > env.now_secs() * 1_000_000_000)
+        }
+        other => panic!("expected the daily idea quota, got {:?}", other),
+    }
+    assert!(create(controller()).is_ok(), "admins are exempt");
+    assert!(usage(controller()).exempt);
+
+    assert_eq!(env.upload_doc(owner, idea_id, "huge.pdf", vec![1; 81]), None, "over max_doc_bytes");
+    env.upload_doc(owner, idea_id, "deck.pdf", vec![1; 60]).unwrap();
+    assert_eq!(env.upload_doc(owner, idea_id, "more.pdf", vec![1; 50]), None, "110 of 100 bytes today");
+    begin_upload(idea_id, 40).unwrap(); // counted though it's never finished
+    let today = usage(owner);
+    assert_eq!((today.exempt, today.ideas_created, today.bytes_uploaded), (false, 2, 100));
+    assert_eq!(today.limits, limits);
+    assert!(begin_upload(idea_id, 1).unwrap_err().contains("per 24 hours"));
+
+    env.advance_days(1);
+    let tomorrow = usage(owner);
+    assert_eq!((tomorrow.ideas_created, tomorrow.bytes_uploaded, tomorrow.resets_at), (0, 0, None));
+    assert!(create(owner).is_ok());
+    env.upload_doc(owner, idea_id, "budget.pdf", vec![2; 20]).unwrap();
+    assert_eq!(env.upload_doc(owner, idea_id, "third.pdf", vec![3; 10]), None, "two docs per idea");
+    assert!(begin_upload(idea_id, 10).unwrap_err().contains("at most 2 documents"));
+
+    env.upgrade(); // no args: the limits stay
+    assert_eq!(usage(owner).limits, limits);
+    let bad = QuotaLimits { docs_per_idea: 0, ..limits };
+    let args = InitArgs { quotas: Some(bad), ..InitArgs::default() };
+    let upgrade = env.pic.upgrade_canister(
+        env.canister,
+        backend_wasm(),
+        candid::encode_one(Some(args)).unwrap(),
+        Some(controller()),
+    );
+    assert!(upgrade.is_err(), "invalid limits fail the upgrade");
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Clone, Debug, PartialEq)]
+struct Category {
+    id: u64,
+    slug: String,
+    display_name: String,
+    active: bool,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct IdeaPage {
+    items: Vec<(u64, Idea)>,
+    total: u64,
+    next_offset: Option<u64>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn ideas_take_registered_categories_in_any_case_and_keep_deactivated_ones() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let categories = || {
+        let (categories,): (Vec<Category>,) = env.query(owner, "list_categories", ());
+        categories
+    };
+    let slugs: Vec<String> = categories().into_iter().map(|c| c.slug).collect();
+    assert!(slugs.contains(&"technology".to_string()) && slugs.contains(&"environment".to_string()));
+
+    let (res,): (Result<Category, String>,) =
+        env.update(owner, "add_category", ("gaming".to_string(), "Gaming".to_string()));
+    assert!(res.is_err(), "admins only");
+    let create = |category: &str| {
+        let a = IdeaArgs::default().category(category);
+        let (res,): (Result<u64, candid::Reserved>,) = env.update(
+            owner,
+            "create_idea",
+            (a.title, a.description, a.funding_goal, a.legal_entity, a.contact_info, a.category, a.business_registration),
+        );
+        res
+    };
+    assert!(create("Gaming").is_err(), "not registered yet");
+    let (res,): (Result<Category, String>,) =
+        env.update(controller(), "add_category", ("Gaming".to_string(), "Gaming".to_string()));
+    let gaming = res.unwrap();
+    assert_eq!(gaming.slug, "gaming");
+    let (res,): (Result<Category, String>,) =
+        env.update(controller(), "add_category", ("GAMING".to_string(), "Again".to_string()));
+    assert!(res.is_err(), "slugs are unique in any case");
+
+    let idea_id = create(" GAMING ").unwrap();
+    assert_eq!(env.idea(idea_id).unwrap().category, "gaming", "stored canonical");
+    let (res,): (Result<Category, String>,) =
+        env.update(controller(), "rename_category", (gaming.id, "Games".to_string()));
+    assert_eq!(res.unwrap().display_name, "Games");
+    let (res,): (Result<Category, String>,) = env.update(controller(), "deactivate_category", (gaming.id,));
+    assert!(!res.unwrap().active);
+    assert!(create("gaming").is_err(), "not offered for new ideas");
+    assert!(categories().iter().any(|c| c.slug == "gaming" && !c.active), "still listed");
+
+    let edit = |category: &str| {
+        let p = UpdateIdeaPayload { category: Some(category.to_string()), ..UpdateIdeaPayload::default() };
+        let (res,): (Result<(), String>,) = env.update(owner, "update_idea", (idea_id, p));
+        res
+    };
+    let p = UpdateIdeaPayload { title: Some("Retro Arcade".into()), ..UpdateIdeaPayload::default() };
+    let (res,): (Result<(), String>,) = env.update(owner, "update_idea", (idea_id, p));
+    res.unwrap();
+    edit("gaming").unwrap(); // unchanged, so still fine
+    assert!(edit("Sports TV").unwrap_err().contains("list_categories"));
+    edit("Sports").unwrap();
+    assert_eq!(env.idea(idea_id).unwrap().category, "sports");
+
+    // Campaign cards filter on the canonical slug whatever case the caller uses.
+    env.approve_idea(idea_id);
+    env.create_campaign(owner, idea_id, 1_000, env.now_secs() + 7 * DAY_SECS).unwrap();
+    let (page,): (CardPage,) = env.query(owner, "get_campaign_cards_by_category", ("SPORTS".to_string(), 0u64, 10u64));
+    assert_eq!(page.total, 1);
+
+    let (res,): (Result<IdeaPage, String>,) = env.query(owner, "get_ideas_with_invalid_category", (0u64, 10u64));
+    assert!(res.is_err(), "admins only");
+    let (res,): (Result<IdeaPage, String>,) =
+        env.query(controller(), "get_ideas_with_invalid_category", (0u64, 10u64));
+    assert_eq!(res.unwrap().total, 0);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct ExpirySweep {
+    finalized: Vec<(u64, CampaignOutcome)>,
+    more_due: bool,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn ended_campaigns_are_finalized_by_the_hourly_sweep() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default());
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + DAY_SECS;
+    let funded = env.create_campaign(owner, idea_id, 500, end).unwrap();
+    let missed = env.create_campaign(owner, idea_id, 10_000, end).unwrap();
+    let later = env.create_campaign(owner, idea_id, 500, end + 7 * DAY_SECS).unwrap();
+    env.contribute(backer(1), funded, 500).unwrap();
+    env.contribute(backer(2), missed, 100).unwrap();
+    let sweep = |sender| {
+        let (res,): (Result<ExpirySweep, String>,) = env.update(sender, "run_expiry_sweep", ());
+        res
+    };
+    let latest_kind = |campaign_id: u64| {
+        let (page,): (EventPage,) = env.query(owner, "get_events_for_campaign", (campaign_id, 0u64, 1u64));
+        page.items[0].kind
+    };
+
+    assert!(sweep(owner).is_err(), "admins only");
+    assert!(sweep(controller()).unwrap().finalized.is_empty(), "nothing has ended");
+    env.upgrade(); // timers don't survive it, so the hooks register the sweep again
+    env.advance_days(2);
+    assert_eq!(latest_kind(funded), EventKind::CampaignCompleted);
+    assert_eq!(latest_kind(missed), EventKind::CampaignExpired);
+    assert_eq!(latest_kind(later), EventKind::CampaignCreated, "still running");
+    let (idea_feed,): (EventPage,) = env.query(owner, "get_events_for_idea", (idea_id, 0u64, 2u64));
+    let kinds: Vec<EventKind> = idea_feed.items.iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, [EventKind::CampaignExpired, EventKind::CampaignCompleted]);
+
+    let (res,): (Result<Payout, String>,) = env.update(backer(2), "claim_refund", (missed,));
+    assert_eq!(res.unwrap().amount, 100, "refunds open without anyone finalizing by hand");
+    let (res,): (Result<Payout, String>,) = env.update(owner, "withdraw_raised", (funded,));
+    assert_eq!(res.unwrap().amount, 500);
+    let run = sweep(controller()).unwrap();
+    assert!(run.finalized.is_empty() && !run.more_due, "finalized once");
+
+    let args = InitArgs { expiry_sweep_secs: Some(30 * DAY_SECS), ..InitArgs::default() };
+    env.pic
+        .upgrade_canister(env.canister, backend_wasm(), candid::encode_one(Some(args)).unwrap(), Some(controller()))
+        .expect("upgrade failed");
+    env.advance_days(8);
+    assert_eq!(latest_kind(later), EventKind::CampaignCreated, "the next sweep is weeks away");
+    let run = sweep(controller()).unwrap();
+    assert_eq!(run.finalized.iter().map(|(id, _)| *id).collect::<Vec<_>>(), [later]);
+    assert!(matches!(run.finalized[0].1, CampaignOutcome::Failed));
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn watchlists_follow_campaigns_per_user_and_count_followers() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default().title("Solar Kiosk"));
+    env.approve_idea(idea_id);
+    let end = env.now_secs() + 7 * DAY_SECS;
+    let first = env.create_campaign(owner, idea_id, 1_000, end).unwrap();
+    let second = env.create_campaign(owner, idea_id, 2_000, end).unwrap();
+    let call = |sender, method: &str, campaign_id: u64| {
+        let (res,): (Result<(), FundVerseError>,) = env.update(sender, method, (campaign_id,));
+        res
+    };
+    let followers = |campaign_id: u64| {
+        let (count,): (u64,) = env.query(candid::Principal::anonymous(), "get_follower_count", (campaign_id,));
+        count
+    };
+    let watchlist = |sender| {
+        let (cards,): (Vec<CampaignCard>,) = env.query(sender, "get_my_watchlist", ());
+        cards.into_iter().map(|c| (c.id, c.idea_title)).collect::<Vec<_>>()
+    };
+
+    assert_eq!(call(candid::Principal::anonymous(), "follow_campaign", first), Err(FundVerseError::Unauthorized));
+    let missing = call(backer(1), "follow_campaign", 999);
+    assert!(matches!(missing, Err(FundVerseError::NotFound(EntityRef::Campaign(999)))));
+    call(backer(1), "follow_campaign", second).unwrap();
+    call(backer(1), "follow_campaign", first).unwrap();
+    call(backer(1), "follow_campaign", first).unwrap(); // already followed: no-op
+    call(backer(2), "follow_campaign", first).unwrap();
+    assert_eq!((followers(first), followers(second), followers(999)), (2, 1, 0));
+    let solar = "Solar Kiosk".to_string();
+    assert_eq!(watchlist(backer(1)), [(first, solar.clone()), (second, solar.clone())]);
+    assert!(watchlist(backer(3)).is_empty());
+
+    call(backer(1), "unfollow_campaign", first).unwrap();
+    call(backer(1), "unfollow_campaign", first).unwrap(); // not followed any more: no-op
+    env.upgrade();
+    assert_eq!((followers(first), followers(second)), (1, 1));
+    assert_eq!(watchlist(backer(1)), [(second, solar)]);
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct CurrencyCard {
+    amount_raised: u64,
+    goal: u64,
+    currency: Currency,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct CurrencyTotals {
+    currency: Currency,
+    campaigns: u64,
+    raised: u64,
+    goal: u64,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct PlatformTotals {
+    totals: Vec<CurrencyTotals>,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn campaigns_raise_in_one_currency_and_reject_pledges_in_another() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let btc_idea = env.create_idea(owner, IdeaArgs::default().title("Solar Kiosk"));
+    let icp_idea = env.create_idea(owner, IdeaArgs::default().title("Rain Barrels"));
+    env.approve_idea(btc_idea);
+    env.approve_idea(icp_idea);
+    let end = env.now_secs() + 30 * DAY_SECS;
+    let create = |idea_id: u64, fiat: Option<FiatGoalRequest>, currency: Option<Currency>| {
+        let (res,): (Result<u64, String>,) = env.update(
+            owner,
+            "create_campaign",
+            (
+                idea_id,
+                1_000u64,
+                end,
+                None::<FundingRound>,
+                None::<String>,
+                None::<String>,
+                None::<EndTime>,
+                fiat,
+                currency,
+            ),
+        );
+        res
+    };
+    let pledge = |campaign_id: u64, amount: u64, currency: Option<Currency>| {
+        let (res,): (Result<u64, String>,) =
+            env.update(backer(1), "contribute", (campaign_id, amount, None::<Channel>, currency));
+        res
+    };
+    let card = |campaign_id: u64| {
+        let (card,): (Option<CampaignWithIdea<CurrencyCard>>,) =
+            env.query(owner, "get_campaign_with_idea", (campaign_id,));
+        card.unwrap().campaign
+    };
+
+    let fiat = FiatGoalRequest { currency: "USD".into(), amount_cents: 5_000_000 };
+    assert!(create(btc_idea, Some(fiat), Some(Currency::CkBtc)).unwrap_err().contains("priced in ICP"));
+    let btc = create(btc_idea, None, Some(Currency::CkBtc)).unwrap();
+    let mixed = env.create_campaign(owner, btc_idea, 1_000, end).unwrap_err();
+    assert!(mixed.contains("raise ckBTC"), "an idea's campaigns share a currency: {}", mixed);
+    let icp = create(icp_idea, None, None).unwrap(); // no currency: ICP
+
+    let mismatch = env.contribute(backer(1), btc, 500).unwrap_err();
+    assert!(mismatch.starts_with("CURRENCY_MISMATCH"), "{}", mismatch);
+    assert!(pledge(icp, 300, Some(Currency::UsdCents)).unwrap_err().starts_with("CURRENCY_MISMATCH"));
+    pledge(btc, 500, Some(Currency::CkBtc)).unwrap();
+    let unverified = pledge(icp, 300, None).unwrap_err();
+    assert!(unverified.starts_with("LEDGER_TRANSFER_REQUIRED"), "ICP needs a ledger transfer: {}", unverified);
+    let (old_call,): (Result<u64, String>,) = env.update(backer(1), "contribute", (icp, 300u64));
+    assert!(old_call.unwrap_err().starts_with("LEDGER_TRANSFER_REQUIRED"), "the two-argument call pledges ICP");
+    let relay = |sender| {
+        let (res,): (Result<(), String>,) = env.update(sender, "receive_icp_contribution", (icp, 300u64));
+        res
+    };
+    assert!(relay(fund_flow()).unwrap_err().contains("no Fund_Flow canister"));
+    env.relay_icp(icp, 300);
+    assert!(relay(backer(1)).unwrap_err().contains("only the Fund_Flow canister"), "credited only as sent");
+
+    env.upgrade();
+    let (btc_card, icp_card) = (card(btc), card(icp));
+    assert_eq!((btc_card.currency, btc_card.amount_raised, btc_card.goal), (Currency::CkBtc, 500, 1_000));
+    assert_eq!((icp_card.currency, icp_card.amount_raised, icp_card.goal), (Currency::Icp, 300, 1_000));
+    let (stats,): (PlatformTotals,) = env.query(owner, "get_platform_stats", ());
+    let totals: Vec<_> = stats.totals.iter().map(|t| (t.currency, t.campaigns, t.raised, t.goal)).collect();
+    assert_eq!(totals, [(Currency::Icp, 1, 300, 1_000), (Currency::CkBtc, 1, 500, 1_000)]);
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn drafts_stay_private_until_submitted_with_the_full_checks() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let create = |sender, payload: UpdateIdeaPayload| {
+        let (res,): (Result<u64, FundVerseError>,) = env.update(sender, "create_idea_draft", (payload,));
+        res
+    };
+    let edit = |sender, idea_id: u64, payload: UpdateIdeaPayload| {
+        let (res,): (Result<(), FundVerseError>,) = env.update(sender, "update_idea_draft", (idea_id, payload));
+        res
+    };
+    let submit = |idea_id: u64| {
+        let (res,): (Result<(), candid::Reserved>,) = env.update(owner, "submit_idea", (idea_id,));
+        res.is_ok()
+    };
+    let own_idea = |idea_id: u64| {
+        let (idea,): (Option<Idea>,) = env.query(owner, "get_idea_by_id", (idea_id,));
+        idea
+    };
+    let listed = |sender| {
+        let (page,): (IdeaPage,) = env.query(sender, "list_ideas_paged", (0u64, 100u64));
+        let (found,): (Result<IdeaPage, String>,) =
+            env.query(sender, "search_ideas", ("solar".to_string(), None::<String>, 0u64, 100u64));
+        (page.total, found.unwrap().total)
+    };
+
+    let title_only = UpdateIdeaPayload { title: Some("Solar Kiosk".into()), ..Default::default() };
+    assert_eq!(create(candid::Principal::anonymous(), title_only.clone()), Err(FundVerseError::Unauthorized));
+    let draft = create(owner, title_only.clone()).unwrap();
+    assert!(env.idea(draft).is_none(), "private to the owner");
+    assert_eq!(own_idea(draft).unwrap().status, IdeaStatus::Draft);
+    assert_eq!((listed(backer(1)), listed(owner)), ((0, 0), (0, 0)), "never listed or found");
+    let (mine,): (Vec<(u64, Idea)>,) = env.query(owner, "get_my_ideas", ());
+    let mine: Vec<(u64, IdeaStatus)> = mine.into_iter().map(|(id, idea)| (id, idea.status)).collect();
+    assert_eq!(mine, [(draft, IdeaStatus::Draft)]);
+    assert!(env.create_campaign(owner, draft, 1_000, env.now_secs() + 7 * DAY_SECS).is_err());
+    let (res,): (Result<(), String>,) = env.update(owner, "update_idea", (draft, title_only.clone()));
+    assert!(res.unwrap_err().contains("draft"));
+
+    assert!(!submit(draft), "description, goal, entity, contact and category are still missing");
+    let rest = UpdateIdeaPayload {
+        description: Some("Solar-powered charging kiosks".into()),
+        funding_goal: Some(5_000),
+        legal_entity: Some("Kiosk Ltd".into()),
+        contact_info: Some("founder@example.com".into()),
+        category: Some("Technology".into()),
+        ..Default::default()
+    };
+    assert_eq!(edit(backer(1), draft, rest.clone()), Err(FundVerseError::Unauthorized));
+    edit(owner, draft, rest).unwrap();
+    assert!(submit(draft));
+    let submitted = env.idea(draft).unwrap();
+    assert_eq!((submitted.status, submitted.category.as_str()), (IdeaStatus::Pending, "technology"));
+    assert_eq!(listed(backer(1)), (1, 1));
+    let (again,): (Result<(), FundVerseError>,) = env.update(owner, "submit_idea", (draft,));
+    assert_eq!(again, Err(FundVerseError::NotADraft { status: IdeaStatus::Pending }));
+    assert_eq!(edit(owner, draft, title_only.clone()), Err(FundVerseError::NotADraft { status: IdeaStatus::Pending }));
+
+    let abandoned = create(owner, title_only).unwrap();
+    let (res,): (Result<(), String>,) = env.update(owner, "delete_idea", (abandoned,));
+    res.unwrap();
+    assert!(own_idea(abandoned).is_none());
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug, PartialEq)]
+struct DocMeta {
+    id: u64,
+    name: String,
+    content_type: String,
+    size: u64,
+    uploaded_at: u64,
+}
+
+#[derive(candid::CandidType, candid::Deserialize, Debug)]
+struct CampaignDetails {
+    campaign: CampaignCard,
+    idea: Idea,
+    risks: Option<String>,
+    docs: Vec<DocMeta>,
+    contribution_count: u64,
+}
+
+#[test]
+#[ignore = "needs POCKET_IC_BIN and a release wasm build"]
+fn batch_getters_keep_the_order_asked_and_bundle_the_detail_page() {
+    let env = TestEnv::new();
+    let owner = principal(1);
+    let idea_id = env.create_idea(owner, IdeaArgs::default().title("Solar Kiosk"));
+    let deck = env.upload_doc(owner, idea_id, "deck.pdf", vec![1, 2, 3]).unwrap();
+    let plan = env.upload_doc(owner, idea_id, "plan.pdf", vec![7; 10]).unwrap();
+    env.approve_idea(idea_id);
+    let campaign_id = env.create_campaign(owner, idea_id, 10_000, env.now_secs() + 7 * DAY_SECS).unwrap();
+    env.contribute(backer(1), campaign_id, 100).unwrap();
+    env.contribute(backer(2), campaign_id, 200).unwrap();
+
+    let (docs,): (Result<Vec<Option<Doc>>, String>,) = env.query(owner, "get_docs", (vec![plan, 999, deck],));
+    let names: Vec<Option<String>> = docs.unwrap().into_iter().map(|d| d.map(|d| d.name)).collect();
+    assert_eq!(names, [Some("plan.pdf".to_string()), None, Some("deck.pdf".to_string())]);
+    let (ideas,): (Result<Vec<Option<Idea>>, String>,) = env.query(owner, "get_ideas", (vec![999, idea_id],));
+    let titles: Vec<Option<String>> = ideas.unwrap().into_iter().map(|i| i.map(|i| i.title)).collect();
+    assert_eq!(titles, [None, Some("Solar Kiosk".to_string())], "unknown ids stay in place");
+    let ids: Vec<u64> = (0..101).collect();
+    let (too_many,): (Result<Vec<Option<Idea>>, String>,) = env.query(owner, "get_ideas", (ids,));
+    assert!(too_many.unwrap_err().contains("at most 100"), "rejected, not truncated");
+
+    let (details,): (Option<CampaignDetails>,) = env.query(backer(1), "get_campaign_details", (campaign_id,));
+    let details = details.unwrap();
+    assert_eq!((details.campaign.id, details.idea.title.as_str()), (campaign_id, "Solar Kiosk"));
+    assert_eq!(details.risks, None);
+    let docs: Vec<(u64, &str, u64)> = details.docs.iter().map(|d| (d.id, d.name.as_str(), d.size)).collect();
+    assert_eq!(docs, [(deck, "deck.pdf", 3), (plan, "plan.pdf", 10)]);
+    assert_eq!(details.contribution_count, 2);
+    let (missing,): (Option<CampaignDetails>,) = env.query(backer(1), "get_campaign_details", (999u64,));
+    assert!(missing.is_none());
+}
@@ -84,7 +84,8 @@ export const ContributionDialog: React.FC<ContributionDialogProps> = ({
       const result = await fundFlowActor.contribute_icp(
         backendPrincipal,
         campaignId,
-        BigInt(amountE8s)
+        BigInt(amountE8s),
+        [] // ack_risks: no disclosure modal yet, so campaigns with risks return RISK_ACK_REQUIRED
       );
 
       if ('Err' in result) {
@@ -169,7 +169,7 @@ export const handleICError = (error: any): ICError => {
   if (errorMessage.includes('not authorized') || errorMessage.includes('unauthorized')) {
     return new ICError('You are not authorized to perform this action', 'NOT_AUTHORIZED');
   }
-  if (errorMessage.includes('campaign not found')) {
+  if (/campaign( \d+)? not found/i.test(errorMessage)) {
     return new ICError('Campaign not found', 'CAMPAIGN_NOT_FOUND');
   }
   if (errorMessage.includes('campaign already ended')) {
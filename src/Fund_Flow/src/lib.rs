@@ -18,7 +18,8 @@ use ic_stable_structures::{
 };
 use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::BTreeMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
 
 // ---------- Type aliases ----------
 type Memory = VirtualMemory<DefaultMemoryImpl>;
@@ -26,6 +27,7 @@ type Memory = VirtualMemory<DefaultMemoryImpl>;
 // ---------- Config ----------
 const MAX_VALUE_SIZE: u32 = 8 * 1024; // 8KB per value (MVP)
 const CANISTER_VERSION: &str = "funding-canister-v1";
+const MAX_STATEMENT_BYTES: usize = 1_500_000; // stay well under the 2MB reply limit
 const LEDGER_CANISTER_ID: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai"; // Mainnet ledger
 // const LEDGER_CANISTER_ID: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai"; // Local ledger for testing
 
@@ -108,6 +110,24 @@ pub struct Contribution {
     pub created_at_ns: u64,
     pub confirmed_at_ns: Option<u64>,
     pub icp_transfer_id: Option<u64>, // Link to ICP transfer if method is ICP
+    pub risks_acked_at_ns: Option<u64>, // set when the campaign had a risk disclosure
+    pub display_excluded: Option<bool>, // hidden from public views (refund / admin); None on older records
+    pub anonymous: Option<bool>,        // backer hidden in public views; resolved at read time
+    pub settled_at_ns: Option<u64>,     // when released or refunded
+}
+impl Contribution {
+    fn is_display_excluded(&self) -> bool {
+        self.display_excluded.unwrap_or(false)
+    }
+
+    /// Copy for public views: anonymous backers are shown as the anonymous principal.
+    fn public_view(&self) -> Contribution {
+        let mut c = self.clone();
+        if c.anonymous.unwrap_or(false) {
+            c.backer = Principal::anonymous();
+        }
+        c
+    }
 }
 impl Storable for Contribution {
     fn to_bytes(&self) -> Cow<[u8]> {
@@ -172,6 +192,60 @@ pub struct CampaignMeta {
     pub goal: u64,
     pub amount_raised: u64,
     pub end_date_secs: u64, // seconds since epoch
+    pub has_risks: Option<bool>, // None from backends that predate risk disclosures
+    pub paused_reason: Option<String>, // Some while the campaign has paused contributions
+    pub cancelled: Option<bool>,       // None from backends that predate cancellation
+}
+
+/// Returned when a campaign has a risk disclosure and the pledge did not acknowledge it;
+/// the frontend shows the disclosure modal on this exact error.
+pub const RISK_ACK_REQUIRED: &str = "RISK_ACK_REQUIRED";
+
+/// What a contribution that passed validation needs to be recorded.
+pub struct ValidContribution {
+    pub meta: CampaignMeta,
+    pub risks_acked: bool,
+}
+
+/// Caller-side checks that need no backend data.
+fn check_contributor(caller: Principal, amount: u64) -> Result<(), String> {
+    if amount == 0 { return Err("amount must be > 0".into()); }
+    if !USERS.with(|u| u.borrow().contains_key(&Pk::from(caller))) {
+        return Err("Only registered users can contribute".into());
+    }
+    Ok(())
+}
+
+/// Campaign-side checks against the backend's metadata.
+fn check_campaign(meta: Option<CampaignMeta>, ack_risks: Option<bool>, now_secs: u64) -> Result<ValidContribution, String> {
+    let meta = meta.ok_or_else(|| "campaign not found".to_string())?;
+    if meta.cancelled.unwrap_or(false) {
+        return Err("campaign was cancelled".into());
+    }
+    if now_secs > meta.end_date_secs {
+        return Err("campaign already ended".into());
+    }
+    if let Some(reason) = &meta.paused_reason {
+        return Err(format!("CONTRIBUTIONS_PAUSED: {}", reason));
+    }
+    let risks_acked = meta.has_risks.unwrap_or(false);
+    if risks_acked && ack_risks != Some(true) {
+        return Err(RISK_ACK_REQUIRED.into());
+    }
+    Ok(ValidContribution { meta, risks_acked })
+}
+
+/// The single validation path shared by `contribute`, `contribute_icp` and `preview_contribution`.
+async fn validate_contribution(
+    backend: Principal,
+    caller: Principal,
+    campaign_id: u64,
+    amount: u64,
+    ack_risks: Option<bool>,
+) -> Result<ValidContribution, String> {
+    check_contributor(caller, amount)?;
+    let meta = fetch_campaign_meta(backend, campaign_id).await?;
+    check_campaign(meta, ack_risks, now_secs())
 }
 
 // ---------- Internal helpers for stable maps ----------
@@ -200,6 +274,14 @@ fn update_contribution(id: u64, c: Contribution) {
     });
 }
 
+fn ensure_controller() -> Result<(), String> {
+    if ic_cdk::api::is_controller(&ic_cdk::api::caller()) {
+        Ok(())
+    } else {
+        Err("only controllers can call this method".into())
+    }
+}
+
 fn insert_icp_transfer(t: ICPTransfer) {
     ICP_TRANSFERS.with(|m| {
         m.borrow_mut().insert(t.id, t);
@@ -329,22 +411,10 @@ fn get_my_profile() -> Option<RegisteredUser> {
 /// Start a contribution with ICP coins. Creates transfer record and initiates ICP transfer.
 /// `backend` is the principal of your backend canister.
 #[update]
-async fn contribute_icp(backend: Principal, campaign_id: u64, amount_e8s: u64) -> Result<u64, String> {
-    if amount_e8s == 0 { return Err("amount must be > 0".into()); }
+async fn contribute_icp(backend: Principal, campaign_id: u64, amount_e8s: u64, ack_risks: Option<bool>) -> Result<u64, String> {
     let caller = ic_cdk::api::caller();
-
-    // registered?
-    if !USERS.with(|u| u.borrow().contains_key(&Pk::from(caller))) {
-        return Err("Only registered users can contribute".into());
-    }
-
-    // check campaign exists and active
-    let meta = fetch_campaign_meta(backend, campaign_id).await?;
-    let meta = meta.ok_or_else(|| "campaign not found".to_string())?;
-    let now = now_secs();
-    if now > meta.end_date_secs {
-        return Err("campaign already ended".into());
-    }
+    let valid = validate_contribution(backend, caller, campaign_id, amount_e8s, ack_risks).await?;
+    let risks_acked_at_ns = valid.risks_acked.then(now_ns);
 
     // Get canister principal (this canister will receive the ICP)
     let canister_principal = ic_cdk::api::id();
@@ -364,6 +434,10 @@ async fn contribute_icp(backend: Principal, campaign_id: u64, amount_e8s: u64) -
         created_at_ns: now_ns(),
         confirmed_at_ns: None,
         icp_transfer_id: Some(transfer_id),
+        risks_acked_at_ns,
+        display_excluded: None,
+        anonymous: None,
+        settled_at_ns: None,
     };
     insert_contribution(c);
     Ok(id)
@@ -372,22 +446,10 @@ async fn contribute_icp(backend: Principal, campaign_id: u64, amount_e8s: u64) -
 /// Start a contribution (Pending). Checks user is registered and campaign exists & active via backend.
 /// `backend` is the principal of your backend canister.
 #[update]
-async fn contribute(backend: Principal, campaign_id: u64, amount: u64, method: PaymentMethod) -> Result<u64, String> {
-    if amount == 0 { return Err("amount must be > 0".into()); }
+async fn contribute(backend: Principal, campaign_id: u64, amount: u64, method: PaymentMethod, ack_risks: Option<bool>) -> Result<u64, String> {
     let caller = ic_cdk::api::caller();
-
-    // registered?
-    if !USERS.with(|u| u.borrow().contains_key(&Pk::from(caller))) {
-        return Err("Only registered users can contribute".into());
-    }
-
-    // check campaign exists and active
-    let meta = fetch_campaign_meta(backend, campaign_id).await?;
-    let meta = meta.ok_or_else(|| "campaign not found".to_string())?;
-    let now = now_secs();
-    if now > meta.end_date_secs {
-        return Err("campaign already ended".into());
-    }
+    let valid = validate_contribution(backend, caller, campaign_id, amount, ack_risks).await?;
+    let risks_acked_at_ns = valid.risks_acked.then(now_ns);
 
     // create pending contribution
     let id = next_contribution_id();
@@ -401,11 +463,57 @@ async fn contribute(backend: Principal, campaign_id: u64, amount: u64, method: P
         created_at_ns: now_ns(),
         confirmed_at_ns: None,
         icp_transfer_id: None,
+        risks_acked_at_ns,
+        display_excluded: None,
+        anonymous: None,
+        settled_at_ns: None,
     };
     insert_contribution(c);
     Ok(id)
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ContributionPreview {
+    pub accepted: bool,
+    pub rejection: Option<String>, // the error `contribute` would return, e.g. RISK_ACK_REQUIRED
+    pub requires_risk_ack: bool,
+    pub amount_raised_after: u64,
+    pub goal: u64,
+    pub reaches_goal: bool, // this contribution crosses the goal
+}
+
+fn build_preview(amount: u64, result: Result<ValidContribution, String>, meta: Option<&CampaignMeta>) -> ContributionPreview {
+    let (raised, goal, has_risks) = meta
+        .map(|m| (m.amount_raised, m.goal, m.has_risks.unwrap_or(false)))
+        .unwrap_or((0, 0, false));
+    let after = raised.saturating_add(amount);
+    ContributionPreview {
+        accepted: result.is_ok(),
+        rejection: result.err(),
+        requires_risk_ack: has_risks,
+        amount_raised_after: after,
+        goal,
+        reaches_goal: goal > 0 && raised < goal && after >= goal,
+    }
+}
+
+/// Dry-run of `contribute` for the caller: same validation, nothing is written.
+#[query(composite = true)]
+async fn preview_contribution(backend: Principal, campaign_id: u64, amount: u64, ack_risks: Option<bool>) -> ContributionPreview {
+    let caller = ic_cdk::api::caller();
+    let meta = match check_contributor(caller, amount) {
+        Ok(()) => fetch_campaign_meta(backend, campaign_id).await,
+        Err(e) => return build_preview(amount, Err(e), None),
+    };
+    match meta {
+        Ok(meta) => {
+            let result = check_campaign(meta.clone(), ack_risks, now_secs());
+            build_preview(amount, result, meta.as_ref())
+        }
+        Err(e) => build_preview(amount, Err(e), None),
+    }
+}
+
 /// Confirm a payment (simulate webhook / admin). This moves Pending -> Held.
 ///
 /// Security note (MVP): this function allows only the canister owner or the backend can call it.
@@ -489,6 +597,7 @@ async fn release_campaign(backend: Principal, campaign_id: u64) -> Result<u64, S
     for id in &held_ids {
         if let Some(mut c) = get_contribution(*id) {
             c.status = EscrowStatus::Released;
+            c.settled_at_ns = Some(now_ns());
             update_contribution(*id, c);
         }
     }
@@ -525,6 +634,8 @@ fn refund_campaign(campaign_id: u64) -> Result<u64, String> {
                     refund_total = refund_total.saturating_add(c.amount);
                 }
                 c.status = EscrowStatus::Refunded;
+                c.display_excluded = Some(true);
+                c.settled_at_ns = Some(now_ns());
                 map.insert(id, c.clone());
                 refunded_count += 1;
             }
@@ -551,12 +662,11 @@ fn get_contributions_by_user(p: Option<Principal>) -> Vec<Contribution> {
     res
 }
 
-#[query]
-fn get_campaign_contributions(campaign_id: u64) -> Vec<Contribution> {
+fn campaign_contributions(campaign_id: u64, include_excluded: bool) -> Vec<Contribution> {
     let mut res: Vec<Contribution> = Vec::new();
     CONTRIBUTIONS.with(|m| {
         for (_, v) in m.borrow().iter() {
-            if v.campaign_id == campaign_id {
+            if v.campaign_id == campaign_id && (include_excluded || !v.is_display_excluded()) {
                 res.push(v.clone());
             }
         }
@@ -564,6 +674,173 @@ fn get_campaign_contributions(campaign_id: u64) -> Vec<Contribution> {
     res
 }
 
+/// Public list: refunded and admin-hidden contributions are left out, anonymous backers are masked.
+#[query]
+fn get_campaign_contributions(campaign_id: u64) -> Vec<Contribution> {
+    campaign_contributions(campaign_id, false).iter().map(Contribution::public_view).collect()
+}
+
+/// Every contribution for the campaign, including display-excluded ones (controllers only).
+#[query]
+fn get_campaign_contributions_admin(campaign_id: u64) -> Result<Vec<Contribution>, String> {
+    ensure_controller()?;
+    Ok(campaign_contributions(campaign_id, true))
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PublicCampaignStats {
+    pub campaign_id: u64,
+    pub backer_count: u64, // distinct backers
+    pub total_amount: u64, // Pending + Held + Released
+}
+
+/// Recompute the public aggregates from the contribution records. Because it is derived on every
+/// call, toggling `display_excluded` back restores the numbers exactly.
+fn public_campaign_stats(campaign_id: u64) -> PublicCampaignStats {
+    let mut backers: BTreeMap<Principal, ()> = BTreeMap::new();
+    let mut total_amount: u64 = 0;
+    for c in campaign_contributions(campaign_id, false) {
+        if c.status == EscrowStatus::Refunded { continue; }
+        backers.insert(c.backer, ());
+        total_amount = total_amount.saturating_add(c.amount);
+    }
+    PublicCampaignStats { campaign_id, backer_count: backers.len() as u64, total_amount }
+}
+
+#[query]
+fn get_public_campaign_stats(campaign_id: u64) -> PublicCampaignStats {
+    public_campaign_stats(campaign_id)
+}
+
+const ANONYMITY_GRACE_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days after settlement
+
+/// Let the backer hide (or reveal) their identity on a contribution, any time before it is
+/// released/refunded and for a 7-day grace period after.
+#[update]
+fn set_contribution_anonymity(contribution_id: u64, anonymous: bool) -> Result<(), String> {
+    let mut c = get_contribution(contribution_id).ok_or_else(|| "contribution not found".to_string())?;
+    if c.backer != ic_cdk::api::caller() {
+        return Err("only the backer can change this contribution".into());
+    }
+    if let Some(settled) = c.settled_at_ns {
+        if now_ns() > settled.saturating_add(ANONYMITY_GRACE_NS) {
+            return Err("anonymity can no longer be changed for this contribution".into());
+        }
+    }
+    c.anonymous = Some(anonymous);
+    update_contribution(contribution_id, c);
+    Ok(())
+}
+
+/// Hide or restore a contribution in public views; the record itself is never removed (controllers only).
+#[update]
+fn set_contribution_display_excluded(contribution_id: u64, excluded: bool) -> Result<(), String> {
+    ensure_controller()?;
+    let mut c = get_contribution(contribution_id).ok_or_else(|| "contribution not found".to_string())?;
+    c.display_excluded = Some(excluded);
+    update_contribution(contribution_id, c);
+    Ok(())
+}
+
+// ---------- Supporter wall ----------
+
+const MAX_WALL_PAGE: u64 = 50;
+const EARLY_BACKER_SLOTS: usize = 10;
+const TOP_BACKER_SLOTS: usize = 10;
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SupporterBadge {
+    EarlyBacker, // among the first 10 named backers
+    Top10,       // among the 10 largest named backers
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Supporter {
+    pub backer: Principal,
+    pub display_name: Option<String>, // registered name (never the email); None if unregistered
+    pub total_amount: u64,
+    pub contribution_count: u64,
+    pub first_backed_at_ns: u64,
+    pub badges: Vec<SupporterBadge>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SupporterWall {
+    pub supporters: Vec<Supporter>, // named backers by first contribution time
+    pub total_supporters: u64,      // named backers across all pages
+    pub anonymous_backers: u64,
+    pub anonymous_contributions: u64,
+    pub next_offset: Option<u64>,   // pass back as `offset`; None => no more supporters
+}
+
+fn display_name(p: Principal) -> Option<String> {
+    USERS.with(|u| u.borrow().get(&Pk::from(p))).map(|u| u.name)
+}
+
+/// Build one page of the wall. Anonymous contributions only feed the anonymous counts, and badges
+/// are ranked among named contributions, so the wall reveals nothing about who backed anonymously.
+fn supporter_wall(campaign_id: u64, offset: u64, limit: u64) -> SupporterWall {
+    let mut named: BTreeMap<Principal, Supporter> = BTreeMap::new();
+    let mut anonymous_backers: BTreeSet<Principal> = BTreeSet::new();
+    let mut anonymous_contributions: u64 = 0;
+    for c in campaign_contributions(campaign_id, false) {
+        if c.status == EscrowStatus::Refunded { continue; }
+        if c.anonymous.unwrap_or(false) {
+            anonymous_backers.insert(c.backer);
+            anonymous_contributions += 1;
+            continue;
+        }
+        let s = named.entry(c.backer).or_insert_with(|| Supporter {
+            backer: c.backer,
+            display_name: None,
+            total_amount: 0,
+            contribution_count: 0,
+            first_backed_at_ns: c.created_at_ns,
+            badges: vec![],
+        });
+        s.total_amount = s.total_amount.saturating_add(c.amount);
+        s.contribution_count += 1;
+        s.first_backed_at_ns = s.first_backed_at_ns.min(c.created_at_ns);
+    }
+
+    let mut supporters: Vec<Supporter> = named.into_values().collect();
+    supporters.sort_by_key(|s| (s.first_backed_at_ns, s.backer));
+    let mut by_amount: Vec<usize> = (0..supporters.len()).collect();
+    by_amount.sort_by_key(|&i| (Reverse(supporters[i].total_amount), i)); // ties go to the earlier backer
+    for s in supporters.iter_mut().take(EARLY_BACKER_SLOTS) {
+        s.badges.push(SupporterBadge::EarlyBacker);
+    }
+    for &i in by_amount.iter().take(TOP_BACKER_SLOTS) {
+        supporters[i].badges.push(SupporterBadge::Top10);
+    }
+
+    let total = supporters.len() as u64;
+    let page: Vec<Supporter> = supporters
+        .into_iter()
+        .skip(offset.min(total) as usize)
+        .take(limit.min(MAX_WALL_PAGE) as usize)
+        .map(|mut s| {
+            s.display_name = display_name(s.backer); // only resolved for the page being returned
+            s
+        })
+        .collect();
+    let end = offset.saturating_add(page.len() as u64);
+    SupporterWall {
+        supporters: page,
+        total_supporters: total,
+        anonymous_backers: anonymous_backers.len() as u64,
+        anonymous_contributions,
+        next_offset: (end < total).then_some(end),
+    }
+}
+
+/// Public "wall of supporters" for a campaign, 50 supporters per page at most. Refunded and
+/// admin-hidden contributions are left out, as in the other public views.
+#[query]
+fn get_supporter_wall(campaign_id: u64, offset: u64, limit: u64) -> SupporterWall {
+    supporter_wall(campaign_id, offset, limit)
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct EscrowSummary {
     pub campaign_id: u64,
@@ -590,6 +867,122 @@ fn get_escrow_summary(campaign_id: u64) -> EscrowSummary {
     s
 }
 
+// ---------- Contribution statements (tax export) ----------
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ContributionStatement {
+    pub year: u32,
+    pub text: String,          // CSV rows followed by per-currency totals
+    pub next_row: Option<u64>, // set when the statement was cut at MAX_STATEMENT_BYTES
+}
+
+/// Civil date (year, month, day) from days since 1970-01-01 (proleptic Gregorian, UTC).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn utc_year(ns: u64) -> i64 {
+    civil_from_days((ns / 1_000_000_000 / 86_400) as i64).0
+}
+
+fn format_utc(ns: u64) -> String {
+    let secs = ns / 1_000_000_000;
+    let (y, m, d) = civil_from_days((secs / 86_400) as i64);
+    let tod = secs % 86_400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", y, m, d, tod / 3_600, tod % 3_600 / 60, tod % 60)
+}
+
+fn method_label(m: &PaymentMethod) -> String {
+    match m {
+        PaymentMethod::ICP => "ICP".into(),
+        PaymentMethod::BankTransfer => "BankTransfer".into(),
+        PaymentMethod::Fawry => "Fawry".into(),
+        PaymentMethod::PayMob => "PayMob".into(),
+        PaymentMethod::Other(name) => format!("Other:{}", name),
+    }
+}
+
+// ICP amounts are e8s, every other method is the smallest EGP unit (see Contribution.amount).
+fn currency_label(m: &PaymentMethod) -> &'static str {
+    if *m == PaymentMethod::ICP { "ICP_E8S" } else { "EGP" }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Plain-text/CSV statement of the caller's contributions created in the given UTC calendar year.
+/// Pass `start_row` from a previous `next_row` to fetch the rest of a long statement.
+#[query]
+fn export_my_contribution_summary(year: u32, start_row: Option<u64>) -> Result<ContributionStatement, String> {
+    let who = ic_cdk::api::caller();
+    let mut rows: Vec<Contribution> = Vec::new();
+    CONTRIBUTIONS.with(|m| {
+        for (_, c) in m.borrow().iter() {
+            if c.backer == who && utc_year(c.created_at_ns) == year as i64 {
+                rows.push(c);
+            }
+        }
+    });
+
+    let start = start_row.unwrap_or(0) as usize;
+    if start > rows.len() {
+        return Err("start_row is past the end of the statement".into());
+    }
+
+    // totals always cover the whole year, regardless of chunking
+    let mut totals: BTreeMap<&'static str, (u64, u64)> = BTreeMap::new(); // currency -> (net, refunded)
+    for c in &rows {
+        let t = totals.entry(currency_label(&c.method)).or_insert((0, 0));
+        if c.status == EscrowStatus::Refunded {
+            t.1 = t.1.saturating_add(c.amount);
+        } else {
+            t.0 = t.0.saturating_add(c.amount);
+        }
+    }
+
+    let mut text = String::from("contribution_id,campaign_id,date_utc,amount,currency,method,status,transfer_id\n");
+    let mut next_row = None;
+    for (i, c) in rows.iter().enumerate().skip(start) {
+        let line = format!(
+            "{},{},{},{},{},{},{:?},{}\n",
+            c.id,
+            c.campaign_id,
+            format_utc(c.created_at_ns),
+            c.amount,
+            currency_label(&c.method),
+            csv_field(&method_label(&c.method)),
+            c.status,
+            c.icp_transfer_id.map(|t| t.to_string()).unwrap_or_default(),
+        );
+        if text.len() + line.len() > MAX_STATEMENT_BYTES {
+            next_row = Some(i as u64);
+            break;
+        }
+        text.push_str(&line);
+    }
+
+    text.push_str("\ncurrency,total_net,total_refunded\n");
+    for (currency, (net, refunded)) in &totals {
+        text.push_str(&format!("{},{},{}\n", currency, net, refunded));
+    }
+
+    Ok(ContributionStatement { year, text, next_row })
+}
+
 // ---------- ICP Transfer Queries ----------
 
 // #[query]
@@ -617,4 +1010,91 @@ fn init() {
     ic_cdk::println!("Funding canister initialized — {}", CANISTER_VERSION);
 }
 
-ic_cdk::export_candid!();
\ No newline at end of file
+ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(end_date_secs: u64, has_risks: Option<bool>) -> CampaignMeta {
+        CampaignMeta { campaign_id: 1, goal: 1_000, amount_raised: 900, end_date_secs, has_risks, paused_reason: None, cancelled: None }
+    }
+
+    fn register(p: Principal) {
+        USERS.with(|u| u.borrow_mut().insert(Pk::from(p), RegisteredUser {
+            user_principal: p, name: "t".into(), email: "t@example.com".into(), registered_at_ns: 0,
+        }));
+    }
+
+    fn backing(id: u64, backer: Principal, amount: u64, at: u64, anonymous: bool) -> Contribution {
+        Contribution {
+            id, campaign_id: 9, backer, amount, method: PaymentMethod::BankTransfer, status: EscrowStatus::Held,
+            created_at_ns: at, confirmed_at_ns: None, icp_transfer_id: None, risks_acked_at_ns: None,
+            display_excluded: None, anonymous: Some(anonymous), settled_at_ns: None,
+        }
+    }
+
+    #[test]
+    fn supporter_wall_orders_by_first_backing_and_hides_anonymous_backers() {
+        let p = |n: u8| Principal::from_slice(&[9, n]);
+        register(p(2));
+        // p(1..=12) back once each, p(12) the most; p(1) also backs again; p(50) only anonymously.
+        for n in 1..=12u8 {
+            insert_contribution(backing(n as u64, p(n), if n == 12 { 500 } else { 10 }, n as u64, false));
+        }
+        insert_contribution(backing(13, p(1), 10, 100, false));
+        insert_contribution(backing(14, p(50), 1_000, 0, true));
+        let mut refunded = backing(15, p(60), 10, 0, false);
+        refunded.status = EscrowStatus::Refunded;
+        insert_contribution(refunded);
+
+        let wall = supporter_wall(9, 0, 1_000);
+        assert_eq!(wall.total_supporters, 12);
+        assert_eq!((wall.anonymous_backers, wall.anonymous_contributions), (1, 1));
+        assert!(wall.supporters.iter().all(|s| s.backer != p(50) && s.backer != p(60)));
+        assert_eq!(wall.supporters[0].backer, p(1));
+        assert_eq!((wall.supporters[0].total_amount, wall.supporters[0].contribution_count), (20, 2));
+        assert_eq!(wall.supporters[1].display_name.as_deref(), Some("t"));
+        let top = &wall.supporters[11];
+        assert_eq!((top.backer, top.badges.clone()), (p(12), vec![SupporterBadge::Top10]));
+        assert_eq!(wall.supporters[10].badges, vec![]); // 11th backer, smaller than the top 10
+        assert_eq!(wall.supporters[0].badges, vec![SupporterBadge::EarlyBacker, SupporterBadge::Top10]);
+
+        let page = supporter_wall(9, 10, 5);
+        assert_eq!((page.supporters.len(), page.next_offset), (2, None));
+        assert_eq!(supporter_wall(9, 0, 5).next_offset, Some(5));
+    }
+
+    // contribute runs check_contributor then check_campaign; preview must agree with that verdict.
+    #[test]
+    fn preview_accepts_exactly_when_contribute_would() {
+        let backer = Principal::from_slice(&[7, 7, 7]);
+        let stranger = Principal::from_slice(&[8, 8, 8]);
+        register(backer);
+        let now = 1_000;
+        let metas = [None, Some(meta(now - 1, None)), Some(meta(now + 1, None)), Some(meta(now + 1, Some(true)))];
+        for caller in [backer, stranger] {
+            for amount in [0, 50, 200] {
+                for m in &metas {
+                    for ack in [None, Some(false), Some(true)] {
+                        let contribute = check_contributor(caller, amount).and_then(|_| check_campaign(m.clone(), ack, now));
+                        let expected = contribute.as_ref().err().cloned();
+                        let preview = build_preview(amount, contribute, m.as_ref());
+                        assert_eq!(preview.rejection, expected);
+                        assert_eq!(preview.accepted, expected.is_none());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn preview_reports_goal_crossing_and_ack_requirement() {
+        let m = meta(10, Some(true));
+        let p = build_preview(100, Err(RISK_ACK_REQUIRED.into()), Some(&m));
+        assert!(p.requires_risk_ack && p.reaches_goal && !p.accepted);
+        assert_eq!(p.amount_raised_after, 1_000);
+        let p = build_preview(50, Ok(ValidContribution { meta: m.clone(), risks_acked: true }), Some(&m));
+        assert!(!p.reaches_goal && p.accepted);
+    }
+}